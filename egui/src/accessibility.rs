@@ -0,0 +1,290 @@
+// UI elements read through the platform accessibility API instead of OCR or vision
+use serde::Serialize;
+
+/// One accessible UI element.
+#[derive(Debug, Clone, Serialize)]
+pub struct Element {
+    /// Accessible name: a button's caption, a field's label, a list item's text.
+    pub name: String,
+    /// Control type, e.g. "button", "check_box", "edit", "menu_item", "list_item".
+    pub role: String,
+    /// Current value of value-bearing controls (an edit box's text, a slider's position).
+    pub value: Option<String>,
+    /// "on", "off" or "indeterminate" for check boxes and toggle buttons.
+    pub toggle_state: Option<&'static str>,
+    pub enabled: bool,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Element {
+    /// Center of the element's bounds, where a click lands.
+    pub fn center(&self) -> (i32, i32) {
+        (self.x + self.width as i32 / 2, self.y + self.height as i32 / 2)
+    }
+}
+
+/// An element with its (visible) descendants.
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNode {
+    #[serde(flatten)]
+    pub element: Element,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TreeNode>,
+}
+
+/// The accessibility tree of one window, cut off at a depth and element budget.
+#[derive(Debug, Clone, Serialize)]
+pub struct ElementTree {
+    pub root: TreeNode,
+    pub element_count: usize,
+    /// Whether elements were left out because of the depth or element limit.
+    pub truncated: bool,
+}
+
+/// Whether this build has an accessibility backend for the current platform.
+pub const SUPPORTED: bool = cfg!(windows);
+
+pub const DEFAULT_TREE_DEPTH: u32 = 6;
+pub const MAX_TREE_DEPTH: u32 = 15;
+pub const DEFAULT_TREE_ELEMENTS: usize = 300;
+// Each element is ~200 bytes of JSON, so this keeps a full dump under half a megabyte
+pub const MAX_TREE_ELEMENTS: usize = 2000;
+
+/// Returns the deepest element at the screen point, or `Ok(None)` when no accessibility backend
+/// is available on this platform.
+pub fn element_at(x: i32, y: i32) -> anyhow::Result<Option<Element>> {
+    imp::element_at(x, y)
+}
+
+/// Walks the accessibility tree of the window with the given native id (HWND on Windows),
+/// skipping off-screen subtrees and stopping at `max_depth` levels below the window or
+/// `max_elements` elements. `Ok(None)` when no accessibility backend is available.
+pub fn window_tree(window_id: u64, max_depth: u32, max_elements: usize) -> anyhow::Result<Option<ElementTree>> {
+    imp::window_tree(window_id, max_depth, max_elements.max(1))
+}
+
+/// Elements of the window whose name contains `name` (case-insensitive) and, when given, whose
+/// role is `role`, in tree order. If some names match exactly, only those are returned, so
+/// "Save" doesn't also match "Save As...". Invisible (zero-size) elements are left out.
+/// `Ok(None)` when no accessibility backend is available.
+pub fn find_elements(window_id: u64, name: &str, role: Option<&str>) -> anyhow::Result<Option<Vec<Element>>> {
+    let Some(tree) = window_tree(window_id, MAX_TREE_DEPTH, MAX_TREE_ELEMENTS)? else {
+        return Ok(None);
+    };
+    let name = name.to_lowercase();
+    let mut matches = Vec::new();
+    let mut pending = vec![tree.root];
+    while let Some(node) = pending.pop() {
+        // Children are pushed in reverse so they pop in tree order
+        pending.extend(node.children.into_iter().rev());
+        let element = node.element;
+        let role_matches = role.is_none_or(|role| element.role.eq_ignore_ascii_case(role));
+        if role_matches && element.width > 0 && element.height > 0 && element.name.to_lowercase().contains(&name) {
+            matches.push(element);
+        }
+    }
+    if matches.iter().any(|e| e.name.to_lowercase() == name) {
+        matches.retain(|e| e.name.to_lowercase() == name);
+    }
+    Ok(Some(matches))
+}
+
+/// Named, visible buttons of the window, in tree order: a dialog's "OK", "Cancel", "Don't Save"...
+/// `Ok(None)` when no accessibility backend is available.
+pub fn buttons(window_id: u64) -> anyhow::Result<Option<Vec<Element>>> {
+    let Some(tree) = window_tree(window_id, MAX_TREE_DEPTH, MAX_TREE_ELEMENTS)? else {
+        return Ok(None);
+    };
+    let mut buttons = Vec::new();
+    let mut pending = vec![tree.root];
+    while let Some(node) = pending.pop() {
+        pending.extend(node.children.into_iter().rev());
+        let element = node.element;
+        let is_button = matches!(element.role.as_str(), "button" | "split_button");
+        if is_button && !element.name.trim().is_empty() && element.width > 0 && element.height > 0 {
+            buttons.push(element);
+        }
+    }
+    Ok(Some(buttons))
+}
+
+// UI Automation; covers Win32, WinForms, WPF, UWP and (with accessibility on) browsers
+#[cfg(windows)]
+mod imp {
+    use super::{Element, ElementTree, TreeNode};
+    use anyhow::Context;
+    use windows::Win32::Foundation::{HWND, POINT};
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED};
+    use windows::Win32::UI::Accessibility::*;
+
+    const ROLES: &[(UIA_CONTROLTYPE_ID, &str)] = &[
+        (UIA_ButtonControlTypeId, "button"),
+        (UIA_CalendarControlTypeId, "calendar"),
+        (UIA_CheckBoxControlTypeId, "check_box"),
+        (UIA_ComboBoxControlTypeId, "combo_box"),
+        (UIA_CustomControlTypeId, "custom"),
+        (UIA_DataGridControlTypeId, "data_grid"),
+        (UIA_DataItemControlTypeId, "data_item"),
+        (UIA_DocumentControlTypeId, "document"),
+        (UIA_EditControlTypeId, "edit"),
+        (UIA_GroupControlTypeId, "group"),
+        (UIA_HeaderControlTypeId, "header"),
+        (UIA_HeaderItemControlTypeId, "header_item"),
+        (UIA_HyperlinkControlTypeId, "hyperlink"),
+        (UIA_ImageControlTypeId, "image"),
+        (UIA_ListControlTypeId, "list"),
+        (UIA_ListItemControlTypeId, "list_item"),
+        (UIA_MenuBarControlTypeId, "menu_bar"),
+        (UIA_MenuControlTypeId, "menu"),
+        (UIA_MenuItemControlTypeId, "menu_item"),
+        (UIA_PaneControlTypeId, "pane"),
+        (UIA_ProgressBarControlTypeId, "progress_bar"),
+        (UIA_RadioButtonControlTypeId, "radio_button"),
+        (UIA_ScrollBarControlTypeId, "scroll_bar"),
+        (UIA_SeparatorControlTypeId, "separator"),
+        (UIA_SliderControlTypeId, "slider"),
+        (UIA_SpinnerControlTypeId, "spinner"),
+        (UIA_SplitButtonControlTypeId, "split_button"),
+        (UIA_StatusBarControlTypeId, "status_bar"),
+        (UIA_TabControlTypeId, "tab"),
+        (UIA_TabItemControlTypeId, "tab_item"),
+        (UIA_TableControlTypeId, "table"),
+        (UIA_TextControlTypeId, "text"),
+        (UIA_ThumbControlTypeId, "thumb"),
+        (UIA_TitleBarControlTypeId, "title_bar"),
+        (UIA_ToolBarControlTypeId, "tool_bar"),
+        (UIA_ToolTipControlTypeId, "tool_tip"),
+        (UIA_TreeControlTypeId, "tree"),
+        (UIA_TreeItemControlTypeId, "tree_item"),
+        (UIA_WindowControlTypeId, "window"),
+    ];
+
+    fn automation() -> anyhow::Result<IUIAutomation> {
+        // SAFETY: plain COM calls; the returned interface manages its own reference count
+        unsafe {
+            // S_FALSE / RPC_E_CHANGED_MODE only mean COM was already initialized on this thread
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER).context("Failed to create the UI Automation client")
+        }
+    }
+
+    fn describe(element: &IUIAutomationElement) -> anyhow::Result<Element> {
+        // SAFETY: plain COM calls on a live element; unsupported patterns come back as errors
+        unsafe {
+            let control_type = element.CurrentControlType()?;
+            let role = match ROLES.iter().find(|(id, _)| *id == control_type) {
+                Some((_, role)) => role.to_string(),
+                None => element.CurrentLocalizedControlType()?.to_string().to_lowercase().replace(' ', "_"),
+            };
+            let value = element
+                .GetCurrentPatternAs::<IUIAutomationValuePattern>(UIA_ValuePatternId)
+                .and_then(|pattern| pattern.CurrentValue())
+                .map(|value| value.to_string())
+                .or_else(|_| {
+                    element
+                        .GetCurrentPatternAs::<IUIAutomationRangeValuePattern>(UIA_RangeValuePatternId)
+                        .and_then(|pattern| pattern.CurrentValue())
+                        .map(|value| value.to_string())
+                })
+                .ok();
+            let toggle_state = element
+                .GetCurrentPatternAs::<IUIAutomationTogglePattern>(UIA_TogglePatternId)
+                .and_then(|pattern| pattern.CurrentToggleState())
+                .ok()
+                .map(|state| match state {
+                    ToggleState_On => "on",
+                    ToggleState_Off => "off",
+                    _ => "indeterminate",
+                });
+            let rect = element.CurrentBoundingRectangle()?;
+            Ok(Element {
+                name: element.CurrentName()?.to_string(),
+                role,
+                value,
+                toggle_state,
+                enabled: element.CurrentIsEnabled()?.as_bool(),
+                x: rect.left,
+                y: rect.top,
+                width: (rect.right - rect.left).max(0) as u32,
+                height: (rect.bottom - rect.top).max(0) as u32,
+            })
+        }
+    }
+
+    pub fn element_at(x: i32, y: i32) -> anyhow::Result<Option<Element>> {
+        let automation = automation()?;
+        // SAFETY: plain COM call with a value argument
+        let element = unsafe { automation.ElementFromPoint(POINT { x, y }) }
+            .with_context(|| format!("No accessible element at ({}, {})", x, y))?;
+        describe(&element).map(Some)
+    }
+
+    // Depth-first walk over the control view (layout-only elements are left out)
+    struct Walk {
+        walker: IUIAutomationTreeWalker,
+        max_depth: u32,
+        remaining: usize,
+        truncated: bool,
+    }
+
+    impl Walk {
+        fn node(&mut self, element: &IUIAutomationElement, depth: u32) -> anyhow::Result<TreeNode> {
+            self.remaining -= 1;
+            let mut node = TreeNode { element: describe(element)?, children: Vec::new() };
+            // SAFETY: plain COM calls; a missing child or sibling comes back as an error
+            let mut child = unsafe { self.walker.GetFirstChildElement(element) }.ok();
+            if depth >= self.max_depth {
+                self.truncated |= child.is_some();
+                return Ok(node);
+            }
+            while let Some(current) = child {
+                if self.remaining == 0 {
+                    self.truncated = true;
+                    break;
+                }
+                // SAFETY: as above
+                let offscreen = unsafe { current.CurrentIsOffscreen() }.map(|b| b.as_bool()).unwrap_or(false);
+                // Elements can disappear mid-walk (closing popups, virtualized lists); skip those
+                if !offscreen {
+                    if let Ok(child_node) = self.node(&current, depth + 1) {
+                        node.children.push(child_node);
+                    }
+                }
+                // SAFETY: as above
+                child = unsafe { self.walker.GetNextSiblingElement(&current) }.ok();
+            }
+            Ok(node)
+        }
+    }
+
+    pub fn window_tree(window_id: u64, max_depth: u32, max_elements: usize) -> anyhow::Result<Option<ElementTree>> {
+        let automation = automation()?;
+        // SAFETY: plain COM calls; the handle is only passed back to the system
+        let (root, walker) = unsafe {
+            let root = automation
+                .ElementFromHandle(HWND(window_id as usize as *mut _))
+                .with_context(|| format!("No accessible window with id {}", window_id))?;
+            (root, automation.ControlViewWalker()?)
+        };
+        let mut walk = Walk { walker, max_depth, remaining: max_elements, truncated: false };
+        let root = walk.node(&root, 0)?;
+        Ok(Some(ElementTree { root, element_count: max_elements - walk.remaining, truncated: walk.truncated }))
+    }
+}
+
+// macOS (AXUIElement) and Linux (AT-SPI over D-Bus) backends need bindings this build doesn't link
+#[cfg(not(windows))]
+mod imp {
+    use super::{Element, ElementTree};
+
+    pub fn element_at(_x: i32, _y: i32) -> anyhow::Result<Option<Element>> {
+        Ok(None)
+    }
+
+    pub fn window_tree(_window_id: u64, _max_depth: u32, _max_elements: usize) -> anyhow::Result<Option<ElementTree>> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,109 @@
+// Append-only JSONL audit trail of executed tool calls, enabled with AIRC_ACTION_LOG=<path>
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// Bumped whenever a field changes meaning, so replaying an old log can detect it.
+pub const FORMAT_VERSION: u32 = 1;
+
+// Longer strings are cut; keeps typed text readable without logging megabytes of payload
+const MAX_STRING_LEN: usize = 2048;
+// Stands in for environment variable values, which often hold API keys
+const REDACTED: &str = "<redacted>";
+
+/// One line of the action log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionRecord {
+    pub version: u32,
+    /// Milliseconds since the Unix epoch when the call started.
+    pub timestamp_ms: u64,
+    pub client: String,
+    pub tool: String,
+    /// Call arguments with large fields redacted.
+    pub params: Option<Map<String, Value>>,
+    /// "success", "tool_error" (recoverable failure the model saw) or "error".
+    pub status: String,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Shared writer for the action log; clones append to the same file.
+#[derive(Clone)]
+pub struct ActionLog {
+    file: Arc<Mutex<File>>,
+}
+
+impl ActionLog {
+    /// Opens the file named by `AIRC_ACTION_LOG` for appending, or returns `None` when unset.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Some(path) = std::env::var_os("AIRC_ACTION_LOG").filter(|p| !p.is_empty()) else {
+            return Ok(None);
+        };
+        let log = Self::open(Path::new(&path))?;
+        info!("Logging executed actions to {:?}", path);
+        Ok(Some(log))
+    }
+
+    /// Opens (or creates) a log file at `path` for appending.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        // Typed text ends up in here, so a new log is readable by its owner only
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options
+            .open(path)
+            .with_context(|| format!("Failed to open action log {:?}", path))?;
+        Ok(Self { file: Arc::new(Mutex::new(file)) })
+    }
+
+    /// Appends a record. Write failures are logged rather than failing the tool call.
+    pub fn append(&self, mut record: ActionRecord) {
+        record.params = record.params.map(|params| params.into_iter().map(|(k, v)| redact(k, v)).collect());
+        let line = match serde_json::to_string(&record) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize action log record: {}", e);
+                return;
+            }
+        };
+        let mut file = self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Err(e) = writeln!(file, "{}", line).and_then(|_| file.flush()) {
+            warn!("Failed to write action log: {}", e);
+        }
+    }
+}
+
+/// Whether the call's environment variables were redacted when it was logged, so replaying it
+/// would pass the placeholder instead of the original values.
+pub fn has_redacted_env(params: &Map<String, Value>) -> bool {
+    params.get("env").and_then(Value::as_object).is_some_and(|env| env.values().any(|v| v == REDACTED))
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+// Drops image payloads entirely, hides environment variable values (often API keys) and
+// truncates any other oversized string
+fn redact(key: String, value: Value) -> (String, Value) {
+    let value = match value {
+        Value::Object(map) if key == "env" => Value::Object(map.into_iter().map(|(k, _)| (k, Value::String(REDACTED.to_string()))).collect()),
+        Value::String(s) if key.contains("base64") => Value::String(format!("<{} bytes omitted>", s.len())),
+        Value::String(s) if s.len() > MAX_STRING_LEN => {
+            let cut = (0..=MAX_STRING_LEN).rev().find(|i| s.is_char_boundary(*i)).unwrap_or(0);
+            Value::String(format!("{}...<truncated {} bytes>", &s[..cut], s.len() - cut))
+        }
+        Value::Object(map) => Value::Object(map.into_iter().map(|(k, v)| redact(k, v)).collect()),
+        Value::Array(items) => Value::Array(items.into_iter().map(|v| redact(String::new(), v).1).collect()),
+        other => other,
+    };
+    (key, value)
+}
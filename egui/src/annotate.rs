@@ -0,0 +1,152 @@
+// Set-of-marks drawing: numbered boxes over screenshot elements, plus coordinate debug markers
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::{draw_filled_circle_mut, draw_hollow_circle_mut, draw_hollow_rect_mut, draw_line_segment_mut};
+use imageproc::rect::Rect;
+use serde::Serialize;
+
+/// An element marked on an annotated screenshot. Coordinates are absolute desktop pixels.
+#[derive(Debug, Clone, Serialize)]
+pub struct Mark {
+    pub id: u32,
+    pub label: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub center_x: i32,
+    pub center_y: i32,
+}
+
+impl Mark {
+    pub fn new(id: u32, label: String, x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            id,
+            label,
+            x,
+            y,
+            width,
+            height,
+            center_x: x + (width / 2) as i32,
+            center_y: y + (height / 2) as i32,
+        }
+    }
+}
+
+const BOX_COLOR: Rgba<u8> = Rgba([255, 0, 255, 255]);
+const LABEL_BG: Rgba<u8> = Rgba([255, 0, 255, 255]);
+const LABEL_FG: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const BOX_THICKNESS: u32 = 2;
+const GLYPH_SCALE: u32 = 3;
+
+// 3x5 bitmap digits, one row per entry, most significant bit on the left
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// Fills a rectangle, clipped to the image bounds.
+pub fn fill_rect(image: &mut RgbaImage, x: i64, y: i64, width: u32, height: u32, color: Rgba<u8>) {
+    let x0 = x.max(0) as u32;
+    let y0 = y.max(0) as u32;
+    let x1 = ((x + width as i64).max(0) as u32).min(image.width());
+    let y1 = ((y + height as i64).max(0) as u32).min(image.height());
+    for py in y0..y1 {
+        for px in x0..x1 {
+            image.put_pixel(px, py, color);
+        }
+    }
+}
+
+/// Draws a rectangle outline, clipped to the image bounds.
+pub fn draw_rect(image: &mut RgbaImage, x: i64, y: i64, width: u32, height: u32, thickness: u32, color: Rgba<u8>) {
+    let t = thickness.min(width).min(height);
+    fill_rect(image, x, y, width, t, color);
+    fill_rect(image, x, y + height as i64 - t as i64, width, t, color);
+    fill_rect(image, x, y, t, height, color);
+    fill_rect(image, x + width as i64 - t as i64, y, t, height, color);
+}
+
+// Draws `number` on a filled background with its top-left corner at (x, y)
+fn draw_number(image: &mut RgbaImage, x: i64, y: i64, number: u32) {
+    let digits: Vec<usize> = number.to_string().bytes().map(|b| (b - b'0') as usize).collect();
+    let glyph_w = 3 * GLYPH_SCALE;
+    let glyph_h = 5 * GLYPH_SCALE;
+    let padding = GLYPH_SCALE;
+    let label_w = digits.len() as u32 * (glyph_w + GLYPH_SCALE) - GLYPH_SCALE + 2 * padding;
+    fill_rect(image, x, y, label_w, glyph_h + 2 * padding, LABEL_BG);
+
+    for (i, digit) in digits.iter().enumerate() {
+        let origin_x = x + (padding + i as u32 * (glyph_w + GLYPH_SCALE)) as i64;
+        let origin_y = y + padding as i64;
+        for (row, bits) in DIGITS[*digit].iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) != 0 {
+                    fill_rect(
+                        image,
+                        origin_x + (col * GLYPH_SCALE) as i64,
+                        origin_y + row as i64 * GLYPH_SCALE as i64,
+                        GLYPH_SCALE,
+                        GLYPH_SCALE,
+                        LABEL_FG,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Draws a numbered box for every mark. `origin_x`/`origin_y` is the desktop position of the
+/// image's top-left pixel, used to convert the marks' absolute coordinates to image space.
+pub fn draw_marks(image: &mut RgbaImage, marks: &[Mark], origin_x: i32, origin_y: i32) {
+    for mark in marks {
+        let x = (mark.x - origin_x) as i64;
+        let y = (mark.y - origin_y) as i64;
+        draw_rect(image, x, y, mark.width, mark.height, BOX_THICKNESS, BOX_COLOR);
+        // Label sits just above the box, or inside it when the box touches the top edge
+        let label_h = (5 + 2) * GLYPH_SCALE as i64;
+        let label_y = if y >= label_h { y - label_h } else { y };
+        draw_number(image, x, label_y, mark.id);
+    }
+}
+
+const MARKER_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const MARKER_OUTLINE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const RECT_COLOR: Rgba<u8> = Rgba([0, 200, 255, 255]);
+const CROSSHAIR_ARM: f32 = 20.0;
+const CROSSHAIR_RADIUS: i32 = 10;
+
+/// Draws a numbered crosshair centred on the image pixel (x, y), e.g. where a click will land.
+/// A white outline keeps it visible on red or dark backgrounds.
+pub fn draw_crosshair(image: &mut RgbaImage, x: i32, y: i32, number: u32) {
+    let (cx, cy) = (x as f32, y as f32);
+    for offset in [-1.0, 1.0] {
+        draw_line_segment_mut(image, (cx - CROSSHAIR_ARM, cy + offset), (cx + CROSSHAIR_ARM, cy + offset), MARKER_OUTLINE);
+        draw_line_segment_mut(image, (cx + offset, cy - CROSSHAIR_ARM), (cx + offset, cy + CROSSHAIR_ARM), MARKER_OUTLINE);
+    }
+    draw_line_segment_mut(image, (cx - CROSSHAIR_ARM, cy), (cx + CROSSHAIR_ARM, cy), MARKER_COLOR);
+    draw_line_segment_mut(image, (cx, cy - CROSSHAIR_ARM), (cx, cy + CROSSHAIR_ARM), MARKER_COLOR);
+    draw_hollow_circle_mut(image, (x, y), CROSSHAIR_RADIUS + 1, MARKER_OUTLINE);
+    draw_hollow_circle_mut(image, (x, y), CROSSHAIR_RADIUS, MARKER_COLOR);
+    draw_filled_circle_mut(image, (x, y), 1, MARKER_COLOR);
+    // Number sits below-right of the crosshair so it doesn't hide the target pixel
+    draw_number(image, (x + CROSSHAIR_RADIUS + 2) as i64, (y + CROSSHAIR_RADIUS + 2) as i64, number);
+}
+
+/// Draws a 2px rectangle outline with its top-left corner at the image pixel (x, y).
+pub fn draw_outline(image: &mut RgbaImage, x: i32, y: i32, width: u32, height: u32) {
+    for inset in 0..2 {
+        let (w, h) = (width.saturating_sub(2 * inset), height.saturating_sub(2 * inset));
+        if w == 0 || h == 0 {
+            break;
+        }
+        draw_hollow_rect_mut(image, Rect::at(x + inset as i32, y + inset as i32).of_size(w, h), RECT_COLOR);
+    }
+}
@@ -0,0 +1,218 @@
+// Applications started by launch_application, tracked so they can be checked on and closed later
+use anyhow::{anyhow, Context};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::time::{sleep, Duration, Instant};
+use tracing::{info, warn};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Children spawned by the server, keyed by PID. Shared by all clients, so an app launched in
+/// one connection can still be terminated after the client reconnects.
+#[derive(Clone, Default)]
+pub struct AppRegistry {
+    children: Arc<Mutex<HashMap<u32, Child>>>,
+}
+
+/// Environment variables set on a spawned process. Values often carry secrets (API keys,
+/// tokens), so `Debug` lists the names only and the action log redacts them.
+#[derive(Clone, Default, Deserialize, Serialize, schemars::JsonSchema)]
+#[serde(transparent)]
+pub struct EnvVars(pub HashMap<String, String>);
+
+impl std::fmt::Debug for EnvVars {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+/// The environment of a spawned process. By default it inherits the server's whole
+/// environment, including anything sensitive in it; `clear` starts from an empty one instead,
+/// which on Windows also drops variables like `SystemRoot` that many programs need.
+#[derive(Debug, Clone, Default)]
+pub struct ChildEnv {
+    pub vars: EnvVars,
+    pub clear: bool,
+}
+
+impl ChildEnv {
+    /// Rejects names the OS can't store: empty, or containing '=' or NUL. Values may not
+    /// contain NUL either.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (name, value) in &self.vars.0 {
+            if name.is_empty() || name.contains(['=', '\0']) {
+                return Err(anyhow!("Invalid environment variable name '{}'", name));
+            }
+            if value.contains('\0') {
+                return Err(anyhow!("Environment variable '{}' contains a NUL character", name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, command: &mut Command) {
+        if self.clear {
+            command.env_clear();
+        }
+        command.envs(&self.vars.0);
+    }
+}
+
+/// How a launch went after the startup wait.
+#[derive(Debug)]
+pub enum LaunchOutcome {
+    /// Still running; the PID is now tracked.
+    Running { pid: u32 },
+    /// Exited within the startup wait. A zero exit usually means a launcher that handed off to
+    /// another (already running) process, e.g. a browser opening a new tab.
+    Exited { pid: u32, status: ExitStatus },
+}
+
+/// How a tracked application ended.
+#[derive(Debug)]
+pub struct Termination {
+    pub exit_code: Option<i32>,
+    /// Whether it had to be killed because it ignored the polite request (or `force` was set).
+    pub forced: bool,
+}
+
+impl AppRegistry {
+    fn lock(&self) -> MutexGuard<'_, HashMap<u32, Child>> {
+        // Nothing in the map can be left half-updated, so a poisoned lock is safe to reuse
+        self.children.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Spawns `program` with `env` applied and watches it for `startup_wait` to catch apps that
+    /// fail immediately. Spawn failures (e.g. program not found) are returned as errors.
+    pub async fn launch(
+        &self,
+        program: &str,
+        args: &[String],
+        working_dir: Option<&str>,
+        env: &ChildEnv,
+        startup_wait: Duration,
+    ) -> anyhow::Result<LaunchOutcome> {
+        let mut command = Command::new(program);
+        command.args(args).stdin(Stdio::null());
+        if let Some(dir) = working_dir {
+            command.current_dir(dir);
+        }
+        env.apply(&mut command);
+        let child = command.spawn().with_context(|| format!("Failed to launch '{}'", program))?;
+        let pid = child.id();
+        info!("Launched '{}' with PID {}", program, pid);
+
+        // Killed if the call is cancelled or times out during the startup wait, so an untracked
+        // app isn't left running that terminate_application could never close
+        let mut pending = KillOnDrop(Some(child));
+        let deadline = Instant::now() + startup_wait;
+        while let Some(child) = pending.0.as_mut() {
+            if let Some(status) = child.try_wait().context("Failed to check launched process")? {
+                info!("'{}' (PID {}) exited during startup with {}", program, pid, status);
+                pending.0 = None;
+                return Ok(LaunchOutcome::Exited { pid, status });
+            }
+            if Instant::now() >= deadline {
+                self.lock().extend(pending.0.take().map(|child| (pid, child)));
+            } else {
+                sleep(POLL_INTERVAL).await;
+            }
+        }
+        Ok(LaunchOutcome::Running { pid })
+    }
+
+    /// Whether `pid` was launched by this server and is still tracked.
+    pub fn is_tracked(&self, pid: u32) -> bool {
+        self.lock().contains_key(&pid)
+    }
+
+    /// Asks a tracked application to close (SIGTERM / WM_CLOSE), killing it if it is still
+    /// running after `grace`, or straight away with `force`. Errors if `pid` isn't tracked.
+    ///
+    /// The child stays tracked until it has been reaped, so a cancelled or timed-out call
+    /// leaves it in the registry and terminate_application can be called again.
+    pub async fn terminate(&self, pid: u32, force: bool, grace: Duration) -> anyhow::Result<Termination> {
+        if let Some(status) = self.try_reap(pid)? {
+            return Ok(Termination { exit_code: status.code(), forced: false });
+        }
+
+        if !force {
+            if let Err(e) = request_close(pid) {
+                warn!("Polite close of PID {} failed, killing instead: {:#}", pid, e);
+            } else {
+                let deadline = Instant::now() + grace;
+                while Instant::now() < deadline {
+                    if let Some(status) = self.try_reap(pid)? {
+                        info!("PID {} closed with {}", pid, status);
+                        return Ok(Termination { exit_code: status.code(), forced: false });
+                    }
+                    sleep(POLL_INTERVAL).await;
+                }
+                info!("PID {} still running after {:?}; killing it.", pid, grace);
+            }
+        }
+
+        let mut children = self.lock();
+        let child = children.get_mut(&pid).ok_or_else(|| not_tracked(pid))?;
+        child.kill().with_context(|| format!("Failed to kill PID {}", pid))?;
+        let status = child.wait().with_context(|| format!("Failed to reap PID {}", pid))?;
+        children.remove(&pid);
+        Ok(Termination { exit_code: status.code(), forced: true })
+    }
+
+    // Returns the exit status and stops tracking `pid` if it has exited
+    fn try_reap(&self, pid: u32) -> anyhow::Result<Option<ExitStatus>> {
+        let mut children = self.lock();
+        let child = children.get_mut(&pid).ok_or_else(|| not_tracked(pid))?;
+        let status = child.try_wait().with_context(|| format!("Failed to check PID {}", pid))?;
+        if status.is_some() {
+            children.remove(&pid);
+        }
+        Ok(status)
+    }
+}
+
+fn not_tracked(pid: u32) -> anyhow::Error {
+    anyhow!("PID {} was not launched by this server", pid)
+}
+
+// A just-spawned child that is killed and reaped if dropped before it is handed over
+struct KillOnDrop(Option<Child>);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.0.take() {
+            warn!("Launch of PID {} was interrupted; killing it.", child.id());
+            if let Err(e) = child.kill().and_then(|_| child.wait().map(|_| ())) {
+                warn!("Failed to kill PID {} after an interrupted launch: {}", child.id(), e);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+pub fn request_close(pid: u32) -> anyhow::Result<()> {
+    let status = Command::new("kill").args(["-TERM", &pid.to_string()]).status().context("Failed to run 'kill'")?;
+    if !status.success() {
+        return Err(anyhow!("'kill -TERM {}' failed with {}", pid, status));
+    }
+    Ok(())
+}
+
+// taskkill without /F posts WM_CLOSE to the process's windows, like clicking the close button
+#[cfg(windows)]
+pub fn request_close(pid: u32) -> anyhow::Result<()> {
+    let status = Command::new("taskkill").args(["/PID", &pid.to_string()]).status().context("Failed to run 'taskkill'")?;
+    if !status.success() {
+        return Err(anyhow!("'taskkill /PID {}' failed with {}", pid, status));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn request_close(_pid: u32) -> anyhow::Result<()> {
+    Err(anyhow!("Graceful close is not supported on this platform"))
+}
@@ -0,0 +1,162 @@
+// Display brightness of built-in panels (and monitors that expose it to the OS)
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DisplayBrightness {
+    /// Position in the platform's list of adjustable displays; pass it as `monitor`.
+    pub index: usize,
+    /// WMI instance name on Windows, backlight device (e.g. "intel_backlight") on Linux.
+    pub name: String,
+    /// 0-100.
+    pub level: u8,
+}
+
+/// Brightness of every adjustable display, or `Ok(None)` when the platform has no backend or
+/// no display whose brightness the OS can control (typical for desktops with external monitors).
+pub fn brightness() -> anyhow::Result<Option<Vec<DisplayBrightness>>> {
+    Ok(imp::brightness()?.filter(|displays| !displays.is_empty()))
+}
+
+/// Sets the brightness (clamped to 0-100) of the display at `index`, or of all of them, and
+/// returns the new levels (`Ok(None)` when unsupported). Errors if `index` doesn't exist.
+pub fn set_brightness(index: Option<usize>, level: u8) -> anyhow::Result<Option<Vec<DisplayBrightness>>> {
+    let Some(displays) = brightness()? else {
+        return Ok(None);
+    };
+    if let Some(index) = index.filter(|&i| i >= displays.len()) {
+        return Err(anyhow::Error::new(NoSuchDisplay { index, count: displays.len() }));
+    }
+    for display in displays.iter().filter(|d| index.is_none_or(|i| i == d.index)) {
+        imp::set_brightness(display, level.min(100))?;
+    }
+    brightness()
+}
+
+/// A `monitor` index past the adjustable displays.
+#[derive(Debug)]
+pub struct NoSuchDisplay {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl std::fmt::Display for NoSuchDisplay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No adjustable display with index {}; there are {}", self.index, self.count)
+    }
+}
+
+impl std::error::Error for NoSuchDisplay {}
+
+// WMI's WmiMonitorBrightness classes, through PowerShell's CIM cmdlets. Only panels that
+// report brightness over ACPI (laptops, some all-in-ones) have instances.
+#[cfg(windows)]
+mod imp {
+    use super::DisplayBrightness;
+    use anyhow::{anyhow, Context};
+    use std::process::Command;
+
+    fn powershell(script: &str) -> anyhow::Result<String> {
+        let output = Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", script])
+            .output()
+            .context("Failed to run powershell")?;
+        if !output.status.success() {
+            return Err(anyhow!("PowerShell WMI query failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    pub fn brightness() -> anyhow::Result<Option<Vec<DisplayBrightness>>> {
+        // "<InstanceName>\t<CurrentBrightness>" per display; no output when nothing is adjustable
+        let output = powershell(
+            "Get-CimInstance -Namespace root/wmi -ClassName WmiMonitorBrightness -ErrorAction SilentlyContinue | \
+             ForEach-Object { \"$($_.InstanceName)`t$($_.CurrentBrightness)\" }",
+        )?;
+        let displays = output
+            .lines()
+            .filter_map(|line| line.trim().split_once('\t'))
+            .enumerate()
+            .filter_map(|(index, (name, level))| {
+                Some(DisplayBrightness { index, name: name.to_string(), level: level.trim().parse::<u32>().ok()?.min(100) as u8 })
+            })
+            .collect();
+        Ok(Some(displays))
+    }
+
+    pub fn set_brightness(display: &DisplayBrightness, level: u8) -> anyhow::Result<()> {
+        // Instance names contain backslashes but no quotes; escape them for the single-quoted literal anyway
+        let script = format!(
+            "Get-CimInstance -Namespace root/wmi -ClassName WmiMonitorBrightnessMethods | \
+             Where-Object {{ $_.InstanceName -eq '{}' }} | \
+             Invoke-CimMethod -MethodName WmiSetBrightness -Arguments @{{ Timeout = 1; Brightness = [byte]{} }} | Out-Null",
+            display.name.replace('\'', "''"),
+            level
+        );
+        powershell(&script)?;
+        Ok(())
+    }
+}
+
+// sysfs backlight devices. Writing `brightness` needs root or a udev rule granting the
+// video group access, which is common but not universal.
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::DisplayBrightness;
+    use anyhow::{anyhow, Context};
+    use std::path::{Path, PathBuf};
+
+    const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+
+    fn read_number(path: &Path) -> anyhow::Result<u64> {
+        let raw = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        raw.trim().parse().map_err(|_| anyhow!("Unexpected content '{}' in {}", raw.trim(), path.display()))
+    }
+
+    fn devices() -> anyhow::Result<Vec<PathBuf>> {
+        let entries = match std::fs::read_dir(BACKLIGHT_DIR) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to list {}", BACKLIGHT_DIR)),
+        };
+        let mut devices: Vec<PathBuf> = entries.filter_map(|entry| Some(entry.ok()?.path())).collect();
+        // Sorted so indexes stay stable between calls
+        devices.sort();
+        Ok(devices)
+    }
+
+    pub fn brightness() -> anyhow::Result<Option<Vec<DisplayBrightness>>> {
+        let mut displays = Vec::new();
+        for (index, device) in devices()?.into_iter().enumerate() {
+            let max = read_number(&device.join("max_brightness"))?.max(1);
+            let current = read_number(&device.join("brightness"))?;
+            displays.push(DisplayBrightness {
+                index,
+                name: device.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+                level: (current * 100).div_ceil(max).min(100) as u8,
+            });
+        }
+        Ok(Some(displays))
+    }
+
+    pub fn set_brightness(display: &DisplayBrightness, level: u8) -> anyhow::Result<()> {
+        let device = Path::new(BACKLIGHT_DIR).join(&display.name);
+        let max = read_number(&device.join("max_brightness"))?;
+        let raw = max * level as u64 / 100;
+        let path = device.join("brightness");
+        std::fs::write(&path, raw.to_string()).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+// macOS keeps display brightness behind private DisplayServices APIs
+#[cfg(not(any(windows, target_os = "linux")))]
+mod imp {
+    use super::DisplayBrightness;
+
+    pub fn brightness() -> anyhow::Result<Option<Vec<DisplayBrightness>>> {
+        Ok(None)
+    }
+
+    pub fn set_brightness(_display: &DisplayBrightness, _level: u8) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
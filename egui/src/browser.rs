@@ -0,0 +1,46 @@
+// URL checks and the platform's "open in default browser" command for open_url
+use anyhow::anyhow;
+use std::sync::OnceLock;
+use tracing::info;
+
+const DEFAULT_SCHEMES: &str = "http,https,file";
+
+/// URL schemes open_url accepts, from `AIRC_OPEN_URL_SCHEMES` (comma-separated, default
+/// "http,https,file"). Read once.
+pub fn allowed_schemes() -> &'static [String] {
+    static SCHEMES: OnceLock<Vec<String>> = OnceLock::new();
+    SCHEMES.get_or_init(|| {
+        let raw = std::env::var("AIRC_OPEN_URL_SCHEMES").unwrap_or_else(|_| DEFAULT_SCHEMES.to_string());
+        let schemes: Vec<String> = raw.split(',').map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()).collect();
+        info!("open_url accepts the schemes: {}", schemes.join(", "));
+        schemes
+    })
+}
+
+/// Checks that `url` is well-formed enough to hand to the OS and returns its (lowercased)
+/// scheme. Whether the scheme is allowed is up to the caller (see [`allowed_schemes`]).
+pub fn scheme(url: &str) -> anyhow::Result<String> {
+    if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(anyhow!("URL must not contain spaces or control characters; percent-encode them"));
+    }
+    // RFC 3986: a letter followed by letters, digits, '+', '-' or '.'
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme)
+        .filter(|s| s.starts_with(|c: char| c.is_ascii_alphabetic()))
+        .filter(|s| s.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')))
+        .ok_or_else(|| anyhow!("'{}' has no URL scheme; use a full URL such as https://example.com", url))?;
+    Ok(scheme.to_lowercase())
+}
+
+/// Program and arguments that open `url` with the user's default handler.
+pub fn opener(url: &str) -> (&'static str, Vec<String>) {
+    if cfg!(windows) {
+        // Unlike `cmd /c start`, no shell parses the URL, so '&' in query strings is safe
+        ("rundll32", vec!["url.dll,FileProtocolHandler".to_string(), url.to_string()])
+    } else if cfg!(target_os = "macos") {
+        ("open", vec![url.to_string()])
+    } else {
+        ("xdg-open", vec![url.to_string()])
+    }
+}
@@ -0,0 +1,51 @@
+// Frame sequences for capture_burst, returned as an animated GIF or as separate PNGs
+use anyhow::{anyhow, Context};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+
+pub const MAX_FRAMES: u32 = 50;
+pub const MIN_INTERVAL_MS: u64 = 20;
+// Longest burst, so a call can't tie up the desktop (and the tool timeout) for minutes
+pub const MAX_DURATION_MS: u64 = 60_000;
+// Raw RGBA kept in memory while capturing; larger bursts are downscaled to fit
+const MAX_RAW_BYTES: u64 = 256 * 1024 * 1024;
+/// Largest encoded result. Base64 adds a third on top of this.
+pub const MAX_OUTPUT_BYTES: usize = 16 * 1024 * 1024;
+// NeuQuant sampling factor: 1 is best and slowest, 30 fastest. Screen content has few colors.
+const GIF_SPEED: i32 = 10;
+
+/// Scale factor (at most 1) that keeps `count` frames of `width`x`height` within the memory cap.
+pub fn scale_for(count: u32, width: u32, height: u32) -> f64 {
+    let raw = count as u64 * width as u64 * height as u64 * 4;
+    if raw <= MAX_RAW_BYTES {
+        return 1.0;
+    }
+    (MAX_RAW_BYTES as f64 / raw as f64).sqrt()
+}
+
+/// Downscales `image` by `scale` (no-op at 1.0).
+pub fn downscale(image: RgbaImage, scale: f64) -> RgbaImage {
+    if scale >= 1.0 {
+        return image;
+    }
+    let width = ((image.width() as f64 * scale) as u32).max(1);
+    let height = ((image.height() as f64 * scale) as u32).max(1);
+    image::imageops::resize(&image, width, height, image::imageops::FilterType::Triangle)
+}
+
+/// Encodes the frames as a looping GIF that shows each frame for `interval_ms`.
+pub fn encode_gif(frames: Vec<RgbaImage>, interval_ms: u64) -> anyhow::Result<Vec<u8>> {
+    if frames.is_empty() {
+        return Err(anyhow!("No frames to encode"));
+    }
+    let mut buf = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut buf, GIF_SPEED);
+        encoder.set_repeat(Repeat::Infinite).context("Failed to write GIF header")?;
+        let delay = Delay::from_numer_denom_ms(interval_ms as u32, 1);
+        encoder
+            .encode_frames(frames.into_iter().map(|frame| Frame::from_parts(frame, 0, 0, delay)))
+            .context("Failed to encode GIF")?;
+    }
+    Ok(buf)
+}
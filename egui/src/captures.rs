@@ -0,0 +1,80 @@
+// Screenshots saved to disk and served as MCP resources instead of inline base64
+use anyhow::Context;
+use image::RgbaImage;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tracing::{info, warn};
+
+// Oldest files are deleted once more captures than this are stored
+const MAX_STORED_CAPTURES: usize = 32;
+
+/// A screenshot written to the capture directory.
+#[derive(Debug, Clone)]
+pub struct StoredCapture {
+    /// `file://` URI of the PNG, also used as the MCP resource URI.
+    pub uri: String,
+    pub path: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: u64,
+}
+
+/// Recently saved captures, shared by all clients so any of them can read a capture back.
+#[derive(Clone, Default)]
+pub struct CaptureStore {
+    captures: Arc<Mutex<VecDeque<StoredCapture>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+fn capture_dir() -> PathBuf {
+    std::env::temp_dir().join("airc-captures")
+}
+
+fn file_uri(path: &std::path::Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    if path.starts_with('/') { format!("file://{}", path) } else { format!("file:///{}", path) }
+}
+
+impl CaptureStore {
+    fn lock(&self) -> MutexGuard<'_, VecDeque<StoredCapture>> {
+        self.captures.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Writes `image` as a PNG into the capture directory and remembers it, deleting the oldest
+    /// stored capture beyond the limit.
+    pub fn save(&self, image: &RgbaImage) -> anyhow::Result<StoredCapture> {
+        let dir = capture_dir();
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create capture directory {:?}", dir))?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        // The PID keeps two server instances from overwriting each other's files
+        let path = dir.join(format!("capture-{}-{}.png", std::process::id(), id));
+        image.save_with_format(&path, image::ImageFormat::Png).with_context(|| format!("Failed to write {:?}", path))?;
+        let size_bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+
+        let capture = StoredCapture { uri: file_uri(&path), path, width: image.width(), height: image.height(), size_bytes };
+        let evicted = {
+            let mut captures = self.lock();
+            captures.push_back(capture.clone());
+            let excess = captures.len().saturating_sub(MAX_STORED_CAPTURES);
+            captures.drain(..excess).collect::<Vec<_>>()
+        };
+        for old in evicted {
+            if let Err(e) = std::fs::remove_file(&old.path) {
+                warn!("Failed to delete old capture {:?}: {}", old.path, e);
+            }
+        }
+        info!("Saved capture to {:?} ({} bytes)", capture.path, capture.size_bytes);
+        Ok(capture)
+    }
+
+    /// Stored captures, oldest first.
+    pub fn list(&self) -> Vec<StoredCapture> {
+        self.lock().iter().cloned().collect()
+    }
+
+    pub fn get(&self, uri: &str) -> Option<StoredCapture> {
+        self.lock().iter().find(|c| c.uri == uri).cloned()
+    }
+}
@@ -0,0 +1,133 @@
+// Policy metadata for every tool, reported by get_tool_catalog
+use serde::Serialize;
+
+/// What kind of effect a tool has. Clients building confirmation or allow-list policies key on
+/// this; `Destructive` tools can lose data, run arbitrary code or capture private input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    /// Only observes the desktop (or just waits).
+    ReadOnly,
+    /// Synthesizes mouse or keyboard input.
+    Input,
+    /// Moves, resizes, focuses or closes windows.
+    Window,
+    /// Changes machine settings or starts programs.
+    System,
+    Destructive,
+    /// Not in the catalog table; treat with care.
+    Uncategorized,
+}
+
+/// Rough estimate of how much harm a mistaken call can do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Risk {
+    Low,
+    Medium,
+    High,
+}
+
+// Keep in sync with the #[tool] handlers; a missing tool is reported as uncategorized/high
+const TOOL_CATALOG: &[(&str, Category, Risk)] = &[
+    ("get_screen_details", Category::ReadOnly, Risk::Low),
+    ("get_all_screens", Category::ReadOnly, Risk::Low),
+    ("translate_coordinates", Category::ReadOnly, Risk::Low),
+    ("find_window", Category::ReadOnly, Risk::Low),
+    ("list_dialogs", Category::ReadOnly, Risk::Low),
+    ("detect_dialogs", Category::ReadOnly, Risk::Low),
+    ("get_active_window", Category::ReadOnly, Risk::Low),
+    ("get_mouse_position", Category::ReadOnly, Risk::Low),
+    ("get_cursor_info", Category::ReadOnly, Risk::Low),
+    ("get_element_at_point", Category::ReadOnly, Risk::Low),
+    ("get_accessibility_tree", Category::ReadOnly, Risk::Low),
+    ("list_tray_icons", Category::ReadOnly, Risk::Low),
+    ("list_virtual_desktops", Category::ReadOnly, Risk::Low),
+    ("get_mouse_acceleration", Category::ReadOnly, Risk::Low),
+    ("get_keyboard_layout", Category::ReadOnly, Risk::Low),
+    ("get_input_state", Category::ReadOnly, Risk::Low),
+    ("get_brightness", Category::ReadOnly, Risk::Low),
+    ("get_power_state", Category::ReadOnly, Risk::Low),
+    ("get_volume", Category::ReadOnly, Risk::Low),
+    ("capture_screen", Category::ReadOnly, Risk::Low),
+    ("capture_window", Category::ReadOnly, Risk::Low),
+    ("capture_screen_annotated", Category::ReadOnly, Risk::Low),
+    ("capture_screen_resource", Category::ReadOnly, Risk::Low),
+    ("capture_burst", Category::ReadOnly, Risk::Low),
+    ("ocr_screen", Category::ReadOnly, Risk::Low),
+    ("find_text_on_screen", Category::ReadOnly, Risk::Low),
+    ("assert_pixel", Category::ReadOnly, Risk::Low),
+    ("assert_text", Category::ReadOnly, Risk::Low),
+    ("wait_for_pixel", Category::ReadOnly, Risk::Low),
+    ("wait_for_window", Category::ReadOnly, Risk::Low),
+    ("screen_changed_since", Category::ReadOnly, Risk::Low),
+    ("ping", Category::ReadOnly, Risk::Low),
+    ("get_metrics", Category::ReadOnly, Risk::Low),
+    ("get_tool_catalog", Category::ReadOnly, Risk::Low),
+    ("get_capabilities", Category::ReadOnly, Risk::Low),
+    ("get_session_state", Category::ReadOnly, Risk::Low),
+    ("get_server_environment", Category::ReadOnly, Risk::Low),
+    ("execute_openai_wait", Category::ReadOnly, Risk::Low),
+    // Exposes file contents, including recordings of everything the user typed
+    ("read_file", Category::ReadOnly, Risk::Medium),
+    ("list_directory", Category::ReadOnly, Risk::Low),
+    ("list_processes", Category::ReadOnly, Risk::Low),
+    ("get_clipboard_formats", Category::ReadOnly, Risk::Low),
+    // Copied passwords and other secrets often pass through the clipboard
+    ("get_clipboard", Category::ReadOnly, Risk::Medium),
+    ("move_mouse", Category::Input, Risk::Medium),
+    ("move_mouse_relative_to_window", Category::Input, Risk::Medium),
+    ("mouse_action", Category::Input, Risk::Medium),
+    ("hold_mouse_button", Category::Input, Risk::Medium),
+    ("click_tray_icon", Category::Input, Risk::Medium),
+    ("click_element", Category::Input, Risk::Medium),
+    ("context_menu_select", Category::Input, Risk::Medium),
+    ("smooth_scroll", Category::Input, Risk::Medium),
+    ("keyboard_action", Category::Input, Risk::Medium),
+    ("execute_openai_click", Category::Input, Risk::Medium),
+    ("execute_openai_scroll", Category::Input, Risk::Medium),
+    ("execute_openai_keypress", Category::Input, Risk::Medium),
+    ("execute_openai_type", Category::Input, Risk::Medium),
+    ("hold_key", Category::Input, Risk::Medium),
+    ("key_sequence", Category::Input, Risk::Medium),
+    ("paste_text", Category::Input, Risk::Medium),
+    ("get_selected_text", Category::Input, Risk::Low),
+    ("type_from_file", Category::Input, Risk::Medium),
+    ("reset_input", Category::Input, Risk::Low),
+    ("reset_input_backend", Category::Input, Risk::Low),
+    ("activate_window", Category::Window, Risk::Low),
+    ("switch_virtual_desktop", Category::Window, Risk::Low),
+    ("set_window_bounds", Category::Window, Risk::Low),
+    ("move_window", Category::Window, Risk::Low),
+    ("resize_window", Category::Window, Risk::Low),
+    ("minimize_window", Category::Window, Risk::Low),
+    ("maximize_window", Category::Window, Risk::Low),
+    ("restore_window", Category::Window, Risk::Low),
+    // Polite, but an app may close without asking to save
+    ("close_window", Category::Window, Risk::Medium),
+    ("set_mouse_acceleration", Category::System, Risk::Low),
+    ("set_keyboard_layout", Category::System, Risk::Low),
+    ("set_brightness", Category::System, Risk::Low),
+    ("set_volume", Category::System, Risk::Low),
+    ("mute", Category::System, Risk::Low),
+    ("unmute", Category::System, Risk::Low),
+    ("stop_recording", Category::System, Risk::Low),
+    ("launch_application", Category::System, Risk::Medium),
+    ("open_url", Category::System, Risk::Medium),
+    ("run_shell_command", Category::Destructive, Risk::High),
+    ("write_file", Category::Destructive, Risk::High),
+    ("save_screenshot", Category::Destructive, Risk::Medium),
+    ("terminate_application", Category::Destructive, Risk::High),
+    ("kill_process", Category::Destructive, Risk::High),
+    // Installs a global input hook and writes everything the user types, passwords included, to disk
+    ("start_recording", Category::Destructive, Risk::High),
+];
+
+/// Category and risk of `tool`; unknown tools are `Uncategorized` with `High` risk.
+pub fn classify(tool: &str) -> (Category, Risk) {
+    TOOL_CATALOG
+        .iter()
+        .find(|(name, _, _)| *name == tool)
+        .map(|(_, category, risk)| (*category, *risk))
+        .unwrap_or((Category::Uncategorized, Risk::High))
+}
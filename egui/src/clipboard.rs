@@ -0,0 +1,110 @@
+// System clipboard access shared by the clipboard-based tools
+use anyhow::anyhow;
+use arboard::Clipboard;
+use image::RgbaImage;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+/// Which kinds of content the clipboard currently holds.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClipboardFormats {
+    pub text: bool,
+    /// A bitmap in a format arboard can decode (PNG, DIB, TIFF depending on the platform).
+    pub image: bool,
+    /// Files copied in a file manager.
+    pub files: bool,
+    pub html: bool,
+}
+
+impl ClipboardFormats {
+    /// Names of the formats present ("text", "image", "files", "html"); empty for an empty clipboard.
+    pub fn names(&self) -> Vec<&'static str> {
+        [("text", self.text), ("image", self.image), ("files", self.files), ("html", self.html)]
+            .into_iter()
+            .filter_map(|(name, present)| present.then_some(name))
+            .collect()
+    }
+}
+
+// Content of a format the clipboard doesn't hold (or can't hand over on this platform) is
+// reported as `None` rather than an error
+fn optional<T>(result: Result<T, arboard::Error>) -> Result<Option<T>, arboard::Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(arboard::Error::ContentNotAvailable | arboard::Error::ClipboardNotSupported | arboard::Error::ConversionFailure) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// A lazily opened clipboard shared by all clients.
+///
+/// The handle is kept open for the server's lifetime: on X11 the process that set the clipboard
+/// has to stay around to serve it, so text set by a tool would vanish with a short-lived handle.
+#[derive(Clone, Default)]
+pub struct ClipboardHandle {
+    clipboard: Arc<Mutex<Option<Clipboard>>>,
+}
+
+impl ClipboardHandle {
+    fn with<R>(&self, f: impl FnOnce(&mut Clipboard) -> Result<R, arboard::Error>) -> anyhow::Result<R> {
+        // Nothing is left half-updated inside arboard on a panic, so a poisoned lock is safe to reuse
+        let mut guard = self.clipboard.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if guard.is_none() {
+            info!("Opening system clipboard.");
+            *guard = Some(Clipboard::new().map_err(|e| anyhow!("Failed to open the clipboard: {}", e))?);
+        }
+        match guard.as_mut() {
+            Some(clipboard) => f(clipboard).map_err(|e| anyhow!("Clipboard access failed: {}", e)),
+            None => Err(anyhow!("Clipboard is not initialized")),
+        }
+    }
+
+    /// The clipboard's text, or `None` when it is empty or holds something other than text.
+    pub fn get_text(&self) -> anyhow::Result<Option<String>> {
+        self.with(|clipboard| match clipboard.get_text() {
+            Ok(text) => Ok(Some(text)),
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(e),
+        })
+    }
+
+    /// The clipboard's image as RGBA pixels, or `None` when it holds no (decodable) image.
+    pub fn get_image(&self) -> anyhow::Result<Option<RgbaImage>> {
+        let data = self.with(|clipboard| optional(clipboard.get_image()))?;
+        match data {
+            Some(data) => RgbaImage::from_raw(data.width as u32, data.height as u32, data.bytes.into_owned())
+                .map(Some)
+                .ok_or_else(|| anyhow!("Clipboard image has {}x{} pixels but a mismatched buffer", data.width, data.height)),
+            None => Ok(None),
+        }
+    }
+
+    /// Paths of the files on the clipboard, or `None` when it holds no file list.
+    pub fn get_files(&self) -> anyhow::Result<Option<Vec<PathBuf>>> {
+        self.with(|clipboard| optional(clipboard.get().file_list()))
+    }
+
+    pub fn get_html(&self) -> anyhow::Result<Option<String>> {
+        self.with(|clipboard| optional(clipboard.get().html()))
+    }
+
+    /// Checks each format by reading it, since arboard has no way to list what is available.
+    /// The image check decodes the image, so this is as slow as reading it.
+    pub fn formats(&self) -> anyhow::Result<ClipboardFormats> {
+        Ok(ClipboardFormats {
+            text: self.get_text()?.is_some(),
+            image: self.get_image()?.is_some(),
+            files: self.get_files()?.is_some_and(|files| !files.is_empty()),
+            html: self.get_html()?.is_some(),
+        })
+    }
+
+    pub fn set_text(&self, text: &str) -> anyhow::Result<()> {
+        self.with(|clipboard| clipboard.set_text(text))
+    }
+
+    pub fn clear(&self) -> anyhow::Result<()> {
+        self.with(|clipboard| clipboard.clear())
+    }
+}
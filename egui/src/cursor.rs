@@ -0,0 +1,81 @@
+// Shape of the system mouse cursor, which screenshots usually leave out
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CursorInfo {
+    /// Standard cursor name ("arrow", "ibeam", "wait", "app_starting", "hand", ...), or
+    /// "custom" for an application-defined cursor.
+    pub cursor: &'static str,
+    /// Whether the cursor is busy ("wait" or "app_starting").
+    pub busy: bool,
+    pub visible: bool,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Reads the current cursor, or `Ok(None)` where the platform doesn't expose its shape.
+pub fn cursor_info() -> anyhow::Result<Option<CursorInfo>> {
+    imp::cursor_info()
+}
+
+// The shared system cursors have fixed handles, so comparing against LoadCursor identifies them
+#[cfg(windows)]
+mod imp {
+    use super::CursorInfo;
+    use anyhow::anyhow;
+    use windows_sys::core::PCWSTR;
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetCursorInfo, LoadCursorW, CURSORINFO, CURSOR_SHOWING, IDC_APPSTARTING, IDC_ARROW, IDC_CROSS, IDC_HAND,
+        IDC_HELP, IDC_IBEAM, IDC_NO, IDC_SIZEALL, IDC_SIZENESW, IDC_SIZENS, IDC_SIZENWSE, IDC_SIZEWE, IDC_UPARROW,
+        IDC_WAIT,
+    };
+
+    const STANDARD_CURSORS: &[(PCWSTR, &str)] = &[
+        (IDC_ARROW, "arrow"),
+        (IDC_IBEAM, "ibeam"),
+        (IDC_WAIT, "wait"),
+        (IDC_APPSTARTING, "app_starting"),
+        (IDC_CROSS, "crosshair"),
+        (IDC_HAND, "hand"),
+        (IDC_HELP, "help"),
+        (IDC_NO, "not_allowed"),
+        (IDC_SIZEALL, "move"),
+        (IDC_SIZENS, "resize_ns"),
+        (IDC_SIZEWE, "resize_ew"),
+        (IDC_SIZENWSE, "resize_nwse"),
+        (IDC_SIZENESW, "resize_nesw"),
+        (IDC_UPARROW, "up_arrow"),
+    ];
+
+    pub fn cursor_info() -> anyhow::Result<Option<CursorInfo>> {
+        // SAFETY: CURSORINFO is plain data; cbSize is set before the call as required
+        let mut info: CURSORINFO = unsafe { std::mem::zeroed() };
+        info.cbSize = std::mem::size_of::<CURSORINFO>() as u32;
+        // SAFETY: info is a valid, sized CURSORINFO
+        if unsafe { GetCursorInfo(&mut info) } == 0 {
+            return Err(anyhow!("GetCursorInfo failed: {}", std::io::Error::last_os_error()));
+        }
+        let cursor = STANDARD_CURSORS
+            .iter()
+            // SAFETY: loading a predefined system cursor by id; the handle is shared and not freed
+            .find(|(id, _)| unsafe { LoadCursorW(std::ptr::null_mut(), *id) } == info.hCursor)
+            .map(|(_, name)| *name)
+            .unwrap_or("custom");
+        Ok(Some(CursorInfo {
+            cursor,
+            busy: matches!(cursor, "wait" | "app_starting"),
+            visible: info.flags & CURSOR_SHOWING != 0,
+            x: info.ptScreenPos.x,
+            y: info.ptScreenPos.y,
+        }))
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use super::CursorInfo;
+
+    pub fn cursor_info() -> anyhow::Result<Option<CursorInfo>> {
+        Ok(None)
+    }
+}
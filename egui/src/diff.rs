@@ -0,0 +1,98 @@
+// Cheap screen-change detection on downscaled luma grids
+use image::RgbaImage;
+use serde::Serialize;
+
+// Side of the square pixel blocks averaged into one grid cell
+const CELL_SIZE: u32 = 8;
+// Average-luma difference (0-255) above which a cell counts as changed; absorbs dithering/noise
+const CELL_THRESHOLD: u8 = 6;
+
+/// A compact fingerprint of a screenshot: the average luma of every `CELL_SIZE` block.
+#[derive(Debug, Clone)]
+pub struct ScreenSignature {
+    pub origin_x: i32,
+    pub origin_y: i32,
+    pub width: u32,
+    pub height: u32,
+    cols: u32,
+    rows: u32,
+    cells: Vec<u8>,
+}
+
+/// Bounding box of the changed area, in absolute desktop coordinates.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ChangedRegion {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// How much of the screen differs between two signatures.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeReport {
+    pub changed: bool,
+    /// Share of grid cells that changed, 0-100.
+    pub changed_percent: f64,
+    pub changed_region: Option<ChangedRegion>,
+}
+
+impl ScreenSignature {
+    /// Builds the signature of an image whose top-left pixel sits at (origin_x, origin_y).
+    pub fn from_image(image: &RgbaImage, origin_x: i32, origin_y: i32) -> Self {
+        let (width, height) = image.dimensions();
+        let cols = width.div_ceil(CELL_SIZE);
+        let rows = height.div_ceil(CELL_SIZE);
+        let mut sums = vec![0u64; (cols * rows) as usize];
+        let mut counts = vec![0u64; (cols * rows) as usize];
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let index = ((y / CELL_SIZE) * cols + x / CELL_SIZE) as usize;
+            // Integer Rec. 601 luma
+            let luma = (299 * pixel[0] as u64 + 587 * pixel[1] as u64 + 114 * pixel[2] as u64) / 1000;
+            sums[index] += luma;
+            counts[index] += 1;
+        }
+        let cells = sums.iter().zip(&counts).map(|(sum, count)| (sum / (*count).max(1)) as u8).collect();
+        Self { origin_x, origin_y, width, height, cols, rows, cells }
+    }
+
+    /// Compares against an earlier signature. Returns `None` when the two can't be compared
+    /// because the captured area changed (resolution or monitor layout change).
+    pub fn compare(&self, earlier: &ScreenSignature) -> Option<ChangeReport> {
+        if (self.origin_x, self.origin_y, self.width, self.height) != (earlier.origin_x, earlier.origin_y, earlier.width, earlier.height) {
+            return None;
+        }
+
+        let mut changed_cells = 0usize;
+        let mut bounds: Option<(u32, u32, u32, u32)> = None; // min col, min row, max col, max row
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                let index = (row * self.cols + col) as usize;
+                if self.cells[index].abs_diff(earlier.cells[index]) <= CELL_THRESHOLD {
+                    continue;
+                }
+                changed_cells += 1;
+                bounds = Some(match bounds {
+                    None => (col, row, col, row),
+                    Some((c0, r0, c1, r1)) => (c0.min(col), r0.min(row), c1.max(col), r1.max(row)),
+                });
+            }
+        }
+
+        let changed_region = bounds.map(|(c0, r0, c1, r1)| {
+            let x = c0 * CELL_SIZE;
+            let y = r0 * CELL_SIZE;
+            ChangedRegion {
+                x: self.origin_x + x as i32,
+                y: self.origin_y + y as i32,
+                width: ((c1 + 1) * CELL_SIZE).min(self.width) - x,
+                height: ((r1 + 1) * CELL_SIZE).min(self.height) - y,
+            }
+        });
+        Some(ChangeReport {
+            changed: changed_cells > 0,
+            changed_percent: changed_cells as f64 * 100.0 / self.cells.len().max(1) as f64,
+            changed_region,
+        })
+    }
+}
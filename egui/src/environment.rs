@@ -0,0 +1,87 @@
+// Build and platform details reported by get_server_environment
+use display_info::DisplayInfo;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Resolution of the primary display, in desktop (scaled) coordinates.
+#[derive(Debug, Clone, Serialize)]
+pub struct PrimaryDisplay {
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerEnvironment {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    /// "win32", "quartz", "x11", "wayland", or "none" when no desktop session was found.
+    pub display_server: &'static str,
+    /// What mouse and keyboard input is synthesized through; on Linux, every compiled enigo
+    /// backend.
+    pub input_backend: String,
+    /// Optional cargo features and whether this build has them.
+    pub features: BTreeMap<&'static str, bool>,
+    /// How OCR runs: the linked libtesseract or the `tesseract` executable.
+    pub ocr_engine: &'static str,
+    pub display_count: usize,
+    pub primary_display: Option<PrimaryDisplay>,
+}
+
+fn display_server() -> &'static str {
+    if cfg!(windows) {
+        "win32"
+    } else if cfg!(target_os = "macos") {
+        "quartz"
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some()
+        || std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t.eq_ignore_ascii_case("wayland"))
+    {
+        "wayland"
+    } else if std::env::var_os("DISPLAY").is_some() {
+        "x11"
+    } else {
+        "none"
+    }
+}
+
+fn input_backend() -> String {
+    if cfg!(windows) {
+        return "SendInput".to_string();
+    }
+    if cfg!(target_os = "macos") {
+        return "CGEvent".to_string();
+    }
+    let backends = [
+        (true, "x11"),
+        (cfg!(feature = "wayland"), "wayland"),
+        (cfg!(feature = "libei"), "libei"),
+    ];
+    backends.iter().filter(|(compiled, _)| *compiled).map(|(_, name)| *name).collect::<Vec<_>>().join("+")
+}
+
+/// Collects the environment. Display details are left empty if displays can't be enumerated.
+pub fn current() -> ServerEnvironment {
+    let displays = DisplayInfo::all().unwrap_or_default();
+    let primary = displays.iter().find(|d| d.is_primary).or_else(|| displays.first());
+    ServerEnvironment {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        display_server: display_server(),
+        input_backend: input_backend(),
+        features: BTreeMap::from([
+            ("battery", cfg!(feature = "battery")),
+            ("leptess", cfg!(feature = "leptess")),
+            ("progress", cfg!(feature = "progress")),
+            ("x11rb", cfg!(feature = "x11rb")),
+            ("wayland", cfg!(feature = "wayland")),
+            ("libei", cfg!(feature = "libei")),
+        ]),
+        ocr_engine: if cfg!(feature = "leptess") { "libtesseract" } else { "tesseract executable" },
+        display_count: displays.len(),
+        primary_display: primary.map(|d| PrimaryDisplay { width: d.width, height: d.height, scale_factor: d.scale_factor }),
+    }
+}
@@ -0,0 +1,54 @@
+// Error categories attached to every failure, so clients can tell an off-screen coordinate from
+// a crashed input backend without parsing messages
+use rmcp::model::{ErrorCode, ErrorData};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// What kind of failure a tool ran into. Reported as `category` in tool error results and in
+/// the `data` of `ErrorData`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// A point or region is off screen, or a monitor index doesn't exist.
+    Coordinate,
+    /// No window, element, file, process, ... matches the request.
+    NotFound,
+    /// The server's configuration doesn't allow it (file sandbox, URL schemes, disabled tools).
+    Permission,
+    /// The target exists but can't be used as asked right now: minimized, hidden, ambiguous,
+    /// already exists, not running.
+    State,
+    /// A size or count limit of the server was exceeded.
+    Limit,
+    /// An assert_* check failed: the desktop isn't in the expected state.
+    Assertion,
+    /// The request itself is malformed or inconsistent.
+    InvalidParams,
+    Timeout,
+    Cancelled,
+    /// The input backend, screen capture or an OS call failed; retrying may not help.
+    Backend,
+}
+
+/// `ErrorData` with the given code and `{"category": ...}` as its data.
+pub fn categorized(code: ErrorCode, category: ErrorCategory, message: impl Into<String>) -> ErrorData {
+    ErrorData::new(code, message.into(), Some(json!({ "category": category })))
+}
+
+/// An internal fault: the input backend, a capture or an OS call failed.
+pub fn backend(message: impl Into<String>) -> ErrorData {
+    categorized(ErrorCode::INTERNAL_ERROR, ErrorCategory::Backend, message)
+}
+
+/// A malformed request, e.g. a missing parameter or an unknown key name.
+pub fn invalid_params(message: impl Into<String>) -> ErrorData {
+    categorized(ErrorCode::INVALID_PARAMS, ErrorCategory::InvalidParams, message)
+}
+
+/// Adds `category` to an error's JSON details object.
+pub fn with_category(mut data: Value, category: ErrorCategory) -> Value {
+    if let Some(object) = data.as_object_mut() {
+        object.insert("category".to_string(), json!(category));
+    }
+    data
+}
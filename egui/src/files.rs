@@ -0,0 +1,245 @@
+// File access for the file tools, confined to the directory named by AIRC_FILE_ROOT
+use crate::errors::ErrorCategory;
+use anyhow::{anyhow, Context};
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::UNIX_EPOCH;
+use tracing::{info, warn};
+
+const DEFAULT_MAX_READ_BYTES: u64 = 1024 * 1024;
+/// Largest file `type_from_file` types. Far below the read limit, since typing runs at a few
+/// hundred characters per second at best.
+pub const MAX_TYPE_FILE_BYTES: u64 = 64 * 1024;
+/// Entries returned by one `list_directory` call; larger directories are truncated.
+pub const MAX_LIST_ENTRIES: usize = 1000;
+
+/// The canonicalized sandbox root, or `None` when `AIRC_FILE_ROOT` is unset or unusable, in
+/// which case the file tools are disabled. Read once on first use.
+pub fn root() -> Option<&'static Path> {
+    static ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+    ROOT.get_or_init(|| {
+        let raw = std::env::var_os("AIRC_FILE_ROOT").filter(|r| !r.is_empty())?;
+        match std::fs::canonicalize(&raw) {
+            Ok(root) if root.is_dir() => {
+                info!("File tools are confined to {:?}", root);
+                Some(root)
+            }
+            Ok(root) => {
+                warn!("AIRC_FILE_ROOT {:?} is not a directory; file tools are disabled.", root);
+                None
+            }
+            Err(e) => {
+                warn!("AIRC_FILE_ROOT {:?} is unusable ({}); file tools are disabled.", raw, e);
+                None
+            }
+        }
+    })
+    .as_deref()
+}
+
+/// Largest file `read_file` returns, from `AIRC_FILE_MAX_READ_BYTES` (default 1 MiB).
+pub fn max_read_bytes() -> u64 {
+    static MAX: OnceLock<u64> = OnceLock::new();
+    *MAX.get_or_init(|| match std::env::var("AIRC_FILE_MAX_READ_BYTES") {
+        Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+            warn!("Ignoring invalid AIRC_FILE_MAX_READ_BYTES value '{}'", raw);
+            DEFAULT_MAX_READ_BYTES
+        }),
+        Err(_) => DEFAULT_MAX_READ_BYTES,
+    })
+}
+
+/// A requested path that resolves outside the sandbox root.
+#[derive(Debug)]
+pub struct OutsideRoot(String);
+
+impl std::fmt::Display for OutsideRoot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Path '{}' resolves outside the allowed directory", self.0)
+    }
+}
+
+impl std::error::Error for OutsideRoot {}
+
+/// Category of a path resolution error: escaping the root is a permission error, anything
+/// else (a missing file or parent directory) means the path wasn't found.
+pub fn error_category(error: &anyhow::Error) -> ErrorCategory {
+    if error.downcast_ref::<OutsideRoot>().is_some() { ErrorCategory::Permission } else { ErrorCategory::NotFound }
+}
+
+fn ensure_inside(root: &Path, canonical: &Path, requested: &str) -> anyhow::Result<()> {
+    if canonical.starts_with(root) {
+        Ok(())
+    } else {
+        Err(OutsideRoot(requested.to_string()).into())
+    }
+}
+
+/// Resolves `requested` (relative to the root) to an existing path inside the root.
+///
+/// The path is canonicalized, so `..` components and symlinks are followed before the
+/// containment check; a symlink pointing out of the root is rejected like a `../` path.
+pub fn resolve_existing(root: &Path, requested: &str) -> anyhow::Result<PathBuf> {
+    let joined = root.join(requested);
+    let canonical = std::fs::canonicalize(&joined).with_context(|| format!("'{}' does not exist", requested))?;
+    ensure_inside(root, &canonical, requested)?;
+    Ok(canonical)
+}
+
+/// Resolves `requested` to a path inside the root that may not exist yet (for writing).
+///
+/// The parent directory must exist and is canonicalized for the containment check. If the
+/// target itself exists (possibly as a symlink) it is canonicalized too, so writing through a
+/// link that points out of the root is rejected.
+pub fn resolve_for_write(root: &Path, requested: &str) -> anyhow::Result<PathBuf> {
+    let relative = Path::new(requested);
+    let file_name = match relative.components().next_back() {
+        Some(Component::Normal(name)) => name.to_owned(),
+        _ => return Err(anyhow!("'{}' does not name a file", requested)),
+    };
+    let parent = root.join(relative.parent().unwrap_or(Path::new("")));
+    let canonical_parent = std::fs::canonicalize(&parent)
+        .with_context(|| format!("Parent directory of '{}' does not exist", requested))?;
+    ensure_inside(root, &canonical_parent, requested)?;
+
+    let target = canonical_parent.join(file_name);
+    if std::fs::symlink_metadata(&target).is_ok() {
+        let canonical = std::fs::canonicalize(&target)
+            .with_context(|| format!("'{}' is a broken symlink", requested))?;
+        ensure_inside(root, &canonical, requested)?;
+        return Ok(canonical);
+    }
+    Ok(target)
+}
+
+/// One entry of a directory listing.
+#[derive(Debug, Clone, Serialize)]
+pub struct DirEntryInfo {
+    pub name: String,
+    pub is_dir: bool,
+    /// Symlinks are reported as-is rather than followed, so a link out of the root doesn't
+    /// reveal anything about its target.
+    pub is_symlink: bool,
+    /// Size in bytes; `None` for directories and symlinks.
+    pub size: Option<u64>,
+    /// Last modification time in milliseconds since the Unix epoch, when the platform reports it.
+    pub modified_ms: Option<u64>,
+}
+
+/// Lists `dir` (an already resolved path), directories first, then by name.
+pub fn list_directory(dir: &Path) -> anyhow::Result<Vec<DirEntryInfo>> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to list {:?}", dir))? {
+        let entry = entry.with_context(|| format!("Failed to list {:?}", dir))?;
+        let Ok(metadata) = entry.path().symlink_metadata() else { continue };
+        let is_symlink = metadata.file_type().is_symlink();
+        entries.push(DirEntryInfo {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            is_dir: metadata.is_dir(),
+            is_symlink,
+            size: metadata.is_file().then_some(metadata.len()),
+            modified_ms: metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64),
+        });
+    }
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A scratch directory with a sandbox root and a sibling outside it, removed on drop
+    struct Scratch {
+        base: PathBuf,
+        root: PathBuf,
+        outside: PathBuf,
+    }
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let base = std::env::temp_dir().join(format!("airc-files-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&base);
+            std::fs::create_dir_all(base.join("root/sub")).unwrap();
+            std::fs::create_dir_all(base.join("outside")).unwrap();
+            std::fs::write(base.join("root/sub/inside.txt"), "inside").unwrap();
+            std::fs::write(base.join("outside/secret.txt"), "secret").unwrap();
+            // Canonical, like root() returns it (the temp dir itself may sit behind a symlink)
+            let base = std::fs::canonicalize(&base).unwrap();
+            Self { root: base.join("root"), outside: base.join("outside"), base }
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.base);
+        }
+    }
+
+    fn is_outside(result: anyhow::Result<PathBuf>) -> bool {
+        result.is_err_and(|e| error_category(&e) == ErrorCategory::Permission)
+    }
+
+    #[test]
+    fn resolves_paths_inside_root() {
+        let scratch = Scratch::new("inside");
+        assert_eq!(resolve_existing(&scratch.root, "sub/inside.txt").unwrap(), scratch.root.join("sub/inside.txt"));
+        assert_eq!(resolve_existing(&scratch.root, "sub/../sub/inside.txt").unwrap(), scratch.root.join("sub/inside.txt"));
+        assert_eq!(resolve_for_write(&scratch.root, "sub/new.txt").unwrap(), scratch.root.join("sub/new.txt"));
+    }
+
+    #[test]
+    fn rejects_parent_escapes() {
+        let scratch = Scratch::new("escape");
+        assert!(is_outside(resolve_existing(&scratch.root, "../outside/secret.txt")));
+        assert!(is_outside(resolve_existing(&scratch.root, "sub/../../outside/secret.txt")));
+        assert!(is_outside(resolve_for_write(&scratch.root, "../outside/new.txt")));
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        let scratch = Scratch::new("absolute");
+        let secret = scratch.outside.join("secret.txt");
+        assert!(is_outside(resolve_existing(&scratch.root, secret.to_str().unwrap())));
+        assert!(is_outside(resolve_for_write(&scratch.root, scratch.outside.join("new.txt").to_str().unwrap())));
+    }
+
+    #[test]
+    fn rejects_bare_parent_component() {
+        let scratch = Scratch::new("dotdot");
+        assert!(is_outside(resolve_existing(&scratch.root, "..")));
+        assert!(resolve_for_write(&scratch.root, "sub/..").is_err());
+        assert!(resolve_for_write(&scratch.root, "..").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlinks_out_of_root() {
+        use std::os::unix::fs::symlink;
+        let scratch = Scratch::new("symlink");
+        symlink(&scratch.outside, scratch.root.join("linked_dir")).unwrap();
+        symlink(scratch.outside.join("secret.txt"), scratch.root.join("linked_file")).unwrap();
+
+        assert!(is_outside(resolve_existing(&scratch.root, "linked_dir/secret.txt")));
+        assert!(is_outside(resolve_for_write(&scratch.root, "linked_dir/new.txt")));
+        assert!(is_outside(resolve_existing(&scratch.root, "linked_file")));
+        assert!(is_outside(resolve_for_write(&scratch.root, "linked_file")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_dangling_symlink() {
+        let scratch = Scratch::new("dangling");
+        std::os::unix::fs::symlink(scratch.outside.join("missing.txt"), scratch.root.join("dangling")).unwrap();
+
+        let existing = resolve_existing(&scratch.root, "dangling");
+        assert!(existing.is_err_and(|e| error_category(&e) == ErrorCategory::NotFound));
+        // Writing would create the file at the link's target, outside the root
+        assert!(resolve_for_write(&scratch.root, "dangling").is_err());
+    }
+}
@@ -0,0 +1,108 @@
+// Startup self-check of input and screen access, also reported by get_capabilities
+use crate::input::{self, InputBackend};
+use anyhow::anyhow;
+use display_info::DisplayInfo;
+use serde::Serialize;
+use std::sync::OnceLock;
+use tracing::{error, info, warn};
+
+/// Outcome of one check: whether the subsystem works and why (or why not).
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub ok: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn from_result(result: anyhow::Result<String>) -> Self {
+        match result {
+            Ok(detail) => Self { ok: true, detail },
+            Err(e) => Self { ok: false, detail: format!("{:#}", e) },
+        }
+    }
+}
+
+/// What works on this host, as far as the server can tell without side effects.
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    pub os: &'static str,
+    /// Mouse/keyboard simulation (the input backend could be created).
+    pub input: Check,
+    /// Monitor enumeration.
+    pub displays: Check,
+    /// Taking a screenshot of the primary monitor.
+    pub capture: Check,
+    pub scancodes_supported: bool,
+    pub dry_run: bool,
+}
+
+impl Capabilities {
+    pub fn all_ok(&self) -> bool {
+        self.input.ok && self.displays.ok && self.capture.ok
+    }
+}
+
+fn check_displays() -> anyhow::Result<String> {
+    let displays = DisplayInfo::all().map_err(|e| anyhow!("Failed to enumerate displays: {}", e))?;
+    if displays.is_empty() {
+        anyhow::bail!("No displays found. Is the server running inside a desktop session?");
+    }
+    Ok(format!("{} display(s) found", displays.len()))
+}
+
+fn check_capture() -> anyhow::Result<String> {
+    let monitors = xcap::Monitor::all().map_err(|e| anyhow!("Failed to list monitors for capture: {}", e))?;
+    let monitor = monitors
+        .iter()
+        .find(|m| m.is_primary().unwrap_or(false))
+        .or_else(|| monitors.first())
+        .ok_or_else(|| anyhow!("No monitor available to capture"))?;
+    let image = monitor.capture_image().map_err(|e| {
+        anyhow!("Screen capture failed: {}. On Wayland or macOS the server may need screen recording permission.", e)
+    })?;
+    Ok(format!("captured {}x{} from the primary monitor", image.width(), image.height()))
+}
+
+/// Runs every check. Creating the input backend here also means the first input tool call
+/// doesn't pay for it.
+pub fn check(input: &InputBackend, dry_run: bool) -> Capabilities {
+    let input_check = input
+        .with(|_| Ok(()))
+        .map(|_| "input backend initialized".to_string())
+        .map_err(|e| anyhow!("{}", e.message));
+    Capabilities {
+        os: std::env::consts::OS,
+        input: Check::from_result(input_check),
+        displays: Check::from_result(check_displays()),
+        capture: Check::from_result(check_capture()),
+        scancodes_supported: input::SCANCODES_SUPPORTED,
+        dry_run,
+    }
+}
+
+/// `AIRC_STRICT_STARTUP=1`: refuse to start when input or capture doesn't work, instead of
+/// serving clients whose first tool call would fail. Read once.
+fn strict_startup() -> bool {
+    static STRICT: OnceLock<bool> = OnceLock::new();
+    *STRICT.get_or_init(|| matches!(std::env::var("AIRC_STRICT_STARTUP").as_deref().map(str::trim), Ok("1") | Ok("true")))
+}
+
+/// Runs the checks at startup and logs a diagnostic for each failure. Errors only in strict mode.
+pub fn startup_check(input: &InputBackend, dry_run: bool) -> anyhow::Result<()> {
+    let capabilities = check(input, dry_run);
+    for (name, result) in [("Input", &capabilities.input), ("Displays", &capabilities.displays), ("Capture", &capabilities.capture)] {
+        if result.ok {
+            info!("Self-check: {} OK ({})", name, result.detail);
+        } else {
+            warn!("Self-check: {} unavailable: {}", name, result.detail);
+        }
+    }
+    if !capabilities.all_ok() {
+        if strict_startup() {
+            error!("Self-check failed and AIRC_STRICT_STARTUP is set; not starting.");
+            anyhow::bail!("Startup self-check failed; see the log above for what is unavailable");
+        }
+        warn!("Starting anyway; tools that need the unavailable features will fail. Set AIRC_STRICT_STARTUP=1 to refuse to start instead.");
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+// Randomized inter-key delays for keyboard_action's `humanize` mode
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::warn;
+
+const DEFAULT_MEAN_MS: f64 = 90.0;
+const DEFAULT_STDDEV_MS: f64 = 35.0;
+// Keeps outliers of the normal distribution from looking like a stuck key or a hang
+const MIN_DELAY_MS: f64 = 15.0;
+const MAX_DELAY_MS: f64 = 1500.0;
+
+/// Mean and standard deviation of the delay between two keystrokes.
+#[derive(Debug, Clone, Copy)]
+pub struct TypingProfile {
+    pub mean_ms: f64,
+    pub stddev_ms: f64,
+}
+
+fn env_ms(var: &str, default: f64) -> f64 {
+    match std::env::var(var) {
+        Ok(raw) => match raw.trim().parse::<f64>() {
+            Ok(ms) if ms.is_finite() && ms >= 0.0 => ms,
+            _ => {
+                warn!("Ignoring invalid {} value '{}'", var, raw);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+impl TypingProfile {
+    /// From `AIRC_HUMANIZE_MEAN_MS` / `AIRC_HUMANIZE_STDDEV_MS` (defaults 90 / 35 ms). Read once.
+    pub fn from_env() -> Self {
+        static PROFILE: OnceLock<TypingProfile> = OnceLock::new();
+        *PROFILE.get_or_init(|| TypingProfile {
+            mean_ms: env_ms("AIRC_HUMANIZE_MEAN_MS", DEFAULT_MEAN_MS),
+            stddev_ms: env_ms("AIRC_HUMANIZE_STDDEV_MS", DEFAULT_STDDEV_MS),
+        })
+    }
+}
+
+// Standard normal sample (Box-Muller)
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen_range(0.0..1.0);
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// The delay to wait before each character of `text` after the first, so the result has one
+/// entry fewer than `text` has characters.
+///
+/// Delays are normally distributed around the profile's mean; after a space there is sometimes
+/// a longer pause (between words), and after sentence punctuation usually one. The same `seed`
+/// always yields the same delays.
+pub fn delays(text: &str, profile: TypingProfile, seed: u64) -> Vec<Duration> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .windows(2)
+        .map(|pair| {
+            let mut ms = profile.mean_ms + profile.stddev_ms * standard_normal(&mut rng);
+            let pause_chance = match pair[0] {
+                ' ' => 0.3,
+                '.' | ',' | '!' | '?' | ';' | ':' | '\n' => 0.6,
+                _ => 0.0,
+            };
+            if pause_chance > 0.0 && rng.gen_bool(pause_chance) {
+                ms += profile.mean_ms * rng.gen_range(1.0..3.0);
+            }
+            Duration::from_millis(ms.clamp(MIN_DELAY_MS, MAX_DELAY_MS) as u64)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: TypingProfile = TypingProfile { mean_ms: DEFAULT_MEAN_MS, stddev_ms: DEFAULT_STDDEV_MS };
+
+    #[test]
+    fn same_seed_same_delays() {
+        let text = "Hello, world. Typing like a person!";
+        assert_eq!(delays(text, PROFILE, 7), delays(text, PROFILE, 7));
+        assert_ne!(delays(text, PROFILE, 7), delays(text, PROFILE, 8));
+        assert_eq!(delays(text, PROFILE, 7).len(), text.chars().count() - 1);
+    }
+
+    #[test]
+    fn delays_stay_within_bounds() {
+        // A wide spread and frequent pauses push samples past both ends before clamping
+        let profile = TypingProfile { mean_ms: 400.0, stddev_ms: 600.0 };
+        let text = "a. b, c! d? ".repeat(500);
+        for delay in delays(&text, profile, 1) {
+            let ms = delay.as_millis() as f64;
+            assert!((MIN_DELAY_MS..=MAX_DELAY_MS).contains(&ms), "{} ms out of bounds", ms);
+        }
+    }
+
+    #[test]
+    fn mean_stays_near_profile() {
+        // No spaces or punctuation, so no pauses: only the normal distribution
+        let text = "x".repeat(20_001);
+        let delays = delays(&text, PROFILE, 42);
+        let mean = delays.iter().map(|d| d.as_millis() as f64).sum::<f64>() / delays.len() as f64;
+        assert!((mean - PROFILE.mean_ms).abs() < 3.0, "mean {} ms, expected about {} ms", mean, PROFILE.mean_ms);
+    }
+}
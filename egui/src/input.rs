@@ -0,0 +1,463 @@
+// Shared enigo input backend used by every mouse/keyboard tool
+use crate::errors;
+use crate::progress;
+use enigo::{Button, Coordinate, Direction, Enigo, Key, Keyboard, Mouse, Settings};
+use rmcp::model::ErrorData;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// A lazily created `Enigo` shared by all tools (and all clients) of the server.
+///
+/// The instance is created on first use so the server can still start (and serve capture
+/// tools) when input simulation is unavailable, and it can be thrown away and rebuilt with
+/// [`InputBackend::reset`] if the platform backend gets wedged.
+#[derive(Clone, Default)]
+pub struct InputBackend {
+    enigo: Arc<Mutex<Option<Enigo>>>,
+}
+
+/// What [`InputBackend::reset`] released before re-creating the backend.
+#[derive(Debug, Default)]
+pub struct ResetReport {
+    pub had_instance: bool,
+    pub released_keys: Vec<String>,
+    pub released_buttons: Vec<String>,
+}
+
+/// Buttons released unconditionally on reset; enigo doesn't track held mouse buttons.
+pub const RELEASE_BUTTONS: [Button; 3] = [Button::Left, Button::Right, Button::Middle];
+/// Modifiers released unconditionally by the `reset_input` tool.
+pub const MODIFIER_KEYS: [Key; 4] = [Key::Control, Key::Shift, Key::Alt, Key::Meta];
+
+/// Modifier of the platform's clipboard shortcuts (Cmd on macOS, Ctrl elsewhere).
+pub const SHORTCUT_MODIFIER: Key = if cfg!(target_os = "macos") { Key::Meta } else { Key::Control };
+
+/// Whether `keyboard_action` can send hardware scancodes here. Windows injects PC set-1
+/// scancodes directly; Linux (X11) takes evdev key codes, which X maps to keycodes by adding 8.
+/// macOS only has layout-independent virtual key codes, not scancodes, so it's unsupported.
+pub const SCANCODES_SUPPORTED: bool = cfg!(any(target_os = "windows", target_os = "linux"));
+
+/// An input that [`InputBackend::hold`] presses and releases.
+#[derive(Debug, Clone, Copy)]
+pub enum HoldTarget {
+    Key(Key),
+    Scancode(u16),
+    Button(Button),
+}
+
+impl HoldTarget {
+    fn send(self, enigo: &mut Enigo, direction: Direction) -> Result<(), ErrorData> {
+        match self {
+            HoldTarget::Key(key) => enigo.key(key, direction),
+            HoldTarget::Scancode(scancode) => enigo.raw(scancode_to_raw(scancode), direction),
+            HoldTarget::Button(button) => enigo.button(button, direction),
+        }
+        .map_err(|e| errors::backend(format!("Failed to {:?} {:?}: {}", direction, self, e)))
+    }
+}
+
+// Releases a held input when dropped, so a hold cut short by a timeout or cancellation doesn't
+// leave it pressed
+struct ReleaseOnDrop<'a> {
+    backend: &'a InputBackend,
+    target: Option<HoldTarget>,
+}
+
+impl ReleaseOnDrop<'_> {
+    fn release(mut self) -> Result<(), ErrorData> {
+        match self.target.take() {
+            Some(target) => self.backend.with(|enigo| target.send(enigo, Direction::Release)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for ReleaseOnDrop<'_> {
+    fn drop(&mut self) {
+        if let Some(target) = self.target.take() {
+            warn!("Hold of {:?} was interrupted; releasing it.", target);
+            if let Err(e) = self.backend.with(|enigo| target.send(enigo, Direction::Release)) {
+                warn!("Failed to release {:?} after an interrupted hold: {}", target, e.message);
+            }
+        }
+    }
+}
+
+/// Parses a key name as accepted by `keyboard_action` ("enter", "ctrl", "f5", "volume_up", or
+/// any single character). Case-insensitive.
+pub fn parse_key(name: &str) -> Option<Key> {
+    let key = match name.to_lowercase().as_str() {
+        "alt" | "altgraph" => Key::Alt, "backspace" => Key::Backspace, "capslock" | "caps_lock" => Key::CapsLock,
+        "control" | "ctrl" => Key::Control, "delete" => Key::Delete, "down" | "downarrow" => Key::DownArrow,
+        "end" => Key::End, "escape" | "esc" => Key::Escape,
+        "f1" => Key::F1, "f2" => Key::F2, "f3" => Key::F3, "f4" => Key::F4, "f5" => Key::F5,
+        "f6" => Key::F6, "f7" => Key::F7, "f8" => Key::F8, "f9" => Key::F9, "f10" => Key::F10,
+        "f11" => Key::F11, "f12" => Key::F12, "home" => Key::Home, "left" | "leftarrow" => Key::LeftArrow,
+        "meta" | "win" | "command" | "super" | "windows" => Key::Meta, "option" => Key::Option,
+        "pagedown" | "page_down" => Key::PageDown, "pageup" | "page_up" => Key::PageUp,
+        "return" | "enter" => Key::Return, "right" | "rightarrow" => Key::RightArrow,
+        "shift" => Key::Shift, "space" => Key::Space, "tab" => Key::Tab, "up" | "uparrow" => Key::UpArrow,
+        s if s.chars().count() == 1 => Key::Unicode(s.chars().next()?),
+        s => return media_key(s),
+    };
+    Some(key)
+}
+
+// Every media/system key name parse_key knows on some platform, for telling "not on this
+// platform" apart from a typo
+const MEDIA_KEY_NAMES: &[&str] = &[
+    "volumeup", "volume_up", "volumedown", "volume_down", "volumemute", "volume_mute",
+    "playpause", "play_pause", "nexttrack", "next_track", "prevtrack", "prev_track", "mediastop", "media_stop",
+    "printscreen", "print_screen", "insert", "pause", "scrolllock", "scroll_lock", "micmute", "mic_mute",
+    "sleep", "browserback", "browser_back", "browserforward", "browser_forward", "browserrefresh", "browser_refresh",
+    "browserhome", "browser_home", "brightnessup", "brightness_up", "brightnessdown", "brightness_down", "eject",
+];
+
+// Media and system keys, limited to the platforms enigo can send each one on
+fn media_key(name: &str) -> Option<Key> {
+    let key = match name {
+        "volumeup" | "volume_up" => Key::VolumeUp,
+        "volumedown" | "volume_down" => Key::VolumeDown,
+        "volumemute" | "volume_mute" => Key::VolumeMute,
+        "playpause" | "play_pause" => Key::MediaPlayPause,
+        "nexttrack" | "next_track" => Key::MediaNextTrack,
+        "prevtrack" | "prev_track" => Key::MediaPrevTrack,
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        "mediastop" | "media_stop" => Key::MediaStop,
+        // VK_PRINT is a different, rarely mapped key on Windows; Print Screen is VK_SNAPSHOT
+        #[cfg(target_os = "windows")]
+        "printscreen" | "print_screen" => Key::Snapshot,
+        #[cfg(target_os = "linux")]
+        "printscreen" | "print_screen" => Key::Print,
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        "insert" => Key::Insert,
+        #[cfg(any(target_os = "windows", target_os = "linux"))]
+        "pause" => Key::Pause,
+        #[cfg(target_os = "linux")]
+        "scrolllock" | "scroll_lock" => Key::ScrollLock,
+        #[cfg(target_os = "linux")]
+        "micmute" | "mic_mute" => Key::MicMute,
+        #[cfg(target_os = "windows")]
+        "sleep" => Key::Sleep,
+        #[cfg(target_os = "windows")]
+        "browserback" | "browser_back" => Key::BrowserBack,
+        #[cfg(target_os = "windows")]
+        "browserforward" | "browser_forward" => Key::BrowserForward,
+        #[cfg(target_os = "windows")]
+        "browserrefresh" | "browser_refresh" => Key::BrowserRefresh,
+        #[cfg(target_os = "windows")]
+        "browserhome" | "browser_home" => Key::BrowserHome,
+        #[cfg(target_os = "macos")]
+        "brightnessup" | "brightness_up" => Key::BrightnessUp,
+        #[cfg(target_os = "macos")]
+        "brightnessdown" | "brightness_down" => Key::BrightnessDown,
+        #[cfg(target_os = "macos")]
+        "eject" => Key::Eject,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// The invalid-params message for a key name [`parse_key`] rejected: a media/system key this
+/// platform can't send, or a name it doesn't know at all.
+pub fn key_error(name: &str) -> String {
+    if MEDIA_KEY_NAMES.contains(&name.to_lowercase().as_str()) {
+        format!("Key '{}' can't be sent on this platform ({}).", name, std::env::consts::OS)
+    } else {
+        format!("Unsupported key specified: '{}'.", name)
+    }
+}
+
+/// Parses a mouse button name as accepted by `mouse_action` ("left", "scroll_up", ...).
+/// Case-insensitive.
+pub fn parse_button(name: &str) -> Option<Button> {
+    let button = match name.to_lowercase().as_str() {
+        "left" => Button::Left, "right" => Button::Right, "middle" => Button::Middle,
+        "back" => Button::Back, "forward" => Button::Forward,
+        "scrollup" | "scroll_up" => Button::ScrollUp,
+        "scrolldown" | "scroll_down" => Button::ScrollDown,
+        "scrollleft" | "scroll_left" => Button::ScrollLeft,
+        "scrollright" | "scroll_right" => Button::ScrollRight,
+        _ => return None,
+    };
+    Some(button)
+}
+
+/// The `enigo::Settings` every backend instance is created with, from the environment. Read
+/// once; `reset_input_backend` reuses them.
+///
+/// - `AIRC_ENIGO_LINUX_DELAY_MS` (X11 only): delay enigo adds between X events, default 12.
+///   Raise it for remote or slow X servers that drop events.
+/// - `AIRC_ENIGO_X11_DISPLAY` (Linux): X display to connect to instead of `$DISPLAY`.
+/// - `AIRC_ENIGO_WAYLAND_DISPLAY` (Linux, `wayland` feature): Wayland display instead of
+///   `$WAYLAND_DISPLAY`.
+/// - `AIRC_ENIGO_RELEASE_KEYS_WHEN_DROPPED` (all platforms): release keys still held when the
+///   backend is dropped, e.g. on `reset_input_backend`. Default true.
+///
+/// Which Linux backends exist (X11, Wayland, libei) is chosen at build time with the `x11rb`,
+/// `wayland` and `libei` cargo features; enigo connects to every compiled one it can.
+pub fn settings() -> Settings {
+    static SETTINGS: OnceLock<Settings> = OnceLock::new();
+    SETTINGS
+        .get_or_init(|| {
+            let defaults = Settings::default();
+            let non_empty = |var: &str| std::env::var(var).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+            let linux_delay = match non_empty("AIRC_ENIGO_LINUX_DELAY_MS").map(|raw| raw.parse::<u32>().map_err(|_| raw)) {
+                Some(Ok(delay)) => delay,
+                Some(Err(raw)) => {
+                    warn!("Ignoring invalid AIRC_ENIGO_LINUX_DELAY_MS value '{}'", raw);
+                    defaults.linux_delay
+                }
+                None => defaults.linux_delay,
+            };
+            let release_keys_when_dropped = match non_empty("AIRC_ENIGO_RELEASE_KEYS_WHEN_DROPPED").as_deref() {
+                Some("1") | Some("true") => true,
+                Some("0") | Some("false") => false,
+                Some(raw) => {
+                    warn!("Ignoring invalid AIRC_ENIGO_RELEASE_KEYS_WHEN_DROPPED value '{}'", raw);
+                    defaults.release_keys_when_dropped
+                }
+                None => defaults.release_keys_when_dropped,
+            };
+            Settings {
+                linux_delay,
+                x11_display: non_empty("AIRC_ENIGO_X11_DISPLAY").or(defaults.x11_display.clone()),
+                wayland_display: non_empty("AIRC_ENIGO_WAYLAND_DISPLAY").or(defaults.wayland_display.clone()),
+                release_keys_when_dropped,
+                ..defaults
+            }
+        })
+        .clone()
+}
+
+// Releases every key, raw key and button `enigo` may be holding. Failures are logged and
+// skipped since the instance is about to be dropped
+fn release_all(enigo: &mut Enigo) -> ResetReport {
+    let mut report = ResetReport { had_instance: true, ..ResetReport::default() };
+    let (held_keys, held_raw) = enigo.held();
+    for key in held_keys {
+        match enigo.key(key, Direction::Release) {
+            Ok(()) => report.released_keys.push(format!("{:?}", key)),
+            Err(e) => warn!("Failed to release key {:?} during reset: {}", key, e),
+        }
+    }
+    for code in held_raw {
+        match enigo.raw(code, Direction::Release) {
+            Ok(()) => report.released_keys.push(format!("Raw({})", code)),
+            Err(e) => warn!("Failed to release raw key {} during reset: {}", code, e),
+        }
+    }
+    for button in RELEASE_BUTTONS {
+        match enigo.button(button, Direction::Release) {
+            Ok(()) => report.released_buttons.push(format!("{:?}", button)),
+            Err(e) => warn!("Failed to release button {:?} during reset: {}", button, e),
+        }
+    }
+    report
+}
+
+// The platform raw keycode enigo expects for a scancode
+fn scancode_to_raw(scancode: u16) -> u16 {
+    if cfg!(target_os = "linux") { scancode.saturating_add(8) } else { scancode }
+}
+
+impl InputBackend {
+    /// Runs `f` with exclusive access to the shared `Enigo`, creating it first if needed.
+    pub fn with<R>(&self, f: impl FnOnce(&mut Enigo) -> Result<R, ErrorData>) -> Result<R, ErrorData> {
+        let mut guard = self.enigo.lock().unwrap_or_else(|poisoned| {
+            // A tool panicked mid-input; its Enigo may still hold keys or buttons, so release
+            // them and start over with a fresh instance instead of failing every later call
+            warn!("Input backend lock was poisoned by a panicking tool; re-initializing the backend.");
+            self.enigo.clear_poison();
+            let mut guard = poisoned.into_inner();
+            if let Some(mut old) = guard.take() {
+                let report = release_all(&mut old);
+                info!("Released inputs held by the panicked tool: {:?}", report);
+            }
+            guard
+        });
+        if guard.is_none() {
+            info!("Initializing input backend.");
+            *guard = Some(
+                Enigo::new(&settings())
+                    .map_err(|e| errors::backend(format!("Failed to initialize input backend: {}", e)))?,
+            );
+        }
+        match guard.as_mut() {
+            Some(enigo) => f(enigo),
+            None => Err(errors::backend("Input backend is not initialized")),
+        }
+    }
+
+    /// Types `text`, either in one `text()` call or, with a non-zero `per_char_delay_ms`, one
+    /// character at a time for targets that drop fast input (terminals, remote desktops, games).
+    ///
+    /// The backend lock is only held per character, so other tools aren't blocked for the
+    /// whole duration of a slow type.
+    pub async fn type_text(&self, text: &str, per_char_delay_ms: Option<u64>) -> Result<(), ErrorData> {
+        let delay = per_char_delay_ms.filter(|ms| *ms > 0).map(Duration::from_millis);
+        let Some(delay) = delay else {
+            return self.with(|enigo| enigo.text(text).map_err(|e| errors::backend(e.to_string())));
+        };
+
+        let delays = vec![delay; text.chars().count().saturating_sub(1)];
+        self.type_with_delays(text, &delays).await
+    }
+
+    /// Types `text` one character at a time, sleeping `delays[i - 1]` before character `i`
+    /// (missing delays count as zero). Like [`InputBackend::type_text`], the backend lock is
+    /// only held per character.
+    pub async fn type_with_delays(&self, text: &str, delays: &[Duration]) -> Result<(), ErrorData> {
+        let total = text.chars().count() as u32;
+        for (i, c) in text.chars().enumerate() {
+            if let Some(delay) = i.checked_sub(1).and_then(|prev| delays.get(prev)) {
+                tokio::time::sleep(*delay).await;
+            }
+            self.with(|enigo| {
+                enigo.key(Key::Unicode(c), Direction::Click)
+                    .map_err(|e| errors::backend(format!("Failed to type {:?}: {}", c, e)))
+            })?;
+            progress::report(i as u32 + 1, Some(total)).await;
+        }
+        Ok(())
+    }
+
+    /// Sends a hardware scancode, bypassing the keyboard layout. Check [`SCANCODES_SUPPORTED`]
+    /// first; on Windows, codes above 0x7F are sent as extended keys (e.g. `0x48 | 0x80` for
+    /// the arrow-key Up rather than numpad 8).
+    pub fn scancode(&self, scancode: u16, direction: Direction) -> Result<(), ErrorData> {
+        self.with(|enigo| {
+            enigo.raw(scancode_to_raw(scancode), direction)
+                .map_err(|e| errors::backend(format!("Failed to send scancode {:#x}: {}", scancode, e)))
+        })
+    }
+
+    /// Sends a shortcut such as Ctrl+V: holds `modifier`, clicks `key`, then releases the
+    /// modifier even if the click failed so it can't be left stuck.
+    pub fn shortcut(&self, modifier: Key, key: Key) -> Result<(), ErrorData> {
+        self.with(|enigo| {
+            enigo.key(modifier, Direction::Press)
+                .map_err(|e| errors::backend(format!("Failed to press {:?}: {}", modifier, e)))?;
+            let clicked = enigo.key(key, Direction::Click);
+            let released = enigo.key(modifier, Direction::Release);
+            clicked.map_err(|e| errors::backend(format!("Failed to press {:?}: {}", key, e)))?;
+            released.map_err(|e| errors::backend(format!("Failed to release {:?}: {}", modifier, e)))
+        })
+    }
+
+    /// Moves the cursor one pixel and back, a no-op that still produces real input events.
+    pub fn nudge(&self) -> Result<(), ErrorData> {
+        self.with(|enigo| {
+            let (x, y) = enigo.location().map_err(|e| errors::backend(e.to_string()))?;
+            // Step left at the right screen edge, where a step right would be clamped
+            let (width, _) = enigo.main_display().map_err(|e| errors::backend(e.to_string()))?;
+            let step = if x + 1 >= width { x - 1 } else { x + 1 };
+            for (to_x, to_y) in [(step, y), (x, y)] {
+                enigo.move_mouse(to_x, to_y, Coordinate::Abs)
+                    .map_err(|e| errors::backend(format!("Failed to move mouse to ({}, {}): {}", to_x, to_y, e)))?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Presses `target`, keeps it down for `duration` and releases it. The backend lock is only
+    /// taken for the press and the release, so other tools (and other clients) aren't blocked
+    /// for the length of the hold; callers track the held input in their session so it's
+    /// released if the client disconnects. The release is also sent if the call times out or is
+    /// cancelled. Returns how long the input was actually held.
+    pub async fn hold(&self, target: HoldTarget, duration: Duration) -> Result<Duration, ErrorData> {
+        self.hold_at(target, None, duration).await.map(|(held, _)| held)
+    }
+
+    /// Like [`InputBackend::hold`], but first moves the mouse to absolute `position` (if given)
+    /// in the same locked step as the press. Also returns the cursor position after the release.
+    pub async fn hold_at(&self, target: HoldTarget, position: Option<(i32, i32)>, duration: Duration) -> Result<(Duration, (i32, i32)), ErrorData> {
+        self.with(|enigo| {
+            if let Some((x, y)) = position {
+                enigo.move_mouse(x, y, Coordinate::Abs)
+                    .map_err(|e| errors::backend(format!("Failed to move mouse to ({}, {}): {}", x, y, e)))?;
+            }
+            target.send(enigo, Direction::Press)
+        })?;
+        let pressed = std::time::Instant::now();
+        let release = ReleaseOnDrop { backend: self, target: Some(target) };
+        tokio::time::sleep(duration).await;
+        release.release()?;
+        let held = pressed.elapsed();
+        let location = self.with(|enigo| enigo.location().map_err(|e| errors::backend(e.to_string())))?;
+        Ok((held, location))
+    }
+
+    /// Releases the given keys, scancodes and buttons, returning the names of those released.
+    /// Failures are logged and skipped so one stuck input doesn't keep the others held.
+    pub fn release(&self, keys: &[Key], scancodes: &[u16], buttons: &[Button]) -> Result<Vec<String>, ErrorData> {
+        self.with(|enigo| {
+            let mut released = Vec::new();
+            for key in keys {
+                match enigo.key(*key, Direction::Release) {
+                    Ok(()) => released.push(format!("{:?}", key)),
+                    Err(e) => warn!("Failed to release key {:?}: {}", key, e),
+                }
+            }
+            for scancode in scancodes {
+                match enigo.raw(scancode_to_raw(*scancode), Direction::Release) {
+                    Ok(()) => released.push(format!("Scancode({:#x})", scancode)),
+                    Err(e) => warn!("Failed to release scancode {:#x}: {}", scancode, e),
+                }
+            }
+            for button in buttons {
+                match enigo.button(*button, Direction::Release) {
+                    Ok(()) => released.push(format!("{:?}", button)),
+                    Err(e) => warn!("Failed to release button {:?}: {}", button, e),
+                }
+            }
+            Ok(released)
+        })
+    }
+
+    /// Releases every held input, drops the current `Enigo` and creates a fresh one.
+    ///
+    /// Release failures are logged and skipped since the old backend is presumed broken.
+    /// If the new instance can't be created the backend is left empty and the next tool
+    /// call retries the initialization.
+    pub fn reset(&self) -> anyhow::Result<ResetReport> {
+        // A poisoned lock is exactly the kind of state a reset is meant to recover from
+        let mut guard = self.enigo.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.enigo.clear_poison();
+        let report = match guard.take() {
+            Some(mut old) => release_all(&mut old),
+            None => ResetReport::default(),
+        };
+
+        let fresh = Enigo::new(&settings())
+            .map_err(|e| anyhow::anyhow!("Failed to re-initialize input backend: {}", e))?;
+        *guard = Some(fresh);
+        info!("Input backend re-initialized: {:?}", report);
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_recovers_from_poisoned_lock() {
+        let backend = InputBackend::default();
+        let poisoner = backend.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.enigo.lock().unwrap();
+            panic!("tool panicked while holding the input backend");
+        })
+        .join();
+        assert!(backend.enigo.is_poisoned());
+
+        // Creating the Enigo needs a display, which a test run may not have; either way the lock
+        // must be usable again instead of failing every later call
+        let first = backend.with(|_| Ok(()));
+        assert!(!backend.enigo.is_poisoned());
+        let second = backend.with(|_| Ok(()));
+        assert_eq!(first.is_ok(), second.is_ok());
+    }
+}
@@ -0,0 +1,157 @@
+// Active keyboard layout, which decides the characters that synthesized key presses produce
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyboardLayout {
+    /// Windows: keyboard layout id (KLID) of the foreground window, e.g. "00000409" for US
+    /// English. Linux (X11): XKB layout, e.g. "us" or "de". macOS: input source id, e.g.
+    /// "com.apple.keylayout.US". Pass it back to set_keyboard_layout to restore it.
+    pub id: String,
+    /// XKB variant, e.g. "nodeadkeys". Only reported on Linux.
+    pub variant: Option<String>,
+}
+
+/// The layout keystrokes are currently interpreted with, or `Ok(None)` when this platform or
+/// session doesn't expose it.
+pub fn keyboard_layout() -> anyhow::Result<Option<KeyboardLayout>> {
+    imp::keyboard_layout()
+}
+
+/// Switches to the layout `id` (plus an XKB `variant` on Linux). On Windows only the foreground
+/// window switches, like the language bar does. Returns `Ok(false)` when unsupported.
+pub fn set_keyboard_layout(id: &str, variant: Option<&str>) -> anyhow::Result<bool> {
+    imp::set_keyboard_layout(id, variant)
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::KeyboardLayout;
+    use anyhow::anyhow;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetKeyboardLayout, LoadKeyboardLayoutW, KLF_ACTIVATE};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetForegroundWindow, GetWindowThreadProcessId, PostMessageW, WM_INPUTLANGCHANGEREQUEST,
+    };
+
+    pub fn keyboard_layout() -> anyhow::Result<Option<KeyboardLayout>> {
+        // Layouts are per thread; the one that matters is the foreground window's
+        // SAFETY: no arguments; returns null when no window is focused
+        let hwnd = unsafe { GetForegroundWindow() };
+        // SAFETY: a null process id pointer is allowed; thread 0 (no window) means this thread
+        let thread = if hwnd.is_null() { 0 } else { unsafe { GetWindowThreadProcessId(hwnd, std::ptr::null_mut()) } };
+        // SAFETY: plain value argument
+        let hkl = unsafe { GetKeyboardLayout(thread) } as usize;
+        if hkl == 0 {
+            return Err(anyhow!("GetKeyboardLayout failed"));
+        }
+        // The low word is the language id; for the standard layouts the KLID is that id
+        // zero-padded (layouts with a different device id, e.g. Dvorak, report their language)
+        Ok(Some(KeyboardLayout { id: format!("{:08X}", hkl & 0xFFFF), variant: None }))
+    }
+
+    pub fn set_keyboard_layout(id: &str, _variant: Option<&str>) -> anyhow::Result<bool> {
+        if id.len() != 8 || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow!("'{}' is not a keyboard layout id; expected 8 hex digits such as '00000409'", id));
+        }
+        let klid: Vec<u16> = id.encode_utf16().chain(std::iter::once(0)).collect();
+        // SAFETY: klid is a null-terminated UTF-16 string that outlives the call
+        let hkl = unsafe { LoadKeyboardLayoutW(klid.as_ptr(), KLF_ACTIVATE) };
+        if hkl.is_null() {
+            return Err(anyhow!("LoadKeyboardLayout({}) failed: {}", id, std::io::Error::last_os_error()));
+        }
+        // SAFETY: no arguments; returns null when no window is focused
+        let hwnd = unsafe { GetForegroundWindow() };
+        if !hwnd.is_null() {
+            // SAFETY: plain value arguments; the message carries the layout handle by value
+            if unsafe { PostMessageW(hwnd, WM_INPUTLANGCHANGEREQUEST, 0, hkl as isize) } == 0 {
+                return Err(anyhow!("PostMessage(WM_INPUTLANGCHANGEREQUEST) failed: {}", std::io::Error::last_os_error()));
+            }
+        }
+        Ok(true)
+    }
+}
+
+// Through `setxkbmap`, which only reaches X11 clients; Wayland compositors own the layout
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::KeyboardLayout;
+    use anyhow::{anyhow, Context};
+    use std::process::Command;
+
+    fn setxkbmap(args: &[&str]) -> anyhow::Result<String> {
+        let output = Command::new("setxkbmap").args(args).output().context("Failed to run 'setxkbmap'. Is it installed?")?;
+        if !output.status.success() {
+            return Err(anyhow!("'setxkbmap {}' failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn x11_session() -> bool {
+        std::env::var_os("DISPLAY").is_some() && std::env::var_os("WAYLAND_DISPLAY").is_none()
+    }
+
+    pub fn keyboard_layout() -> anyhow::Result<Option<KeyboardLayout>> {
+        if !x11_session() {
+            return Ok(None);
+        }
+        // Lines like "layout:     us,de" and "variant:    ,nodeadkeys"; the first group is active
+        // unless the user switched groups, which setxkbmap doesn't report
+        let output = setxkbmap(&["-query"])?;
+        let field = |name: &str| {
+            output
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.trim_start().strip_prefix(':'))
+                .and_then(|value| value.trim().split(',').next())
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        let id = field("layout").ok_or_else(|| anyhow!("'setxkbmap -query' output has no layout line"))?;
+        Ok(Some(KeyboardLayout { id, variant: field("variant") }))
+    }
+
+    pub fn set_keyboard_layout(id: &str, variant: Option<&str>) -> anyhow::Result<bool> {
+        if !x11_session() {
+            return Ok(false);
+        }
+        // An empty variant resets it, so switching from "de" with "nodeadkeys" to "us" doesn't keep it
+        setxkbmap(&["-layout", id, "-variant", variant.unwrap_or("")])?;
+        Ok(true)
+    }
+}
+
+// The current input source is readable from the HIToolbox preferences; selecting one needs
+// the Text Input Sources API
+#[cfg(target_os = "macos")]
+mod imp {
+    use super::KeyboardLayout;
+    use std::process::Command;
+
+    pub fn keyboard_layout() -> anyhow::Result<Option<KeyboardLayout>> {
+        let output = Command::new("defaults")
+            .args(["read", "com.apple.HIToolbox", "AppleCurrentKeyboardLayoutInputSourceID"])
+            .output();
+        Ok(match output {
+            Ok(output) if output.status.success() => {
+                let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                (!id.is_empty()).then_some(KeyboardLayout { id, variant: None })
+            }
+            _ => None,
+        })
+    }
+
+    pub fn set_keyboard_layout(_id: &str, _variant: Option<&str>) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+mod imp {
+    use super::KeyboardLayout;
+
+    pub fn keyboard_layout() -> anyhow::Result<Option<KeyboardLayout>> {
+        Ok(None)
+    }
+
+    pub fn set_keyboard_layout(_id: &str, _variant: Option<&str>) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+}
@@ -4,9 +4,9 @@ use anyhow::{anyhow, Context}; use base64::Engine;
 use display_info::DisplayInfo;
 // *** Using enigo now ***
 use enigo::{
-    Button, Coordinate,
+    Axis, Button, Coordinate,
     Direction, // For key press/release/click actions
-    Enigo, Key, Keyboard, Mouse, Settings, // Note: enigo::Mouse/Keyboard traits
+    Key, Keyboard, Mouse, // Note: enigo::Mouse/Keyboard traits
 };
 // *** Added for wait tool ***
 use tokio::time::{sleep, Duration};
@@ -17,44 +17,126 @@ use rmcp::schemars; // For deriving schema
 use rmcp::handler::server::ServerHandler;
 // use rmcp::transport::stdio;
 use tokio::net::TcpListener; // Added TcpListener
+use tokio_util::sync::CancellationToken;
 // Import types needed for tool return values and ServerHandler impl
 use rmcp::model::{
     // *** Added ErrorCode, ErrorData ***
-    CallToolResult, Content, ErrorCode, ErrorData, Implementation, ProtocolVersion, ServerCapabilities,
-    ServerInfo,
+    AnnotateAble, CallToolRequestParam, CallToolResult, Content, ErrorCode, ErrorData, Implementation,
+    ListResourcesResult, ListToolsResult, PaginatedRequestParam, ProtocolVersion, RawResource,
+    ReadResourceRequestParam, ReadResourceResult, ResourceContents, ServerCapabilities, ServerInfo,
 };
+use rmcp::handler::server::tool::ToolCallContext;
+use rmcp::service::{serve_server_with_ct, RequestContext, RoleServer};
 // Added serve_server back
 // *** Ensure tool_box is imported ***
 // Removed rmcp::tool_box from here as it's applied via attribute macro
-use rmcp::{serve_server, tool}; // Keep McpError for type alias if needed internally
+use rmcp::tool; // Keep McpError for type alias if needed internally
 
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::io::Cursor;
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::process::Command;
-use tracing::{info, warn}; // Added warn
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+use tracing::{debug, info, info_span, warn, Instrument}; // Added warn
 use tracing_subscriber::EnvFilter; // Import EnvFilter for tracing setup
+use errors::ErrorCategory;
+
+mod accessibility;
+mod action_log;
+mod annotate;
+mod apps;
+mod brightness;
+mod browser;
+mod burst;
+mod captures;
+mod catalog;
+mod clipboard;
+mod cursor;
+mod diff;
+mod environment;
+mod errors;
+mod files;
+mod health;
+mod humanize;
+mod input;
+mod keyboard_layout;
+mod metrics;
+mod ocr;
+mod pointer;
+mod power;
+mod presence;
+mod processes;
+mod progress;
+mod recorder;
+mod replay;
+mod screen;
+mod scroll;
+mod session;
+mod timeouts;
+mod tray;
+mod volume;
+mod window;
+mod ws;
+
+/// Error code returned when an `assert_*` tool's check does not hold (JSON-RPC server error range).
+const ASSERTION_FAILED: ErrorCode = ErrorCode(-32000);
+// How long paste_text waits after Ctrl+V before putting the previous clipboard text back
+const PASTE_RESTORE_DELAY_MS: u64 = 300;
+// smooth_scroll sends one wheel event per frame at roughly 60 Hz
+const SMOOTH_SCROLL_STEP_MS: u64 = 16;
+const MAX_SCROLL_DURATION_MS: u64 = 10_000;
+// Upper bound on the wheel notches of one execute_openai_scroll, per axis
+const MAX_OPENAI_SCROLL_NOTCHES: i32 = 50;
+// Longest hold_key / hold_mouse_button press
+const MAX_HOLD_MS: u64 = 60_000;
+const MAX_KEY_SEQUENCE_STEPS: usize = 200;
+const DEFAULT_KEY_SEQUENCE_DELAY_MS: u64 = 50;
+// Processes returned by list_processes unless the caller asks for more
+const DEFAULT_PROCESS_LIMIT: usize = 100;
+// JPEG quality of the capture tools when the caller doesn't pick one
+const DEFAULT_JPEG_QUALITY: u8 = 85;
 
 // --- Tool Parameter Struct Definitions ---
 
-// Structs for existing custom tools
-#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
-struct GetScreenDetailsParams {
-    #[schemars(description = "Ignored dummy field.")]
-    _dummy: Option<bool>,
-}
-#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
-struct GetMousePositionParams {
-     #[schemars(description = "Ignored dummy field.")]
-    _dummy: Option<bool>,
+/// Parameters of tools that take none. Serializes to `{"type": "object", "properties": {},
+/// "required": [], "additionalProperties": false}`: schemars omits empty `properties`, which
+/// strict function-calling schemas reject, and the closed object tells clients to send no
+/// arguments. Deserialization itself is lenient, so a stray argument that slips through is
+/// dropped instead of failing the call.
+#[derive(Deserialize, Debug)]
+struct NoParams {}
+
+impl schemars::JsonSchema for NoParams {
+    fn schema_name() -> String {
+        "NoParams".to_string()
+    }
+
+    fn json_schema(_gen: &mut schemars::r#gen::SchemaGenerator) -> schemars::schema::Schema {
+        let mut schema = schemars::schema::SchemaObject {
+            instance_type: Some(schemars::schema::InstanceType::Object.into()),
+            object: Some(Box::new(schemars::schema::ObjectValidation {
+                additional_properties: Some(Box::new(false.into())),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        schema.extensions.insert("properties".to_string(), json!({}));
+        schema.extensions.insert("required".to_string(), json!([]));
+        schema.into()
+    }
 }
+
+
+// Structs for existing custom tools
 #[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
 struct MoveMouseParams {
     #[schemars(description = "Target X coordinate.")]
     x: i32,
     #[schemars(description = "Target Y coordinate.")]
     y: i32,
-    #[schemars(description = "Type of mouse move ('Absolute'/'Abs' for absolute coordinates, 'Relative'/'Rel' for relative offset).")]
+    #[schemars(description = "Type of mouse move ('Absolute'/'Abs' for absolute coordinates, 'Relative'/'Rel' for relative offset). Relative moves are scaled by OS pointer acceleration; turn it off with set_mouse_acceleration for predictable offsets.")]
     coordinate: String
 }
 #[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
@@ -68,10 +150,107 @@ struct MouseClickParams { // Renamed to avoid conflict, used by 'mouse_action' t
 struct KeyboardActionParams {
     #[schemars(description = "Optional: Text to type using enigo's text input method.")]
     text: Option<String>,
-    #[schemars(description = "Optional: A specific key to press/release/click (e.g., 'a', 'Enter', 'Control', 'Shift', 'Alt', 'F5', 'PageDown'). Takes precedence over 'text' if both are provided.")]
+    #[schemars(description = "Optional: A specific key to press/release/click (e.g., 'a', 'Enter', 'Control', 'Shift', 'Alt', 'F5', 'PageDown'). Media and system keys: 'volume_up', 'volume_down', 'volume_mute', 'play_pause', 'next_track', 'prev_track' everywhere; 'media_stop', 'print_screen', 'insert', 'pause' on Windows and Linux; 'scroll_lock', 'mic_mute' on Linux; 'sleep', 'browser_back', 'browser_forward', 'browser_refresh', 'browser_home' on Windows; 'brightness_up', 'brightness_down', 'eject' on macOS. Takes precedence over 'text' if both are provided.")]
     key: Option<String>,
     #[schemars(description = "Action for the specified 'key': 'Click' (default), 'Press', 'Release'. Ignored if 'text' is used.", default)]
     key_action: Option<String>,
+    #[schemars(description = "Optional: milliseconds to wait between characters when typing 'text'. Use for targets that drop fast input (terminals, remote desktops, games). Omit or 0 to type at full speed.")]
+    per_char_delay_ms: Option<u64>,
+    #[schemars(description = "Optional: type 'text' like a person, one character at a time with randomized delays (normally distributed, with occasional longer pauses after spaces and punctuation). Slower; use only when constant-speed typing is a problem. Overrides per_char_delay_ms.", default)]
+    humanize: Option<bool>,
+    #[schemars(description = "Optional: seed for the humanized delays; the same seed reproduces the same timing. A random seed is used (and returned) if omitted.", default)]
+    humanize_seed: Option<u64>,
+    #[schemars(description = "Optional: a hardware scancode to send with 'key_action' instead of a layout-dependent key, e.g. for games that read physical keys (WASD). Windows: PC set-1 scancode, add 0x80 for extended keys (0xC8 = arrow Up). Linux (X11): evdev key code (KEY_W = 17). Not supported on macOS (returns supported: false). Takes precedence over 'key' and 'text'.")]
+    scancode: Option<u16>,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct HoldKeyParams {
+    #[schemars(description = "Key to hold, by the same names as keyboard_action (e.g. 'w', 'Shift', 'Space', 'Up').")]
+    key: Option<String>,
+    #[schemars(description = "Optional: a hardware scancode to hold instead of 'key' (see keyboard_action). Not supported on macOS.")]
+    scancode: Option<u16>,
+    #[schemars(description = "How long to keep the key down, in milliseconds (at most 60000).")]
+    duration_ms: u64,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct KeyStep {
+    #[schemars(description = "Key to click, by the same names as keyboard_action (e.g. 'Up', 'Enter', '7').")]
+    key: String,
+    #[schemars(description = "Optional: milliseconds to wait after this key before the next one. Defaults to 50.")]
+    delay_ms: Option<u64>,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct KeySequenceParams {
+    #[schemars(description = "Keys to click one after another, each fully pressed and released before the next.")]
+    keys: Vec<KeyStep>,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct SmoothScrollParams {
+    #[schemars(description = "Optional absolute X coordinate to move the mouse to first (the scroll goes to the element under the cursor). Requires y.")]
+    x: Option<i32>,
+    #[schemars(description = "Optional absolute Y coordinate to move the mouse to first. Requires x.")]
+    y: Option<i32>,
+    #[schemars(description = "Direction the content moves: 'down' (default), 'up', 'left' or 'right'.", default)]
+    direction: Option<String>,
+    #[schemars(description = "Total distance to scroll in pixels, roughly (one wheel notch is about 100 px).")]
+    total_pixels: u32,
+    #[schemars(description = "How long the glide takes in milliseconds. Defaults to 400, at most 10000.", default)]
+    duration_ms: Option<u64>,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct HoldMouseButtonParams {
+    #[schemars(description = "Button to hold: 'Left', 'Right', 'Middle', 'Back' or 'Forward'. Case-insensitive.")]
+    button: String,
+    #[schemars(description = "Optional absolute X coordinate to move to before pressing. Requires y.")]
+    x: Option<i32>,
+    #[schemars(description = "Optional absolute Y coordinate to move to before pressing. Requires x.")]
+    y: Option<i32>,
+    #[schemars(description = "How long to keep the button down, in milliseconds (at most 60000).")]
+    duration_ms: u64,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct ClickTrayIconParams {
+    #[schemars(description = "Tooltip text of the icon (as listed by list_tray_icons). Case-insensitive; an exact match wins over a substring match.")]
+    name: String,
+    #[schemars(description = "Button to click with: 'Left' (default), 'Right' (usually opens the app's tray menu) or 'Middle'.", default)]
+    button: Option<String>,
+    #[schemars(description = "Double-click instead of a single click (many tray apps open their main window this way). Defaults to false.", default)]
+    double: Option<bool>,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct PasteTextParams {
+    #[schemars(description = "Text to paste into the focused window.")]
+    text: String,
+    #[schemars(description = "Put the previous clipboard text back after pasting. Only text can be restored; other clipboard contents (images, files) are lost. Defaults to true.", default)]
+    restore_clipboard: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct GetSelectedTextParams {
+    #[schemars(description = "Optional: milliseconds to wait for the application to put the selection on the clipboard. Defaults to 500.")]
+    wait_ms: Option<u64>,
+    #[schemars(description = "Put the previous clipboard text back afterwards. Only text can be restored; other clipboard contents (images, files) are lost. Defaults to true.", default)]
+    restore_clipboard: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct GetClipboardParams {
+    #[schemars(description = "Also return the clipboard image, if it holds one, as PNG image content (subject to the server's capture pixel cap). Defaults to false.", default)]
+    include_image: Option<bool>,
+    #[schemars(description = "Also return the HTML version of copied rich text, if any. Defaults to false.", default)]
+    include_html: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct TypeFromFileParams {
+    #[schemars(description = "File path relative to the server's allowed file directory. Must be UTF-8 text.")]
+    path: String,
+    #[schemars(description = "Optional: milliseconds to wait between characters, as in keyboard_action. Omit or 0 to type at full speed.")]
+    per_char_delay_ms: Option<u64>,
+    #[schemars(description = "Optional: type with humanized, randomized delays, as in keyboard_action. Overrides per_char_delay_ms.", default)]
+    humanize: Option<bool>,
+    #[schemars(description = "Optional: seed for the humanized delays; a random seed is used (and returned) if omitted.", default)]
+    humanize_seed: Option<u64>,
 }
 #[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
 struct CaptureScreenParams {
@@ -83,11 +262,110 @@ struct CaptureScreenParams {
     width: Option<u32>,
     #[schemars(description = "Optional height for regional capture.")]
     height: Option<u32>,
+    #[schemars(description = "Optional: also return a copy of the screenshot with numbered boxes drawn over detected on-screen elements, plus an 'elements' list mapping each box id to its label and coordinates (set-of-marks). Refer to elements by box id, e.g. 'click box 3'. Defaults to false.", default)]
+    annotate: Option<bool>,
+    #[schemars(description = "Optional: return a single JSON item with the image as 'base64_data' (and 'annotated_base64_data'), as older clients expect, instead of MCP image content plus a JSON metadata item. Defaults to false.", default)]
+    legacy_json: Option<bool>,
+    #[schemars(description = "Optional: downscale so the image is at most this many pixels wide, keeping the aspect ratio. The returned 'scale' includes it. Defaults to full size.", default)]
+    max_width: Option<u32>,
+    #[schemars(description = "Optional: 'png' (default) or 'jpeg'. JPEG is much smaller for photos and busy screens but loses detail in small text.", default)]
+    format: Option<String>,
+    #[schemars(description = "Optional JPEG quality from 1 to 100. Defaults to 85.", default)]
+    quality: Option<u8>,
 }
 #[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
 struct RunShellParams {
     command: String,
     args: Vec<String>,
+    #[schemars(description = "Optional environment variables to set for the command, e.g. {\"DISPLAY\": \":1\"}. Values are redacted from the action log.")]
+    env: Option<apps::EnvVars>,
+    #[schemars(description = "Optional: start from an empty environment instead of inheriting the server's (which may hold secrets); only 'env' is set. On Windows many programs need at least SystemRoot. Defaults to false.", default)]
+    env_clear: Option<bool>,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct ReadFileParams {
+    #[schemars(description = "File path relative to the server's allowed file directory.")]
+    path: String,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct ListDirectoryParams {
+    #[schemars(description = "Optional directory path relative to the server's allowed file directory. Defaults to that directory itself.")]
+    path: Option<String>,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct WriteFileParams {
+    #[schemars(description = "File path relative to the server's allowed file directory. The parent directory must exist.")]
+    path: String,
+    #[schemars(description = "Content to write.")]
+    content: String,
+    #[schemars(description = "Optional: 'text' (default) to write 'content' as UTF-8, or 'base64' to decode it as binary data first.")]
+    encoding: Option<String>,
+    #[schemars(description = "Optional: append to the file instead of replacing it. Defaults to false.")]
+    append: Option<bool>,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct SaveScreenshotParams {
+    #[schemars(description = "File path relative to the server's allowed file directory. The parent directory must exist.")]
+    path: String,
+    #[schemars(description = "Optional X coordinate (absolute desktop pixels) of the region's top-left corner. Give x, y, width and height together; omit all four for the whole primary screen.")]
+    x: Option<i32>,
+    #[schemars(description = "Optional Y coordinate of the region's top-left corner.")]
+    y: Option<i32>,
+    #[schemars(description = "Optional region width.")]
+    width: Option<u32>,
+    #[schemars(description = "Optional region height.")]
+    height: Option<u32>,
+    #[schemars(description = "Optional: 'png' or 'jpeg'. Defaults to the path's extension, else png.")]
+    format: Option<String>,
+    #[schemars(description = "Optional JPEG quality from 1 to 100. Defaults to 85.")]
+    quality: Option<u8>,
+    #[schemars(description = "Optional: replace the file if it already exists. Defaults to false.")]
+    overwrite: Option<bool>,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct LaunchApplicationParams {
+    #[schemars(description = "Program to start: an executable name on PATH or a full path.")]
+    program: String,
+    #[schemars(description = "Optional command line arguments.")]
+    args: Option<Vec<String>>,
+    #[schemars(description = "Optional working directory for the program.")]
+    working_dir: Option<String>,
+    #[schemars(description = "Optional: milliseconds to watch the process for an immediate crash before reporting it as running. Defaults to 1000.")]
+    startup_wait_ms: Option<u64>,
+    #[schemars(description = "Optional environment variables to set for the program, e.g. {\"DISPLAY\": \":1\"}. Values are redacted from the action log.")]
+    env: Option<apps::EnvVars>,
+    #[schemars(description = "Optional: start from an empty environment instead of inheriting the server's (which may hold secrets); only 'env' is set. On Windows many programs need at least SystemRoot. Defaults to false.", default)]
+    env_clear: Option<bool>,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct OpenUrlParams {
+    #[schemars(description = "Full URL to open, e.g. https://example.com or file:///C:/report.html. Allowed schemes are set by AIRC_OPEN_URL_SCHEMES (default http, https, file).")]
+    url: String,
+    #[schemars(description = "Optional: milliseconds to wait for the browser to come up before reading the foreground window. Defaults to 1500.")]
+    wait_ms: Option<u64>,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct TerminateApplicationParams {
+    #[schemars(description = "PID returned by launch_application.")]
+    pid: u32,
+    #[schemars(description = "Optional: kill immediately instead of first asking the application to close (which lets it save state). Defaults to false.")]
+    force: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct ListProcessesParams {
+    #[schemars(description = "Optional: only processes whose name contains this text (case-insensitive), e.g. 'chrome'.")]
+    name_filter: Option<String>,
+    #[schemars(description = "Optional: maximum number of processes to return, busiest (by CPU, then memory) first. Defaults to 100.")]
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct KillProcessParams {
+    #[schemars(description = "PID of the process, as list_processes reports it.")]
+    pid: u32,
+    #[schemars(description = "Optional: kill immediately instead of first asking the process to close (which lets it save state). Defaults to false.")]
+    force: Option<bool>,
 }
 
 // --- Structs for NEW OpenAI Action Tools ---
@@ -124,6 +402,12 @@ struct OpenAIKeyPressParams {
 struct OpenAITypeParams {
      #[schemars(description = "The text string to type.")]
     text: String,
+    #[schemars(description = "Optional: milliseconds to wait between characters. Omit or 0 to type at full speed.")]
+    per_char_delay_ms: Option<u64>,
+    #[schemars(description = "Optional: type with humanized, randomized delays, as in keyboard_action. Overrides per_char_delay_ms.", default)]
+    humanize: Option<bool>,
+    #[schemars(description = "Optional: seed for the humanized delays; a random seed is used (and returned) if omitted.", default)]
+    humanize_seed: Option<u64>,
 }
 
 #[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
@@ -132,82 +416,953 @@ struct OpenAIWaitParams {
     duration_ms: Option<u64>,
 }
 
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct TranslateCoordinatesParams {
+    #[schemars(description = "'to_global' (default): (x, y) is a pixel in a screenshot of monitor 'monitor_index'; returns desktop coordinates for move_mouse. 'to_local': (x, y) are desktop coordinates; returns the monitor and the pixel in its screenshot.", default)]
+    direction: Option<String>,
+    #[schemars(description = "Index of the monitor in get_all_screens' list. Required for 'to_global'; optional for 'to_local', where it defaults to the monitor containing the point.", default)]
+    monitor_index: Option<usize>,
+    #[schemars(description = "X coordinate to translate.")]
+    x: f64,
+    #[schemars(description = "Y coordinate to translate.")]
+    y: f64,
+}
+
 #[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
 struct FindWindowParams {
     #[schemars(description = "The title (or part of the title) of the window to find. Case-insensitive search.")]
     title_query: String,
 }
 
-// --- Tool Provider Implementation ---
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct GetBrightnessParams {
+    #[schemars(description = "Optional index of one adjustable display, as listed by get_brightness. Defaults to all of them.")]
+    monitor: Option<usize>,
+}
 
-#[derive(Clone)] // Clone is required by ServerHandler
-struct DesktopToolProvider;
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct SetBrightnessParams {
+    #[schemars(description = "Brightness from 0 (dimmest) to 100.")]
+    level: u8,
+    #[schemars(description = "Optional index of the display to change, as listed by get_brightness. Defaults to all of them.")]
+    monitor: Option<usize>,
+}
 
-// *** First impl block: Contains the tool definitions ***
-#[tool(tool_box)]// Apply tool_box here as well
-impl DesktopToolProvider {
-    // --- Existing Custom Tools (Unchanged) ---
-    #[tool(name = "get_screen_details", description = "Gets the primary screen resolution (width and height).")]
-    async fn get_screen_details(
-        &self,
-        #[tool(aggr)] _params: GetScreenDetailsParams // Use dummy struct with aggr
-    ) -> Result<CallToolResult, ErrorData> {
-        info!("Received request to get screen details.");
-        let display_infos = DisplayInfo::all()
-            .map_err(|e| anyhow!(e).context("display_info::DisplayInfo::all() failed"))
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct SetVolumeParams {
+    #[schemars(description = "Output volume level from 0 (silent) to 100.")]
+    level: u8,
+}
 
-        let mut screens = vec![];
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct AnnotatedPoint {
+    #[schemars(description = "Absolute X coordinate to mark.")]
+    x: i32,
+    #[schemars(description = "Absolute Y coordinate to mark.")]
+    y: i32,
+}
 
-        for screen in display_infos.iter() {
-            screens.push(
-                json!({
-                    "screen_id": screen.id,
-                    "name": screen.name,
-                    "width": screen.width,
-                    "height": screen.height,
-                    "scale_factor": screen.scale_factor,
-                    "x": screen.x,
-                    "y": screen.y
-                })
-            );
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct AnnotatedRect {
+    #[schemars(description = "Absolute X coordinate of the rectangle's left edge.")]
+    x: i32,
+    #[schemars(description = "Absolute Y coordinate of the rectangle's top edge.")]
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct CaptureScreenAnnotatedParams {
+    #[schemars(description = "Points to mark with numbered crosshairs, e.g. where you intend to click. Numbered from 1 in the order given.")]
+    points: Vec<AnnotatedPoint>,
+    #[schemars(description = "Optional rectangles to outline, e.g. a window or element's bounds.")]
+    rects: Option<Vec<AnnotatedRect>>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct ElementAtPointParams {
+    #[schemars(description = "Absolute X coordinate of the point.")]
+    x: i32,
+    #[schemars(description = "Absolute Y coordinate of the point.")]
+    y: i32,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct AccessibilityTreeParams {
+    #[schemars(description = "Part of the title of the window to walk (case-insensitive). The first match wins. Defaults to the focused window.")]
+    title_substring: Option<String>,
+    #[schemars(description = "Exact window id as returned by find_window. Takes precedence over title_substring.")]
+    window_id: Option<u32>,
+    #[schemars(description = "How many levels below the window to include (default 6, at most 15).", default)]
+    max_depth: Option<u32>,
+    #[schemars(description = "Maximum number of elements to return (default 300, at most 2000). The result says whether the tree was truncated.", default)]
+    max_elements: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct ClickElementParams {
+    #[schemars(description = "Accessible name of the element (its caption or label), case-insensitive. An exact match wins over elements whose name merely contains it.")]
+    name: String,
+    #[schemars(description = "Optional role to narrow the search, as reported by get_accessibility_tree: 'button', 'check_box', 'edit', 'menu_item', 'list_item', 'tab_item', 'hyperlink', ...", default)]
+    role: Option<String>,
+    #[schemars(description = "Part of the title of the window to search (case-insensitive). Defaults to the focused window.")]
+    title_substring: Option<String>,
+    #[schemars(description = "Exact window id as returned by find_window. Takes precedence over title_substring.")]
+    window_id: Option<u32>,
+    #[schemars(description = "Which match to click (0-based) when several elements match, as listed in the 'candidates' of an earlier ambiguous call.", default)]
+    index: Option<usize>,
+    #[schemars(description = "Button to click with: 'Left' (default), 'Right' or 'Middle'.", default)]
+    button: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct AssertPixelParams {
+    #[schemars(description = "Absolute X coordinate of the pixel to check.")]
+    x: i32,
+    #[schemars(description = "Absolute Y coordinate of the pixel to check.")]
+    y: i32,
+    #[schemars(description = "Expected color as '#RRGGBB'.")]
+    expected_hex: String,
+    #[schemars(description = "Optional maximum per-channel difference (0-255) still counted as a match. Defaults to 0 (exact match).", default)]
+    tolerance: Option<u8>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct WaitForPixelParams {
+    #[schemars(description = "Absolute X coordinate of the pixel to watch.")]
+    x: i32,
+    #[schemars(description = "Absolute Y coordinate of the pixel to watch.")]
+    y: i32,
+    #[schemars(description = "Color to wait for as '#RRGGBB'.")]
+    hex: String,
+    #[schemars(description = "Optional maximum per-channel difference (0-255) still counted as a match. Defaults to 0 (exact match).", default)]
+    tolerance: Option<u8>,
+    #[schemars(description = "Optional maximum time to wait in milliseconds. Defaults to 10000.")]
+    timeout_ms: Option<u64>,
+    #[schemars(description = "Optional delay between checks in milliseconds. Defaults to 100.")]
+    poll_interval_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct WaitForWindowParams {
+    #[schemars(description = "Text the window title must contain (case-insensitive).")]
+    title_substring: String,
+    #[schemars(description = "Optional: also wait until the window is the foreground window. Defaults to false.")]
+    foreground: Option<bool>,
+    #[schemars(description = "Optional maximum time to wait in milliseconds. Defaults to 10000.")]
+    timeout_ms: Option<u64>,
+    #[schemars(description = "Optional delay between checks in milliseconds. Defaults to 250.")]
+    poll_interval_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct ScreenChangedSinceParams {
+    #[schemars(description = "Optional token returned by a previous screen_changed_since call to compare against. Omit on the first call to just record a baseline.")]
+    since_token: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct CaptureBurstParams {
+    #[schemars(description = "Number of frames to capture, 2-50.")]
+    count: u32,
+    #[schemars(description = "Milliseconds between frames (at least 20). count x interval_ms may not exceed 60000.")]
+    interval_ms: u64,
+    #[schemars(description = "Optional X coordinate of the top-left corner of the region to capture. Region requires x, y, width and height; omit all four for the primary screen. A small region keeps the result small.")]
+    x: Option<i32>,
+    #[schemars(description = "Optional Y coordinate of the top-left corner of the region to capture.")]
+    y: Option<i32>,
+    #[schemars(description = "Optional width of the region to capture.")]
+    width: Option<u32>,
+    #[schemars(description = "Optional height of the region to capture.")]
+    height: Option<u32>,
+    #[schemars(description = "Optional: 'gif' (default) for one animated GIF, or 'frames' for an array of PNGs.", default)]
+    format: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct AssertTextParams {
+    #[schemars(description = "Text that must appear on screen.")]
+    expected_text: String,
+    #[schemars(description = "Optional X coordinate of the top-left corner of the region to OCR. Requires y, width and height.")]
+    x: Option<i32>,
+    #[schemars(description = "Optional Y coordinate of the top-left corner of the region to OCR.")]
+    y: Option<i32>,
+    #[schemars(description = "Optional width of the region to OCR.")]
+    width: Option<u32>,
+    #[schemars(description = "Optional height of the region to OCR.")]
+    height: Option<u32>,
+    #[schemars(description = "Whether the match is case-sensitive. Defaults to false.", default)]
+    case_sensitive: Option<bool>,
+    #[schemars(description = "Optional tesseract language code. Defaults to 'eng'.", default)]
+    lang: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct SwitchVirtualDesktopParams {
+    #[schemars(description = "Index of the desktop to switch to, as returned by list_virtual_desktops (starting at 0).")]
+    index: u32,
+}
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct ActivateWindowParams {
+    #[schemars(description = "Part of the title of the window to activate (case-insensitive). The first match wins.")]
+    title_substring: Option<String>,
+    #[schemars(description = "Exact window id as returned by find_window. Takes precedence over title_substring.")]
+    window_id: Option<u32>,
+    #[schemars(description = "Optional: downscale so the image is at most this many pixels wide, keeping the aspect ratio. The returned 'scale' includes it. Defaults to full size.", default)]
+    max_width: Option<u32>,
+    #[schemars(description = "Optional: 'png' (default) or 'jpeg'. JPEG is much smaller for photos and busy windows but loses detail in small text.", default)]
+    format: Option<String>,
+    #[schemars(description = "Optional JPEG quality from 1 to 100. Defaults to 85.", default)]
+    quality: Option<u8>,
+    #[schemars(description = "Optional: return a single JSON item with the image as 'base64_data', as older clients expect, instead of MCP image content plus a JSON metadata item. Defaults to false.", default)]
+    legacy_json: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct SetWindowBoundsParams {
+    #[schemars(description = "Part of the title of the window (case-insensitive). The first match wins.")]
+    title_substring: Option<String>,
+    #[schemars(description = "Exact window id as returned by find_window. Takes precedence over title_substring.")]
+    window_id: Option<u32>,
+    #[schemars(description = "New absolute X coordinate of the window's top-left corner.")]
+    x: i32,
+    #[schemars(description = "New absolute Y coordinate of the window's top-left corner.")]
+    y: i32,
+    #[schemars(description = "New width in pixels.")]
+    width: u32,
+    #[schemars(description = "New height in pixels.")]
+    height: u32,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct MoveWindowParams {
+    #[schemars(description = "Part of the title of the window (case-insensitive). The first match wins.")]
+    title_substring: Option<String>,
+    #[schemars(description = "Exact window id as returned by find_window. Takes precedence over title_substring.")]
+    window_id: Option<u32>,
+    #[schemars(description = "New absolute X coordinate of the window's top-left corner.")]
+    x: i32,
+    #[schemars(description = "New absolute Y coordinate of the window's top-left corner.")]
+    y: i32,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct ResizeWindowParams {
+    #[schemars(description = "Part of the title of the window (case-insensitive). The first match wins.")]
+    title_substring: Option<String>,
+    #[schemars(description = "Exact window id as returned by find_window. Takes precedence over title_substring.")]
+    window_id: Option<u32>,
+    #[schemars(description = "New width in pixels. The top-left corner stays where it is.")]
+    width: u32,
+    #[schemars(description = "New height in pixels.")]
+    height: u32,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct WindowTargetParams {
+    #[schemars(description = "Part of the title of the window (case-insensitive). The first match wins.")]
+    title_substring: Option<String>,
+    #[schemars(description = "Exact window id as returned by find_window. Takes precedence over title_substring.")]
+    window_id: Option<u32>,
+    #[schemars(description = "Optional: downscale so the image is at most this many pixels wide, keeping the aspect ratio. The returned 'scale' includes it. Defaults to full size.", default)]
+    max_width: Option<u32>,
+    #[schemars(description = "Optional: 'png' (default) or 'jpeg'. JPEG is much smaller for photos and busy windows but loses detail in small text.", default)]
+    format: Option<String>,
+    #[schemars(description = "Optional JPEG quality from 1 to 100. Defaults to 85.", default)]
+    quality: Option<u8>,
+    #[schemars(description = "Optional: return a single JSON item with the image as 'base64_data', as older clients expect, instead of MCP image content plus a JSON metadata item. Defaults to false.", default)]
+    legacy_json: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct CaptureWindowParams {
+    #[schemars(description = "Part of the title of the window to capture (case-insensitive). If several windows match, the first is captured.")]
+    title_substring: Option<String>,
+    #[schemars(description = "Exact window id as returned by find_window. Takes precedence over title_substring.")]
+    window_id: Option<u32>,
+    #[schemars(description = "Optional: downscale so the image is at most this many pixels wide, keeping the aspect ratio. The returned 'scale' includes it. Defaults to full size.", default)]
+    max_width: Option<u32>,
+    #[schemars(description = "Optional: 'png' (default) or 'jpeg'. JPEG is much smaller for photos and busy windows but loses detail in small text.", default)]
+    format: Option<String>,
+    #[schemars(description = "Optional JPEG quality from 1 to 100. Defaults to 85.", default)]
+    quality: Option<u8>,
+    #[schemars(description = "Optional: return a single JSON item with the image as 'base64_data', as older clients expect, instead of MCP image content plus a JSON metadata item. Defaults to false.", default)]
+    legacy_json: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct OcrScreenParams {
+    #[schemars(description = "Optional X coordinate of the top-left corner of the region to read. Region requires x, y, width and height; omit all four for the primary screen.")]
+    x: Option<i32>,
+    #[schemars(description = "Optional Y coordinate of the top-left corner of the region to read.")]
+    y: Option<i32>,
+    #[schemars(description = "Optional width of the region to read.")]
+    width: Option<u32>,
+    #[schemars(description = "Optional height of the region to read.")]
+    height: Option<u32>,
+    #[schemars(description = "Optional tesseract language code(s), e.g. 'eng', 'deu' or 'eng+fra'. Defaults to 'eng'.")]
+    lang: Option<String>,
+    #[schemars(description = "Optional minimum word confidence (0-100) to include. Defaults to 0 (all words).")]
+    min_confidence: Option<f32>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct FindTextOnScreenParams {
+    #[schemars(description = "The text to look for, e.g. 'Submit' or 'Save As'. May span several words on one line.")]
+    query: String,
+    #[schemars(description = "Optional: match case exactly. Defaults to false.")]
+    case_sensitive: Option<bool>,
+    #[schemars(description = "Optional: only match whole words, so 'Save' doesn't match 'Unsaved'. Defaults to false.")]
+    whole_word: Option<bool>,
+    #[schemars(description = "Optional tesseract language code(s). Defaults to 'eng'.")]
+    lang: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct ContextMenuSelectParams {
+    #[schemars(description = "X coordinate (absolute desktop pixels) to right-click.")]
+    x: i32,
+    #[schemars(description = "Y coordinate (absolute desktop pixels) to right-click.")]
+    y: i32,
+    #[schemars(description = "Text of the menu item to click, e.g. 'Copy' or 'Open with'.")]
+    item: String,
+    #[schemars(description = "Optional: match case exactly. Defaults to false.")]
+    case_sensitive: Option<bool>,
+    #[schemars(description = "Optional: milliseconds to wait for the menu to appear. Defaults to 2000.")]
+    wait_ms: Option<u64>,
+    #[schemars(description = "Optional tesseract language code(s). Defaults to 'eng'.")]
+    lang: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct SetMouseAccelerationParams {
+    #[schemars(description = "Whether OS pointer acceleration ('Enhance pointer precision' on Windows) should be on.")]
+    enabled: bool,
+    #[schemars(description = "Optional pointer speed 1-20 (10 is the Windows default). Windows only; ignored elsewhere.")]
+    speed: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct SetKeyboardLayoutParams {
+    #[schemars(description = "Layout to switch to, as get_keyboard_layout reports it: a KLID such as '00000409' (US) or '00000407' (German) on Windows, an XKB layout such as 'us' or 'de' on Linux.")]
+    id: String,
+    #[schemars(description = "Optional XKB variant, e.g. 'nodeadkeys'. Linux only; ignored elsewhere.")]
+    variant: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Serialize, schemars::JsonSchema)]
+struct MoveMouseRelativeToWindowParams {
+    #[schemars(description = "Part of the title of the window to use as the anchor (case-insensitive). Omit to use the currently focused window.")]
+    title_substring: Option<String>,
+    #[schemars(description = "Horizontal offset in pixels from the window's left edge.")]
+    dx: i32,
+    #[schemars(description = "Vertical offset in pixels from the window's top edge.")]
+    dy: i32,
+}
+
+/// Reports a recoverable failure the model can act on (off-screen coordinates, no matching
+/// window, ...) as a tool result with `is_error: true`. `ErrorData` is reserved for genuine
+/// internal faults and malformed requests.
+fn tool_error(category: ErrorCategory, message: impl Into<String>, details: Option<serde_json::Value>) -> Result<CallToolResult, ErrorData> {
+    let message = message.into();
+    warn!("Tool error ({:?}): {}", category, message);
+    let mut body = json!({ "status": "error", "category": category, "message": message });
+    if let Some(details) = details {
+        body["details"] = details;
+    }
+    Ok(CallToolResult::error(vec![Content::json(body)
+        .map_err(|e| anyhow!(e).context("Failed to serialize tool error"))
+        .map_err(|e| errors::backend(e.to_string()))?
+    ]))
+}
+
+/// The message a tool panicked with; `panic!` payloads are a `&str` or a formatted `String`.
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map_or_else(|| "unknown panic payload".to_string(), |s| s.to_string()),
+    }
+}
+
+/// Runs a tool call on its own task, turning a timeout, a client cancellation or a panic into
+/// a result for the caller.
+///
+/// Handlers make blocking calls (enigo, captures) that a plain `timeout` couldn't interrupt;
+/// on a separate task the caller still gets its timeout result while the stuck call finishes
+/// (or doesn't) in the background.
+async fn run_tool_task(
+    name: &str,
+    limit: Duration,
+    cancelled: CancellationToken,
+    call: impl Future<Output = Result<CallToolResult, ErrorData>> + Send + 'static,
+) -> Result<CallToolResult, ErrorData> {
+    let started = Instant::now();
+    let mut call = tokio::spawn(call.in_current_span());
+    tokio::select! {
+        finished = tokio::time::timeout(limit, &mut call) => match finished {
+            Ok(Ok(result)) => result,
+            // The panic stays inside the tool's task, so the server and other clients carry on;
+            // the input backend recovers its lock on the next use
+            Ok(Err(join_error)) if join_error.is_panic() => {
+                let message = panic_message(join_error.into_panic());
+                warn!("Tool '{}' panicked: {}", name, message);
+                Err(errors::backend(format!("Tool '{}' panicked: {}. The server is still running; the call can be retried.", name, message)))
+            }
+            Ok(Err(join_error)) => Err(errors::backend(format!("Tool '{}' failed unexpectedly: {}", name, join_error))),
+            Err(_) => {
+                // Stops the handler at its next await point; a blocking call runs to completion first
+                call.abort();
+                tool_error(
+                    ErrorCategory::Timeout,
+                    format!("Tool '{}' timed out after {} ms. The desktop may be busy; try again or use a simpler action.", name, limit.as_millis()),
+                    Some(json!({ "timeout_ms": limit.as_millis() as u64 })),
+                )
+            }
+        },
+        _ = cancelled.cancelled() => {
+            // Same as a timeout: waits and capture loops stop at their next await point, and
+            // held keys or buttons are still released when an interrupted hold is dropped
+            call.abort();
+            let elapsed_ms = started.elapsed().as_millis() as u64;
+            info!("Tool '{}' cancelled by the client after {} ms.", name, elapsed_ms);
+            Ok(CallToolResult::error(vec![Content::json(json!({
+                "status": "cancelled",
+                "category": ErrorCategory::Cancelled,
+                "message": format!("Tool '{}' was cancelled by the client.", name),
+                "elapsed_ms": elapsed_ms,
+            }))
+                .map_err(|e| anyhow!(e).context("Failed to serialize cancellation result"))
+                .map_err(|e| errors::backend(e.to_string()))?
+            ]))
         }
+    }
+}
 
-        Ok(CallToolResult::success(
-            vec![
-                Content::json(screens)
-                    .map_err(|e| anyhow!(e).context("Failed to serialize screen details to JSON"))
-                    .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
-            ]
-        ))
+// The `format` option of the capture tools; PNG unless asked otherwise
+fn capture_format(name: Option<&str>) -> Result<screen::SaveFormat, ErrorData> {
+    match name {
+        Some(name) => screen::SaveFormat::parse(name)
+            .ok_or_else(|| errors::invalid_params(format!("Unsupported format '{}'. Use 'png' or 'jpeg'.", name))),
+        None => Ok(screen::SaveFormat::Png),
     }
+}
 
-    #[tool(name = "find_window", description = "Finds the first non-minimized window whose title contains the given query string (case-insensitive) and returns its details.")]
-    async fn find_window(
-        &self,
-        #[tool(aggr)] params: FindWindowParams
-    ) -> Result<CallToolResult, ErrorData> {
-        info!("Executing find window with query: '{}'", params.title_query);
+// The Computer Use model names keys like browsers do ("ArrowUp", "CMD"); map the ones
+// parse_key doesn't know to its names
+fn openai_key_name(name: &str) -> &str {
+    match name.to_lowercase().as_str() {
+        "arrowup" => "up",
+        "arrowdown" => "down",
+        "arrowleft" => "left",
+        "arrowright" => "right",
+        "cmd" => "command",
+        _ => name,
+    }
+}
 
-        let windows = xcap::Window::all()
-            .context("Failed to get window list")
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
+/// The window under a click, for the `target_window` of click results. Best effort: lets the
+/// caller see a click that landed on the wrong window without a screenshot.
+fn target_window_at(x: i32, y: i32) -> Option<window::ActiveWindow> {
+    match window::window_at(x, y) {
+        Ok(found) => found,
+        Err(e) => {
+            warn!("Could not look up the window at ({}, {}): {:#}", x, y, e);
+            None
+        }
+    }
+}
 
-        let query_lower = params.title_query.to_lowercase();
+/// The error result for a capture rejected by the server's pixel cap.
+fn oversized_error(oversized: screen::Oversized) -> Result<CallToolResult, ErrorData> {
+    tool_error(ErrorCategory::Limit, format!("{}. Capture a smaller region.", oversized), Some(json!(oversized)))
+}
 
-        for window in windows {
-            // Skip minimized windows
-            let is_minimized = window.is_minimized()
-                .unwrap_or(true); // Treat error as minimized to skip
-            if is_minimized {
-                continue;
-            }
+// Shared result shape of the volume tools
+fn volume_result(state: Option<volume::VolumeState>, tool: &str) -> Result<CallToolResult, ErrorData> {
+    let result_json = match state {
+        Some(state) => {
+            info!("{}: volume {}%, muted: {}", tool, state.level, state.muted);
+            json!({ "status": "success", "supported": true, "level": state.level, "muted": state.muted })
+        }
+        None => {
+            info!("{}: no audio backend available on this platform/session.", tool);
+            json!({ "status": "success", "supported": false })
+        }
+    };
+    Ok(CallToolResult::success(vec![Content::json(result_json)
+        .map_err(|e| anyhow!(e).context(format!("Failed to serialize {} result", tool)))
+        .map_err(|e| errors::backend(e.to_string()))?
+    ]))
+}
 
-            // Get window title
-            let title = match window.title() {
-                 Ok(t) => t,
-                 Err(_) => continue, // Skip windows where title cannot be retrieved
-            };
+// Shared result shape of the brightness tools; `monitor` narrows the list to one display
+fn brightness_result(displays: Option<Vec<brightness::DisplayBrightness>>, monitor: Option<usize>, tool: &str) -> Result<CallToolResult, ErrorData> {
+    let result_json = match displays {
+        Some(displays) => {
+            let count = displays.len();
+            let displays: Vec<_> = displays.into_iter().filter(|d| monitor.is_none_or(|m| m == d.index)).collect();
+            if displays.is_empty() {
+                return tool_error(
+                    ErrorCategory::Coordinate,
+                    format!("No adjustable display with index {}; there are {}.", monitor.unwrap_or_default(), count),
+                    Some(json!({ "monitor": monitor, "count": count })),
+                );
+            }
+            info!("{}: {:?}", tool, displays);
+            json!({ "status": "success", "supported": true, "displays": displays })
+        }
+        None => {
+            info!("{}: no adjustable display on this platform/machine.", tool);
+            json!({ "status": "success", "supported": false })
+        }
+    };
+    Ok(CallToolResult::success(vec![Content::json(result_json)
+        .map_err(|e| anyhow!(e).context(format!("Failed to serialize {} result", tool)))
+        .map_err(|e| errors::backend(e.to_string()))?
+    ]))
+}
+
+/// Native id and title of the window the accessibility tools work on: the one given by id or
+/// title, or else the focused window. `Ok(None)` when nothing matches or nothing has focus.
+fn accessibility_target(window_id: Option<u32>, title_substring: Option<&str>) -> Result<Option<(u64, String)>, ErrorData> {
+    if window_id.is_some() || title_substring.is_some() {
+        let target = find_target_window(window_id, title_substring)?;
+        return Ok(target.map(|w| (w.id().unwrap_or_default() as u64, w.title().unwrap_or_default())));
+    }
+    match window::active_window() {
+        Ok(active) => Ok(active.map(|w| (w.id, w.title))),
+        Err(e) => {
+            warn!("Could not determine the focused window: {:#}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// Looks up a window by exact id or, failing that, by case-insensitive title substring (first
+/// match wins). `Ok(None)` when nothing matches; invalid params when neither is given.
+fn find_target_window(window_id: Option<u32>, title_substring: Option<&str>) -> Result<Option<xcap::Window>, ErrorData> {
+    let windows = xcap::Window::all()
+        .context("Failed to get window list")
+        .map_err(|e| errors::backend(e.to_string()))?;
+    Ok(match (window_id, title_substring) {
+        (Some(id), _) => windows.into_iter().find(|w| w.id().ok() == Some(id)),
+        (None, Some(query)) => {
+            let query_lower = query.to_lowercase();
+            windows.into_iter().find(|w| w.title().is_ok_and(|t| t.to_lowercase().contains(&query_lower)))
+        }
+        (None, None) => {
+            return Err(errors::invalid_params("Provide either 'window_id' or 'title_substring'"));
+        }
+    })
+}
+
+// Shared body of set_window_bounds / move_window / resize_window: applies the geometry and
+// reads the resulting bounds back so the model can verify them
+async fn apply_window_bounds(
+    tool: &str,
+    window_id: Option<u32>,
+    title_substring: Option<&str>,
+    x: Option<i32>,
+    y: Option<i32>,
+    width: Option<u32>,
+    height: Option<u32>,
+) -> Result<CallToolResult, ErrorData> {
+    if width == Some(0) || height == Some(0) {
+        return Err(errors::invalid_params("'width' and 'height' must be greater than 0"));
+    }
+    let Some(target) = find_target_window(window_id, title_substring)? else {
+        return tool_error(
+            ErrorCategory::NotFound,
+            "No window matches the given id or title. Use find_window to look up open windows.",
+            Some(json!({ "window_id": window_id, "title_substring": title_substring })),
+        );
+    };
+    let target_id = target.id().unwrap_or_default();
+    let target_title = target.title().unwrap_or_default();
+
+    let supported = window::set_window_bounds(target_id as u64, x, y, width, height)
+        .map_err(|e| errors::backend(format!("{:#}", e)))?;
+    let result_json = if supported {
+        // Window managers apply geometry requests asynchronously
+        sleep(Duration::from_millis(100)).await;
+        let bounds = window::window_bounds(target_id as u64)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        info!("{}: window '{}' (id {}) is now {:?}", tool, target_title, target_id, bounds);
+        json!({ "status": "success", "supported": true, "window_id": target_id, "title": target_title, "bounds": bounds })
+    } else {
+        info!("{}: window placement is not supported on this platform/session.", tool);
+        json!({ "status": "success", "supported": false })
+    };
+    Ok(CallToolResult::success(vec![Content::json(result_json)
+        .map_err(|e| anyhow!(e).context(format!("Failed to serialize {} result", tool)))
+        .map_err(|e| errors::backend(e.to_string()))?
+    ]))
+}
+
+// Shared body of minimize_window / maximize_window / restore_window / close_window
+async fn apply_window_command(
+    tool: &str,
+    params: WindowTargetParams,
+    command: window::WindowCommand,
+) -> Result<CallToolResult, ErrorData> {
+    let Some(target) = find_target_window(params.window_id, params.title_substring.as_deref())? else {
+        return tool_error(
+            ErrorCategory::NotFound,
+            "No window matches the given id or title. Use find_window to look up open windows.",
+            Some(json!({ "window_id": params.window_id, "title_substring": params.title_substring })),
+        );
+    };
+    let target_id = target.id().unwrap_or_default();
+    let target_title = target.title().unwrap_or_default();
+
+    let supported = window::apply_window_command(target_id as u64, command)
+        .map_err(|e| errors::backend(format!("{:#}", e)))?;
+    let result_json = if supported {
+        // Give the window (and the window manager's animation) a moment before reading the state back
+        sleep(Duration::from_millis(250)).await;
+        let state = window::window_state(target_id as u64)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        info!("{}: window '{}' (id {}) is now {:?}", tool, target_title, target_id, state);
+        json!({ "status": "success", "supported": true, "window_id": target_id, "title": target_title, "state": state })
+    } else {
+        info!("{}: window state changes are not supported on this platform/session.", tool);
+        json!({ "status": "success", "supported": false })
+    };
+    Ok(CallToolResult::success(vec![Content::json(result_json)
+        .map_err(|e| anyhow!(e).context(format!("Failed to serialize {} result", tool)))
+        .map_err(|e| errors::backend(e.to_string()))?
+    ]))
+}
+
+// --- Tool Provider Implementation ---
+
+#[derive(Clone, Default)] // Clone is required by ServerHandler
+struct DesktopToolProvider {
+    /// Shared input backend; cloning the provider shares the same `Enigo`.
+    input: input::InputBackend,
+    /// Applications started with `launch_application`, shared across clients.
+    apps: apps::AppRegistry,
+    /// Screenshots saved by `capture_screen_resource`, readable as MCP resources by any client.
+    captures: captures::CaptureStore,
+    /// Per-tool call counters and latencies across all clients, reported by `get_metrics`.
+    metrics: metrics::Metrics,
+    /// System clipboard, kept open so text set by a tool stays available to other apps.
+    clipboard: clipboard::ClipboardHandle,
+    /// Global input recording controlled by `start_recording` / `stop_recording`.
+    recorder: recorder::Recorder,
+    /// Address of the connected client, set on the per-connection clone. Used in tracing spans.
+    client_addr: Option<SocketAddr>,
+    /// State private to one client connection; replaced with a fresh one per connection.
+    session: Arc<Mutex<session::ClientSession>>,
+    /// Audit trail of executed tool calls, when `AIRC_ACTION_LOG` is set.
+    action_log: Option<action_log::ActionLog>,
+}
+
+/// Tools that only observe the desktop (or just wait). Dry-run mode lets these run and replays
+/// skip them; any tool not listed here is treated as changing the machine.
+const READ_ONLY_TOOLS: &[&str] = &[
+    "get_screen_details",
+    "get_all_screens",
+    "translate_coordinates",
+    "find_window",
+    "list_dialogs",
+    "detect_dialogs",
+    "get_active_window",
+    "get_mouse_position",
+    "get_cursor_info",
+    "get_element_at_point",
+    "get_accessibility_tree",
+    "list_tray_icons",
+    "list_virtual_desktops",
+    "get_input_state",
+    "get_mouse_acceleration",
+    "get_keyboard_layout",
+    "get_brightness",
+    "get_power_state",
+    "get_volume",
+    "read_file",
+    "get_clipboard_formats",
+    "get_clipboard",
+    "list_processes",
+    "list_directory",
+    "capture_screen",
+    "capture_window",
+    "capture_screen_annotated",
+    "capture_screen_resource",
+    "capture_burst",
+    "ocr_screen",
+    "find_text_on_screen",
+    "assert_pixel",
+    "assert_text",
+    "wait_for_pixel",
+    "wait_for_window",
+    "screen_changed_since",
+    "ping",
+    "get_metrics",
+    "get_tool_catalog",
+    "get_capabilities",
+    "get_server_environment",
+    "get_session_state",
+    "execute_openai_wait",
+];
+
+/// `AIRC_DRY_RUN=1`: state-changing tools log what they would do and return a synthetic
+/// success instead of running, while captures still return real screenshots. Read once.
+fn dry_run_enabled() -> bool {
+    static DRY_RUN: OnceLock<bool> = OnceLock::new();
+    *DRY_RUN.get_or_init(|| {
+        let enabled = matches!(std::env::var("AIRC_DRY_RUN").as_deref().map(str::trim), Ok("1") | Ok("true"));
+        if enabled {
+            warn!("AIRC_DRY_RUN is set: input and other state-changing tools will NOT be executed.");
+        }
+        enabled
+    })
+}
+
+impl DesktopToolProvider {
+    /// Enforces the minimum interval between this client's input actions, sleeping as needed.
+    /// Returns the delay that was applied, in milliseconds.
+    async fn throttle(&self) -> Result<u64, ErrorData> {
+        let interval = session::min_action_interval();
+        if interval.is_zero() {
+            return Ok(0);
+        }
+        let slot = self.lock_session()?.reserve_action_slot(interval);
+        let delay = slot.saturating_duration_since(Instant::now());
+        if !delay.is_zero() {
+            sleep(delay).await;
+        }
+        Ok(delay.as_millis() as u64)
+    }
+
+    fn lock_session(&self) -> Result<std::sync::MutexGuard<'_, session::ClientSession>, ErrorData> {
+        // The session is only touched in short, non-panicking updates, so a panic elsewhere in a
+        // tool that held the guard leaves it consistent
+        Ok(self.session.lock().unwrap_or_else(|poisoned| {
+            warn!("Client session lock was poisoned by a panicking tool; recovering it.");
+            self.session.clear_poison();
+            poisoned.into_inner()
+        }))
+    }
+
+    /// Runs the tool on its own task under its time limit (see [`timeouts::tool_timeout`]).
+    async fn call_with_timeout(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let name = request.name.to_string();
+        let limit = timeouts::tool_timeout(&name);
+        let provider = self.clone();
+        let reporter = progress::Reporter::for_request(&context);
+        // Cancelled by rmcp when the client sends notifications/cancelled for this request
+        let cancelled = context.ct.clone();
+        let call = async move { reporter.scope(Self::tool_box().call(ToolCallContext::new(&provider, request, context))).await };
+        run_tool_task(&name, limit, cancelled, call).await
+    }
+
+    /// Runs the input warm-up (see [`session::input_warmup`]) if `tool` is this client's first
+    /// input tool call. Failures are only logged; the tool call goes ahead either way.
+    async fn warm_up_input(&self, tool: &str) {
+        let Some(settle) = session::input_warmup() else {
+            return;
+        };
+        if catalog::classify(tool).0 != catalog::Category::Input {
+            return;
+        }
+        if !self.lock_session().map(|mut session| session.begin_warm_up()).unwrap_or(false) {
+            return;
+        }
+        info!("Warming up input before the first input call ({}): nudging the cursor, then waiting {} ms", tool, settle.as_millis());
+        let input = self.input.clone();
+        match tokio::task::spawn_blocking(move || input.nudge()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Input warm-up failed: {}", e.message),
+            Err(e) => warn!("Input warm-up failed unexpectedly: {}", e),
+        }
+        sleep(settle).await;
+    }
+
+    /// Releases every key and button this client left pressed. Called when the client disconnects
+    /// so a dropped connection mid-drag doesn't leave the user's mouse button held down.
+    fn release_held_inputs(&self) {
+        session::release_held(&self.session, |held| match self.input.release(&held.keys, &held.scancodes, &held.buttons) {
+            Ok(released) => info!("Released inputs held by disconnected client: {:?}", released),
+            Err(e) => warn!("Failed to release inputs held by disconnected client: {}", e.message),
+        });
+    }
+}
+
+// *** First impl block: Contains the tool definitions ***
+#[tool(tool_box)]// Apply tool_box here as well
+impl DesktopToolProvider {
+    // --- Existing Custom Tools (Unchanged) ---
+    #[tool(name = "get_screen_details", description = "Gets the primary screen resolution (width and height).")]
+    async fn get_screen_details(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Received request to get screen details.");
+        let display_infos = DisplayInfo::all()
+            .map_err(|e| anyhow!(e).context("display_info::DisplayInfo::all() failed"))
+            .map_err(|e| errors::backend(e.to_string()))?;
+
+        let mut screens = vec![];
+
+        for screen in display_infos.iter() {
+            screens.push(
+                json!({
+                    "screen_id": screen.id,
+                    "name": screen.name,
+                    "width": screen.width,
+                    "height": screen.height,
+                    "scale_factor": screen.scale_factor,
+                    "x": screen.x,
+                    "y": screen.y
+                })
+            );
+        }
+
+        Ok(CallToolResult::success(
+            vec![
+                Content::json(screens)
+                    .map_err(|e| anyhow!(e).context("Failed to serialize screen details to JSON"))
+                    .map_err(|e| errors::backend(e.to_string()))?
+            ]
+        ))
+    }
+
+    #[tool(name = "get_all_screens", description = "Lists every connected monitor with its position in the shared desktop coordinate space, size, scale factor and whether it is primary, plus 'virtual_bounds': the bounding box spanning all of them. Use this to map coordinates on multi-monitor setups.")]
+    async fn get_all_screens(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_all_screens.");
+        let display_infos = DisplayInfo::all()
+            .map_err(|e| anyhow!(e).context("display_info::DisplayInfo::all() failed"))
+            .map_err(|e| errors::backend(e.to_string()))?;
+
+        let screens: Vec<_> = display_infos
+            .iter()
+            .map(|screen| json!({
+                "screen_id": screen.id,
+                "name": screen.name,
+                "width": screen.width,
+                "height": screen.height,
+                "scale_factor": screen.scale_factor,
+                "x": screen.x,
+                "y": screen.y,
+                "is_primary": screen.is_primary,
+            }))
+            .collect();
+        let virtual_bounds = screen::virtual_bounds(&display_infos);
+        info!("Found {} screen(s), virtual bounds {:?}", screens.len(), virtual_bounds);
+
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "count": screens.len(),
+            "screens": screens,
+            "virtual_bounds": virtual_bounds,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_all_screens result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "translate_coordinates", description = "Converts between a pixel in a per-monitor screenshot and global desktop coordinates (the space move_mouse uses), accounting for each monitor's origin and scale factor. Use 'to_global' before clicking something seen in a monitor screenshot.")]
+    async fn translate_coordinates(
+        &self,
+        #[tool(aggr)] params: TranslateCoordinatesParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing translate_coordinates: {:?}", params);
+        let display_infos = DisplayInfo::all()
+            .map_err(|e| anyhow!(e).context("display_info::DisplayInfo::all() failed"))
+            .map_err(|e| errors::backend(e.to_string()))?;
+        let direction = params.direction.as_deref().unwrap_or("to_global").to_lowercase();
+
+        let (index, global, local) = match direction.as_str() {
+            "to_global" | "global" => {
+                let index = params.monitor_index
+                    .ok_or_else(|| errors::invalid_params("'monitor_index' is required for 'to_global'"))?;
+                let Some(display) = display_infos.get(index) else {
+                    return tool_error(
+                        ErrorCategory::Coordinate,
+                        format!("Monitor index {} does not exist; there are {} monitor(s). Use get_all_screens.", index, display_infos.len()),
+                        Some(json!({ "monitor_count": display_infos.len() })),
+                    );
+                };
+                (index, screen::local_to_global(display, params.x, params.y), (params.x.round() as i32, params.y.round() as i32))
+            }
+            "to_local" | "local" => {
+                let (gx, gy) = (params.x.round() as i32, params.y.round() as i32);
+                let index = match params.monitor_index {
+                    Some(index) => index,
+                    None => match display_infos.iter().position(|d| screen::display_contains(d, gx, gy)) {
+                        Some(index) => index,
+                        None => return tool_error(
+                            ErrorCategory::Coordinate,
+                            format!("Point ({}, {}) is not on any monitor.", gx, gy),
+                            Some(json!({ "virtual_bounds": screen::virtual_bounds(&display_infos) })),
+                        ),
+                    },
+                };
+                let Some(display) = display_infos.get(index) else {
+                    return tool_error(
+                        ErrorCategory::Coordinate,
+                        format!("Monitor index {} does not exist; there are {} monitor(s). Use get_all_screens.", index, display_infos.len()),
+                        Some(json!({ "monitor_count": display_infos.len() })),
+                    );
+                };
+                (index, (gx, gy), screen::global_to_local(display, params.x, params.y))
+            }
+            other => {
+                return Err(errors::invalid_params(format!("Unknown direction '{}'; use 'to_global' or 'to_local'.", other)));
+            }
+        };
+        let display = &display_infos[index];
+        let on_monitor = screen::display_contains(display, global.0, global.1);
+        info!("Monitor {}: local {:?} <-> global {:?} (on monitor: {})", index, local, global, on_monitor);
+
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "monitor_index": index,
+            "monitor": { "screen_id": display.id, "x": display.x, "y": display.y, "width": display.width, "height": display.height, "scale_factor": display.scale_factor },
+            "local": { "x": local.0, "y": local.1 },
+            "global": { "x": global.0, "y": global.1 },
+            "on_monitor": on_monitor,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize translate_coordinates result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "find_window", description = "Finds the first non-minimized window whose title contains the given query string (case-insensitive) and returns its details.")]
+    async fn find_window(
+        &self,
+        #[tool(aggr)] params: FindWindowParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing find window with query: '{}'", params.title_query);
+
+        let windows = xcap::Window::all()
+            .context("Failed to get window list")
+            .map_err(|e| errors::backend(e.to_string()))?;
+
+        let query_lower = params.title_query.to_lowercase();
+
+        for window in windows {
+            // Skip minimized windows
+            let is_minimized = window.is_minimized()
+                .unwrap_or(true); // Treat error as minimized to skip
+            if is_minimized {
+                continue;
+            }
+
+            // Get window title
+            let title = match window.title() {
+                 Ok(t) => t,
+                 Err(_) => continue, // Skip windows where title cannot be retrieved
+            };
 
             // Perform case-insensitive partial match
             if title.to_lowercase().contains(&query_lower) {
@@ -217,368 +1372,3037 @@ impl DesktopToolProvider {
                 let height = window.height().unwrap_or(0);
                 let app_name = window.app_name().unwrap_or_default(); // Get app name if available
 
-                info!("Found matching window: Title='{}', App='{}', Pos=({}, {}), Size=({}x{})", title, app_name, x, y, width, height);
+                info!("Found matching window: Title='{}', App='{}', Pos=({}, {}), Size=({}x{})", title, app_name, x, y, width, height);
+
+                let result_json = json!({
+                    "status": "success",
+                    "found": true,
+                    "id": window.id().unwrap_or_default(),
+                    "title": title,
+                    "app_name": app_name,
+                    "x": x,
+                    "y": y,
+                    "width": width,
+                    "height": height,
+                    "is_maximized": window.is_maximized().unwrap_or(false) // Include maximized state
+                });
+
+                return Ok(CallToolResult::success(vec![Content::json(result_json)
+                    .map_err(|e| anyhow!(e).context("Failed to serialize find_window result"))
+                    .map_err(|e| errors::backend(e.to_string()))?
+                ]));
+            }
+        }
+
+        // If no window was found after checking all
+        info!("No matching window found for query: '{}'", params.title_query);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success", // Still a successful tool execution, just no result found
+            "found": false,
+            "message": format!("No non-minimized window found matching title query '{}'", params.title_query)
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize find_window 'not found' result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+        // Alternatively, you could return an error:
+        // Err(ErrorData::new(ErrorCode::NOT_FOUND, format!("No non-minimized window found matching title query '{}'", params.title_query), None))
+    }
+
+    #[tool(name = "list_dialogs", description = "Lists open dialog/modal/popup windows ordered topmost first (the order to dismiss them in), with titles, bounds and owner window. Returns supported=false where dialogs can't be detected.")]
+    async fn list_dialogs(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing list_dialogs.");
+        let result_json = match window::list_dialogs()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+        {
+            Some(dialogs) => {
+                info!("Found {} open dialog(s).", dialogs.len());
+                json!({ "status": "success", "supported": true, "count": dialogs.len(), "dialogs": dialogs })
+            }
+            None => {
+                info!("Dialog detection is not supported on this platform/session.");
+                json!({ "status": "success", "supported": false })
+            }
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize list_dialogs result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "detect_dialogs", description = "Finds open dialogs and modal popups (Save As, permission prompts, error boxes), topmost first, with title, geometry, owner and, where accessibility is available, their button labels with click coordinates. Use it instead of a screenshot when something may be blocking input. Returns an empty list when none are open.")]
+    async fn detect_dialogs(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing detect_dialogs.");
+        let Some(dialogs) = window::list_dialogs().map_err(|e| errors::backend(format!("{:#}", e)))? else {
+            info!("Dialog detection is not supported on this platform/session.");
+            return Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "supported": false, "dialogs": [] }))
+                .map_err(|e| anyhow!(e).context("Failed to serialize detect_dialogs result"))
+                .map_err(|e| errors::backend(e.to_string()))?
+            ]));
+        };
+        let mut dialogs_json = Vec::with_capacity(dialogs.len());
+        for dialog in &dialogs {
+            // A dialog can close between the listing and the walk; report it without buttons then
+            let buttons = match accessibility::buttons(dialog.id) {
+                Ok(buttons) => buttons.map(|buttons| {
+                    buttons
+                        .iter()
+                        .map(|b| {
+                            let (center_x, center_y) = b.center();
+                            json!({ "name": b.name, "enabled": b.enabled, "center_x": center_x, "center_y": center_y })
+                        })
+                        .collect::<Vec<_>>()
+                }),
+                Err(e) => {
+                    warn!("Could not read the buttons of dialog '{}': {:#}", dialog.title, e);
+                    None
+                }
+            };
+            let mut dialog_json = json!(dialog);
+            dialog_json["buttons"] = json!(buttons);
+            dialogs_json.push(dialog_json);
+        }
+        info!("Found {} open dialog(s).", dialogs_json.len());
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "supported": true,
+            "buttons_supported": accessibility::SUPPORTED,
+            "count": dialogs_json.len(),
+            "dialogs": dialogs_json,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize detect_dialogs result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_active_window", description = "Returns the window that currently has keyboard focus (title, process name, pid, bounds). Check this before typing to make sure keystrokes go to the intended window. Returns supported=false where focus can't be queried.")]
+    async fn get_active_window(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_active_window.");
+        let result_json = match window::active_window()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+        {
+            Some(active) => {
+                info!("Active window: '{}' ({:?})", active.title, active.process_name);
+                json!({ "status": "success", "supported": true, "window": active })
+            }
+            None => {
+                info!("Focused window detection is not supported on this platform/session.");
+                json!({ "status": "success", "supported": false })
+            }
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_active_window result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "activate_window", description = "Brings a window to the foreground and gives it keyboard focus (restoring it if minimized). Identify it by window_id (from find_window) or title_substring. Returns the focused window's bounds afterwards.")]
+    async fn activate_window(
+        &self,
+        #[tool(aggr)] params: ActivateWindowParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing activate_window: {:?}", params);
+        let Some(target) = find_target_window(params.window_id, params.title_substring.as_deref())? else {
+            return tool_error(
+                ErrorCategory::NotFound,
+                "No window matches the given id or title. Use find_window to look up open windows.",
+                Some(json!({ "window_id": params.window_id, "title_substring": params.title_substring })),
+            );
+        };
+        let target_id = target.id().unwrap_or_default();
+        let target_title = target.title().unwrap_or_default();
+
+        let supported = window::activate_window(target_id as u64)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let result_json = if supported {
+            // Give the window manager a moment to apply the focus change before reading it back
+            sleep(Duration::from_millis(100)).await;
+            let active = window::active_window()
+                .map_err(|e| errors::backend(format!("{:#}", e)))?;
+            let focused = active.as_ref().is_some_and(|w| w.id == target_id as u64);
+            if !focused {
+                warn!("Activated window '{}' but focus is on {:?}", target_title, active.as_ref().map(|w| &w.title));
+            }
+            info!("Activated window '{}' (id {}).", target_title, target_id);
+            json!({ "status": "success", "supported": true, "window_id": target_id, "focused": focused, "active_window": active })
+        } else {
+            info!("Window activation is not supported on this platform/session.");
+            json!({ "status": "success", "supported": false })
+        };
+
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize activate_window result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "list_virtual_desktops", description = "Lists the virtual desktops (workspaces) with their index, name and which one is current. A window that find_window or the screen doesn't show may be on another desktop; switch there with switch_virtual_desktop.")]
+    async fn list_virtual_desktops(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing list_virtual_desktops.");
+        let result_json = match window::virtual_desktops()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+        {
+            Some(desktops) => {
+                let current = desktops.iter().find(|d| d.current).map(|d| d.index);
+                info!("Found {} virtual desktop(s), current {:?}.", desktops.len(), current);
+                json!({ "status": "success", "supported": true, "current": current, "desktops": desktops })
+            }
+            None => {
+                info!("Virtual desktops are not available on this platform/session.");
+                json!({ "status": "success", "supported": false })
+            }
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize list_virtual_desktops result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "switch_virtual_desktop", description = "Switches to the virtual desktop (workspace) with the given index from list_virtual_desktops. Returns the desktop that is current afterwards.")]
+    async fn switch_virtual_desktop(
+        &self,
+        #[tool(aggr)] params: SwitchVirtualDesktopParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing switch_virtual_desktop: {:?}", params);
+        let Some(desktops) = window::virtual_desktops()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+        else {
+            info!("Virtual desktops are not available on this platform/session.");
+            return Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "supported": false }))
+                .map_err(|e| anyhow!(e).context("Failed to serialize switch_virtual_desktop result"))
+                .map_err(|e| errors::backend(e.to_string()))?
+            ]));
+        };
+        if params.index as usize >= desktops.len() {
+            return tool_error(
+                ErrorCategory::NotFound,
+                format!("There is no virtual desktop {}; indices run from 0 to {}.", params.index, desktops.len().saturating_sub(1)),
+                Some(json!({ "desktops": desktops })),
+            );
+        }
+        let previous = desktops.iter().find(|d| d.current).map(|d| d.index);
+        window::switch_virtual_desktop(params.index)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        // Give the switch animation a moment before reading the desktop back
+        sleep(Duration::from_millis(300)).await;
+        let current = window::virtual_desktops()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+            .and_then(|desktops| desktops.into_iter().find(|d| d.current).map(|d| d.index));
+        if current != Some(params.index) {
+            warn!("Requested virtual desktop {} but desktop {:?} is current", params.index, current);
+        }
+        info!("Switched virtual desktop from {:?} to {:?}.", previous, current);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "supported": true,
+            "previous": previous,
+            "current": current,
+            "switched": current == Some(params.index),
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize switch_virtual_desktop result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "set_window_bounds", description = "Moves and resizes a window to exact bounds in absolute screen coordinates (restoring it first if maximized or minimized). Identify it by window_id (from find_window) or title_substring. Returns the window's new bounds; the window manager may adjust them, e.g. to a minimum size.")]
+    async fn set_window_bounds(
+        &self,
+        #[tool(aggr)] params: SetWindowBoundsParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing set_window_bounds: {:?}", params);
+        apply_window_bounds("set_window_bounds", params.window_id, params.title_substring.as_deref(), Some(params.x), Some(params.y), Some(params.width), Some(params.height)).await
+    }
+
+    #[tool(name = "move_window", description = "Moves a window's top-left corner to an absolute screen position without changing its size. Prefer this over dragging the title bar. Returns the window's new bounds.")]
+    async fn move_window(
+        &self,
+        #[tool(aggr)] params: MoveWindowParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing move_window: {:?}", params);
+        apply_window_bounds("move_window", params.window_id, params.title_substring.as_deref(), Some(params.x), Some(params.y), None, None).await
+    }
+
+    #[tool(name = "resize_window", description = "Resizes a window without moving its top-left corner. Prefer this over dragging the window border. Returns the window's new bounds.")]
+    async fn resize_window(
+        &self,
+        #[tool(aggr)] params: ResizeWindowParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing resize_window: {:?}", params);
+        apply_window_bounds("resize_window", params.window_id, params.title_substring.as_deref(), None, None, Some(params.width), Some(params.height)).await
+    }
+
+    #[tool(name = "minimize_window", description = "Minimizes a window, identified by window_id (from find_window) or title_substring. Returns the window's resulting state.")]
+    async fn minimize_window(
+        &self,
+        #[tool(aggr)] params: WindowTargetParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing minimize_window: {:?}", params);
+        apply_window_command("minimize_window", params, window::WindowCommand::Minimize).await
+    }
+
+    #[tool(name = "maximize_window", description = "Maximizes a window, identified by window_id (from find_window) or title_substring. Returns the window's resulting state.")]
+    async fn maximize_window(
+        &self,
+        #[tool(aggr)] params: WindowTargetParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing maximize_window: {:?}", params);
+        apply_window_command("maximize_window", params, window::WindowCommand::Maximize).await
+    }
+
+    #[tool(name = "restore_window", description = "Restores a minimized or maximized window to its normal size and position. Returns the window's resulting state.")]
+    async fn restore_window(
+        &self,
+        #[tool(aggr)] params: WindowTargetParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing restore_window: {:?}", params);
+        apply_window_command("restore_window", params, window::WindowCommand::Restore).await
+    }
+
+    #[tool(name = "close_window", description = "Asks a window to close, like clicking its close button (the process is not killed). The app may show a save prompt instead; if the returned state isn't 'closed', check list_dialogs.")]
+    async fn close_window(
+        &self,
+        #[tool(aggr)] params: WindowTargetParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing close_window: {:?}", params);
+        apply_window_command("close_window", params, window::WindowCommand::Close).await
+    }
+
+    #[tool(name = "list_tray_icons", description = "Lists the system tray (notification area) icons with their tooltips and click-ready center coordinates, including icons in the 'hidden icons' overflow panel (overflow=true; visible=false while the panel is closed). Windows only; returns supported=false elsewhere.")]
+    async fn list_tray_icons(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing list_tray_icons.");
+        let result_json = match tray::list_icons()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+        {
+            Some(icons) => {
+                info!("Found {} tray icon(s).", icons.len());
+                json!({ "status": "success", "supported": true, "icons": icons })
+            }
+            None => {
+                info!("list_tray_icons: no enumerable tray on this platform.");
+                json!({ "status": "success", "supported": false })
+            }
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize list_tray_icons result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "click_tray_icon", description = "Clicks a system tray icon found by its tooltip text, wherever the taskbar puts it. Right-click usually opens the app's tray menu. Icons in the closed 'hidden icons' overflow panel can't be clicked directly; open the panel first. Windows only; returns supported=false elsewhere.")]
+    async fn click_tray_icon(
+        &self,
+        #[tool(aggr)] params: ClickTrayIconParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing click_tray_icon: {:?}", params);
+        let button_name = params.button.as_deref().unwrap_or("left");
+        let button = match input::parse_button(button_name) {
+            Some(button @ (Button::Left | Button::Right | Button::Middle)) => button,
+            _ => return Err(errors::invalid_params(format!("Invalid button '{}'. Use 'Left', 'Right' or 'Middle'.", button_name))),
+        };
+        let Some(icons) = tray::list_icons()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+        else {
+            info!("click_tray_icon: no enumerable tray on this platform.");
+            return Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "supported": false }))
+                .map_err(|e| anyhow!(e).context("Failed to serialize click_tray_icon result"))
+                .map_err(|e| errors::backend(e.to_string()))?
+            ]));
+        };
+        let Some(icon) = tray::find_icon(&icons, &params.name) else {
+            let names: Vec<&str> = icons.iter().map(|icon| icon.name.as_str()).collect();
+            return tool_error(ErrorCategory::NotFound, format!("No tray icon matches '{}'.", params.name), Some(json!({ "available": names })));
+        };
+        if !icon.visible {
+            return tool_error(
+                ErrorCategory::State,
+                format!("Tray icon '{}' is in the closed hidden-icons panel. Open the panel (the arrow next to the tray icons) and try again.", icon.name),
+                Some(json!({ "icon": icon })),
+            );
+        }
+
+        let clicks = if params.double.unwrap_or(false) { 2 } else { 1 };
+        let throttled_ms = self.throttle().await?;
+        self.input.with(|enigo| {
+            enigo.move_mouse(icon.center_x, icon.center_y, Coordinate::Abs)
+                .map_err(|e| errors::backend(format!("Couldnt move mouse: {e:?}")))?;
+            for _ in 0..clicks {
+                enigo.button(button, Direction::Click).map_err(|e| errors::backend(e.to_string()))?;
+            }
+            Ok(())
+        })?;
+        info!("Clicked tray icon '{}' at ({}, {}) with {:?} x{}.", icon.name, icon.center_x, icon.center_y, button, clicks);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "supported": true,
+            "icon": icon,
+            "button": button_name.to_lowercase(),
+            "clicks": clicks,
+            "throttled_ms": throttled_ms,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize click_tray_icon result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "move_mouse", description = "Moves the mouse cursor. If an absolute move leaves the cursor away from the target (e.g. the OS rejected it on a locked or secure desktop), the result includes warning='cursor did not reach target'.")]
+    async fn move_mouse(
+        &self,
+        #[tool(aggr)] params: MoveMouseParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing move mouse to: {:?}", params);
+        let coordinate = match params.coordinate.to_lowercase().as_str() {
+            "absolute" | "abs" => Coordinate::Abs,
+            "relative" | "rel" | _ => Coordinate::Rel,
+        };
+        if coordinate == Coordinate::Rel { info!("Moving mouse relatively by ({}, {})", params.x, params.y); }
+        else { info!("Moving mouse absolutely to ({}, {})", params.x, params.y); }
+        let (target_x, target_y, clamped) = if coordinate == Coordinate::Abs {
+            screen::clamp_mouse_target(params.x, params.y)
+                .map_err(|e| errors::backend(format!("{:#}", e)))?
+        } else {
+            (params.x, params.y, false)
+        };
+        if coordinate == Coordinate::Abs && !screen::point_on_screen(target_x, target_y) {
+            return tool_error(
+                ErrorCategory::Coordinate,
+                format!("Coordinate ({}, {}) is off screen. Use get_screen_details for the valid bounds.", target_x, target_y),
+                Some(json!({ "x": target_x, "y": target_y })),
+            );
+        }
+
+        let throttled_ms = self.throttle().await?;
+        let (x, y) = self.input.with(|enigo| {
+            enigo.move_mouse(target_x, target_y, coordinate)
+                .map_err(|e| errors::backend(format!("Couldnt move mouse: {e:?}")))?;
+            enigo.location().map_err(|e| errors::backend(e.to_string()))
+        })?;
+        let mut result_json = json!({ "status": "success", "current_x": x, "current_y": y, "clamped": clamped, "throttled_ms": throttled_ms });
+        if coordinate == Coordinate::Abs && screen::missed_target((target_x, target_y), (x, y)) {
+            warn!("Cursor did not reach target: requested ({}, {}), now at ({}, {}).", target_x, target_y, x, y);
+            result_json["warning"] = json!("cursor did not reach target");
+        } else {
+            info!("Mouse moved successfully.");
+        }
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize move_mouse result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "move_mouse_relative_to_window", description = "Moves the mouse to an offset (dx, dy) from a window's top-left corner. The window is found by title_substring, or is the focused window if omitted. Returns both the absolute and the window-relative position.")]
+    async fn move_mouse_relative_to_window(
+        &self,
+        #[tool(aggr)] params: MoveMouseRelativeToWindowParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing move_mouse_relative_to_window: {:?}", params);
+        let anchor = match params.title_substring.as_deref() {
+            Some(query) => {
+                let query_lower = query.to_lowercase();
+                xcap::Window::all()
+                    .context("Failed to get window list")
+                    .map_err(|e| errors::backend(e.to_string()))?
+                    .into_iter()
+                    .filter(|w| !w.is_minimized().unwrap_or(true))
+                    .find(|w| w.title().is_ok_and(|t| t.to_lowercase().contains(&query_lower)))
+                    .map(|w| (w.title().unwrap_or_default(), w.x().unwrap_or(0), w.y().unwrap_or(0)))
+            }
+            None => match window::active_window()
+                .map_err(|e| errors::backend(format!("{:#}", e)))?
+            {
+                Some(active) => Some((active.title, active.x, active.y)),
+                // No native focus query here; xcap still knows which window is focused
+                None => xcap::Window::all()
+                    .context("Failed to get window list")
+                    .map_err(|e| errors::backend(e.to_string()))?
+                    .into_iter()
+                    .find(|w| w.is_focused().unwrap_or(false))
+                    .map(|w| (w.title().unwrap_or_default(), w.x().unwrap_or(0), w.y().unwrap_or(0))),
+            },
+        };
+        let Some((title, window_x, window_y)) = anchor else {
+            return tool_error(
+                ErrorCategory::NotFound,
+                match &params.title_substring {
+                    Some(query) => format!("No visible window matches title '{}'. Use find_window to look up open windows.", query),
+                    None => "No window currently has focus.".to_string(),
+                },
+                None,
+            );
+        };
+
+        let (x, y, clamped) = screen::clamp_mouse_target(window_x + params.dx, window_y + params.dy)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        if !screen::point_on_screen(x, y) {
+            return tool_error(
+                ErrorCategory::Coordinate,
+                format!("Offset ({}, {}) from window '{}' resolves to ({}, {}), which is off screen.", params.dx, params.dy, title, x, y),
+                Some(json!({ "x": x, "y": y, "window_x": window_x, "window_y": window_y })),
+            );
+        }
+
+        let throttled_ms = self.throttle().await?;
+        self.input.with(|enigo| {
+            enigo.move_mouse(x, y, Coordinate::Abs)
+                .map_err(|e| errors::backend(format!("Couldnt move mouse: {e:?}")))
+        })?;
+        info!("Mouse moved to ({}, {}) = window '{}' + ({}, {}).", x, y, title, params.dx, params.dy);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "window_title": title,
+            "window_x": window_x,
+            "window_y": window_y,
+            "absolute_x": x,
+            "absolute_y": y,
+            "relative_x": params.dx,
+            "relative_y": params.dy,
+            "clamped": clamped,
+            "throttled_ms": throttled_ms,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize move_mouse_relative_to_window result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_mouse_position", description = "Gets the current absolute screen coordinates (X, Y) of the mouse cursor")]
+    async fn get_mouse_position(
+        &self,
+        #[tool(aggr)] _params: NoParams,
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get mouse position.");
+        let (x, y) = self.input.with(|enigo| {
+            enigo.location().map_err(|e| errors::backend(e.to_string()))
+        })?;
+        info!("Mouse position retrieved successfully: ({}, {})", x, y);
+        let result_json = json!({ "status": "success", "x": x, "y": y });
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_mouse_position result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_cursor_info", description = "Reports the current mouse cursor shape ('arrow', 'ibeam', 'wait', 'app_starting', 'hand', ... or 'custom'), whether it shows the app is busy, and its position. Screenshots usually don't include the cursor, so use this to tell if an app is still loading. Returns supported=false where the shape can't be read.")]
+    async fn get_cursor_info(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_cursor_info.");
+        let result_json = match cursor::cursor_info()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+        {
+            Some(info) => {
+                info!("Cursor is '{}' at ({}, {}).", info.cursor, info.x, info.y);
+                json!({ "status": "success", "supported": true, "cursor": info.cursor, "busy": info.busy, "visible": info.visible, "x": info.x, "y": info.y })
+            }
+            None => {
+                info!("Cursor shape is not available on this platform/session.");
+                json!({ "status": "success", "supported": false })
+            }
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_cursor_info result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_element_at_point", description = "Reads the UI element at (x, y) through the accessibility API: its name (label or caption), role ('button', 'check_box', 'edit', ...), current value, toggle state ('on'/'off' for check boxes), enabled flag and bounds. Far more reliable than OCR or vision for reading control state, e.g. to verify a checkbox is now checked. Returns supported=false where no accessibility backend is available (currently Windows only).")]
+    async fn get_element_at_point(
+        &self,
+        #[tool(aggr)] params: ElementAtPointParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_element_at_point: {:?}", params);
+        let result_json = match accessibility::element_at(params.x, params.y)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+        {
+            Some(element) => {
+                info!("Element at ({}, {}): {} '{}'.", params.x, params.y, element.role, element.name);
+                json!({ "status": "success", "supported": true, "element": element })
+            }
+            None => {
+                info!("No accessibility backend available on this platform.");
+                json!({ "status": "success", "supported": false })
+            }
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_element_at_point result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_accessibility_tree", description = "Returns the accessibility tree of a window (the focused one by default): nested elements with role, name, value, toggle state, enabled flag and screen bounds. Use it instead of a screenshot to understand a standard desktop app's UI and get exact click coordinates for controls by name. Off-screen elements are skipped and the tree is cut at max_depth levels and max_elements elements (truncated=true). Returns supported=false where no accessibility backend is available (currently Windows only).")]
+    async fn get_accessibility_tree(
+        &self,
+        #[tool(aggr)] params: AccessibilityTreeParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_accessibility_tree: {:?}", params);
+        if !accessibility::SUPPORTED {
+            info!("No accessibility backend available on this platform.");
+            return Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "supported": false }))
+                .map_err(|e| anyhow!(e).context("Failed to serialize get_accessibility_tree result"))
+                .map_err(|e| errors::backend(e.to_string()))?
+            ]));
+        }
+        let max_depth = params.max_depth.unwrap_or(accessibility::DEFAULT_TREE_DEPTH).min(accessibility::MAX_TREE_DEPTH);
+        let max_elements = params.max_elements.unwrap_or(accessibility::DEFAULT_TREE_ELEMENTS).min(accessibility::MAX_TREE_ELEMENTS);
+        let Some((window_id, title)) = accessibility_target(params.window_id, params.title_substring.as_deref())? else {
+            return tool_error(
+                ErrorCategory::NotFound,
+                "No window matches the given id or title, or no window has focus. Use find_window to look up open windows.",
+                Some(json!({ "window_id": params.window_id, "title_substring": params.title_substring })),
+            );
+        };
+        let tree = accessibility::window_tree(window_id, max_depth, max_elements)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let result_json = match tree {
+            Some(tree) => {
+                info!("Accessibility tree of '{}': {} element(s), truncated: {}.", title, tree.element_count, tree.truncated);
+                json!({
+                    "status": "success",
+                    "supported": true,
+                    "window_id": window_id,
+                    "title": title,
+                    "element_count": tree.element_count,
+                    "truncated": tree.truncated,
+                    "tree": tree.root,
+                })
+            }
+            None => json!({ "status": "success", "supported": false }),
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_accessibility_tree result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "click_element", description = "Clicks a UI element found by its accessible name (and optionally role) in a window, by default the focused one; no coordinates needed. If several elements match, nothing is clicked and the candidates are returned: call again with 'index' or a 'role'. The accessibility analogue of find_text_on_screen, and more robust than pixel-based clicking in standard desktop apps. Returns supported=false where no accessibility backend is available (currently Windows only).")]
+    async fn click_element(
+        &self,
+        #[tool(aggr)] params: ClickElementParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing click_element: {:?}", params);
+        if !accessibility::SUPPORTED {
+            info!("No accessibility backend available on this platform.");
+            return Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "supported": false }))
+                .map_err(|e| anyhow!(e).context("Failed to serialize click_element result"))
+                .map_err(|e| errors::backend(e.to_string()))?
+            ]));
+        }
+        let button_name = params.button.as_deref().unwrap_or("left");
+        let button = match input::parse_button(button_name) {
+            Some(button @ (Button::Left | Button::Right | Button::Middle)) => button,
+            _ => return Err(errors::invalid_params(format!("Invalid button '{}'. Use 'Left', 'Right' or 'Middle'.", button_name))),
+        };
+        let Some((window_id, title)) = accessibility_target(params.window_id, params.title_substring.as_deref())? else {
+            return tool_error(
+                ErrorCategory::NotFound,
+                "No window matches the given id or title, or no window has focus. Use find_window to look up open windows.",
+                Some(json!({ "window_id": params.window_id, "title_substring": params.title_substring })),
+            );
+        };
+        let matches = accessibility::find_elements(window_id, &params.name, params.role.as_deref())
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+            .unwrap_or_default();
+        let element = match (matches.len(), params.index) {
+            (0, _) => {
+                return tool_error(
+                    ErrorCategory::NotFound,
+                    format!("No element named '{}' in window '{}'. Use get_accessibility_tree to see the available elements.", params.name, title),
+                    Some(json!({ "name": params.name, "role": params.role, "window_id": window_id })),
+                );
+            }
+            (_, Some(index)) => match matches.get(index) {
+                Some(element) => element,
+                None => {
+                    return tool_error(
+                        ErrorCategory::NotFound,
+                        format!("'index' {} is out of range; {} element(s) match.", index, matches.len()),
+                        Some(json!({ "candidates": matches })),
+                    );
+                }
+            },
+            (1, None) => &matches[0],
+            (count, None) => {
+                return tool_error(
+                    ErrorCategory::State,
+                    format!("{} elements match '{}'. Call again with 'index' (0-{}) or a 'role' to pick one.", count, params.name, count - 1),
+                    Some(json!({ "candidates": matches })),
+                );
+            }
+        };
+        if !element.enabled {
+            warn!("Clicking disabled element '{}'; the click will probably have no effect.", element.name);
+        }
+
+        let (x, y) = element.center();
+        let throttled_ms = self.throttle().await?;
+        self.input.with(|enigo| {
+            enigo.move_mouse(x, y, Coordinate::Abs)
+                .map_err(|e| errors::backend(format!("Couldnt move mouse: {e:?}")))?;
+            enigo.button(button, Direction::Click).map_err(|e| errors::backend(e.to_string()))
+        })?;
+        info!("Clicked {} '{}' at ({}, {}) in '{}'.", element.role, element.name, x, y, title);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "supported": true,
+            "element": element,
+            "clicked_x": x,
+            "clicked_y": y,
+            "button": button_name.to_lowercase(),
+            "throttled_ms": throttled_ms,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize click_element result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "context_menu_select", description = "Right-clicks at (x, y), waits for the context menu, finds the item by its text (OCR) and clicks it. If the menu doesn't appear or has no such item, the menu is dismissed with Escape and the error lists the items that were read.")]
+    async fn context_menu_select(
+        &self,
+        #[tool(aggr)] params: ContextMenuSelectParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing context_menu_select: {:?}", params);
+        if params.item.trim().is_empty() {
+            return Err(errors::invalid_params("'item' must not be empty."));
+        }
+        if !screen::point_on_screen(params.x, params.y) {
+            return tool_error(
+                ErrorCategory::Coordinate,
+                format!("Coordinate ({}, {}) is off screen. Use get_screen_details for the valid bounds.", params.x, params.y),
+                Some(json!({ "x": params.x, "y": params.y })),
+            );
+        }
+        let capture = || -> Result<(image::RgbaImage, diff::ScreenSignature), ErrorData> {
+            let (image, local_x, local_y) = screen::capture_monitor_at(params.x, params.y)
+                .map_err(|e| errors::backend(format!("{:#}", e)))?;
+            let signature = diff::ScreenSignature::from_image(&image, params.x - local_x as i32, params.y - local_y as i32);
+            Ok((image, signature))
+        };
+        let dismiss = || {
+            if let Err(e) = self.input.with(|enigo| enigo.key(Key::Escape, Direction::Click).map_err(|e| errors::backend(e.to_string()))) {
+                warn!("Failed to dismiss the context menu: {}", e.message);
+            }
+        };
+
+        let (_, before) = capture()?;
+        let throttled_ms = self.throttle().await?;
+        self.input.with(|enigo| {
+            enigo.move_mouse(params.x, params.y, Coordinate::Abs)
+                .map_err(|e| errors::backend(format!("Couldnt move mouse: {e:?}")))?;
+            enigo.button(Button::Right, Direction::Click).map_err(|e| errors::backend(e.to_string()))
+        })?;
+
+        // The menu is whatever changed since the click; wait until it has stopped changing so a
+        // fade-in animation isn't read half-drawn
+        let deadline = Instant::now() + Duration::from_millis(params.wait_ms.unwrap_or(2000));
+        let mut previous: Option<diff::ScreenSignature> = None;
+        let menu = loop {
+            sleep(Duration::from_millis(150)).await;
+            let (image, signature) = capture()?;
+            let region = signature.compare(&before).and_then(|report| report.changed_region);
+            let settled = previous.as_ref().and_then(|p| signature.compare(p)).is_some_and(|report| !report.changed);
+            if let (Some(region), true) = (region, settled) {
+                break Some((image, signature, region));
+            }
+            if Instant::now() >= deadline {
+                break None;
+            }
+            previous = Some(signature);
+        };
+        let Some((image, signature, region)) = menu else {
+            dismiss();
+            return tool_error(
+                ErrorCategory::State,
+                format!("No context menu appeared after right-clicking ({}, {}).", params.x, params.y),
+                Some(json!({ "x": params.x, "y": params.y })),
+            );
+        };
+
+        let local_x = (region.x - signature.origin_x) as u32;
+        let local_y = (region.y - signature.origin_y) as u32;
+        let menu_image = image::imageops::crop_imm(&image, local_x, local_y, region.width, region.height).to_image();
+        let lang = params.lang.as_deref().unwrap_or(ocr::DEFAULT_LANG);
+        let words: Vec<ocr::OcrWord> = match ocr::recognize_words(&menu_image, lang) {
+            Ok(words) => words.into_iter().map(|w| ocr::OcrWord { x: w.x + region.x, y: w.y + region.y, ..w }).collect(),
+            Err(e) => {
+                dismiss();
+                return Err(errors::backend(format!("{:#}", e)));
+            }
+        };
+        let case_sensitive = params.case_sensitive.unwrap_or(false);
+        // Whole-word matches first, so "Copy" picks "Copy" rather than "Copy link" further up
+        let found = ocr::find_text(&words, &params.item, case_sensitive, true)
+            .into_iter()
+            .next()
+            .or_else(|| ocr::find_text(&words, &params.item, case_sensitive, false).into_iter().next());
+        let Some(found) = found else {
+            dismiss();
+            let items: Vec<String> = ocr::group_lines(&words).into_iter().map(|line| line.text).collect();
+            return tool_error(
+                ErrorCategory::NotFound,
+                format!("The context menu has no item '{}'.", params.item),
+                Some(json!({ "menu_items": items, "menu_region": region })),
+            );
+        };
+
+        self.input.with(|enigo| {
+            enigo.move_mouse(found.center_x, found.center_y, Coordinate::Abs)
+                .map_err(|e| errors::backend(format!("Couldnt move mouse: {e:?}")))?;
+            enigo.button(Button::Left, Direction::Click).map_err(|e| errors::backend(e.to_string()))
+        })?;
+        info!("Selected context menu item '{}' at ({}, {}).", found.text, found.center_x, found.center_y);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "item": found.text,
+            "clicked_x": found.center_x,
+            "clicked_y": found.center_y,
+            "menu_region": region,
+            "throttled_ms": throttled_ms,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize context_menu_select result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_mouse_acceleration", description = "Returns whether OS pointer acceleration is on (plus the pointer speed on Windows). Relative mouse moves are only deterministic with acceleration off. Returns supported=false where the setting can't be read.")]
+    async fn get_mouse_acceleration(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_mouse_acceleration.");
+        let result_json = match pointer::mouse_acceleration()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+        {
+            Some(acceleration) => json!({ "status": "success", "supported": true, "acceleration": acceleration }),
+            None => json!({ "status": "success", "supported": false }),
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_mouse_acceleration result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "set_mouse_acceleration", description = "Turns OS pointer acceleration on or off (and optionally sets the pointer speed on Windows). Turn it off before relying on relative mouse moves, and restore the 'previous' setting this returns when done. Not persisted past logout. Returns supported=false where the setting can't be changed.")]
+    async fn set_mouse_acceleration(
+        &self,
+        #[tool(aggr)] params: SetMouseAccelerationParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing set_mouse_acceleration: {:?}", params);
+        let previous = pointer::mouse_acceleration()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let supported = pointer::set_mouse_acceleration(params.enabled, params.speed)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let result_json = if supported {
+            let current = pointer::mouse_acceleration()
+                .map_err(|e| errors::backend(format!("{:#}", e)))?;
+            info!("Mouse acceleration changed from {:?} to {:?}", previous, current);
+            json!({ "status": "success", "supported": true, "previous": previous, "current": current })
+        } else {
+            info!("Changing mouse acceleration is not supported on this platform/session.");
+            json!({ "status": "success", "supported": false })
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize set_mouse_acceleration result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_keyboard_layout", description = "Returns the active keyboard layout (the foreground window's on Windows). Typed text and key names are translated through it, so non-US layouts can produce the wrong characters; switch with set_keyboard_layout if typing misbehaves. Returns supported=false where the layout can't be read.")]
+    async fn get_keyboard_layout(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_keyboard_layout.");
+        let result_json = match keyboard_layout::keyboard_layout()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+        {
+            Some(layout) => json!({ "status": "success", "supported": true, "layout": layout }),
+            None => json!({ "status": "success", "supported": false }),
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_keyboard_layout result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "set_keyboard_layout", description = "Switches the keyboard layout, e.g. to US English before typing text that comes out wrong under the current layout. On Windows only the foreground window switches. Restore the 'previous' layout this returns when done. Returns supported=false where the layout can't be changed (macOS, Wayland).")]
+    async fn set_keyboard_layout(
+        &self,
+        #[tool(aggr)] params: SetKeyboardLayoutParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing set_keyboard_layout: {:?}", params);
+        if params.id.trim().is_empty() {
+            return Err(errors::invalid_params("'id' must not be empty"));
+        }
+        let previous = keyboard_layout::keyboard_layout()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let supported = keyboard_layout::set_keyboard_layout(params.id.trim(), params.variant.as_deref())
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let result_json = if supported {
+            // The target window applies the switch asynchronously on Windows
+            sleep(Duration::from_millis(100)).await;
+            let current = keyboard_layout::keyboard_layout()
+                .map_err(|e| errors::backend(format!("{:#}", e)))?;
+            info!("Keyboard layout changed from {:?} to {:?}", previous, current);
+            json!({ "status": "success", "supported": true, "previous": previous, "current": current })
+        } else {
+            info!("Changing the keyboard layout is not supported on this platform/session.");
+            json!({ "status": "success", "supported": false })
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize set_keyboard_layout result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "mouse_action", description = "Performs a mouse action (click, press, release) or scrolls the mouse wheel. The result includes target_window, the window under the cursor after the action (title, class, process), or null if it can't be determined.")]
+    async fn mouse_action(
+        &self,
+        #[tool(aggr)] params: MouseClickParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing mouse action: {:?}", params);
+        let button_str = params.button.to_lowercase();
+        let action_str = params.click_type.as_deref().unwrap_or("click").to_lowercase();
+
+        let direction = match action_str.as_str() {
+            "click" => Direction::Click, "press" => Direction::Press, "release" => Direction::Release,
+            "double" => { warn!("Double click not directly supported by enigo, performing single click instead."); Direction::Click }
+            _ => { warn!("Invalid click_type '{}', defaulting to Click.", action_str); Direction::Click }
+        };
+
+        let button_enum = input::parse_button(&button_str)
+            .ok_or_else(|| errors::invalid_params(format!("Invalid mouse button/action specified: '{}'.", params.button)))?;
+
+        let throttled_ms = self.throttle().await?;
+        let location = self.input.with(|enigo| {
+            enigo.button(button_enum, direction).map_err(|e| errors::backend(e.to_string()))?;
+            Ok(enigo.location().ok())
+        })?;
+        self.lock_session()?.track_button(button_enum, direction);
+        info!("Mouse action successful: Button='{}', Action='{:?}'", button_str, direction);
+
+        let target_window = location.and_then(|(x, y)| target_window_at(x, y));
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "button": button_str,
+            "action": action_str,
+            "target_window": target_window,
+            "throttled_ms": throttled_ms,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize mouse_action result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "smooth_scroll", description = "Scrolls smoothly by about total_pixels over duration_ms, optionally at (x, y), as a glide of many small wheel events instead of a few jumpy notches. Where the platform can't send sub-notch wheel events it falls back to whole notches spread over the duration; the result's 'mode' says which was used ('smooth' or 'notch').")]
+    async fn smooth_scroll(
+        &self,
+        #[tool(aggr)] params: SmoothScrollParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing smooth_scroll: {:?}", params);
+        let direction_name = params.direction.as_deref().unwrap_or("down");
+        let direction = scroll::ScrollDirection::parse(direction_name)
+            .ok_or_else(|| errors::invalid_params(format!("Invalid direction '{}'. Use 'up', 'down', 'left' or 'right'.", direction_name)))?;
+        let duration = Duration::from_millis(params.duration_ms.unwrap_or(400).min(MAX_SCROLL_DURATION_MS));
+        let position = match (params.x, params.y) {
+            (Some(x), Some(y)) => {
+                let (x, y, _) = screen::clamp_mouse_target(x, y)
+                    .map_err(|e| errors::backend(format!("{:#}", e)))?;
+                if !screen::point_on_screen(x, y) {
+                    return tool_error(
+                        ErrorCategory::Coordinate,
+                        format!("Coordinate ({}, {}) is off screen. Use get_screen_details for the valid bounds.", x, y),
+                        Some(json!({ "x": x, "y": y })),
+                    );
+                }
+                Some((x, y))
+            }
+            (None, None) => None,
+            _ => return Err(errors::invalid_params("'x' and 'y' must be given together.")),
+        };
+
+        let throttled_ms = self.throttle().await?;
+        if let Some((x, y)) = position {
+            self.input.with(|enigo| {
+                enigo.move_mouse(x, y, Coordinate::Abs)
+                    .map_err(|e| errors::backend(format!("Couldnt move mouse: {e:?}")))
+            })?;
+        }
+        let started = Instant::now();
+        // The backend lock is taken per event, so other tools aren't blocked for the whole glide
+        let (mode, events) = if scroll::SMOOTH_SUPPORTED {
+            let steps = (duration.as_millis() as u64 / SMOOTH_SCROLL_STEP_MS).max(1);
+            let mut sent = 0u32;
+            let mut events = 0u64;
+            for step in 1..=steps {
+                let target = (params.total_pixels as f64 * scroll::ease(step as f64 / steps as f64)).round() as u32;
+                if target > sent {
+                    self.input.with(|_| {
+                        scroll::send_pixels(direction, target - sent)
+                            .map_err(|e| errors::backend(format!("{:#}", e)))
+                    })?;
+                    sent = target;
+                    events += 1;
+                }
+                if step < steps {
+                    sleep(Duration::from_millis(SMOOTH_SCROLL_STEP_MS)).await;
+                }
+            }
+            (scroll::ScrollMode::Smooth, events)
+        } else {
+            let notches = ((params.total_pixels as f64 / scroll::PIXELS_PER_NOTCH as f64).round() as u64).max(1);
+            let (length, axis) = match direction {
+                scroll::ScrollDirection::Down => (1, Axis::Vertical),
+                scroll::ScrollDirection::Up => (-1, Axis::Vertical),
+                scroll::ScrollDirection::Right => (1, Axis::Horizontal),
+                scroll::ScrollDirection::Left => (-1, Axis::Horizontal),
+            };
+            let interval = duration / notches as u32;
+            for notch in 0..notches {
+                self.input.with(|enigo| {
+                    enigo.scroll(length, axis)
+                        .map_err(|e| errors::backend(format!("Failed to scroll: {}", e)))
+                })?;
+                if notch + 1 < notches {
+                    sleep(interval).await;
+                }
+            }
+            (scroll::ScrollMode::Notch, notches)
+        };
+        info!("smooth_scroll: {} px {:?} in {:?} mode ({} event(s)).", params.total_pixels, direction, mode, events);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "mode": mode,
+            "direction": direction_name.to_lowercase(),
+            "total_pixels": params.total_pixels,
+            "events": events,
+            "duration_ms": started.elapsed().as_millis() as u64,
+            "throttled_ms": throttled_ms,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize smooth_scroll result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "hold_mouse_button", description = "Optionally moves the mouse to (x, y), then presses a button, keeps it down for duration_ms and releases it, in one call. Use for long-press context menus or charge-up mechanics instead of separate press/release calls. The button is released even if the call times out. Returns the held duration and the final cursor position.")]
+    async fn hold_mouse_button(
+        &self,
+        #[tool(aggr)] params: HoldMouseButtonParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing hold_mouse_button: {:?}", params);
+        if params.duration_ms > MAX_HOLD_MS {
+            return Err(errors::invalid_params(format!("'duration_ms' may be at most {}.", MAX_HOLD_MS)));
+        }
+        let button = match input::parse_button(&params.button) {
+            Some(Button::ScrollUp | Button::ScrollDown | Button::ScrollLeft | Button::ScrollRight) => {
+                return Err(errors::invalid_params("Scroll buttons can't be held; use mouse_action to scroll."));
+            }
+            Some(button) => button,
+            None => return Err(errors::invalid_params(format!("Invalid mouse button specified: '{}'.", params.button))),
+        };
+        let (position, clamped) = match (params.x, params.y) {
+            (Some(x), Some(y)) => {
+                let (x, y, clamped) = screen::clamp_mouse_target(x, y)
+                    .map_err(|e| errors::backend(format!("{:#}", e)))?;
+                if !screen::point_on_screen(x, y) {
+                    return tool_error(
+                        ErrorCategory::Coordinate,
+                        format!("Coordinate ({}, {}) is off screen. Use get_screen_details for the valid bounds.", x, y),
+                        Some(json!({ "x": x, "y": y })),
+                    );
+                }
+                (Some((x, y)), clamped)
+            }
+            (None, None) => (None, false),
+            _ => return Err(errors::invalid_params("'x' and 'y' must be given together.")),
+        };
+
+        let throttled_ms = self.throttle().await?;
+        let target = input::HoldTarget::Button(button);
+        // Tracked for the length of the hold so a disconnect mid-hold releases it
+        self.lock_session()?.track_hold(target, Direction::Press);
+        let result = self.input.hold_at(target, position, Duration::from_millis(params.duration_ms)).await;
+        self.lock_session()?.track_hold(target, Direction::Release);
+        let (held, (x, y)) = result?;
+        info!("Held {:?} for {} ms; cursor now at ({}, {}).", button, held.as_millis(), x, y);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "button": params.button,
+            "requested_ms": params.duration_ms,
+            "held_ms": held.as_millis() as u64,
+            "current_x": x,
+            "current_y": y,
+            "clamped": clamped,
+            "throttled_ms": throttled_ms,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize hold_mouse_button result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "keyboard_action", description = "Types text or performs a key event (click, press, release), optionally by hardware scancode")]
+    async fn keyboard_action(
+        &self,
+        #[tool(aggr)] params: KeyboardActionParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing keyboard action: {:?}", params);
+        let action_str = params.key_action.as_deref().unwrap_or("click").to_lowercase();
+        let direction = match action_str.as_str() {
+            "click" => Direction::Click, "press" => Direction::Press, "release" => Direction::Release,
+             _ => { warn!("Invalid key_action '{}', defaulting to Click.", action_str); Direction::Click }
+        };
+        if let Some(scancode) = params.scancode {
+            if !input::SCANCODES_SUPPORTED {
+                return Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "supported": false }))
+                    .map_err(|e| anyhow!(e).context("Failed to serialize keyboard scancode result"))
+                    .map_err(|e| errors::backend(e.to_string()))?
+                ]));
+            }
+            info!("Performing scancode action: scancode={:#x}, action='{}'", scancode, action_str);
+            let throttled_ms = self.throttle().await?;
+            self.input.scancode(scancode, direction)?;
+            self.lock_session()?.track_scancode(scancode, direction);
+            info!("Scancode action successful.");
+            Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "supported": true, "scancode": scancode, "action": action_str, "throttled_ms": throttled_ms }))
+                .map_err(|e| anyhow!(e).context("Failed to serialize keyboard scancode result"))
+                .map_err(|e| errors::backend(e.to_string()))?
+            ]))
+        } else if let Some(key_str) = &params.key {
+            info!("Performing key action: key='{}', action='{}'", key_str, action_str);
+            let key_enum = input::parse_key(key_str)
+                .ok_or_else(|| errors::invalid_params(input::key_error(key_str)))?;
+            let throttled_ms = self.throttle().await?;
+            self.input.with(|enigo| {
+                enigo.key(key_enum, direction).map_err(|e| errors::backend(e.to_string()))
+            })?;
+            self.lock_session()?.track_key(key_enum, direction);
+            info!("Key action successful.");
+            Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "key": key_str, "action": action_str, "throttled_ms": throttled_ms }))
+                .map_err(|e| anyhow!(e).context("Failed to serialize keyboard key action result"))
+                .map_err(|e| errors::backend(e.to_string()))?
+            ]))
+        } else if let Some(text_to_type) = &params.text {
+            info!("Typing text: '{}'", text_to_type);
+            let throttled_ms = self.throttle().await?;
+            let humanize_seed = if params.humanize.unwrap_or(false) {
+                let seed = params.humanize_seed.unwrap_or_else(action_log::now_ms);
+                let delays = humanize::delays(text_to_type, humanize::TypingProfile::from_env(), seed);
+                self.input.type_with_delays(text_to_type, &delays).await?;
+                Some(seed)
+            } else {
+                self.input.type_text(text_to_type, params.per_char_delay_ms).await?;
+                None
+            };
+            info!("Text typing successful.");
+            Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "text_typed": text_to_type, "humanize_seed": humanize_seed, "throttled_ms": throttled_ms }))
+                .map_err(|e| anyhow!(e).context("Failed to serialize keyboard text typing result"))
+                .map_err(|e| errors::backend(e.to_string()))?
+            ]))
+        } else {
+            Err(errors::invalid_params("Keyboard action requires either 'key' or 'text' parameter."))
+        }
+    }
+
+    #[tool(name = "key_sequence", description = "Clicks keys one after another with a pause after each (delay_ms), e.g. Up Up Down Down or the digits of a code on a keypad UI. Unlike a chord (keyboard_action press/release, or a key combination), each key is released before the next is pressed. Use keyboard_action with 'text' for ordinary typing. At most 200 keys and 60 s of delays.")]
+    async fn key_sequence(
+        &self,
+        #[tool(aggr)] params: KeySequenceParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing key_sequence: {:?}", params);
+        if params.keys.is_empty() || params.keys.len() > MAX_KEY_SEQUENCE_STEPS {
+            return Err(errors::invalid_params(format!("'keys' must hold 1 to {} keys.", MAX_KEY_SEQUENCE_STEPS)));
+        }
+        // Everything is checked up front so a bad key doesn't leave the sequence half sent
+        let steps = params.keys
+            .iter()
+            .map(|step| {
+                let key = input::parse_key(&step.key)
+                    .ok_or_else(|| errors::invalid_params(input::key_error(&step.key)))?;
+                Ok((key, Duration::from_millis(step.delay_ms.unwrap_or(DEFAULT_KEY_SEQUENCE_DELAY_MS))))
+            })
+            .collect::<Result<Vec<_>, ErrorData>>()?;
+        let total_delay: Duration = steps.iter().map(|(_, delay)| *delay).sum();
+        if total_delay > Duration::from_millis(MAX_HOLD_MS) {
+            return Err(errors::invalid_params(format!("The delays add up to {} ms; at most {} ms are allowed.", total_delay.as_millis(), MAX_HOLD_MS)));
+        }
+
+        let throttled_ms = self.throttle().await?;
+        let started = Instant::now();
+        let count = steps.len();
+        for (i, (key, delay)) in steps.into_iter().enumerate() {
+            self.input.with(|enigo| enigo.key(key, Direction::Click).map_err(|e| errors::backend(e.to_string())))?;
+            progress::report(i as u32 + 1, Some(count as u32)).await;
+            // No pause after the last key
+            if i + 1 < count {
+                sleep(delay).await;
+            }
+        }
+        info!("Clicked {} key(s) in {} ms.", count, started.elapsed().as_millis());
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "keys_pressed": count,
+            "duration_ms": started.elapsed().as_millis() as u64,
+            "throttled_ms": throttled_ms,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize key_sequence result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "hold_key", description = "Presses a key, keeps it down for duration_ms and releases it, in one call (e.g. hold 'w' for 2000 ms to walk in a game). More reliable than separate press/release calls, and the key is released even if the call times out. Other input tools wait while the key is held. Returns the actual held duration.")]
+    async fn hold_key(
+        &self,
+        #[tool(aggr)] params: HoldKeyParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing hold_key: {:?}", params);
+        if params.duration_ms > MAX_HOLD_MS {
+            return Err(errors::invalid_params(format!("'duration_ms' may be at most {}.", MAX_HOLD_MS)));
+        }
+        let target = match (params.scancode, &params.key) {
+            (Some(_), _) if !input::SCANCODES_SUPPORTED => {
+                return Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "supported": false }))
+                    .map_err(|e| anyhow!(e).context("Failed to serialize hold_key result"))
+                    .map_err(|e| errors::backend(e.to_string()))?
+                ]));
+            }
+            (Some(scancode), _) => input::HoldTarget::Scancode(scancode),
+            (None, Some(key_str)) => input::HoldTarget::Key(
+                input::parse_key(key_str)
+                    .ok_or_else(|| errors::invalid_params(input::key_error(key_str)))?,
+            ),
+            (None, None) => return Err(errors::invalid_params("hold_key requires 'key' or 'scancode'.")),
+        };
+
+        let throttled_ms = self.throttle().await?;
+        // Tracked for the length of the hold so a disconnect mid-hold releases it
+        self.lock_session()?.track_hold(target, Direction::Press);
+        let result = self.input.hold(target, Duration::from_millis(params.duration_ms)).await;
+        self.lock_session()?.track_hold(target, Direction::Release);
+        let held = result?;
+        info!("Held {:?} for {} ms.", target, held.as_millis());
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "key": params.key,
+            "scancode": params.scancode,
+            "requested_ms": params.duration_ms,
+            "held_ms": held.as_millis() as u64,
+            "throttled_ms": throttled_ms,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize hold_key result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "paste_text", description = "Pastes text into the focused window through the clipboard (sets it, then sends Ctrl+V / Cmd+V). Much faster and more reliable than keyboard_action for long or non-ASCII text. Restores the previous clipboard text afterwards by default. The target must accept paste; use keyboard_action for apps that don't (e.g. some terminals or games).")]
+    async fn paste_text(
+        &self,
+        #[tool(aggr)] params: PasteTextParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing paste_text ({} chars).", params.text.chars().count());
+        let restore = params.restore_clipboard.unwrap_or(true);
+        let previous = if restore {
+            self.clipboard.get_text().map_err(|e| errors::backend(format!("{:#}", e)))?
+        } else {
+            None
+        };
+        self.clipboard.set_text(&params.text)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+
+        let throttled_ms = self.throttle().await?;
+        self.input.shortcut(input::SHORTCUT_MODIFIER, Key::Unicode('v'))?;
+
+        let restored = match previous {
+            Some(previous) => {
+                // The target reads the clipboard asynchronously; swapping it back too early pastes the old text
+                sleep(Duration::from_millis(PASTE_RESTORE_DELAY_MS)).await;
+                self.clipboard.set_text(&previous)
+                    .map_err(|e| errors::backend(format!("{:#}", e)))?;
+                true
+            }
+            None => false,
+        };
+        info!("Pasted text (clipboard restored: {}).", restored);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "chars_pasted": params.text.chars().count(),
+            "clipboard_restored": restored,
+            "throttled_ms": throttled_ms,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize paste_text result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_clipboard_formats", description = "Lists the kinds of content on the clipboard: any of 'text', 'image', 'files' (copied in a file manager) and 'html' (rich text). An empty list means the clipboard is empty or holds something else. Use get_clipboard to read the content.")]
+    async fn get_clipboard_formats(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_clipboard_formats.");
+        let formats = self.clipboard.formats().map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let names = formats.names();
+        info!("Clipboard formats: {:?}", names);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "empty": names.is_empty(),
+            "formats": names,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_clipboard_formats result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_clipboard", description = "Returns the clipboard's text and copied file paths (null when absent), plus the formats present as get_clipboard_formats lists them. With include_image the clipboard image comes first as PNG image content; with include_html the HTML of copied rich text is included.")]
+    async fn get_clipboard(
+        &self,
+        #[tool(aggr)] params: GetClipboardParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_clipboard: {:?}", params);
+        let formats = self.clipboard.formats().map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let text = self.clipboard.get_text().map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let files = self.clipboard.get_files().map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let mut result_json = json!({
+            "status": "success",
+            "formats": formats.names(),
+            "text": text,
+            "files": files,
+        });
+        if params.include_html.unwrap_or(false) {
+            result_json["html"] = json!(self.clipboard.get_html().map_err(|e| errors::backend(format!("{:#}", e)))?);
+        }
+        let mut contents = Vec::new();
+        if params.include_image.unwrap_or(false) {
+            let image = self.clipboard.get_image().map_err(|e| errors::backend(format!("{:#}", e)))?;
+            result_json["image"] = match image {
+                Some(image) => {
+                    let (image, scale) = match screen::limit_pixels(image) {
+                        Ok(limited) => limited,
+                        Err(e) => return oversized_error(e),
+                    };
+                    let base64_image = screen::encode_png_base64(&image)
+                        .map_err(|e| errors::backend(format!("{:#}", e)))?;
+                    contents.push(Content::image(base64_image, "image/png"));
+                    json!({ "format": "png", "width": image.width(), "height": image.height(), "scale": scale })
+                }
+                None => json!(null),
+            };
+        }
+        info!("Clipboard read: formats {:?}, image returned: {}.", formats.names(), !contents.is_empty());
+        contents.push(Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_clipboard result"))
+            .map_err(|e| errors::backend(e.to_string()))?);
+        Ok(CallToolResult::success(contents))
+    }
+
+    #[tool(name = "get_selected_text", description = "Returns the text currently selected in the focused window by copying it (Ctrl+C / Cmd+C) through the clipboard, then restoring the previous clipboard text by default. Returns an empty string when nothing is selected or the app doesn't copy it.")]
+    async fn get_selected_text(
+        &self,
+        #[tool(aggr)] params: GetSelectedTextParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_selected_text: {:?}", params);
+        let restore = params.restore_clipboard.unwrap_or(true);
+        let previous = if restore {
+            self.clipboard.get_text().map_err(|e| errors::backend(format!("{:#}", e)))?
+        } else {
+            None
+        };
+        // With nothing selected most apps leave the clipboard alone, so a marker that is still
+        // there afterwards means there was no selection
+        let marker = format!("airc-selection-probe-{:016x}", rand::random::<u64>());
+        self.clipboard.set_text(&marker)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+
+        let throttled_ms = self.throttle().await?;
+        let copied = self.input.shortcut(input::SHORTCUT_MODIFIER, Key::Unicode('c'));
+        let deadline = Instant::now() + Duration::from_millis(params.wait_ms.unwrap_or(500));
+        let mut selection = None;
+        while copied.is_ok() {
+            sleep(Duration::from_millis(25)).await;
+            let text = self.clipboard.get_text().map_err(|e| errors::backend(format!("{:#}", e)))?;
+            if text.as_deref() != Some(marker.as_str()) {
+                selection = text;
+                break;
+            }
+            if Instant::now() >= deadline {
+                break;
+            }
+        }
+
+        // Put the clipboard back even if the copy failed, so the marker never lingers
+        let restored = match &previous {
+            Some(previous) => self.clipboard.set_text(previous).map(|_| true),
+            None if restore => self.clipboard.clear().map(|_| true),
+            None if selection.is_none() => self.clipboard.clear().map(|_| false),
+            None => Ok(false),
+        }
+        .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        copied?;
+        let text = selection.unwrap_or_default();
+        info!("Selected text: {} chars (clipboard restored: {}).", text.chars().count(), restored);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "text": text,
+            "has_selection": !text.is_empty(),
+            "clipboard_restored": restored,
+            "throttled_ms": throttled_ms,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_selected_text result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "type_from_file", description = "Types the contents of a text file inside the server's allowed file directory (AIRC_FILE_ROOT) into the focused window, so large boilerplate doesn't have to pass through the tool arguments. Supports the same per_char_delay_ms and humanize options as keyboard_action. Files over 64 KiB and paths outside that directory are rejected.")]
+    async fn type_from_file(
+        &self,
+        #[tool(aggr)] params: TypeFromFileParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing type_from_file: {:?}", params);
+        let Some(root) = files::root() else {
+            return tool_error(ErrorCategory::Permission, "File tools are disabled. The server operator must set AIRC_FILE_ROOT to a directory.", None);
+        };
+        let path = match files::resolve_existing(root, &params.path) {
+            Ok(path) => path,
+            Err(e) => return tool_error(files::error_category(&e), format!("{:#}", e), Some(json!({ "path": params.path }))),
+        };
+        let size = match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.is_dir() => return tool_error(ErrorCategory::InvalidParams, format!("'{}' is a directory.", params.path), None),
+            Ok(metadata) => metadata.len(),
+            Err(e) => return tool_error(ErrorCategory::Backend, format!("Failed to read '{}': {}", params.path, e), None),
+        };
+        if size > files::MAX_TYPE_FILE_BYTES {
+            return tool_error(
+                ErrorCategory::Limit,
+                format!("'{}' is {} bytes, over the {} byte limit for typing.", params.path, size, files::MAX_TYPE_FILE_BYTES),
+                Some(json!({ "size": size, "max_bytes": files::MAX_TYPE_FILE_BYTES })),
+            );
+        }
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                return tool_error(ErrorCategory::InvalidParams, format!("'{}' is not UTF-8 text.", params.path), None);
+            }
+            Err(e) => return tool_error(ErrorCategory::Backend, format!("Failed to read '{}': {}", params.path, e), None),
+        };
+
+        let throttled_ms = self.throttle().await?;
+        let humanize_seed = if params.humanize.unwrap_or(false) {
+            let seed = params.humanize_seed.unwrap_or_else(action_log::now_ms);
+            let delays = humanize::delays(&text, humanize::TypingProfile::from_env(), seed);
+            self.input.type_with_delays(&text, &delays).await?;
+            Some(seed)
+        } else {
+            self.input.type_text(&text, params.per_char_delay_ms).await?;
+            None
+        };
+        let chars_typed = text.chars().count();
+        info!("Typed {} character(s) from '{}'.", chars_typed, params.path);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "path": params.path,
+            "size": size,
+            "chars_typed": chars_typed,
+            "humanize_seed": humanize_seed,
+            "throttled_ms": throttled_ms,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize type_from_file result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "capture_screen", description = "Captures the screen and returns it as PNG (or, with format='jpeg', JPEG) image content followed by a JSON metadata item (format, size, and with annotate=true the marked elements; the annotated copy is a second image). Use max_width to downscale. Set legacy_json=true for the older single JSON item with base64 data.")]
+    async fn capture_screen(
+        &self,
+        #[tool(aggr)] params: CaptureScreenParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing screen capture with params: {:?}", params);
+        let format = capture_format(params.format.as_deref())?;
+        let quality = params.quality.unwrap_or(DEFAULT_JPEG_QUALITY);
+        let max_width = params.max_width.unwrap_or(0);
+        let encode = |image: &image::RgbaImage| -> Result<String, ErrorData> {
+            screen::encode_image_base64(image, format, quality).map_err(|e| errors::backend(format!("{:#}", e)))
+        };
+        let screens =  xcap::Monitor::all()
+            .context("Failed to get screen list")
+            .map_err(|e| errors::backend(e.to_string()))?;
+        let screen_to_capture = screens.first()
+            .ok_or_else(|| anyhow!("No screen found to capture"))
+            .map_err(|e| errors::backend(e.to_string()))?;
+        info!("Capturing from screen ID: {:?}", screen_to_capture.id());
+        let image = screen_to_capture
+            .capture_image()
+            .context("Failed to capture screen area")
+            .map_err(|e| errors::backend(e.to_string()))?;
+
+        info!("Capture successful ({}x{})", image.width(), image.height());
+        let mut result_json = json!({ "status": "success", "format": format.name() });
+
+        let mut annotated_base64 = None;
+        if params.annotate.unwrap_or(false) {
+            let origin_x = screen_to_capture.x().unwrap_or(0);
+            let origin_y = screen_to_capture.y().unwrap_or(0);
+            // Elements are the text lines found by OCR; labelled controls (buttons, menu items,
+            // fields with captions) all show up this way
+            let words = ocr::recognize_words(&image, ocr::DEFAULT_LANG)
+                .map_err(|e| errors::backend(format!("{:#}", e)))?;
+            let marks: Vec<annotate::Mark> = ocr::group_lines(&words)
+                .into_iter()
+                .enumerate()
+                .map(|(i, line)| annotate::Mark::new(i as u32 + 1, line.text, origin_x + line.x, origin_y + line.y, line.width, line.height))
+                .collect();
+
+            let mut annotated = image.clone();
+            annotate::draw_marks(&mut annotated, &marks, origin_x, origin_y);
+            // Capped after drawing, so the boxes stay on their elements; the coordinates in
+            // 'elements' remain full-resolution desktop coordinates
+            let annotated = match screen::limit_pixels(annotated) {
+                Ok((annotated, _)) => screen::fit_width(annotated, max_width).0,
+                Err(e) => return oversized_error(e),
+            };
+            annotated_base64 = Some(encode(&annotated)?);
+            info!("Annotated screenshot with {} element(s).", marks.len());
+            result_json["elements"] = json!(marks);
+        }
+
+        let (image, scale) = match screen::limit_pixels(image) {
+            Ok((image, limit_scale)) => {
+                let (image, width_scale) = screen::fit_width(image, max_width);
+                (image, limit_scale * width_scale)
+            }
+            Err(e) => return oversized_error(e),
+        };
+        let base64_image = encode(&image)?;
+        info!("Encoded {} image to base64 (length: {})", format.name(), base64_image.len());
+        result_json["width"] = json!(image.width());
+        result_json["height"] = json!(image.height());
+        result_json["scale"] = json!(scale);
+
+        if params.legacy_json.unwrap_or(false) {
+            result_json["base64_data"] = json!(base64_image);
+            if let Some(annotated_base64) = annotated_base64 {
+                result_json["annotated_base64_data"] = json!(annotated_base64);
+            }
+            return Ok(CallToolResult::success(vec![Content::json(result_json)
+                .map_err(|e| anyhow!(e).context("Failed to serialize capture_screen result"))
+                .map_err(|e| errors::backend(e.to_string()))?
+            ]));
+        }
+        // The plain screenshot comes first; the annotated copy, if any, follows it
+        result_json["annotated"] = json!(annotated_base64.is_some());
+        let mut contents = vec![Content::image(base64_image, format.mime_type())];
+        contents.extend(annotated_base64.map(|annotated| Content::image(annotated, format.mime_type())));
+        contents.push(Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize capture_screen result"))
+            .map_err(|e| errors::backend(e.to_string()))?);
+        Ok(CallToolResult::success(contents))
+    }
+
+    #[tool(name = "capture_screen_resource", description = "Captures the primary screen to a PNG file and returns its resource URI (fetch it with resources/read) plus dimensions, instead of inline base64. Use this when your client can read MCP resources, to keep screenshots out of the conversation.")]
+    async fn capture_screen_resource(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing capture_screen_resource.");
+        let (image, origin_x, origin_y) = screen::capture_primary()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let (image, scale) = match screen::limit_pixels(image) {
+            Ok(limited) => limited,
+            Err(e) => return oversized_error(e),
+        };
+        let capture = self.captures.save(&image)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "uri": capture.uri,
+            "path": capture.path,
+            "mime_type": "image/png",
+            "width": capture.width,
+            "height": capture.height,
+            "size_bytes": capture.size_bytes,
+            "origin": { "x": origin_x, "y": origin_y },
+            "scale": scale,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize capture_screen_resource result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "capture_window", description = "Captures a single window's content (by window_id or title_substring) and returns it as PNG (or, with format='jpeg', JPEG) image content followed by a JSON metadata item, like capture_screen. Supports the same max_width, format, quality and legacy_json options. Cheaper than capture_screen when only one app matters.")]
+    async fn capture_window(
+        &self,
+        #[tool(aggr)] params: CaptureWindowParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing capture_window: {:?}", params);
+        let format = capture_format(params.format.as_deref())?;
+        let windows = xcap::Window::all()
+            .context("Failed to get window list")
+            .map_err(|e| errors::backend(e.to_string()))?;
+
+        let mut matches: Vec<xcap::Window> = match (params.window_id, params.title_substring.as_deref()) {
+            (Some(id), _) => windows.into_iter().filter(|w| w.id().ok() == Some(id)).collect(),
+            (None, Some(query)) => {
+                let query_lower = query.to_lowercase();
+                windows.into_iter().filter(|w| w.title().is_ok_and(|t| t.to_lowercase().contains(&query_lower))).collect()
+            }
+            (None, None) => {
+                return Err(errors::invalid_params("Provide either 'window_id' or 'title_substring'"));
+            }
+        };
+        let match_count = matches.len();
+        if match_count == 0 {
+            return tool_error(
+                ErrorCategory::NotFound,
+                "No window matches the given id or title. Use find_window to look up open windows.",
+                Some(json!({ "window_id": params.window_id, "title_substring": params.title_substring })),
+            );
+        }
+        let target = matches.swap_remove(0);
+        let title = target.title().unwrap_or_default();
+        if target.is_minimized().unwrap_or(false) {
+            return tool_error(
+                ErrorCategory::State,
+                format!("Window '{}' is minimized and has no visible content. Use activate_window to restore it first.", title),
+                Some(json!({ "window_id": target.id().unwrap_or_default(), "title": title })),
+            );
+        }
+
+        let image = target
+            .capture_image()
+            .context("Failed to capture window")
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        info!("Captured window '{}' ({}x{}); {} window(s) matched.", title, image.width(), image.height(), match_count);
+        let (image, scale) = match screen::limit_pixels(image) {
+            Ok((image, limit_scale)) => {
+                let (image, width_scale) = screen::fit_width(image, params.max_width.unwrap_or(0));
+                (image, limit_scale * width_scale)
+            }
+            Err(e) => return oversized_error(e),
+        };
+        let base64_image = screen::encode_image_base64(&image, format, params.quality.unwrap_or(DEFAULT_JPEG_QUALITY))
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+
+        let mut result_json = json!({
+            "status": "success",
+            "format": format.name(),
+            "width": image.width(),
+            "height": image.height(),
+            "window_id": target.id().unwrap_or_default(),
+            "title": title,
+            "x": target.x().unwrap_or(0),
+            "y": target.y().unwrap_or(0),
+            "match_count": match_count,
+            "scale": scale,
+        });
+        if params.legacy_json.unwrap_or(false) {
+            result_json["base64_data"] = json!(base64_image);
+            return Ok(CallToolResult::success(vec![Content::json(result_json)
+                .map_err(|e| anyhow!(e).context("Failed to serialize capture_window result"))
+                .map_err(|e| errors::backend(e.to_string()))?
+            ]));
+        }
+        Ok(CallToolResult::success(vec![
+            Content::image(base64_image, format.mime_type()),
+            Content::json(result_json)
+                .map_err(|e| anyhow!(e).context("Failed to serialize capture_window result"))
+                .map_err(|e| errors::backend(e.to_string()))?,
+        ]))
+    }
+
+    #[tool(name = "capture_burst", description = "Captures a short sequence of screenshots (count frames, interval_ms apart) of the primary screen or a region, to see animations, transitions or loading states a single screenshot misses. Returns an animated GIF as base64 (format 'gif') or an array of base64 PNG frames (format 'frames'), plus each frame's capture time. Large bursts are downscaled.")]
+    async fn capture_burst(
+        &self,
+        #[tool(aggr)] params: CaptureBurstParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing capture_burst: {:?}", params);
+        let as_gif = match params.format.as_deref().unwrap_or("gif") {
+            "gif" => true,
+            "frames" => false,
+            other => return Err(errors::invalid_params(format!("Unknown format '{}'. Use 'gif' or 'frames'.", other))),
+        };
+        if !(2..=burst::MAX_FRAMES).contains(&params.count) {
+            return Err(errors::invalid_params(format!("'count' must be between 2 and {}.", burst::MAX_FRAMES)));
+        }
+        let interval_ms = params.interval_ms.max(burst::MIN_INTERVAL_MS);
+        // Checked, since interval_ms comes straight from the caller and could overflow
+        if (params.count as u64).checked_mul(interval_ms).is_none_or(|total| total > burst::MAX_DURATION_MS) {
+            return Err(errors::invalid_params(format!("count x interval_ms may not exceed {} ms; capture fewer frames or use a shorter interval.", burst::MAX_DURATION_MS)));
+        }
+        let region = match (params.x, params.y, params.width, params.height) {
+            (Some(x), Some(y), Some(width), Some(height)) => Some((x, y, width, height)),
+            (None, None, None, None) => None,
+            _ => return Err(errors::invalid_params("Region requires all of 'x', 'y', 'width' and 'height'.")),
+        };
+        let capture = || match region {
+            Some((x, y, width, height)) => screen::capture_region(x, y, width, height),
+            None => screen::capture_primary().map(|(image, _, _)| image),
+        };
+
+        let started = Instant::now();
+        let mut frames = Vec::with_capacity(params.count as usize);
+        let mut timestamps_ms = Vec::with_capacity(params.count as usize);
+        let mut scale = 1.0;
+        for i in 0..params.count {
+            // Frames are scheduled from the start, so slow captures don't stretch the burst
+            sleep((started + Duration::from_millis((i as u64).saturating_mul(interval_ms))).saturating_duration_since(Instant::now())).await;
+            timestamps_ms.push(started.elapsed().as_millis() as u64);
+            let image = capture().map_err(|e| errors::backend(format!("{:#}", e)))?;
+            if i == 0 {
+                let cap = screen::pixel_cap();
+                let pixels = image.width() as u64 * image.height() as u64;
+                if cap.reject && pixels > cap.max_pixels {
+                    return oversized_error(screen::Oversized { width: image.width(), height: image.height(), max_pixels: cap.max_pixels });
+                }
+                // Whichever limit is tighter: the per-frame pixel cap or the burst's memory budget
+                let cap_scale = (cap.max_pixels as f64 / pixels as f64).sqrt().min(1.0);
+                scale = burst::scale_for(params.count, image.width(), image.height()).min(cap_scale);
+            }
+            frames.push(burst::downscale(image, scale));
+            progress::report(i + 1, Some(params.count)).await;
+        }
+        let (width, height) = (frames[0].width(), frames[0].height());
+        info!("Captured {} frame(s) of {}x{} in {} ms.", frames.len(), width, height, started.elapsed().as_millis());
+
+        let mut result_json = json!({
+            "status": "success",
+            "count": frames.len(),
+            "interval_ms": interval_ms,
+            "width": width,
+            "height": height,
+            "downscaled": scale < 1.0,
+            "scale": scale,
+            "timestamps_ms": timestamps_ms,
+        });
+        let encoded_bytes = if as_gif {
+            let gif = tokio::task::spawn_blocking(move || burst::encode_gif(frames, interval_ms))
+                .await
+                .map_err(|e| errors::backend(format!("GIF encoding failed unexpectedly: {}", e)))?
+                .map_err(|e| errors::backend(format!("{:#}", e)))?;
+            result_json["format"] = json!("gif");
+            result_json["base64_data"] = json!(base64::engine::general_purpose::STANDARD.encode(&gif));
+            gif.len()
+        } else {
+            let pngs = frames
+                .iter()
+                .map(screen::encode_png_base64)
+                .collect::<anyhow::Result<Vec<String>>>()
+                .map_err(|e| errors::backend(format!("{:#}", e)))?;
+            result_json["format"] = json!("png");
+            // Base64 is 4/3 of the encoded size
+            let bytes = pngs.iter().map(|png| png.len() / 4 * 3).sum();
+            result_json["frames"] = json!(pngs);
+            bytes
+        };
+        if encoded_bytes > burst::MAX_OUTPUT_BYTES {
+            return tool_error(
+                ErrorCategory::Limit,
+                format!(
+                    "The burst encodes to {} bytes, over the {} byte limit. Capture a smaller region or fewer frames.",
+                    encoded_bytes,
+                    burst::MAX_OUTPUT_BYTES
+                ),
+                Some(json!({ "size": encoded_bytes, "max_bytes": burst::MAX_OUTPUT_BYTES })),
+            );
+        }
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize capture_burst result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "capture_screen_annotated", description = "Captures the primary screen with numbered crosshairs drawn at the given absolute points (and outlines around optional rectangles). Use it to check where a click would land before or after a miss. Returns the annotated PNG as base64.")]
+    async fn capture_screen_annotated(
+        &self,
+        #[tool(aggr)] params: CaptureScreenAnnotatedParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing capture_screen_annotated: {:?}", params);
+        let (mut image, origin_x, origin_y) = screen::capture_primary()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+
+        for rect in params.rects.iter().flatten() {
+            annotate::draw_outline(&mut image, rect.x - origin_x, rect.y - origin_y, rect.width, rect.height);
+        }
+        let mut points = Vec::with_capacity(params.points.len());
+        for (i, point) in params.points.iter().enumerate() {
+            let (x, y) = (point.x - origin_x, point.y - origin_y);
+            let on_image = x >= 0 && y >= 0 && (x as u32) < image.width() && (y as u32) < image.height();
+            annotate::draw_crosshair(&mut image, x, y, i as u32 + 1);
+            points.push(json!({ "id": i + 1, "x": point.x, "y": point.y, "on_image": on_image }));
+        }
+
+        let (image, scale) = match screen::limit_pixels(image) {
+            Ok(limited) => limited,
+            Err(e) => return oversized_error(e),
+        };
+        let base64_image = screen::encode_png_base64(&image)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        info!("Annotated capture with {} point(s) ({}x{})", points.len(), image.width(), image.height());
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "format": "png",
+            "width": image.width(),
+            "height": image.height(),
+            "origin": { "x": origin_x, "y": origin_y },
+            "points": points,
+            "scale": scale,
+            "base64_data": base64_image,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize capture_screen_annotated result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "ocr_screen", description = "Reads text on screen (or in a region) with OCR, without a vision model round-trip. Returns the full text plus every word and line with absolute bounding boxes and center points usable as click coordinates.")]
+    async fn ocr_screen(
+        &self,
+        #[tool(aggr)] params: OcrScreenParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing ocr_screen: {:?}", params);
+        let off_screen_origin = match (params.x, params.y) {
+            (Some(x), Some(y)) if !screen::point_on_screen(x, y) => Some((x, y)),
+            _ => None,
+        };
+        if let Some((x, y)) = off_screen_origin {
+            return tool_error(
+                ErrorCategory::Coordinate,
+                format!("Region origin ({}, {}) is off screen. Use get_screen_details for the valid bounds.", x, y),
+                Some(json!({ "x": x, "y": y })),
+            );
+        }
+        let (image, origin_x, origin_y) = match (params.x, params.y, params.width, params.height) {
+            (Some(x), Some(y), Some(width), Some(height)) => screen::capture_region(x, y, width, height).map(|image| (image, x, y)),
+            (None, None, None, None) => screen::capture_primary(),
+            _ => return Err(errors::invalid_params("Region requires all of 'x', 'y', 'width' and 'height'.")),
+        }
+        .map_err(|e| errors::backend(format!("{:#}", e)))?;
+
+        let lang = params.lang.as_deref().unwrap_or(ocr::DEFAULT_LANG);
+        let min_confidence = params.min_confidence.unwrap_or(0.0);
+        let words: Vec<ocr::OcrWord> = ocr::recognize_words(&image, lang)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+            .into_iter()
+            .filter(|w| w.confidence >= min_confidence)
+            .map(|w| ocr::OcrWord { x: w.x + origin_x, y: w.y + origin_y, ..w })
+            .collect();
+        let lines = ocr::group_lines(&words);
+        info!("OCR found {} word(s) in {} line(s).", words.len(), lines.len());
+
+        let center = |x: i32, y: i32, width: u32, height: u32| (x + (width / 2) as i32, y + (height / 2) as i32);
+        let words_json: Vec<_> = words
+            .iter()
+            .map(|w| {
+                let (center_x, center_y) = center(w.x, w.y, w.width, w.height);
+                json!({ "text": w.text, "confidence": w.confidence, "x": w.x, "y": w.y, "width": w.width, "height": w.height, "center_x": center_x, "center_y": center_y })
+            })
+            .collect();
+        let lines_json: Vec<_> = lines
+            .iter()
+            .map(|l| {
+                let (center_x, center_y) = center(l.x, l.y, l.width, l.height);
+                json!({ "text": l.text, "x": l.x, "y": l.y, "width": l.width, "height": l.height, "center_x": center_x, "center_y": center_y })
+            })
+            .collect();
+        let text = lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>().join("\n");
+
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "lang": lang,
+            "text": text,
+            "lines": lines_json,
+            "words": words_json,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize ocr_screen result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "find_text_on_screen", description = "Finds text on the primary screen with OCR and returns every match's bounding box and center point in absolute coordinates, ready for move_mouse + mouse_action. Returns an empty 'matches' list when nothing matches.")]
+    async fn find_text_on_screen(
+        &self,
+        #[tool(aggr)] params: FindTextOnScreenParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing find_text_on_screen: {:?}", params);
+        if params.query.trim().is_empty() {
+            return Err(errors::invalid_params("'query' must not be empty."));
+        }
+        let (image, origin_x, origin_y) = screen::capture_primary()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let lang = params.lang.as_deref().unwrap_or(ocr::DEFAULT_LANG);
+        let words: Vec<ocr::OcrWord> = ocr::recognize_words(&image, lang)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+            .into_iter()
+            .map(|w| ocr::OcrWord { x: w.x + origin_x, y: w.y + origin_y, ..w })
+            .collect();
+
+        let matches = ocr::find_text(
+            &words,
+            &params.query,
+            params.case_sensitive.unwrap_or(false),
+            params.whole_word.unwrap_or(false),
+        );
+        info!("Found {} match(es) for '{}'.", matches.len(), params.query);
+
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "query": params.query,
+            "count": matches.len(),
+            "matches": matches,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize find_text_on_screen result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "run_shell_command", description = "Runs a command in the default system shell.")]
+     async fn run_shell_command(
+        &self,
+        #[tool(aggr)] params: RunShellParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Received request to run command: {:?}", params);
+        let env = apps::ChildEnv { vars: params.env.unwrap_or_default(), clear: params.env_clear.unwrap_or(false) };
+        env.validate().map_err(|e| errors::invalid_params(e.to_string()))?;
+        let mut command = Command::new(&params.command);
+        command.args(&params.args);
+        env.apply(&mut command);
+        let _ = command
+            .spawn()
+            .context(format!("Failed to execute command: {}", params.command))
+            .map_err(|e| errors::backend(e.to_string()))?;
+        // let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        // let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        // let exit_code = output.status.code().unwrap_or(-1);
+        // info!( "Command '{}' executed. Status: {}, Stdout len: {}, Stderr len: {}", params.command, exit_code, stdout.len(), stderr.len());
+        let result_json = json!({ "status": "success"  }); // , "exit_code": exit_code, "stdout": stdout, "stderr": stderr,
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+             .map_err(|e| anyhow!(e).context("Failed to serialize run_shell_command result"))
+             .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "read_file", description = "Reads a file inside the server's allowed file directory (AIRC_FILE_ROOT). Returns text content, or base64 for binary files. Paths outside that directory are rejected.")]
+    async fn read_file(
+        &self,
+        #[tool(aggr)] params: ReadFileParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing read_file: {:?}", params);
+        let Some(root) = files::root() else {
+            return tool_error(ErrorCategory::Permission, "File tools are disabled. The server operator must set AIRC_FILE_ROOT to a directory.", None);
+        };
+        let path = match files::resolve_existing(root, &params.path) {
+            Ok(path) => path,
+            Err(e) => return tool_error(files::error_category(&e), format!("{:#}", e), Some(json!({ "path": params.path }))),
+        };
+        let size = match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.is_dir() => return tool_error(ErrorCategory::InvalidParams, format!("'{}' is a directory.", params.path), None),
+            Ok(metadata) => metadata.len(),
+            Err(e) => return tool_error(ErrorCategory::Backend, format!("Failed to read '{}': {}", params.path, e), None),
+        };
+        if size > files::max_read_bytes() {
+            return tool_error(
+                ErrorCategory::Limit,
+                format!("'{}' is {} bytes, over the {} byte read limit.", params.path, size, files::max_read_bytes()),
+                Some(json!({ "size": size, "max_bytes": files::max_read_bytes() })),
+            );
+        }
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => return tool_error(ErrorCategory::Backend, format!("Failed to read '{}': {}", params.path, e), None),
+        };
+
+        let result_json = match String::from_utf8(bytes) {
+            Ok(text) => json!({ "status": "success", "path": params.path, "size": size, "encoding": "text", "content": text }),
+            Err(e) => json!({
+                "status": "success", "path": params.path, "size": size, "encoding": "base64",
+                "content": base64::engine::general_purpose::STANDARD.encode(e.as_bytes()),
+            }),
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize read_file result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "list_directory", description = "Lists a directory inside the server's allowed file directory (AIRC_FILE_ROOT): each entry's name, is_dir, size and modified time. Use it to find a file before read_file. Paths outside that directory are rejected.")]
+    async fn list_directory(
+        &self,
+        #[tool(aggr)] params: ListDirectoryParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing list_directory: {:?}", params);
+        let Some(root) = files::root() else {
+            return tool_error(ErrorCategory::Permission, "File tools are disabled. The server operator must set AIRC_FILE_ROOT to a directory.", None);
+        };
+        let requested = params.path.as_deref().unwrap_or(".");
+        let dir = match files::resolve_existing(root, requested) {
+            Ok(dir) if dir.is_dir() => dir,
+            Ok(_) => return tool_error(ErrorCategory::InvalidParams, format!("'{}' is not a directory.", requested), None),
+            Err(e) => return tool_error(files::error_category(&e), format!("{:#}", e), Some(json!({ "path": requested }))),
+        };
+        let mut entries = match files::list_directory(&dir) {
+            Ok(entries) => entries,
+            Err(e) => return tool_error(ErrorCategory::Backend, format!("{:#}", e), Some(json!({ "path": requested }))),
+        };
+
+        let total = entries.len();
+        entries.truncate(files::MAX_LIST_ENTRIES);
+        info!("Listed {} entries in {:?}", total, dir);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "path": requested,
+            "entries": entries,
+            "total": total,
+            "truncated": total > files::MAX_LIST_ENTRIES,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize list_directory result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "write_file", description = "Writes (or appends to) a file inside the server's allowed file directory (AIRC_FILE_ROOT). Content is text unless encoding='base64'. Paths outside that directory are rejected. Returns the number of bytes written.")]
+    async fn write_file(
+        &self,
+        #[tool(aggr)] params: WriteFileParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing write_file: path={:?}, {} byte(s) of content", params.path, params.content.len());
+        let Some(root) = files::root() else {
+            return tool_error(ErrorCategory::Permission, "File tools are disabled. The server operator must set AIRC_FILE_ROOT to a directory.", None);
+        };
+        let bytes = match params.encoding.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("text") | Some("utf8") | Some("utf-8") => params.content.into_bytes(),
+            Some("base64") => base64::engine::general_purpose::STANDARD
+                .decode(params.content.trim())
+                .map_err(|e| errors::invalid_params(format!("'content' is not valid base64: {}", e)))?,
+            Some(other) => return Err(errors::invalid_params(format!("Unsupported encoding '{}'. Use 'text' or 'base64'.", other))),
+        };
+        let path = match files::resolve_for_write(root, &params.path) {
+            Ok(path) => path,
+            Err(e) => return tool_error(files::error_category(&e), format!("{:#}", e), Some(json!({ "path": params.path }))),
+        };
+
+        let append = params.append.unwrap_or(false);
+        let written = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&path)
+            .and_then(|mut file| std::io::Write::write_all(&mut file, &bytes));
+        if let Err(e) = written {
+            return tool_error(ErrorCategory::Backend, format!("Failed to write '{}': {}", params.path, e), None);
+        }
+        info!("Wrote {} byte(s) to {:?}", bytes.len(), path);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "path": params.path,
+            "bytes_written": bytes.len(),
+            "appended": append,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize write_file result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "save_screenshot", description = "Captures the primary screen (or a region) and saves it as a PNG or JPEG file inside the server's allowed file directory, returning the absolute path and size instead of image data. Use this to keep evidence or artifacts without sending the image through the conversation.")]
+    async fn save_screenshot(
+        &self,
+        #[tool(aggr)] params: SaveScreenshotParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing save_screenshot: {:?}", params);
+        let Some(root) = files::root() else {
+            return tool_error(ErrorCategory::Permission, "File tools are disabled. The server operator must set AIRC_FILE_ROOT to a directory.", None);
+        };
+        let format = match params.format.as_deref() {
+            Some(name) => screen::SaveFormat::parse(name)
+                .ok_or_else(|| errors::invalid_params(format!("Unsupported format '{}'. Use 'png' or 'jpeg'.", name)))?,
+            None => screen::SaveFormat::from_path(&params.path).unwrap_or(screen::SaveFormat::Png),
+        };
+        let path = match files::resolve_for_write(root, &params.path) {
+            Ok(path) => path,
+            Err(e) => return tool_error(files::error_category(&e), format!("{:#}", e), Some(json!({ "path": params.path }))),
+        };
+        if !params.overwrite.unwrap_or(false) && path.exists() {
+            return tool_error(
+                ErrorCategory::State,
+                format!("'{}' already exists. Pass overwrite=true to replace it.", params.path),
+                Some(json!({ "path": path })),
+            );
+        }
+
+        let (image, origin_x, origin_y) = match (params.x, params.y, params.width, params.height) {
+            (Some(x), Some(y), Some(width), Some(height)) => (
+                screen::capture_region(x, y, width, height)
+                    .map_err(|e| errors::backend(format!("{:#}", e)))?,
+                x,
+                y,
+            ),
+            (None, None, None, None) => screen::capture_primary()
+                .map_err(|e| errors::backend(format!("{:#}", e)))?,
+            _ => return Err(errors::invalid_params("Give x, y, width and height together, or none of them.")),
+        };
+        let bytes = screen::encode_image(&image, format, params.quality.unwrap_or(DEFAULT_JPEG_QUALITY))
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        if let Err(e) = std::fs::write(&path, &bytes) {
+            return tool_error(ErrorCategory::Backend, format!("Failed to write '{}': {}", params.path, e), None);
+        }
+        info!("Saved {}x{} {} screenshot ({} bytes) to {:?}", image.width(), image.height(), format.name(), bytes.len(), path);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "path": path,
+            "format": format.name(),
+            "size_bytes": bytes.len(),
+            "width": image.width(),
+            "height": image.height(),
+            "origin": { "x": origin_x, "y": origin_y },
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize save_screenshot result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "launch_application", description = "Starts an application and confirms it didn't crash on startup. Returns its pid for terminate_application. Prefer this over run_shell_command for opening apps. Bring its window forward with activate_window if it doesn't take focus.")]
+    async fn launch_application(
+        &self,
+        #[tool(aggr)] params: LaunchApplicationParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing launch_application: {:?}", params);
+        let startup_wait = Duration::from_millis(params.startup_wait_ms.unwrap_or(1000));
+        let args = params.args.unwrap_or_default();
+        let env = apps::ChildEnv { vars: params.env.unwrap_or_default(), clear: params.env_clear.unwrap_or(false) };
+        env.validate().map_err(|e| errors::invalid_params(e.to_string()))?;
+        let outcome = match self.apps.launch(&params.program, &args, params.working_dir.as_deref(), &env, startup_wait).await {
+            Ok(outcome) => outcome,
+            Err(e) => return tool_error(ErrorCategory::Backend, format!("{:#}", e), Some(json!({ "program": params.program }))),
+        };
+
+        let result_json = match outcome {
+            apps::LaunchOutcome::Running { pid } => json!({ "status": "success", "pid": pid, "running": true }),
+            // Exit code 0 during startup usually means the program handed off to an existing instance
+            apps::LaunchOutcome::Exited { pid, status } if status.success() => json!({
+                "status": "success",
+                "pid": pid,
+                "running": false,
+                "exit_code": status.code(),
+                "note": "The process exited right away without an error; it may have handed off to an already running instance. It is not tracked.",
+            }),
+            apps::LaunchOutcome::Exited { pid, status } => {
+                return tool_error(
+                    ErrorCategory::Backend,
+                    format!("'{}' exited during startup with {}.", params.program, status),
+                    Some(json!({ "pid": pid, "exit_code": status.code() })),
+                );
+            }
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize launch_application result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "open_url", description = "Opens a URL in the default browser (or the registered handler for file URLs). Only allowed schemes are accepted. Returns the launcher's pid and, best effort, the foreground window afterwards, which is normally the browser.")]
+    async fn open_url(
+        &self,
+        #[tool(aggr)] params: OpenUrlParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing open_url: {:?}", params);
+        let scheme = match browser::scheme(&params.url) {
+            Ok(scheme) => scheme,
+            Err(e) => return tool_error(ErrorCategory::InvalidParams, format!("{:#}", e), Some(json!({ "url": params.url }))),
+        };
+        if !browser::allowed_schemes().contains(&scheme) {
+            return tool_error(
+                ErrorCategory::Permission,
+                format!("Scheme '{}' is not allowed.", scheme),
+                Some(json!({ "allowed_schemes": browser::allowed_schemes() })),
+            );
+        }
+        let (program, args) = browser::opener(&params.url);
+        let wait = Duration::from_millis(params.wait_ms.unwrap_or(1500));
+        let launcher = match self.apps.launch(program, &args, None, &apps::ChildEnv::default(), wait).await {
+            // The opener usually hands the URL to the browser and exits; a running opener is the browser itself
+            Ok(apps::LaunchOutcome::Running { pid }) => json!({ "pid": pid, "running": true }),
+            Ok(apps::LaunchOutcome::Exited { pid, status }) if status.success() => {
+                json!({ "pid": pid, "running": false, "exit_code": status.code() })
+            }
+            Ok(apps::LaunchOutcome::Exited { pid, status }) => {
+                return tool_error(
+                    ErrorCategory::Backend,
+                    format!("'{}' failed to open the URL: exited with {}.", program, status),
+                    Some(json!({ "url": params.url, "pid": pid, "exit_code": status.code() })),
+                );
+            }
+            Err(e) => return tool_error(ErrorCategory::Backend, format!("{:#}", e), Some(json!({ "url": params.url, "program": program }))),
+        };
+
+        // Browsers are single-instance and the opener doesn't report which process took the URL,
+        // so the foreground window is the best available answer
+        let browser_window = window::active_window().unwrap_or_else(|e| {
+            warn!("open_url could not read the foreground window: {:#}", e);
+            None
+        });
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "url": params.url,
+            "scheme": scheme,
+            "launcher": launcher,
+            "browser_window": browser_window,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize open_url result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "terminate_application", description = "Closes an application started with launch_application, identified by its pid. Asks it to close first and kills it if it doesn't exit within a few seconds (or immediately with force=true).")]
+    async fn terminate_application(
+        &self,
+        #[tool(aggr)] params: TerminateApplicationParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing terminate_application: {:?}", params);
+        let termination = match self.apps.terminate(params.pid, params.force.unwrap_or(false), Duration::from_secs(3)).await {
+            Ok(termination) => termination,
+            Err(e) => return tool_error(ErrorCategory::NotFound, format!("{:#}", e), Some(json!({ "pid": params.pid }))),
+        };
+        info!("Terminated PID {}: {:?}", params.pid, termination);
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "pid": params.pid,
+            "exit_code": termination.exit_code,
+            "forced": termination.forced,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize terminate_application result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "list_processes", description = "Lists running processes with pid, parent pid, name, executable path, CPU usage (percent of one core, sampled over ~200 ms) and resident memory, busiest first. Covers background processes and extra instances that have no window. Use name_filter to narrow it down.")]
+    async fn list_processes(
+        &self,
+        #[tool(aggr)] params: ListProcessesParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing list_processes: {:?}", params);
+        let limit = params.limit.unwrap_or(DEFAULT_PROCESS_LIMIT);
+        let mut processes = processes::list(params.name_filter.as_deref()).await;
+        let total = processes.len();
+        processes.truncate(limit);
+        info!("Found {} process(es), returning {}.", total, processes.len());
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "total": total,
+            "truncated": total > processes.len(),
+            "processes": processes,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize list_processes result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "kill_process", description = "Ends any process by pid (see list_processes). Asks it to close first and kills it if it doesn't exit within a few seconds (or immediately with force=true). Prefer terminate_application for apps started with launch_application; their pids are handed over to it automatically.")]
+    async fn kill_process(
+        &self,
+        #[tool(aggr)] params: KillProcessParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing kill_process: {:?}", params);
+        if params.pid == std::process::id() {
+            return tool_error(ErrorCategory::Permission, "Refusing to kill the MCP server's own process.", Some(json!({ "pid": params.pid })));
+        }
+        // A launched app is a child of this server; ending it through the registry also reaps it
+        if self.apps.is_tracked(params.pid) {
+            return self.terminate_application(TerminateApplicationParams { pid: params.pid, force: params.force }).await;
+        }
+        let killed = match processes::kill(params.pid, params.force.unwrap_or(false), Duration::from_secs(3)).await {
+            Ok(Some(killed)) => killed,
+            Ok(None) => return tool_error(ErrorCategory::NotFound, format!("No process with PID {} is running.", params.pid), Some(json!({ "pid": params.pid }))),
+            Err(e) => return tool_error(ErrorCategory::Permission, format!("{:#}", e), Some(json!({ "pid": params.pid }))),
+        };
+        info!("Killed PID {}: {:?}", params.pid, killed);
+        let mut result_json = json!({
+            "status": "success",
+            "pid": params.pid,
+            "name": killed.name,
+            "forced": killed.forced,
+            "exited": killed.exited,
+        });
+        if !killed.exited {
+            result_json["warning"] = json!("The process was signalled but still appears to be running.");
+        }
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize kill_process result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    // --- Tools for OpenAI Computer Use Actions ---
+    #[tool(name = "execute_openai_click", description = "Executes a mouse click action requested by the OpenAI Computer Use model: moves to (x, y), then clicks 'button' ('left', 'right', 'middle', 'back', 'forward', or 'none' to only move). The result includes target_window, the window under the cursor afterwards, or null if it can't be determined.")]
+    async fn execute_openai_click(
+        &self,
+        #[tool(aggr)] params: OpenAIClickParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing OpenAI action: click at ({}, {}) with button '{}'", params.x, params.y, params.button);
+        let button = match params.button.to_lowercase().as_str() {
+            "none" => None,
+            name => Some(input::parse_button(name)
+                .ok_or_else(|| errors::invalid_params(format!("OpenAI Click: Invalid button '{}'", params.button)))?),
+        };
+        let (x, y, clamped) = screen::clamp_mouse_target(params.x, params.y)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        if !screen::point_on_screen(x, y) {
+            return tool_error(
+                ErrorCategory::Coordinate,
+                format!("Coordinate ({}, {}) is off screen. Use get_screen_details for the valid bounds.", x, y),
+                Some(json!({ "x": x, "y": y })),
+            );
+        }
+        let throttled_ms = self.throttle().await?;
+        let location = self.input.with(|enigo| {
+            enigo.move_mouse(x, y, Coordinate::Abs)
+                .map_err(|e| errors::backend(format!("OpenAI Click: Failed to move mouse: {e:?}")))?;
+            if let Some(button) = button {
+                enigo.button(button, Direction::Click)
+                    .map_err(|e| errors::backend(format!("OpenAI Click: Failed to click button: {e:?}")))?;
+            }
+            Ok(enigo.location().ok())
+        })?;
+        let target_window = location.and_then(|(x, y)| target_window_at(x, y));
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "x": x,
+            "y": y,
+            "clamped": clamped,
+            "target_window": target_window,
+            "throttled_ms": throttled_ms,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize execute_openai_click result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "execute_openai_scroll", description = "Executes a mouse scroll action requested by the OpenAI Computer Use model: moves to (x, y), then scrolls by scroll_x/scroll_y pixels, sent as wheel notches of about 100 pixels each.")]
+    async fn execute_openai_scroll(
+        &self,
+        #[tool(aggr)] params: OpenAIScrollParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing OpenAI action: scroll at ({}, {}) with delta ({}, {})", params.x, params.y, params.scroll_x, params.scroll_y);
+        // Any non-zero delta scrolls at least one notch
+        let notches = |pixels: i32| {
+            let notches = (pixels as f64 / scroll::PIXELS_PER_NOTCH as f64).round() as i32;
+            if notches == 0 { pixels.signum() } else { notches.clamp(-MAX_OPENAI_SCROLL_NOTCHES, MAX_OPENAI_SCROLL_NOTCHES) }
+        };
+        let (notches_x, notches_y) = (notches(params.scroll_x), notches(params.scroll_y));
+        let (x, y, clamped) = screen::clamp_mouse_target(params.x, params.y)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        if !screen::point_on_screen(x, y) {
+            return tool_error(
+                ErrorCategory::Coordinate,
+                format!("Coordinate ({}, {}) is off screen. Use get_screen_details for the valid bounds.", x, y),
+                Some(json!({ "x": x, "y": y })),
+            );
+        }
+        let throttled_ms = self.throttle().await?;
+        self.input.with(|enigo| {
+            enigo.move_mouse(x, y, Coordinate::Abs)
+                .map_err(|e| errors::backend(format!("OpenAI Scroll: Failed to move mouse: {e:?}")))?;
+            // enigo scrolls down/right for positive lengths, like the OpenAI deltas
+            if notches_y != 0 {
+                enigo.scroll(notches_y, Axis::Vertical)
+                    .map_err(|e| errors::backend(format!("OpenAI Scroll: Failed vertical scroll: {e:?}")))?;
+            }
+            if notches_x != 0 {
+                enigo.scroll(notches_x, Axis::Horizontal)
+                    .map_err(|e| errors::backend(format!("OpenAI Scroll: Failed horizontal scroll: {e:?}")))?;
+            }
+            Ok(())
+        })?;
+        Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "x": x, "y": y, "clamped": clamped, "notches_x": notches_x, "notches_y": notches_y, "throttled_ms": throttled_ms }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize execute_openai_scroll result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "execute_openai_keypress", description = "Executes key presses requested by the OpenAI Computer Use model: presses all keys in order (e.g. ['CTRL', 'c']), then releases them in reverse.")]
+    async fn execute_openai_keypress(
+        &self,
+        #[tool(aggr)] params: OpenAIKeyPressParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing OpenAI action: keypress sequence: {:?}", params.keys);
+        if params.keys.is_empty() {
+            return Err(errors::invalid_params("OpenAI Keypress: 'keys' must not be empty."));
+        }
+        let keys = params.keys
+            .iter()
+            .map(|name| input::parse_key(openai_key_name(name)).ok_or_else(|| errors::invalid_params(format!("OpenAI Keypress: {}", input::key_error(name)))))
+            .collect::<Result<Vec<Key>, ErrorData>>()?;
+        let throttled_ms = self.throttle().await?;
+        self.input.with(|enigo| {
+            let mut pressed = Vec::with_capacity(keys.len());
+            let mut result = Ok(());
+            for key in &keys {
+                if let Err(e) = enigo.key(*key, Direction::Press) {
+                    result = Err(errors::backend(format!("OpenAI Keypress: Failed to press key '{:?}': {}", key, e)));
+                    break;
+                }
+                pressed.push(*key);
+            }
+            // Released in reverse even after a failed press, so no modifier stays down
+            for key in pressed.iter().rev() {
+                if let Err(e) = enigo.key(*key, Direction::Release) {
+                    warn!("OpenAI Keypress: Failed to release key '{:?}': {}", key, e);
+                }
+            }
+            result
+        })?;
+        info!("OpenAI keypress sequence executed successfully.");
+        Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "throttled_ms": throttled_ms }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize execute_openai_keypress result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "execute_openai_type", description = "Executes typing text requested by the OpenAI Computer Use model. Set per_char_delay_ms for targets that drop fast input, or humanize for randomized human-like timing.")]
+    async fn execute_openai_type(
+        &self,
+        #[tool(aggr)] params: OpenAITypeParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing OpenAI action: type {} chars (per_char_delay_ms: {:?}, humanize: {:?})", params.text.chars().count(), params.per_char_delay_ms, params.humanize);
+        let throttled_ms = self.throttle().await?;
+        let humanize_seed = if params.humanize.unwrap_or(false) {
+            let seed = params.humanize_seed.unwrap_or_else(action_log::now_ms);
+            let delays = humanize::delays(&params.text, humanize::TypingProfile::from_env(), seed);
+            self.input.type_with_delays(&params.text, &delays).await?;
+            Some(seed)
+        } else {
+            self.input.type_text(&params.text, params.per_char_delay_ms).await?;
+            None
+        };
+        info!("OpenAI text typing successful.");
+        Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "humanize_seed": humanize_seed, "throttled_ms": throttled_ms }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize execute_openai_type result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_power_state", description = "Reports whether the machine runs on AC or battery, the battery percentage and whether it is charging. Returns supported=false when the server was built without the 'battery' feature.")]
+    async fn get_power_state(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_power_state.");
+        let result_json = match power::power_state().map_err(|e| errors::backend(format!("{:#}", e)))? {
+            Some(state) => {
+                info!("Power source: {}, charge: {:?}%, charging: {}", state.source, state.percent, state.charging);
+                json!({
+                    "status": "success",
+                    "supported": true,
+                    "source": state.source,
+                    "percent": state.percent,
+                    "charging": state.charging,
+                    "batteries": state.batteries,
+                })
+            }
+            None => json!({ "status": "success", "supported": false }),
+        };
+        Ok(CallToolResult::success(vec![Content::json(result_json)
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_power_state result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_brightness", description = "Returns the brightness (0-100) of each display whose brightness the OS controls (laptop panels, mostly), with the index to pass as 'monitor'. Returns supported=false where there is none or no backend.")]
+    async fn get_brightness(
+        &self,
+        #[tool(aggr)] params: GetBrightnessParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_brightness: {:?}", params);
+        let displays = brightness::brightness().map_err(|e| errors::backend(format!("{:#}", e)))?;
+        brightness_result(displays, params.monitor, "get_brightness")
+    }
+
+    #[tool(name = "set_brightness", description = "Sets the brightness (0-100) of one display ('monitor' index from get_brightness) or of all adjustable displays. Returns the new levels, or supported=false where brightness can't be controlled.")]
+    async fn set_brightness(
+        &self,
+        #[tool(aggr)] params: SetBrightnessParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing set_brightness: {:?}", params);
+        if params.level > 100 {
+            return Err(errors::invalid_params(format!("Brightness level must be 0-100, got {}.", params.level)));
+        }
+        let displays = match brightness::set_brightness(params.monitor, params.level) {
+            Ok(displays) => displays,
+            Err(e) if e.downcast_ref::<brightness::NoSuchDisplay>().is_some() => {
+                return tool_error(ErrorCategory::Coordinate, format!("{:#}.", e), Some(json!({ "monitor": params.monitor })));
+            }
+            Err(e) if e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::PermissionDenied) => {
+                return tool_error(
+                    ErrorCategory::Permission,
+                    format!("{:#}. The server needs write access to the backlight device (root, or a udev rule for the video group).", e),
+                    Some(json!({ "monitor": params.monitor })),
+                );
+            }
+            Err(e) => return Err(errors::backend(format!("{:#}", e))),
+        };
+        brightness_result(displays, params.monitor, "set_brightness")
+    }
+
+    #[tool(name = "get_volume", description = "Returns the default output device's volume (0-100) and whether it is muted. Returns supported=false where no audio backend is available.")]
+    async fn get_volume(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_volume.");
+        let state = volume::volume().map_err(|e| errors::backend(format!("{:#}", e)))?;
+        volume_result(state, "get_volume")
+    }
+
+    #[tool(name = "set_volume", description = "Sets the default output device's volume (0-100). Does not change the mute state. Returns the new volume, or supported=false where no audio backend is available.")]
+    async fn set_volume(
+        &self,
+        #[tool(aggr)] params: SetVolumeParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing set_volume: {:?}", params);
+        if params.level > 100 {
+            return Err(errors::invalid_params(format!("Volume level must be 0-100, got {}.", params.level)));
+        }
+        let state = volume::set_volume(params.level)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        volume_result(state, "set_volume")
+    }
+
+    #[tool(name = "mute", description = "Mutes the default output device. Returns the new volume state, or supported=false where no audio backend is available.")]
+    async fn mute(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing mute.");
+        let state = volume::set_muted(true).map_err(|e| errors::backend(format!("{:#}", e)))?;
+        volume_result(state, "mute")
+    }
+
+    #[tool(name = "unmute", description = "Unmutes the default output device. Returns the new volume state, or supported=false where no audio backend is available.")]
+    async fn unmute(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing unmute.");
+        let state = volume::set_muted(false).map_err(|e| errors::backend(format!("{:#}", e)))?;
+        volume_result(state, "unmute")
+    }
+
+    #[tool(name = "reset_input", description = "Releases all mouse buttons and the modifier keys (Ctrl, Shift, Alt, Meta), plus any other key this client pressed. Call this if a key or button seems stuck, e.g. text comes out in capitals or clicks behave like drags.")]
+    async fn reset_input(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing reset_input.");
+        let held = self.lock_session()?.take_held();
+        let were_held: Vec<String> = held.keys.iter().map(|k| format!("{:?}", k))
+            .chain(held.scancodes.iter().map(|s| format!("Scancode({:#x})", s)))
+            .chain(held.buttons.iter().map(|b| format!("{:?}", b)))
+            .collect();
+
+        // Release unconditionally: the model may have held something through another tool or client
+        let mut keys = input::MODIFIER_KEYS.to_vec();
+        keys.extend(held.keys.into_iter().filter(|k| !input::MODIFIER_KEYS.contains(k)));
+        let released = self.input.release(&keys, &held.scancodes, &input::RELEASE_BUTTONS)?;
+        info!("reset_input released {:?} (tracked as held: {:?})", released, were_held);
+
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "released": released,
+            "were_held": were_held,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize reset_input result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_input_state", description = "Returns which mouse buttons and modifier keys this client currently holds down (pressed with click_type/key_action 'Press' and not yet released), as booleans, plus any other held keys or scancodes. Use it to work out what is stuck before calling reset_input. Input from the physical user or other clients is not included.")]
+    async fn get_input_state(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_input_state.");
+        let held = self.lock_session()?.held();
+        let button = |b: Button| held.buttons.contains(&b);
+        let key = |k: Key| held.keys.contains(&k);
+        let other_keys: Vec<String> = held.keys.iter()
+            .filter(|k| !input::MODIFIER_KEYS.contains(k))
+            .map(|k| format!("{:?}", k))
+            .collect();
+        let scancodes: Vec<String> = held.scancodes.iter().map(|s| format!("{:#x}", s)).collect();
+
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "any_held": !held.is_empty(),
+            "mouse_buttons": {
+                "left": button(Button::Left),
+                "right": button(Button::Right),
+                "middle": button(Button::Middle),
+                "back": button(Button::Back),
+                "forward": button(Button::Forward),
+            },
+            "modifiers": {
+                "control": key(Key::Control),
+                "shift": key(Key::Shift),
+                "alt": key(Key::Alt),
+                "meta": key(Key::Meta),
+            },
+            "other_keys": other_keys,
+            "scancodes": scancodes,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_input_state result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "reset_input_backend", description = "Releases all held inputs and re-initializes the input backend. Use this if mouse/keyboard tools stop working or inputs appear stuck.")]
+    async fn reset_input_backend(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing reset_input_backend.");
+        let report = self.input.reset()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "had_instance": report.had_instance,
+            "released_keys": report.released_keys,
+            "released_buttons": report.released_buttons,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize reset_input_backend result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "assert_pixel", description = "Asserts that the pixel at (x, y) has the expected color. Fails the call with the actual and expected colors if it does not match.")]
+    async fn assert_pixel(
+        &self,
+        #[tool(aggr)] params: AssertPixelParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing assert_pixel: {:?}", params);
+        let expected = screen::parse_hex_color(&params.expected_hex)
+            .map_err(|e| errors::invalid_params(e.to_string()))?;
+        let tolerance = params.tolerance.unwrap_or(0);
+        if !screen::point_on_screen(params.x, params.y) {
+            return tool_error(
+                ErrorCategory::Coordinate,
+                format!("Coordinate ({}, {}) is off screen. Use get_screen_details for the valid bounds.", params.x, params.y),
+                Some(json!({ "x": params.x, "y": params.y })),
+            );
+        }
+
+        let pixel = screen::read_screen_pixel(params.x, params.y)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let actual_hex = screen::to_hex_color(&pixel);
+        let distance = screen::color_distance(&pixel, expected);
 
-                let result_json = json!({
-                    "status": "success",
-                    "found": true,
-                    "title": title,
-                    "app_name": app_name,
-                    "x": x,
-                    "y": y,
-                    "width": width,
-                    "height": height,
-                    "is_maximized": window.is_maximized().unwrap_or(false) // Include maximized state
-                });
+        let details = json!({
+            "x": params.x,
+            "y": params.y,
+            "expected": params.expected_hex,
+            "actual": actual_hex,
+            "distance": distance,
+            "tolerance": tolerance,
+        });
+        if distance > tolerance {
+            warn!("assert_pixel failed at ({}, {}): expected {}, got {} (distance {} > tolerance {})", params.x, params.y, params.expected_hex, actual_hex, distance, tolerance);
+            return Err(ErrorData::new(
+                ASSERTION_FAILED,
+                format!("Assertion failed: pixel at ({}, {}) is {} but expected {} (distance {}, tolerance {})", params.x, params.y, actual_hex, params.expected_hex, distance, tolerance),
+                Some(errors::with_category(details, ErrorCategory::Assertion)),
+            ));
+        }
 
-                return Ok(CallToolResult::success(vec![Content::json(result_json)
-                    .map_err(|e| anyhow!(e).context("Failed to serialize find_window result"))
-                    .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
-                ]));
+        info!("assert_pixel passed at ({}, {}).", params.x, params.y);
+        Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "passed": true, "details": details }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize assert_pixel result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "wait_for_pixel", description = "Waits until the pixel at (x, y) has the given color (within tolerance) or the timeout expires. Use instead of fixed waits when waiting for UI to load. Reports whether it matched, the last color seen and how long it waited.")]
+    async fn wait_for_pixel(
+        &self,
+        #[tool(aggr)] params: WaitForPixelParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing wait_for_pixel: {:?}", params);
+        let expected = screen::parse_hex_color(&params.hex)
+            .map_err(|e| errors::invalid_params(e.to_string()))?;
+        let tolerance = params.tolerance.unwrap_or(0);
+        let timeout = Duration::from_millis(params.timeout_ms.unwrap_or(10_000));
+        let poll_interval = Duration::from_millis(params.poll_interval_ms.unwrap_or(100).max(10));
+        if !screen::point_on_screen(params.x, params.y) {
+            return tool_error(
+                ErrorCategory::Coordinate,
+                format!("Coordinate ({}, {}) is off screen. Use get_screen_details for the valid bounds.", params.x, params.y),
+                Some(json!({ "x": params.x, "y": params.y })),
+            );
+        }
+
+        let started = Instant::now();
+        let (matched, last_pixel) = loop {
+            let pixel = screen::read_screen_pixel(params.x, params.y)
+                .map_err(|e| errors::backend(format!("{:#}", e)))?;
+            if screen::color_distance(&pixel, expected) <= tolerance {
+                break (true, pixel);
+            }
+            if started.elapsed() >= timeout {
+                break (false, pixel);
             }
+            progress::report(started.elapsed().as_millis() as u32, Some(timeout.as_millis() as u32)).await;
+            sleep(poll_interval.min(timeout.saturating_sub(started.elapsed()))).await;
+        };
+        let waited_ms = started.elapsed().as_millis() as u64;
+        let last_hex = screen::to_hex_color(&last_pixel);
+        if matched {
+            info!("wait_for_pixel matched {} at ({}, {}) after {} ms.", params.hex, params.x, params.y, waited_ms);
+        } else {
+            info!("wait_for_pixel timed out after {} ms; last color at ({}, {}) was {}.", waited_ms, params.x, params.y, last_hex);
         }
 
-        // If no window was found after checking all
-        info!("No matching window found for query: '{}'", params.title_query);
         Ok(CallToolResult::success(vec![Content::json(json!({
-            "status": "success", // Still a successful tool execution, just no result found
-            "found": false,
-            "message": format!("No non-minimized window found matching title query '{}'", params.title_query)
+            "status": "success",
+            "matched": matched,
+            "waited_ms": waited_ms,
+            "expected": params.hex,
+            "last_color": last_hex,
+            "distance": screen::color_distance(&last_pixel, expected),
+            "tolerance": tolerance,
         }))
-            .map_err(|e| anyhow!(e).context("Failed to serialize find_window 'not found' result"))
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
+            .map_err(|e| anyhow!(e).context("Failed to serialize wait_for_pixel result"))
+            .map_err(|e| errors::backend(e.to_string()))?
         ]))
-        // Alternatively, you could return an error:
-        // Err(ErrorData::new(ErrorCode::NOT_FOUND, format!("No non-minimized window found matching title query '{}'", params.title_query), None))
     }
 
-    #[tool(name = "move_mouse", description = "Moves the mouse cursor")]
-    async fn move_mouse(
+    #[tool(name = "wait_for_window", description = "Waits until a non-minimized window whose title contains title_substring exists (and, with foreground=true, is focused) or the timeout expires. Use after launch_application instead of a fixed sleep. Reports whether it matched, the window's id and geometry, and how long it waited.")]
+    async fn wait_for_window(
         &self,
-        #[tool(aggr)] params: MoveMouseParams
+        #[tool(aggr)] params: WaitForWindowParams
     ) -> Result<CallToolResult, ErrorData> {
-        info!("Executing move mouse to: {:?}", params);
-        let mut enigo = Enigo::new(&Settings::default())
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
+        info!("Executing wait_for_window: {:?}", params);
+        if params.title_substring.trim().is_empty() {
+            return Err(errors::invalid_params("'title_substring' must not be empty."));
+        }
+        let query = params.title_substring.to_lowercase();
+        let foreground = params.foreground.unwrap_or(false);
+        let timeout = Duration::from_millis(params.timeout_ms.unwrap_or(10_000));
+        let poll_interval = Duration::from_millis(params.poll_interval_ms.unwrap_or(250).max(10));
 
-        let coordinate = match params.coordinate.to_lowercase().as_str() {
-            "absolute" | "abs" => Coordinate::Abs,
-            "relative" | "rel" | _ => Coordinate::Rel,
+        let started = Instant::now();
+        // The matching window of the latest check, even if it isn't the foreground window
+        let mut last_seen: Option<serde_json::Value>;
+        let matched = loop {
+            let windows = xcap::Window::all()
+                .context("Failed to get window list")
+                .map_err(|e| errors::backend(e.to_string()))?;
+            let active_id = if foreground {
+                window::active_window().map_err(|e| errors::backend(format!("{:#}", e)))?.map(|w| w.id)
+            } else {
+                None
+            };
+            last_seen = None;
+            let mut found = false;
+            for window in windows {
+                if window.is_minimized().unwrap_or(true) {
+                    continue;
+                }
+                let Ok(title) = window.title() else { continue };
+                if !title.to_lowercase().contains(&query) {
+                    continue;
+                }
+                let id = window.id().unwrap_or_default();
+                let is_foreground = active_id == Some(id as u64);
+                last_seen = Some(json!({
+                    "id": id,
+                    "title": title,
+                    "app_name": window.app_name().unwrap_or_default(),
+                    "x": window.x().unwrap_or(0),
+                    "y": window.y().unwrap_or(0),
+                    "width": window.width().unwrap_or(0),
+                    "height": window.height().unwrap_or(0),
+                    "foreground": foreground.then_some(is_foreground),
+                }));
+                if !foreground || is_foreground {
+                    found = true;
+                    break;
+                }
+            }
+            if found {
+                break true;
+            }
+            if started.elapsed() >= timeout {
+                break false;
+            }
+            progress::report(started.elapsed().as_millis() as u32, Some(timeout.as_millis() as u32)).await;
+            sleep(poll_interval.min(timeout.saturating_sub(started.elapsed()))).await;
         };
-        if coordinate == Coordinate::Rel { info!("Moving mouse relatively by ({}, {})", params.x, params.y); }
-        else { info!("Moving mouse absolutely to ({}, {})", params.x, params.y); }
-
-        enigo.move_mouse(params.x, params.y, coordinate)
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, format!("Couldnt move mouse: {e:?}"), None))?;
+        let waited_ms = started.elapsed().as_millis() as u64;
+        if matched {
+            info!("wait_for_window found '{}' after {} ms.", params.title_substring, waited_ms);
+        } else if last_seen.is_some() {
+            info!("wait_for_window timed out after {} ms; '{}' exists but never became the foreground window.", waited_ms, params.title_substring);
+        } else {
+            info!("wait_for_window timed out after {} ms; no window matches '{}'.", waited_ms, params.title_substring);
+        }
 
-        let (x, y) = enigo.location().map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
-        info!("Mouse moved successfully.");
-        Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "current_x": x, "current_y": y }))
-            .map_err(|e| anyhow!(e).context("Failed to serialize move_mouse result"))
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "matched": matched,
+            "waited_ms": waited_ms,
+            "window": last_seen,
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize wait_for_window result"))
+            .map_err(|e| errors::backend(e.to_string()))?
         ]))
     }
 
-    #[tool(name = "get_mouse_position", description = "Gets the current absolute screen coordinates (X, Y) of the mouse cursor")]
-    async fn get_mouse_position(
+    #[tool(name = "screen_changed_since", description = "Cheaply checks whether the primary screen changed since an earlier call, without a vision round-trip. Pass the 'token' from the previous call as since_token; returns the changed percentage and the bounding box of the change, plus a new token for the next check.")]
+    async fn screen_changed_since(
         &self,
-        #[tool(aggr)] _params: GetMousePositionParams, // Use aggr with dummy struct
+        #[tool(aggr)] params: ScreenChangedSinceParams
     ) -> Result<CallToolResult, ErrorData> {
-        info!("Executing get mouse position.");
-        let enigo = Enigo::new(&Settings::default())
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
+        info!("Executing screen_changed_since: {:?}", params);
+        let (image, origin_x, origin_y) = screen::capture_primary()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
+        let signature = diff::ScreenSignature::from_image(&image, origin_x, origin_y);
+
+        let mut session = self.lock_session()?;
+        let mut result_json = json!({ "status": "success" });
+        match params.since_token.as_deref() {
+            None => {
+                result_json["baseline"] = json!(true);
+            }
+            Some(token) => match session.snapshot(token) {
+                None => {
+                    result_json["baseline"] = json!(true);
+                    result_json["message"] = json!(format!("Unknown or expired token '{}'; recorded a new baseline instead.", token));
+                }
+                Some(earlier) => match signature.compare(earlier) {
+                    Some(report) => {
+                        info!("Screen changed {:.2}% since {} (region {:?}).", report.changed_percent, token, report.changed_region);
+                        result_json["changed"] = json!(report.changed);
+                        result_json["changed_percent"] = json!(report.changed_percent);
+                        result_json["changed_region"] = json!(report.changed_region);
+                    }
+                    None => {
+                        // Different resolution or layout: everything is new as far as the model is concerned
+                        result_json["changed"] = json!(true);
+                        result_json["changed_percent"] = json!(100.0);
+                        result_json["message"] = json!("The screen size or layout changed since the earlier capture.");
+                    }
+                },
+            },
+        }
+        result_json["token"] = json!(session.remember_snapshot(signature));
 
-        let (x, y) = enigo.location().map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
-        info!("Mouse position retrieved successfully: ({}, {})", x, y);
-        let result_json = json!({ "status": "success", "x": x, "y": y });
         Ok(CallToolResult::success(vec![Content::json(result_json)
-            .map_err(|e| anyhow!(e).context("Failed to serialize get_mouse_position result"))
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
+            .map_err(|e| anyhow!(e).context("Failed to serialize screen_changed_since result"))
+            .map_err(|e| errors::backend(e.to_string()))?
         ]))
     }
 
-    #[tool(name = "mouse_action", description = "Performs a mouse action (click, press, release) or scrolls the mouse wheel")]
-    async fn mouse_action(
+    #[tool(name = "assert_text", description = "Asserts (via OCR) that the given text is visible on screen or inside a region. Fails the call with the recognized text if it is not found.")]
+    async fn assert_text(
         &self,
-        #[tool(aggr)] params: MouseClickParams
+        #[tool(aggr)] params: AssertTextParams
     ) -> Result<CallToolResult, ErrorData> {
-        info!("Executing mouse action: {:?}", params);
-        let mut enigo = Enigo::new(&Settings::default())
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
+        info!("Executing assert_text: {:?}", params);
+        let off_screen_origin = match (params.x, params.y) {
+            (Some(x), Some(y)) if !screen::point_on_screen(x, y) => Some((x, y)),
+            _ => None,
+        };
+        if let Some((x, y)) = off_screen_origin {
+            return tool_error(
+                ErrorCategory::Coordinate,
+                format!("Region origin ({}, {}) is off screen. Use get_screen_details for the valid bounds.", x, y),
+                Some(json!({ "x": x, "y": y })),
+            );
+        }
+        let image = match (params.x, params.y, params.width, params.height) {
+            (Some(x), Some(y), Some(width), Some(height)) => screen::capture_region(x, y, width, height),
+            (None, None, None, None) => screen::capture_primary().map(|(image, _, _)| image),
+            _ => return Err(errors::invalid_params("Region requires all of 'x', 'y', 'width' and 'height'.")),
+        }
+        .map_err(|e| errors::backend(format!("{:#}", e)))?;
 
-        let button_str = params.button.to_lowercase();
-        let action_str = params.click_type.as_deref().unwrap_or("click").to_lowercase();
+        let lang = params.lang.as_deref().unwrap_or(ocr::DEFAULT_LANG);
+        let recognized = ocr::recognize_text(&image, lang)
+            .map_err(|e| errors::backend(format!("{:#}", e)))?;
 
-        let direction = match action_str.as_str() {
-            "click" => Direction::Click, "press" => Direction::Press, "release" => Direction::Release,
-            "double" => { warn!("Double click not directly supported by enigo, performing single click instead."); Direction::Click }
-            _ => { warn!("Invalid click_type '{}', defaulting to Click.", action_str); Direction::Click }
+        let case_sensitive = params.case_sensitive.unwrap_or(false);
+        let found = if case_sensitive {
+            recognized.contains(&params.expected_text)
+        } else {
+            recognized.to_lowercase().contains(&params.expected_text.to_lowercase())
         };
 
-        let button_enum = match button_str.as_str() {
-            "left" => Button::Left, "right" => Button::Right, "middle" => Button::Middle,
-            "back" => Button::Back, "forward" => Button::Forward,
-            "scrollup" | "scroll_up" => Button::ScrollUp,
-            "scrolldown" | "scroll_down" => Button::ScrollDown,
-            "scrollleft" | "scroll_left" => Button::ScrollLeft,
-            "scrollright" | "scroll_right" => Button::ScrollRight,
-            _ => return Err(ErrorData::invalid_params( format!("Invalid mouse button/action specified: '{}'.", params.button), None)),
-        };
+        let details = json!({
+            "expected": params.expected_text,
+            "actual": recognized,
+            "case_sensitive": case_sensitive,
+            "region": { "x": params.x, "y": params.y, "width": params.width, "height": params.height },
+        });
+        if !found {
+            warn!("assert_text failed: '{}' not found in OCR output", params.expected_text);
+            return Err(ErrorData::new(
+                ASSERTION_FAILED,
+                format!("Assertion failed: text '{}' was not found on screen", params.expected_text),
+                Some(errors::with_category(details, ErrorCategory::Assertion)),
+            ));
+        }
 
-        enigo.button(button_enum, direction).map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
-        info!("Mouse action successful: Button='{}', Action='{:?}'", button_str, direction);
-        Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "button": button_str, "action": action_str }))
-            .map_err(|e| anyhow!(e).context("Failed to serialize mouse_action result"))
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
+        info!("assert_text passed: '{}' found.", params.expected_text);
+        Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "passed": true, "details": details }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize assert_text result"))
+            .map_err(|e| errors::backend(e.to_string()))?
         ]))
     }
 
-    #[tool(name = "keyboard_action", description = "Types text or performs a key event (click, press, release)")]
-    async fn keyboard_action(
+    #[tool(name = "ping", description = "Liveness check. Returns immediately with the server's current time; does not touch the desktop.")]
+    async fn ping(
         &self,
-        #[tool(aggr)] params: KeyboardActionParams
+        #[tool(aggr)] _params: NoParams
     ) -> Result<CallToolResult, ErrorData> {
-        info!("Executing keyboard action: {:?}", params);
-        let mut enigo = Enigo::new(&Settings::default())
-             .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
+        let server_time_ms = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
 
-        if let Some(key_str) = &params.key {
-            let action_str = params.key_action.as_deref().unwrap_or("click").to_lowercase();
-            info!("Performing key action: key='{}', action='{}'", key_str, action_str);
-            let direction = match action_str.as_str() {
-                "click" => Direction::Click, "press" => Direction::Press, "release" => Direction::Release,
-                 _ => { warn!("Invalid key_action '{}', defaulting to Click.", action_str); Direction::Click }
-            };
-            let key_enum = match key_str.to_lowercase().as_str() {
-                "alt" | "altgraph" => Key::Alt, "backspace" => Key::Backspace, "capslock" | "caps_lock" => Key::CapsLock,
-                "control" | "ctrl" => Key::Control, "delete" => Key::Delete, "down" | "downarrow" => Key::DownArrow,
-                "end" => Key::End, "escape" | "esc" => Key::Escape,
-                "f1" => Key::F1, "f2" => Key::F2, "f3" => Key::F3, "f4" => Key::F4, "f5" => Key::F5,
-                "f6" => Key::F6, "f7" => Key::F7, "f8" => Key::F8, "f9" => Key::F9, "f10" => Key::F10,
-                "f11" => Key::F11, "f12" => Key::F12, "home" => Key::Home, "left" | "leftarrow" => Key::LeftArrow,
-                "meta" | "win" | "command" | "super" | "windows" => Key::Meta, "option" => Key::Option,
-                "pagedown" | "page_down" => Key::PageDown, "pageup" | "page_up" => Key::PageUp,
-                "return" | "enter" => Key::Return, "right" | "rightarrow" => Key::RightArrow,
-                "shift" => Key::Shift, "space" => Key::Space, "tab" => Key::Tab, "up" | "uparrow" => Key::UpArrow,
-                s if s.chars().count() == 1 => Key::Unicode(s.chars().next().unwrap()),
-                _ => return Err(ErrorData::invalid_params( format!("Unsupported key specified: '{}'.", key_str), None)),
-            };
-            enigo.key(key_enum, direction).map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
-            info!("Key action successful.");
-            Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "key": key_str, "action": action_str }))
-                .map_err(|e| anyhow!(e).context("Failed to serialize keyboard key action result"))
-                .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
-            ]))
-        } else if let Some(text_to_type) = &params.text {
-            info!("Typing text: '{}'", text_to_type);
-            enigo.text(text_to_type).map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
-            info!("Text typing successful.");
-            Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "text_typed": text_to_type }))
-                .map_err(|e| anyhow!(e).context("Failed to serialize keyboard text typing result"))
-                .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
-            ]))
-        } else {
-            Err(ErrorData::invalid_params("Keyboard action requires either 'key' or 'text' parameter.".to_string(), None))
-        }
+        Ok(CallToolResult::success(vec![Content::json(json!({ "pong": true, "server_time_ms": server_time_ms }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize ping result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
     }
 
-    #[tool(name = "capture_screen", description = "Captures the screen (or a region) and returns image data as base64.")]
-    async fn capture_screen(
+    #[tool(name = "get_metrics", description = "Returns server call metrics since startup: per tool invocations, error counts, error rate and latency (mean, max, p50, p95 in ms).")]
+    async fn get_metrics(
         &self,
-        #[tool(aggr)] params: CaptureScreenParams
+        #[tool(aggr)] _params: NoParams
     ) -> Result<CallToolResult, ErrorData> {
-        info!("Executing screen capture with params: {:?}", params);
-        let screens =  xcap::Monitor::all()
-            .context("Failed to get screen list")
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
-        let screen_to_capture = screens.first()
-            .ok_or_else(|| anyhow!("No screen found to capture"))
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
-        info!("Capturing from screen ID: {:?}", screen_to_capture.id());
-        let image = screen_to_capture
-            .capture_image()
-            .context("Failed to capture screen area")
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
+        info!("Executing get_metrics.");
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "status": "success",
+            "uptime_secs": self.metrics.uptime_secs(),
+            "tools": self.metrics.snapshot(),
+        }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_metrics result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
 
-        info!("Capture successful ({}x{})", image.width(), image.height());
-        let mut buf: Vec<u8> = Vec::new();
-        image.write_to(&mut Cursor::new(&mut buf), image::ImageFormat::Png).map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
-        let base64_image = base64::engine::general_purpose::STANDARD.encode(&buf);
-        info!("Encoded image to base64 (length: {})", base64_image.len());
-        let result_json = json!({
-            "status": "success", "format": "png", "width": image.width(), "height": image.height(), "base64_data": base64_image,
-        });
-        Ok(CallToolResult::success(vec![Content::json(result_json)
-            .map_err(|e| anyhow!(e).context("Failed to serialize capture_screen result"))
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
+    #[tool(name = "get_capabilities", description = "Checks what works on this host right now: input simulation, display enumeration and screen capture (each with ok and a diagnostic detail), plus the OS, scancode support and whether dry-run mode is on. Call it first if tools fail unexpectedly.")]
+    async fn get_capabilities(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_capabilities.");
+        let capabilities = health::check(&self.input, dry_run_enabled());
+        Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "all_ok": capabilities.all_ok(), "capabilities": capabilities }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_capabilities result"))
+            .map_err(|e| errors::backend(e.to_string()))?
         ]))
     }
 
-    #[tool(name = "run_shell_command", description = "Runs a command in the default system shell.")]
-     async fn run_shell_command(
+    #[tool(name = "get_server_environment", description = "Reports what this server is and runs on: name and version, OS, CPU architecture, display server (win32, quartz, x11, wayland), input backend, compiled optional features, OCR engine, and the primary display's resolution. Include it in bug reports.")]
+    async fn get_server_environment(
         &self,
-        #[tool(aggr)] params: RunShellParams
+        #[tool(aggr)] _params: NoParams
     ) -> Result<CallToolResult, ErrorData> {
-        info!("Received request to run command: {:?}", params);
-        let _ = Command::new(&params.command)
-            .args(&params.args)
-            .spawn()
-            .context(format!("Failed to execute command: {}", params.command))
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
-        // let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        // let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        // let exit_code = output.status.code().unwrap_or(-1);
-        // info!( "Command '{}' executed. Status: {}, Stdout len: {}, Stderr len: {}", params.command, exit_code, stdout.len(), stderr.len());
-        let result_json = json!({ "status": "success"  }); // , "exit_code": exit_code, "stdout": stdout, "stderr": stderr,
+        info!("Executing get_server_environment.");
+        let environment = environment::current();
+        Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "environment": environment }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_server_environment result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_session_state", description = "Reports whether the workstation is locked, whether a screensaver is running and how long since the last keyboard/mouse input (idle_ms). Check it before a run: while locked or in the screensaver, clicks and keys silently go nowhere. Fields are null where unknown; returns supported=false where none can be queried.")]
+    async fn get_session_state(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_session_state.");
+        let result_json = match presence::session_state()
+            .map_err(|e| errors::backend(format!("{:#}", e)))?
+        {
+            Some(state) => {
+                info!("Session state: locked {:?}, screensaver {:?}, idle {:?} ms", state.locked, state.screensaver_active, state.idle_ms);
+                json!({ "status": "success", "supported": true, "input_reaches_desktop": state.input_reaches_desktop(), "session": state })
+            }
+            None => {
+                info!("Session state is not available on this platform/session.");
+                json!({ "status": "success", "supported": false })
+            }
+        };
         Ok(CallToolResult::success(vec![Content::json(result_json)
-             .map_err(|e| anyhow!(e).context("Failed to serialize run_shell_command result"))
-             .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
-        ]))
-    }
-
-    // // --- NEW Tools for OpenAI Computer Use Actions ---
-    // #[tool(name = "execute_openai_click", description = "Executes a mouse click action requested by the OpenAI Computer Use model.")]
-    // async fn execute_openai_click(
-    //     &self,
-    //     #[tool(aggr)] params: OpenAIClickParams
-    // ) -> Result<CallToolResult, ErrorData> {
-    //     info!("Executing OpenAI action: click at ({}, {}) with button '{}'", params.x, params.y, params.button);
-    //     let mut enigo = Enigo::new(&Settings::default())
-    //         .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
-
-    //     // Move mouse first
-    //     enigo.move_mouse(params.x, params.y, Coordinate::Abs)
-    //         .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, format!("OpenAI Click: Failed to move mouse: {e:?}"), None))?;
-
-    //     // Determine button
-    //     let button_enum = match params.button.to_lowercase().as_str() {
-    //         "left" => Button::Left,
-    //         "right" => Button::Right,
-    //         "middle" => Button::Middle,
-    //         _ => return Err(ErrorData::invalid_params(format!("OpenAI Click: Invalid button '{}'", params.button), None)),
-    //     };
-
-    //     // Perform click
-    //     enigo.button(button_enum, Direction::Click)
-    //         .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, format!("OpenAI Click: Failed to click button: {e:?}"), None))?;
-
-    //     Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success" }))
-    //         .map_err(|e| anyhow!(e).context("Failed to serialize execute_openai_click result"))
-    //         .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
-    //     ]))
-    // }
-
-    // #[tool(name = "execute_openai_scroll", description = "Executes a mouse scroll action requested by the OpenAI Computer Use model.")]
-    // async fn execute_openai_scroll(
-    //     &self,
-    //     #[tool(aggr)] params: OpenAIScrollParams
-    // ) -> Result<CallToolResult, ErrorData> {
-    //     info!("Executing OpenAI action: scroll at ({}, {}) with delta ({}, {})", params.x, params.y, params.scroll_x, params.scroll_y);
-    //     let mut enigo = Enigo::new(&Settings::default())
-    //         .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
-
-    //     // Move mouse to scroll origin first
-    //     enigo.move_mouse(params.x, params.y, Coordinate::Abs)
-    //          .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, format!("OpenAI Scroll: Failed to move mouse: {e:?}"), None))?;
-
-    //     // Perform scroll - enigo uses Button enum for scroll direction
-    //     // Note: This scrolls once per direction. Magnitude requires looping.
-    //     if params.scroll_y != 0 {
-    //         let button = if params.scroll_y < 0 { Button::ScrollUp } else { Button::ScrollDown };
-    //         let count = params.scroll_y.abs();
-    //         info!("Scrolling vertically: {:?} {} times", button, count);
-    //         for _ in 0..count { // Loop for magnitude
-    //              enigo.button(button, Direction::Click)
-    //                 .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, format!("OpenAI Scroll: Failed vertical scroll: {e:?}"), None))?;
-    //              // Optional small delay between scroll clicks might be needed
-    //              // tokio::time::sleep(Duration::from_millis(10)).await;
-    //         }
-    //     }
-    //     if params.scroll_x != 0 {
-    //          let button = if params.scroll_x < 0 { Button::ScrollLeft } else { Button::ScrollRight };
-    //          let count = params.scroll_x.abs();
-    //          info!("Scrolling horizontally: {:?} {} times", button, count);
-    //          for _ in 0..count { // Loop for magnitude
-    //              enigo.button(button, Direction::Click)
-    //                 .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, format!("OpenAI Scroll: Failed horizontal scroll: {e:?}"), None))?;
-    //              // Optional small delay
-    //              // tokio::time::sleep(Duration::from_millis(10)).await;
-    //          }
-    //     }
-
-    //     Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success" }))
-    //         .map_err(|e| anyhow!(e).context("Failed to serialize execute_openai_scroll result"))
-    //         .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
-    //     ]))
-    // }
-
-    //  #[tool(name = "execute_openai_keypress", description = "Executes key presses requested by the OpenAI Computer Use model.")]
-    // async fn execute_openai_keypress(
-    //     &self,
-    //     #[tool(aggr)] params: OpenAIKeyPressParams
-    // ) -> Result<CallToolResult, ErrorData> {
-    //     info!("Executing OpenAI action: keypress sequence: {:?}", params.keys);
-    //     let mut enigo = Enigo::new(&Settings::default())
-    //          .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
-
-    //     // OpenAI keypress action sends an array of keys to be pressed sequentially (like modifiers + key)
-    //     // We simulate this by pressing down all keys then releasing them in reverse.
-    //     // This might need refinement based on observed OpenAI behavior.
-    //     let mut key_enums = Vec::new();
-    //     for key_str in &params.keys {
-    //          let key_enum = match key_str.to_lowercase().as_str() {
-    //             "alt" | "altgraph" => Key::Alt, "backspace" => Key::Backspace, "capslock" | "caps_lock" => Key::CapsLock,
-    //             "control" | "ctrl" => Key::Control, "delete" => Key::Delete, "down" | "downarrow" => Key::DownArrow,
-    //             "end" => Key::End, "escape" | "esc" => Key::Escape,
-    //             "f1" => Key::F1, "f2" => Key::F2, "f3" => Key::F3, "f4" => Key::F4, "f5" => Key::F5,
-    //             "f6" => Key::F6, "f7" => Key::F7, "f8" => Key::F8, "f9" => Key::F9, "f10" => Key::F10,
-    //             "f11" => Key::F11, "f12" => Key::F12, "home" => Key::Home, "left" | "leftarrow" => Key::LeftArrow,
-    //             "meta" | "win" | "command" | "super" | "windows" => Key::Meta, "option" => Key::Option,
-    //             "pagedown" | "page_down" => Key::PageDown, "pageup" | "page_up" => Key::PageUp,
-    //             "return" | "enter" => Key::Return, "right" | "rightarrow" => Key::RightArrow,
-    //             "shift" => Key::Shift, "space" => Key::Space, "tab" => Key::Tab, "up" | "uparrow" => Key::UpArrow,
-    //             s if s.chars().count() == 1 => Key::Unicode(s.chars().next().unwrap()),
-    //             _ => return Err(ErrorData::invalid_params(format!("OpenAI Keypress: Unsupported key specified: '{}'.", key_str), None)),
-    //         };
-    //         key_enums.push(key_enum);
-    //     }
-
-    //     // Press keys down
-    //     for key_enum in &key_enums {
-    //          enigo.key(*key_enum, Direction::Press)
-    //               .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, format!("OpenAI Keypress: Failed to press key '{:?}': {}", key_enum, e), None))?;
-    //     }
-    //     // Release keys in reverse order
-    //     for key_enum in key_enums.iter().rev() {
-    //          enigo.key(*key_enum, Direction::Release)
-    //               .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, format!("OpenAI Keypress: Failed to release key '{:?}': {}", key_enum, e), None))?;
-    //     }
-
-    //     info!("OpenAI keypress sequence executed successfully.");
-    //     Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success" }))
-    //         .map_err(|e| anyhow!(e).context("Failed to serialize execute_openai_keypress result"))
-    //         .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
-    //     ]))
-    // }
-
-    //  #[tool(name = "execute_openai_type", description = "Executes typing text requested by the OpenAI Computer Use model.")]
-    // async fn execute_openai_type(
-    //     &self,
-    //     #[tool(aggr)] params: OpenAITypeParams
-    // ) -> Result<CallToolResult, ErrorData> {
-    //     info!("Executing OpenAI action: type text: '{}'", params.text);
-    //     let mut enigo = Enigo::new(&Settings::default())
-    //          .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?;
-
-    //     enigo.text(&params.text)
-    //         .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, format!("OpenAI Type: Failed to type text: {e:?}"), None))?;
-
-    //     info!("OpenAI text typing successful.");
-    //     Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success" }))
-    //         .map_err(|e| anyhow!(e).context("Failed to serialize execute_openai_type result"))
-    //         .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
-    //     ]))
-    // }
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_session_state result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "get_tool_catalog", description = "Lists every tool with policy metadata: category (read_only, input, window, system, destructive), whether it changes system state, and an estimated risk level (low, medium, high). Intended for clients that decide which calls need confirmation.")]
+    async fn get_tool_catalog(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing get_tool_catalog.");
+        let tools: Vec<serde_json::Value> = Self::tool_box()
+            .list()
+            .into_iter()
+            .map(|tool| {
+                let (category, risk) = catalog::classify(&tool.name);
+                json!({
+                    "name": tool.name,
+                    "description": tool.description,
+                    "category": category,
+                    "mutates_state": !READ_ONLY_TOOLS.contains(&tool.name.as_ref()),
+                    "risk": risk,
+                })
+            })
+            .collect();
+        Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "count": tools.len(), "tools": tools }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize get_tool_catalog result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "start_recording", description = "Starts recording the user's real mouse and keyboard input (moves, clicks, scrolls, key presses) with timing, so a human can demonstrate a task. Input sent by this server's own tools while recording is captured too. Call stop_recording when done; the file can be re-run with the server's --replay mode.")]
+    async fn start_recording(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing start_recording.");
+        let path = match self.recorder.start() {
+            Ok(path) => path,
+            Err(e) => return tool_error(ErrorCategory::State, format!("{:#}", e), None),
+        };
+        Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "recording": true, "path": path }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize start_recording result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
+
+    #[tool(name = "stop_recording", description = "Stops the input recording started with start_recording and returns the path of the recorded action file, the number of actions and the duration.")]
+    async fn stop_recording(
+        &self,
+        #[tool(aggr)] _params: NoParams
+    ) -> Result<CallToolResult, ErrorData> {
+        info!("Executing stop_recording.");
+        let Some(summary) = self.recorder.stop() else {
+            return tool_error(ErrorCategory::State, "No recording is running. Call start_recording first.", None);
+        };
+        Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "recording": false, "summary": summary }))
+            .map_err(|e| anyhow!(e).context("Failed to serialize stop_recording result"))
+            .map_err(|e| errors::backend(e.to_string()))?
+        ]))
+    }
 
      #[tool(name = "execute_openai_wait", description = "Executes a wait/sleep action requested by the OpenAI Computer Use model.")]
     async fn execute_openai_wait(
@@ -588,25 +4412,140 @@ impl DesktopToolProvider {
         let duration_ms = params.duration_ms.unwrap_or(2000); // Default to 2000ms if not specified
         info!("Executing OpenAI action: wait for {} ms", duration_ms);
 
-        sleep(Duration::from_millis(duration_ms)).await;
+        progress::sleep(Duration::from_millis(duration_ms)).await;
 
         info!("Wait completed.");
         Ok(CallToolResult::success(vec![Content::json(json!({ "status": "success", "duration_ms": duration_ms }))
             .map_err(|e| anyhow!(e).context("Failed to serialize execute_openai_wait result"))
-            .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
+            .map_err(|e| errors::backend(e.to_string()))?
         ]))
     }
 
 }
 
-#[tool(tool_box)] // Added missing attribute
 impl ServerHandler for DesktopToolProvider {
+    async fn list_tools(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, ErrorData> {
+        Ok(ListToolsResult { next_cursor: None, tools: Self::tool_box().list() })
+    }
+
+    // Screenshots saved by capture_screen_resource, newest last
+    async fn list_resources(
+        &self,
+        _request: PaginatedRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, ErrorData> {
+        let resources = self.captures.list()
+            .into_iter()
+            .map(|capture| {
+                let name = capture.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                let mut resource = RawResource::new(capture.uri, name);
+                resource.description = Some(format!("Screenshot ({}x{})", capture.width, capture.height));
+                resource.mime_type = Some("image/png".to_string());
+                resource.size = u32::try_from(capture.size_bytes).ok();
+                resource.no_annotation()
+            })
+            .collect();
+        Ok(ListResourcesResult { next_cursor: None, resources })
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, ErrorData> {
+        // Only captures this server saved are served, never arbitrary file:// paths
+        let capture = self.captures.get(&request.uri).ok_or_else(|| {
+            ErrorData::resource_not_found(format!("Unknown or expired capture '{}'", request.uri), None)
+        })?;
+        let bytes = std::fs::read(&capture.path)
+            .map_err(|e| ErrorData::resource_not_found(format!("Capture file {:?} is unavailable: {}", capture.path, e), None))?;
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::BlobResourceContents {
+                uri: capture.uri,
+                mime_type: Some("image/png".to_string()),
+                blob: base64::engine::general_purpose::STANDARD.encode(bytes),
+            }],
+        })
+    }
+
+    // Same dispatch as `#[tool(tool_box)]` generates, wrapped in a span per call so
+    // RUST_LOG=debug shows a timeline of which tool ran for whom, how long it took and how it ended
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let _activity = session::CallGuard::begin(&self.session);
+        let client = self.client_addr.map(|addr| addr.to_string()).unwrap_or_else(|| "unknown".to_string());
+        let span = info_span!(
+            "tool",
+            name = %request.name,
+            client = %client,
+            duration_ms = tracing::field::Empty,
+            outcome = tracing::field::Empty,
+        );
+        let started = Instant::now();
+        let tool_name = request.name.to_string();
+        let timestamp_ms = action_log::now_ms();
+        let logged_call = self.action_log.as_ref().map(|_| (request.name.to_string(), request.arguments.clone()));
+        let tool_box = Self::tool_box();
+        let skip_for_dry_run = dry_run_enabled()
+            && tool_box.map.contains_key(request.name.as_ref())
+            && !READ_ONLY_TOOLS.contains(&request.name.as_ref());
+        let result = if skip_for_dry_run {
+            span.in_scope(|| info!(params = ?request.arguments, "Dry run: not executing tool"));
+            Content::json(json!({
+                "status": "success",
+                "dry_run": true,
+                "tool": request.name,
+                "params": request.arguments,
+            }))
+                .map(|content| CallToolResult::success(vec![content]))
+                .map_err(|e| errors::backend(format!("Failed to serialize dry run result: {}", e)))
+        } else {
+            self.warm_up_input(&tool_name).instrument(span.clone()).await;
+            self.call_with_timeout(request, context).instrument(span.clone()).await
+        };
+
+        let outcome = match &result {
+            Ok(call_result) if call_result.is_error == Some(true) => "tool_error",
+            Ok(_) => "success",
+            Err(_) => "error",
+        };
+        let duration_ms = started.elapsed().as_millis() as u64;
+        self.metrics.record(&tool_name, outcome, duration_ms);
+        span.record("duration_ms", duration_ms);
+        span.record("outcome", outcome);
+        span.in_scope(|| match &result {
+            Err(e) => debug!(duration_ms, outcome, error = %e.message, "Tool call finished"),
+            Ok(_) => debug!(duration_ms, outcome, "Tool call finished"),
+        });
+        if let (Some(log), Some((tool, params))) = (&self.action_log, logged_call) {
+            log.append(action_log::ActionRecord {
+                version: action_log::FORMAT_VERSION,
+                timestamp_ms,
+                client,
+                tool,
+                params,
+                status: outcome.to_string(),
+                duration_ms,
+                error: result.as_ref().err().map(|e| e.message.to_string()),
+            });
+        }
+        result
+    }
+
     // Provide basic server information
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::V_2024_11_05,
             capabilities: ServerCapabilities::builder()
                 .enable_tools()
+                .enable_resources()
                 .build(),
             server_info: Implementation::from_build_env(),
             instructions: Some(
@@ -621,6 +4560,55 @@ impl ServerHandler for DesktopToolProvider {
 }
 
 
+// --- Command Line ---
+
+#[derive(Debug, Default)]
+struct CliArgs {
+    /// `--replay <path>`: re-run the actions recorded in an action log instead of serving clients.
+    replay: Option<PathBuf>,
+    /// `--speed <factor>`: replay pace multiplier, e.g. 2 for twice as fast. Defaults to 1.
+    speed: Option<f64>,
+    /// `--transport tcp|ws`: serve MCP over raw TCP (default) or WebSocket.
+    transport: Transport,
+}
+
+/// Wire protocol the server listens with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Transport {
+    #[default]
+    Tcp,
+    /// For browser-based and web-service MCP clients; one JSON-RPC message per text frame.
+    WebSocket,
+}
+
+impl CliArgs {
+    fn parse() -> anyhow::Result<Self> {
+        let mut parsed = CliArgs::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--replay" => {
+                    let path = args.next().context("--replay requires an action log path")?;
+                    parsed.replay = Some(PathBuf::from(path));
+                }
+                "--speed" => {
+                    let speed = args.next().context("--speed requires a number")?;
+                    parsed.speed = Some(speed.parse().with_context(|| format!("Invalid --speed value '{}'", speed))?);
+                }
+                "--transport" => {
+                    parsed.transport = match args.next().context("--transport requires 'tcp' or 'ws'")?.to_lowercase().as_str() {
+                        "tcp" => Transport::Tcp,
+                        "ws" | "websocket" => Transport::WebSocket,
+                        other => anyhow::bail!("Unknown --transport '{}', expected 'tcp' or 'ws'", other),
+                    };
+                }
+                other => anyhow::bail!("Unknown argument: {}", other),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
 // --- Main Function (Using TCP) ---
 
 #[tokio::main]
@@ -632,9 +4620,22 @@ async fn main() -> anyhow::Result<()> {
         .with_ansi(true)
         .init();
 
-    // Spawn the TCP server task
+    let cli = CliArgs::parse()?;
+    if let Some(path) = &cli.replay {
+        return replay::run(path, cli.speed.unwrap_or(1.0), DesktopToolProvider::default()).await;
+    }
+
+    let tool_provider = shared_provider()?;
+    health::startup_check(&tool_provider.input, dry_run_enabled())?;
+
+    // Spawn the server task
+    let transport = cli.transport;
     tokio::spawn(async move {
-        if let Err(e) = run_mcp_server_tcp().await {
+        let result = match transport {
+            Transport::Tcp => run_mcp_server_tcp(tool_provider).await,
+            Transport::WebSocket => run_mcp_server_ws(tool_provider).await,
+        };
+        if let Err(e) = result {
             tracing::error!("MCP Server error: {:?}", e);
         }
     });
@@ -646,39 +4647,187 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-// --- TCP Server Function ---
-async fn run_mcp_server_tcp() -> anyhow::Result<()> {
-    let addr = "127.0.0.1:9001"; // The TCP address to listen on
-    let listener = TcpListener::bind(addr).await?;
-    info!("MCP Server listening on TCP {}", addr);
+// Address both transports listen on
+const LISTEN_ADDR: &str = "127.0.0.1:9001";
+
+// Provider shared by every connection; each connection gets a clone with its own session
+fn shared_provider() -> anyhow::Result<DesktopToolProvider> {
+    Ok(DesktopToolProvider {
+        action_log: action_log::ActionLog::from_env()?,
+        ..Default::default()
+    })
+}
 
-    let tool_provider = DesktopToolProvider; // Create the tool provider instance
+// Cancels the connection once the client has made no tool call for `limit`. Stops by itself
+// when the connection ends some other way.
+async fn close_when_idle(
+    session: Arc<Mutex<session::ClientSession>>,
+    connected: Instant,
+    limit: Duration,
+    cancel: CancellationToken,
+    client_addr: SocketAddr,
+) {
+    // Check often enough that a connection outlives its limit by at most a quarter
+    let check_every = (limit / 4).clamp(Duration::from_millis(100), Duration::from_secs(30));
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = sleep(check_every) => {}
+        }
+        let idle = session.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).idle_for(connected);
+        if idle >= limit {
+            info!("Client {} made no tool call for {} s; closing its connection.", client_addr, idle.as_secs());
+            cancel.cancel();
+            return;
+        }
+    }
+}
+
+// Serves one client connection until it ends, whatever the transport
+async fn serve_connection<T, E, A>(tool_provider: &DesktopToolProvider, transport: T, client_addr: SocketAddr)
+where
+    T: rmcp::transport::IntoTransport<RoleServer, E, A>,
+    E: std::error::Error + From<std::io::Error> + Send + Sync + 'static,
+{
+    let provider_clone = DesktopToolProvider {
+        client_addr: Some(client_addr),
+        session: Default::default(),
+        ..tool_provider.clone()
+    };
+    info!("Serving client {}...", client_addr);
+    // Shares the connection's session, so it sees what the client left pressed
+    let cleanup_provider = provider_clone.clone();
+    let connected = Instant::now();
+    let cancel = CancellationToken::new();
+    match serve_server_with_ct(provider_clone, transport, cancel.clone()).await {
+        Ok(server_handle) => {
+            if let Some(limit) = session::idle_timeout() {
+                tokio::spawn(close_when_idle(cleanup_provider.session.clone(), connected, limit, cancel.clone(), client_addr));
+            }
+            if let Err(e) = server_handle.waiting().await {
+                if !e.to_string().contains("connection closed")
+                    && !e.to_string().contains("Connection reset by peer")
+                    && !e.to_string().contains("broken pipe")
+                   {
+                    tracing::error!("Client {} error: {:?}", client_addr, e);
+                } else {
+                    info!("Client {} disconnected.", client_addr);
+                }
+            }
+        }
+        Err(e) => {
+            tracing::error!("Failed to start serving client {}: {:?}", client_addr, e);
+        }
+    }
+    // Also stops the idle watchdog
+    cancel.cancel();
+    // Dead-man's switch: however the connection ended, don't leave inputs held
+    cleanup_provider.release_held_inputs();
+}
+
+// --- TCP Server Function ---
+async fn run_mcp_server_tcp(tool_provider: DesktopToolProvider) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(LISTEN_ADDR).await?;
+    info!("MCP Server listening on TCP {}", LISTEN_ADDR);
 
     loop {
         let (stream, client_addr) = listener.accept().await?;
         info!("Accepted TCP connection from: {}", client_addr);
-        let provider_clone = tool_provider.clone();
+        let tool_provider = tool_provider.clone();
+        tokio::spawn(async move {
+            serve_connection(&tool_provider, stream, client_addr).await;
+        });
+    }
+    // Ok(()) // Unreachable
+}
 
+// --- WebSocket Server Function ---
+async fn run_mcp_server_ws(tool_provider: DesktopToolProvider) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(LISTEN_ADDR).await?;
+    info!("MCP Server listening on WebSocket ws://{}", LISTEN_ADDR);
+
+    loop {
+        let (stream, client_addr) = listener.accept().await?;
+        info!("Accepted TCP connection from: {}", client_addr);
+        let tool_provider = tool_provider.clone();
         tokio::spawn(async move {
-            info!("Serving client {}...", client_addr);
-            match serve_server(provider_clone, stream).await {
-                Ok(server_handle) => {
-                    if let Err(e) = server_handle.waiting().await {
-                        if !e.to_string().contains("connection closed")
-                            && !e.to_string().contains("Connection reset by peer")
-                            && !e.to_string().contains("broken pipe")
-                           {
-                            tracing::error!("Client {} error: {:?}", client_addr, e);
-                        } else {
-                            info!("Client {} disconnected.", client_addr);
-                        }
-                    }
-                }
+            // The upgrade handshake runs on the connection's task so a slow client can't stall accept()
+            let socket = match tokio_tungstenite::accept_async(stream).await {
+                Ok(socket) => socket,
                 Err(e) => {
-                    tracing::error!("Failed to start serving client {}: {:?}", client_addr, e);
+                    warn!("WebSocket handshake with {} failed: {}", client_addr, e);
+                    return;
                 }
-            }
+            };
+            serve_connection(&tool_provider, ws::into_transport(socket), client_addr).await;
         });
     }
-    // Ok(()) // Unreachable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::{serve_client, serve_server, ServiceError};
+
+    // Answers every tool call through the same task boundary as DesktopToolProvider; the tool
+    // named "explode" panics
+    #[derive(Clone)]
+    struct StubServer;
+
+    impl ServerHandler for StubServer {
+        async fn call_tool(
+            &self,
+            request: CallToolRequestParam,
+            context: RequestContext<RoleServer>,
+        ) -> Result<CallToolResult, ErrorData> {
+            let name = request.name.to_string();
+            let explode = name == "explode";
+            let call = async move {
+                if explode {
+                    panic!("boom");
+                }
+                Ok(CallToolResult::success(vec![Content::text("ok")]))
+            };
+            run_tool_task(&name, Duration::from_secs(5), context.ct.clone(), call).await
+        }
+    }
+
+    #[tokio::test]
+    async fn panicking_tool_leaves_server_usable() {
+        let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+        // The server only finishes starting once the client has initialized, so both run at once
+        let (server, client) = tokio::join!(serve_server(StubServer, server_io), serve_client((), client_io));
+        let (server, client) = (server.expect("server starts"), client.expect("client connects"));
+
+        let call = |name: &'static str| CallToolRequestParam { name: name.into(), arguments: None };
+        match client.call_tool(call("explode")).await {
+            Err(ServiceError::McpError(error)) => {
+                assert_eq!(error.code, ErrorCode::INTERNAL_ERROR);
+                assert!(error.message.contains("boom"), "{}", error.message);
+            }
+            other => panic!("expected an internal error, got {:?}", other),
+        }
+
+        // Same session, after the panic
+        let next = client.call_tool(call("echo")).await.expect("the next call must still run");
+        assert_ne!(next.is_error, Some(true));
+
+        client.cancel().await.expect("client closes");
+        let _ = server.waiting().await;
+    }
+
+    #[test]
+    fn no_params_schema_is_a_closed_empty_object() {
+        let schema = serde_json::to_value(schemars::schema_for!(NoParams)).expect("schema serializes");
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["properties"], json!({}));
+        assert_eq!(schema["required"], json!([]));
+        assert_eq!(schema["additionalProperties"], false);
+    }
+
+    #[test]
+    fn no_params_drops_stray_arguments() {
+        assert!(serde_json::from_value::<NoParams>(json!({})).is_ok());
+        assert!(serde_json::from_value::<NoParams>(json!({ "unexpected": 1 })).is_ok());
+    }
 }
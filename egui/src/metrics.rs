@@ -0,0 +1,107 @@
+// In-process per-tool call counters and latency histograms, reported by get_metrics
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// Upper bounds (inclusive, in ms) of the latency buckets; slower calls land in a final overflow bucket
+const BUCKET_BOUNDS_MS: [u64; 13] = [5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 30_000, 60_000];
+
+#[derive(Debug, Default, Clone)]
+struct ToolStats {
+    invocations: u64,
+    /// Recoverable failures reported to the model as `is_error` results (includes timeouts).
+    tool_errors: u64,
+    /// Protocol-level failures: invalid params and internal errors.
+    errors: u64,
+    total_ms: u64,
+    max_ms: u64,
+    buckets: [u64; BUCKET_BOUNDS_MS.len() + 1],
+}
+
+impl ToolStats {
+    /// Upper bound of the bucket holding the `q` quantile; `None` for the overflow bucket.
+    fn quantile_ms(&self, q: f64) -> Option<u64> {
+        let rank = ((self.invocations as f64) * q).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return BUCKET_BOUNDS_MS.get(i).copied();
+            }
+        }
+        None
+    }
+}
+
+/// Snapshot of one tool's counters. Percentiles are bucket upper bounds, so they overestimate
+/// by at most one bucket; `null` means the call fell in the overflow bucket (over 60 s).
+#[derive(Debug, Serialize)]
+pub struct ToolSnapshot {
+    pub invocations: u64,
+    pub tool_errors: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub mean_ms: u64,
+    pub max_ms: u64,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+}
+
+/// Call metrics shared by every connection of the server.
+#[derive(Clone)]
+pub struct Metrics {
+    started: Instant,
+    tools: Arc<Mutex<BTreeMap<String, ToolStats>>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self { started: Instant::now(), tools: Default::default() }
+    }
+}
+
+impl Metrics {
+    /// Records one finished call. `outcome` is the same "success" / "tool_error" / "error"
+    /// label used in the tracing span.
+    pub fn record(&self, tool: &str, outcome: &str, duration_ms: u64) {
+        let mut tools = self.tools.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let stats = tools.entry(tool.to_string()).or_default();
+        stats.invocations += 1;
+        match outcome {
+            "tool_error" => stats.tool_errors += 1,
+            "error" => stats.errors += 1,
+            _ => {}
+        }
+        stats.total_ms += duration_ms;
+        stats.max_ms = stats.max_ms.max(duration_ms);
+        let bucket = BUCKET_BOUNDS_MS.iter().position(|bound| duration_ms <= *bound).unwrap_or(BUCKET_BOUNDS_MS.len());
+        stats.buckets[bucket] += 1;
+    }
+
+    pub fn uptime_secs(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+
+    /// Per-tool snapshots, ordered by tool name.
+    pub fn snapshot(&self) -> BTreeMap<String, ToolSnapshot> {
+        let tools = self.tools.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        tools
+            .iter()
+            .map(|(name, stats)| {
+                let failures = stats.tool_errors + stats.errors;
+                let snapshot = ToolSnapshot {
+                    invocations: stats.invocations,
+                    tool_errors: stats.tool_errors,
+                    errors: stats.errors,
+                    error_rate: failures as f64 / stats.invocations.max(1) as f64,
+                    mean_ms: stats.total_ms / stats.invocations.max(1),
+                    max_ms: stats.max_ms,
+                    p50_ms: stats.quantile_ms(0.5),
+                    p95_ms: stats.quantile_ms(0.95),
+                };
+                (name.clone(), snapshot)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,292 @@
+// OCR helpers backed by tesseract: the `tesseract` command-line tool by default, or the linked
+// library when built with the `leptess` feature
+use anyhow::{anyhow, Context};
+use image::RgbaImage;
+use serde::Serialize;
+use std::io::Cursor;
+
+pub const DEFAULT_LANG: &str = "eng";
+
+/// A single word recognized by the OCR engine, in the pixel space of the image it was run on.
+#[derive(Debug, Clone, Serialize)]
+pub struct OcrWord {
+    pub text: String,
+    pub confidence: f32,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Words sharing the same (block, paragraph, line) key are on the same text line.
+    #[serde(skip)]
+    pub line_key: (u32, u32, u32),
+}
+
+/// Runs tesseract on the image and returns the recognized words.
+///
+/// Words with a negative confidence (tesseract's marker for layout-only rows) or empty text
+/// are dropped.
+pub fn recognize_words(image: &RgbaImage, lang: &str) -> anyhow::Result<Vec<OcrWord>> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .context("Failed to encode image for OCR")?;
+    Ok(parse_tsv(&run_tesseract_tsv(&png, lang)?))
+}
+
+// The image is piped through stdin so no temporary files are written
+#[cfg(not(feature = "leptess"))]
+fn run_tesseract_tsv(png: &[u8], lang: &str) -> anyhow::Result<String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("tesseract")
+        .args(["stdin", "stdout", "-l", lang, "tsv"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to start 'tesseract'. Is it installed and on PATH?")?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("Failed to open tesseract stdin"))?
+        .write_all(png)
+        .context("Failed to send image to tesseract")?;
+    let output = child.wait_with_output().context("Failed to read tesseract output")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "tesseract exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(feature = "leptess")]
+fn run_tesseract_tsv(png: &[u8], lang: &str) -> anyhow::Result<String> {
+    let mut tess = leptess::LepTess::new(None, lang)
+        .map_err(|e| anyhow!("Failed to initialize tesseract for language '{}': {}", lang, e))?;
+    tess.set_image_from_mem(png).map_err(|e| anyhow!("Failed to load image into tesseract: {}", e))?;
+    tess.get_tsv_text(0).context("tesseract returned invalid UTF-8")
+}
+
+/// Runs OCR and joins the recognized words into plain text, one line of output per text line.
+pub fn recognize_text(image: &RgbaImage, lang: &str) -> anyhow::Result<String> {
+    let words = recognize_words(image, lang)?;
+    let mut text = String::new();
+    let mut current_line = None;
+    for word in &words {
+        if current_line.is_some() {
+            text.push(if current_line == Some(word.line_key) { ' ' } else { '\n' });
+        }
+        text.push_str(&word.text);
+        current_line = Some(word.line_key);
+    }
+    Ok(text)
+}
+
+// Columns: level page_num block_num par_num line_num word_num left top width height conf text
+fn parse_tsv(tsv: &str) -> Vec<OcrWord> {
+    // The header row (CLI output only) fails to parse like any other malformed row
+    tsv.lines()
+        .filter_map(|line| {
+            let cols: Vec<&str> = line.splitn(12, '\t').collect();
+            if cols.len() < 12 {
+                return None;
+            }
+            let confidence: f32 = cols[10].trim().parse().ok()?;
+            let text = cols[11].trim();
+            if confidence < 0.0 || text.is_empty() {
+                return None;
+            }
+            Some(OcrWord {
+                text: text.to_string(),
+                confidence,
+                x: cols[6].parse().ok()?,
+                y: cols[7].parse().ok()?,
+                width: cols[8].parse().ok()?,
+                height: cols[9].parse().ok()?,
+                line_key: (cols[2].parse().ok()?, cols[3].parse().ok()?, cols[4].parse().ok()?),
+            })
+        })
+        .collect()
+}
+
+/// A line of text assembled from consecutive OCR words, with the bounding box of all of them.
+#[derive(Debug, Clone, Serialize)]
+pub struct OcrLine {
+    pub text: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Groups words into text lines, preserving tesseract's reading order.
+pub fn group_lines(words: &[OcrWord]) -> Vec<OcrLine> {
+    let mut lines: Vec<(OcrLine, (u32, u32, u32))> = Vec::new();
+    for word in words {
+        match lines.last_mut() {
+            Some((line, key)) if *key == word.line_key => {
+                let right = (line.x + line.width as i32).max(word.x + word.width as i32);
+                let bottom = (line.y + line.height as i32).max(word.y + word.height as i32);
+                line.x = line.x.min(word.x);
+                line.y = line.y.min(word.y);
+                line.width = (right - line.x) as u32;
+                line.height = (bottom - line.y) as u32;
+                line.text.push(' ');
+                line.text.push_str(&word.text);
+            }
+            _ => lines.push((
+                OcrLine { text: word.text.clone(), x: word.x, y: word.y, width: word.width, height: word.height },
+                word.line_key,
+            )),
+        }
+    }
+    lines.into_iter().map(|(line, _)| line).collect()
+}
+
+/// An occurrence of a search string in OCR output. The box covers every word the match touches.
+#[derive(Debug, Clone, Serialize)]
+pub struct TextMatch {
+    /// The matched words as recognized (may be longer than the query for partial-word matches).
+    pub text: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub center_x: i32,
+    pub center_y: i32,
+    /// Lowest confidence among the matched words.
+    pub confidence: f32,
+}
+
+/// Finds `query` in the recognized text, line by line, so multi-word queries ("Save As") match
+/// across word boundaries. With `whole_word`, a match must start and end on word boundaries.
+pub fn find_text(words: &[OcrWord], query: &str, case_sensitive: bool, whole_word: bool) -> Vec<TextMatch> {
+    let normalize = |s: &str| if case_sensitive { s.to_string() } else { s.to_lowercase() };
+    let query = normalize(query.trim());
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches = Vec::new();
+    for line in words.chunk_by(|a, b| a.line_key == b.line_key) {
+        // Line text joined with single spaces, remembering each word's byte range in it
+        let mut text = String::new();
+        let mut ranges = Vec::with_capacity(line.len());
+        for word in line {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            let start = text.len();
+            text.push_str(&normalize(&word.text));
+            ranges.push(start..text.len());
+        }
+
+        for (start, found) in text.match_indices(&query) {
+            let end = start + found.len();
+            if whole_word && !(ranges.iter().any(|r| r.start == start) && ranges.iter().any(|r| r.end == end)) {
+                continue;
+            }
+            let hit: Vec<&OcrWord> = line
+                .iter()
+                .zip(&ranges)
+                .filter(|(_, r)| r.start < end && start < r.end)
+                .map(|(word, _)| word)
+                .collect();
+            let Some(first) = hit.first() else { continue };
+
+            let left = hit.iter().map(|w| w.x).min().unwrap_or(first.x);
+            let top = hit.iter().map(|w| w.y).min().unwrap_or(first.y);
+            let right = hit.iter().map(|w| w.x + w.width as i32).max().unwrap_or(left);
+            let bottom = hit.iter().map(|w| w.y + w.height as i32).max().unwrap_or(top);
+            let (width, height) = ((right - left).max(0) as u32, (bottom - top).max(0) as u32);
+            matches.push(TextMatch {
+                text: hit.iter().map(|w| w.text.as_str()).collect::<Vec<_>>().join(" "),
+                x: left,
+                y: top,
+                width,
+                height,
+                center_x: left + (width / 2) as i32,
+                center_y: top + (height / 2) as i32,
+                confidence: hit.iter().map(|w| w.confidence).fold(f32::MAX, f32::min),
+            });
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Trimmed `tesseract - - tsv` output: page/block/paragraph/line rows carry conf -1, and the
+    // last word row is a blank word tesseract sometimes reports
+    const SAMPLE_TSV: &str = concat!(
+        "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n",
+        "1\t1\t0\t0\t0\t0\t0\t0\t800\t600\t-1\t\n",
+        "2\t1\t1\t0\t0\t0\t10\t10\t300\t60\t-1\t\n",
+        "3\t1\t1\t1\t0\t0\t10\t10\t300\t60\t-1\t\n",
+        "4\t1\t1\t1\t1\t0\t10\t10\t140\t20\t-1\t\n",
+        "5\t1\t1\t1\t1\t1\t10\t10\t50\t20\t96.5\tFile\n",
+        "5\t1\t1\t1\t1\t2\t70\t12\t40\t18\t95.1\tSave\n",
+        "5\t1\t1\t1\t1\t3\t120\t10\t30\t20\t93.0\tAs\n",
+        "4\t1\t1\t1\t2\t0\t10\t40\t150\t20\t-1\t\n",
+        "5\t1\t1\t1\t2\t1\t10\t40\t80\t20\t91.2\tSaved\n",
+        "5\t1\t1\t1\t2\t2\t100\t42\t60\t18\t88.7\tchanges\n",
+        "5\t1\t1\t1\t2\t3\t170\t40\t10\t20\t95.0\t \n",
+    );
+
+    fn texts(matches: &[TextMatch]) -> Vec<&str> {
+        matches.iter().map(|m| m.text.as_str()).collect()
+    }
+
+    #[test]
+    fn parse_tsv_keeps_only_recognized_words() {
+        let words = parse_tsv(SAMPLE_TSV);
+        let texts: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(texts, ["File", "Save", "As", "Saved", "changes"]);
+        let save = &words[1];
+        assert_eq!((save.x, save.y, save.width, save.height), (70, 12, 40, 18));
+        assert_eq!(save.confidence, 95.1);
+        assert_eq!(save.line_key, (1, 1, 1));
+        assert_eq!(words[3].line_key, (1, 1, 2));
+    }
+
+    #[test]
+    fn group_lines_joins_words_and_their_boxes() {
+        let lines = group_lines(&parse_tsv(SAMPLE_TSV));
+        let texts: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, ["File Save As", "Saved changes"]);
+        assert_eq!((lines[0].x, lines[0].y, lines[0].width, lines[0].height), (10, 10, 140, 20));
+        assert_eq!((lines[1].x, lines[1].y, lines[1].width, lines[1].height), (10, 40, 150, 20));
+    }
+
+    #[test]
+    fn find_text_matches_across_words() {
+        let matches = find_text(&parse_tsv(SAMPLE_TSV), "Save As", true, false);
+        assert_eq!(texts(&matches), ["Save As"]);
+        let hit = &matches[0];
+        assert_eq!((hit.x, hit.y, hit.width, hit.height), (70, 10, 80, 20));
+        assert_eq!((hit.center_x, hit.center_y), (110, 20));
+        assert_eq!(hit.confidence, 93.0);
+    }
+
+    #[test]
+    fn find_text_whole_word_rejects_partial_matches() {
+        let words = parse_tsv(SAMPLE_TSV);
+        assert_eq!(texts(&find_text(&words, "Save", true, false)), ["Save", "Saved"]);
+        assert_eq!(texts(&find_text(&words, "Save", true, true)), ["Save"]);
+        assert!(find_text(&words, "ile Sa", true, true).is_empty());
+    }
+
+    #[test]
+    fn find_text_case_sensitivity() {
+        let words = parse_tsv(SAMPLE_TSV);
+        assert!(find_text(&words, "save as", true, false).is_empty());
+        assert_eq!(texts(&find_text(&words, "save as", false, false)), ["Save As"]);
+        assert_eq!(texts(&find_text(&words, "SAVED", false, true)), ["Saved"]);
+    }
+}
@@ -0,0 +1,135 @@
+// OS pointer settings that change where relative mouse moves land
+use serde::Serialize;
+
+/// The system pointer acceleration ("Enhance pointer precision" on Windows) and speed.
+#[derive(Debug, Clone, Serialize)]
+pub struct MouseAcceleration {
+    pub enabled: bool,
+    /// Pointer speed, 1-20 with 10 as the default. Only reported on Windows.
+    pub speed: Option<u32>,
+    /// Raw X11 acceleration factor and threshold, e.g. "2/1" and 4. Only reported on X11.
+    pub x11_acceleration: Option<String>,
+    pub x11_threshold: Option<i32>,
+}
+
+/// Reads the current pointer acceleration, or `Ok(None)` when this platform or session
+/// doesn't expose it.
+pub fn mouse_acceleration() -> anyhow::Result<Option<MouseAcceleration>> {
+    imp::mouse_acceleration()
+}
+
+/// Turns pointer acceleration on or off and optionally sets the pointer speed (Windows only).
+/// Changes last until logout and are not persisted. Returns `Ok(false)` when unsupported.
+pub fn set_mouse_acceleration(enabled: bool, speed: Option<u32>) -> anyhow::Result<bool> {
+    imp::set_mouse_acceleration(enabled, speed)
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::MouseAcceleration;
+    use anyhow::anyhow;
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPIF_SENDCHANGE, SPI_GETMOUSE, SPI_GETMOUSESPEED, SPI_SETMOUSE, SPI_SETMOUSESPEED,
+    };
+
+    // Windows defaults for the two acceleration thresholds, used when turning acceleration on
+    const DEFAULT_THRESHOLDS: [i32; 2] = [6, 10];
+
+    pub fn mouse_acceleration() -> anyhow::Result<Option<MouseAcceleration>> {
+        // [threshold1, threshold2, acceleration]
+        let mut params = [0i32; 3];
+        // SAFETY: SPI_GETMOUSE writes three ints to the buffer
+        if unsafe { SystemParametersInfoW(SPI_GETMOUSE, 0, params.as_mut_ptr().cast(), 0) } == 0 {
+            return Err(anyhow!("SystemParametersInfo(SPI_GETMOUSE) failed: {}", std::io::Error::last_os_error()));
+        }
+        let mut speed = 0u32;
+        // SAFETY: SPI_GETMOUSESPEED writes one int to the pointer
+        if unsafe { SystemParametersInfoW(SPI_GETMOUSESPEED, 0, (&mut speed as *mut u32).cast(), 0) } == 0 {
+            return Err(anyhow!("SystemParametersInfo(SPI_GETMOUSESPEED) failed: {}", std::io::Error::last_os_error()));
+        }
+        Ok(Some(MouseAcceleration { enabled: params[2] != 0, speed: Some(speed), x11_acceleration: None, x11_threshold: None }))
+    }
+
+    pub fn set_mouse_acceleration(enabled: bool, speed: Option<u32>) -> anyhow::Result<bool> {
+        let mut params = if enabled { [DEFAULT_THRESHOLDS[0], DEFAULT_THRESHOLDS[1], 1] } else { [0, 0, 0] };
+        // SAFETY: SPI_SETMOUSE reads three ints from the buffer
+        if unsafe { SystemParametersInfoW(SPI_SETMOUSE, 0, params.as_mut_ptr().cast(), SPIF_SENDCHANGE) } == 0 {
+            return Err(anyhow!("SystemParametersInfo(SPI_SETMOUSE) failed: {}", std::io::Error::last_os_error()));
+        }
+        if let Some(speed) = speed {
+            // SPI_SETMOUSESPEED takes the value itself in the pointer argument
+            // SAFETY: no memory is read through the pointer
+            if unsafe { SystemParametersInfoW(SPI_SETMOUSESPEED, 0, speed.clamp(1, 20) as usize as *mut _, SPIF_SENDCHANGE) } == 0 {
+                return Err(anyhow!("SystemParametersInfo(SPI_SETMOUSESPEED) failed: {}", std::io::Error::last_os_error()));
+            }
+        }
+        Ok(true)
+    }
+}
+
+// Best effort through `xset`; Wayland compositors keep pointer settings to themselves
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::MouseAcceleration;
+    use anyhow::{anyhow, Context};
+    use std::process::Command;
+
+    fn xset(args: &[&str]) -> anyhow::Result<String> {
+        let output = Command::new("xset").args(args).output().context("Failed to run 'xset'. Is it installed?")?;
+        if !output.status.success() {
+            return Err(anyhow!("'xset {}' failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    pub fn mouse_acceleration() -> anyhow::Result<Option<MouseAcceleration>> {
+        if std::env::var_os("DISPLAY").is_none() {
+            return Ok(None);
+        }
+        // Looks like "  acceleration:  2/1    threshold:  4"
+        let output = xset(&["q"])?;
+        let line = output
+            .lines()
+            .find(|line| line.trim_start().starts_with("acceleration:"))
+            .ok_or_else(|| anyhow!("'xset q' output has no pointer acceleration line"))?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let acceleration = fields.get(1).map(|s| s.to_string()).ok_or_else(|| anyhow!("Malformed xset line '{}'", line))?;
+        let threshold = fields.get(3).and_then(|s| s.parse().ok());
+        // A factor of 1 (e.g. "1/1") means pointer motion is not scaled
+        let factor = match acceleration.split_once('/') {
+            Some((num, den)) => num.parse::<f64>().unwrap_or(1.0) / den.parse::<f64>().unwrap_or(1.0),
+            None => acceleration.parse().unwrap_or(1.0),
+        };
+        Ok(Some(MouseAcceleration {
+            enabled: factor != 1.0,
+            speed: None,
+            x11_acceleration: Some(acceleration),
+            x11_threshold: threshold,
+        }))
+    }
+
+    pub fn set_mouse_acceleration(enabled: bool, _speed: Option<u32>) -> anyhow::Result<bool> {
+        if std::env::var_os("DISPLAY").is_none() {
+            return Ok(false);
+        }
+        if enabled {
+            xset(&["m", "default"])?;
+        } else {
+            xset(&["m", "1/1", "0"])?;
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+mod imp {
+    use super::MouseAcceleration;
+
+    pub fn mouse_acceleration() -> anyhow::Result<Option<MouseAcceleration>> {
+        Ok(None)
+    }
+
+    pub fn set_mouse_acceleration(_enabled: bool, _speed: Option<u32>) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+}
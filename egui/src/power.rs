@@ -0,0 +1,84 @@
+// Battery and AC power state, so automations can hold off heavy work on battery
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatteryInfo {
+    /// State of charge, 0-100.
+    pub percent: f32,
+    /// "charging", "discharging", "full", "empty" or "unknown".
+    pub state: &'static str,
+    /// Estimated seconds until empty while discharging, when the OS reports it.
+    pub time_to_empty_s: Option<u64>,
+    /// Estimated seconds until full while charging, when the OS reports it.
+    pub time_to_full_s: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PowerState {
+    /// "ac", "battery" or "unknown". Machines without a battery are always on AC.
+    pub source: &'static str,
+    /// Average charge over all batteries; `None` without a battery.
+    pub percent: Option<f32>,
+    pub charging: bool,
+    pub batteries: Vec<BatteryInfo>,
+}
+
+impl PowerState {
+    fn from_batteries(batteries: Vec<BatteryInfo>) -> Self {
+        let percent = (!batteries.is_empty()).then(|| batteries.iter().map(|b| b.percent).sum::<f32>() / batteries.len() as f32);
+        let charging = batteries.iter().any(|b| b.state == "charging");
+        // The batteries are all that is reported; a discharging one means the machine runs on it
+        let source = if batteries.is_empty() || batteries.iter().any(|b| matches!(b.state, "charging" | "full")) {
+            "ac"
+        } else if batteries.iter().any(|b| matches!(b.state, "discharging" | "empty")) {
+            "battery"
+        } else {
+            "unknown"
+        };
+        Self { source, percent, charging, batteries }
+    }
+}
+
+/// Reads the power state, or `Ok(None)` when this build has no battery backend.
+pub fn power_state() -> anyhow::Result<Option<PowerState>> {
+    Ok(imp::batteries()?.map(PowerState::from_batteries))
+}
+
+#[cfg(feature = "battery")]
+mod imp {
+    use super::BatteryInfo;
+    use anyhow::Context;
+    use battery::units::ratio::percent;
+    use battery::units::time::second;
+
+    pub fn batteries() -> anyhow::Result<Option<Vec<BatteryInfo>>> {
+        let manager = battery::Manager::new().context("Failed to open the battery manager")?;
+        let mut batteries = Vec::new();
+        for battery in manager.batteries().context("Failed to list batteries")? {
+            let battery = battery.context("Failed to read a battery")?;
+            let state = match battery.state() {
+                battery::State::Charging => "charging",
+                battery::State::Discharging => "discharging",
+                battery::State::Full => "full",
+                battery::State::Empty => "empty",
+                _ => "unknown",
+            };
+            batteries.push(BatteryInfo {
+                percent: battery.state_of_charge().get::<percent>(),
+                state,
+                time_to_empty_s: battery.time_to_empty().map(|t| t.get::<second>() as u64),
+                time_to_full_s: battery.time_to_full().map(|t| t.get::<second>() as u64),
+            });
+        }
+        Ok(Some(batteries))
+    }
+}
+
+#[cfg(not(feature = "battery"))]
+mod imp {
+    use super::BatteryInfo;
+
+    pub fn batteries() -> anyhow::Result<Option<Vec<BatteryInfo>>> {
+        Ok(None)
+    }
+}
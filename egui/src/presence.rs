@@ -0,0 +1,161 @@
+// Whether synthesized input can reach the desktop: lock screen, screensaver and user idle time
+use serde::Serialize;
+
+/// What the interactive session is doing. Each field is `None` when this platform or session
+/// can't tell.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionState {
+    /// The workstation is locked; clicks and keys go to the lock screen, if anywhere.
+    pub locked: Option<bool>,
+    /// Time since the last real (or synthesized) keyboard/mouse input.
+    pub idle_ms: Option<u64>,
+    pub screensaver_active: Option<bool>,
+}
+
+impl SessionState {
+    /// False when input is known to be swallowed by the lock screen or screensaver.
+    pub fn input_reaches_desktop(&self) -> bool {
+        self.locked != Some(true) && self.screensaver_active != Some(true)
+    }
+}
+
+/// Queries the session state, or `Ok(None)` when none of it is available here.
+pub fn session_state() -> anyhow::Result<Option<SessionState>> {
+    let state = imp::session_state()?;
+    if state.locked.is_none() && state.idle_ms.is_none() && state.screensaver_active.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(state))
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::SessionState;
+    use anyhow::anyhow;
+    use windows_sys::Win32::System::StationsAndDesktops::{CloseDesktop, OpenInputDesktop, SwitchDesktop, DESKTOP_SWITCHDESKTOP};
+    use windows_sys::Win32::System::SystemInformation::GetTickCount;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{SystemParametersInfoW, SPI_GETSCREENSAVERRUNNING};
+
+    // While the lock screen (the Winlogon desktop) is up, a user process can't open or switch
+    // to the input desktop
+    fn locked() -> bool {
+        // SAFETY: the handle is checked for null and closed before returning
+        unsafe {
+            let desktop = OpenInputDesktop(0, 0, DESKTOP_SWITCHDESKTOP);
+            if desktop.is_null() {
+                return true;
+            }
+            let switched = SwitchDesktop(desktop);
+            CloseDesktop(desktop);
+            switched == 0
+        }
+    }
+
+    pub fn session_state() -> anyhow::Result<SessionState> {
+        let mut info = LASTINPUTINFO { cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32, dwTime: 0 };
+        // SAFETY: info is a properly sized LASTINPUTINFO
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return Err(anyhow!("GetLastInputInfo failed: {}", std::io::Error::last_os_error()));
+        }
+        // Both are 32-bit tick counts that wrap after ~49 days
+        // SAFETY: no arguments
+        let idle_ms = unsafe { GetTickCount() }.wrapping_sub(info.dwTime) as u64;
+        let mut running = 0i32;
+        // SAFETY: SPI_GETSCREENSAVERRUNNING writes one BOOL to the pointer
+        let screensaver_active =
+            (unsafe { SystemParametersInfoW(SPI_GETSCREENSAVERRUNNING, 0, (&mut running as *mut i32).cast(), 0) } != 0).then_some(running != 0);
+        Ok(SessionState { locked: Some(locked()), idle_ms: Some(idle_ms), screensaver_active })
+    }
+}
+
+// Best effort through whatever is installed: logind for the lock state, the session bus
+// screensaver interface, and `xprintidle` for X11 idle time
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::SessionState;
+    use std::process::Command;
+
+    // stdout of a successful run; None if the program is missing or fails
+    fn run(program: &str, args: &[&str]) -> Option<String> {
+        let output = Command::new(program).args(args).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn locked() -> Option<bool> {
+        let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+        match run("loginctl", &["show-session", &session, "-p", "LockedHint", "--value"])?.as_str() {
+            "yes" => Some(true),
+            "no" => Some(false),
+            _ => None,
+        }
+    }
+
+    fn screensaver_active() -> Option<bool> {
+        // GNOME only implements its own name; other desktops implement the freedesktop one
+        ["org.freedesktop.ScreenSaver", "org.gnome.ScreenSaver"].iter().find_map(|name| {
+            let path = format!("/{}", name.replace('.', "/"));
+            let method = format!("{}.GetActive", name);
+            // Prints "(true,)" or "(false,)"
+            let output = run("gdbus", &["call", "--session", "--dest", name, "--object-path", &path, "--method", &method])?;
+            Some(output.contains("true"))
+        })
+    }
+
+    fn idle_ms() -> Option<u64> {
+        std::env::var_os("DISPLAY")?;
+        run("xprintidle", &[])?.parse().ok()
+    }
+
+    pub fn session_state() -> anyhow::Result<SessionState> {
+        Ok(SessionState { locked: locked(), idle_ms: idle_ms(), screensaver_active: screensaver_active() })
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use super::SessionState;
+    use std::process::Command;
+
+    fn run(program: &str, args: &[&str]) -> Option<String> {
+        let output = Command::new(program).args(args).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    // "HIDIdleTime" = 1234567890 (nanoseconds)
+    fn idle_ms() -> Option<u64> {
+        let output = run("ioreg", &["-c", "IOHIDSystem", "-d", "4"])?;
+        let line = output.lines().find(|line| line.contains("\"HIDIdleTime\""))?;
+        let nanos: u64 = line.rsplit('=').next()?.trim().parse().ok()?;
+        Some(nanos / 1_000_000)
+    }
+
+    // The window server publishes the lock state in the root IORegistry entry's session info
+    fn locked() -> Option<bool> {
+        let output = run("ioreg", &["-n", "Root", "-d", "1"])?;
+        Some(output.contains("\"CGSSessionScreenIsLocked\"=Yes"))
+    }
+
+    fn screensaver_active() -> Option<bool> {
+        let output = Command::new("pgrep").args(["-x", "ScreenSaverEngine"]).output().ok()?;
+        // pgrep exits with 1 when nothing matched
+        match output.status.code() {
+            Some(0) => Some(true),
+            Some(1) => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn session_state() -> anyhow::Result<SessionState> {
+        Ok(SessionState { locked: locked(), idle_ms: idle_ms(), screensaver_active: screensaver_active() })
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+mod imp {
+    use super::SessionState;
+
+    pub fn session_state() -> anyhow::Result<SessionState> {
+        Ok(SessionState { locked: None, idle_ms: None, screensaver_active: None })
+    }
+}
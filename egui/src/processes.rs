@@ -0,0 +1,115 @@
+// Running processes through sysinfo, including background ones that window enumeration misses
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessStatus, ProcessesToUpdate, System};
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use crate::apps;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// How long a forced kill may take to show up as gone before the result says so
+const KILL_WAIT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    pub name: String,
+    /// Executable path; `None` when the OS doesn't disclose it (other users' processes).
+    pub exe: Option<String>,
+    /// CPU usage over a short sample, as a percentage of one core (so it can exceed 100).
+    pub cpu_percent: f32,
+    /// Resident memory in bytes.
+    pub memory_bytes: u64,
+}
+
+/// Every running process whose name contains `name_filter` (case-insensitive), busiest first.
+/// CPU usage needs two samples, so this takes at least sysinfo's minimum update interval.
+pub async fn list(name_filter: Option<&str>) -> Vec<ProcessInfo> {
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::All, true);
+    sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
+    system.refresh_processes(ProcessesToUpdate::All, true);
+
+    let filter = name_filter.map(str::to_lowercase).filter(|f| !f.is_empty());
+    let mut processes: Vec<ProcessInfo> = system
+        .processes()
+        .values()
+        .map(|process| ProcessInfo {
+            pid: process.pid().as_u32(),
+            parent_pid: process.parent().map(Pid::as_u32),
+            name: process.name().to_string_lossy().into_owned(),
+            exe: process.exe().map(|path| path.display().to_string()),
+            cpu_percent: process.cpu_usage(),
+            memory_bytes: process.memory(),
+        })
+        .filter(|process| filter.as_ref().is_none_or(|f| process.name.to_lowercase().contains(f)))
+        .collect();
+    processes.sort_by(|a, b| {
+        b.cpu_percent.total_cmp(&a.cpu_percent).then(b.memory_bytes.cmp(&a.memory_bytes))
+    });
+    processes
+}
+
+/// How a kill_process call ended.
+#[derive(Debug)]
+pub struct Killed {
+    pub name: String,
+    /// Whether it had to be killed because it ignored the polite request (or `force` was set).
+    pub forced: bool,
+    /// Whether it was confirmed gone afterwards.
+    pub exited: bool,
+}
+
+// A killed process stays listed as a zombie until its parent reaps it, which may be never
+fn is_running(system: &mut System, pid: Pid) -> bool {
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    system.process(pid).is_some_and(|process| !matches!(process.status(), ProcessStatus::Zombie))
+}
+
+/// Asks process `pid` to close (SIGTERM / WM_CLOSE) and kills it if it is still running after
+/// `grace`, or straight away with `force`. Returns `Ok(None)` when no such process exists.
+pub async fn kill(pid: u32, force: bool, grace: Duration) -> anyhow::Result<Option<Killed>> {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    let Some(name) = system.process(pid).map(|process| process.name().to_string_lossy().into_owned()) else {
+        return Ok(None);
+    };
+
+    if !force {
+        match apps::request_close(pid.as_u32()) {
+            Err(e) => warn!("Polite close of PID {} failed, killing instead: {:#}", pid, e),
+            Ok(()) => {
+                let deadline = Instant::now() + grace;
+                while Instant::now() < deadline {
+                    sleep(POLL_INTERVAL).await;
+                    if !is_running(&mut system, pid) {
+                        info!("PID {} ({}) closed.", pid, name);
+                        return Ok(Some(Killed { name, forced: false, exited: true }));
+                    }
+                }
+                info!("PID {} ({}) still running after {:?}; killing it.", pid, name, grace);
+            }
+        }
+    }
+
+    let Some(process) = system.process(pid) else {
+        // Exited between the last check and now
+        return Ok(Some(Killed { name, forced: false, exited: true }));
+    };
+    if !process.kill() {
+        return Err(anyhow::anyhow!("Failed to kill PID {} ({}); it may belong to another user", pid, name));
+    }
+    let deadline = Instant::now() + KILL_WAIT;
+    let mut exited = false;
+    while Instant::now() < deadline {
+        sleep(POLL_INTERVAL).await;
+        if !is_running(&mut system, pid) {
+            exited = true;
+            break;
+        }
+    }
+    Ok(Some(Killed { name, forced: true, exited }))
+}
@@ -0,0 +1,106 @@
+// MCP progress notifications from long-running tools (cargo feature `progress`)
+//
+// rmcp 0.1.5 can send `notifications/progress` but drops the client's `_meta.progressToken`
+// before the request reaches a handler, so the JSON-RPC request id is used as the token
+// instead. That is what clients correlating by request id expect, but not what the spec says,
+// hence the opt-in feature; without it every function here is a no-op.
+use rmcp::service::{RequestContext, RoleServer};
+use std::future::Future;
+use std::time::Duration;
+
+// A wait shorter than this finishes before anyone would miss a liveness signal
+const TICK: Duration = Duration::from_millis(1000);
+
+pub use imp::{report, Reporter};
+
+/// Sleeps for `duration`, reporting elapsed/total milliseconds every second.
+pub async fn sleep(duration: Duration) {
+    let total_ms = duration.as_millis().min(u32::MAX as u128) as u32;
+    let mut remaining = duration;
+    while !remaining.is_zero() {
+        let step = remaining.min(TICK);
+        tokio::time::sleep(step).await;
+        remaining -= step;
+        report(total_ms - remaining.as_millis() as u32, Some(total_ms)).await;
+    }
+}
+
+#[cfg(feature = "progress")]
+mod imp {
+    use super::*;
+    use rmcp::model::{ProgressNotificationParam, ProgressToken};
+    use rmcp::service::Peer;
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+    use tracing::debug;
+
+    // Caps the notification rate of tools that report often (e.g. per typed character)
+    const MIN_INTERVAL: Duration = Duration::from_millis(250);
+
+    tokio::task_local! {
+        static CURRENT: Reporter;
+    }
+
+    /// Sends progress for one tool call to the client that made it.
+    #[derive(Clone)]
+    pub struct Reporter {
+        peer: Peer<RoleServer>,
+        token: ProgressToken,
+        last_sent: Arc<Mutex<Option<Instant>>>,
+    }
+
+    impl Reporter {
+        pub fn for_request(context: &RequestContext<RoleServer>) -> Self {
+            Self { peer: context.peer.clone(), token: context.id.clone(), last_sent: Arc::new(Mutex::new(None)) }
+        }
+
+        /// Runs the tool call with this reporter as the target of [`report`].
+        pub async fn scope<F: Future>(self, call: F) -> F::Output {
+            CURRENT.scope(self, call).await
+        }
+
+        fn due(&self, finished: bool) -> bool {
+            let mut last_sent = self.last_sent.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let due = finished || last_sent.is_none_or(|sent| sent.elapsed() >= MIN_INTERVAL);
+            if due {
+                *last_sent = Some(Instant::now());
+            }
+            due
+        }
+    }
+
+    /// Reports `progress` (out of `total`, if known) for the tool call running on this task.
+    /// Rate limited, except for the final report; does nothing outside a tool call.
+    pub async fn report(progress: u32, total: Option<u32>) {
+        let Ok(reporter) = CURRENT.try_with(Reporter::clone) else {
+            return;
+        };
+        if !reporter.due(total == Some(progress)) {
+            return;
+        }
+        let params = ProgressNotificationParam { progress_token: reporter.token, progress, total };
+        // Progress is advisory; a client that went away will see the call fail anyway
+        if let Err(e) = reporter.peer.notify_progress(params).await {
+            debug!("Failed to send progress notification: {}", e);
+        }
+    }
+}
+
+#[cfg(not(feature = "progress"))]
+mod imp {
+    use super::*;
+
+    pub struct Reporter;
+
+    impl Reporter {
+        pub fn for_request(_context: &RequestContext<RoleServer>) -> Self {
+            Self
+        }
+
+        pub async fn scope<F: Future>(self, call: F) -> F::Output {
+            call.await
+        }
+    }
+
+    pub async fn report(_progress: u32, _total: Option<u32>) {}
+}
@@ -0,0 +1,326 @@
+// Records real user input (global mouse/keyboard hook) as action log lines that --replay can re-run
+use crate::action_log::{now_ms, ActionLog, ActionRecord, FORMAT_VERSION};
+use anyhow::{anyhow, Context};
+use rdev::{Button, Event, EventType, Key};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, UNIX_EPOCH};
+use tracing::{info, warn};
+
+// Mouse moves are sampled rather than logged one per pixel; the last position before a click is
+// always written so clicks replay at the right spot
+const MOVE_SAMPLE_MS: u64 = 50;
+// How long to wait for the hook to report a startup failure (no X display, hook refused, ...)
+const LISTENER_STARTUP: Duration = Duration::from_millis(300);
+// Value of `client` in recorded lines, to tell them apart from tool calls made by a model
+const RECORDER_CLIENT: &str = "recorder";
+
+/// What a finished recording contains.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingSummary {
+    pub path: PathBuf,
+    /// Lines written to the file.
+    pub actions: u64,
+    /// Keys and buttons with no equivalent in keyboard_action / mouse_action, left out of the file.
+    pub skipped_events: u64,
+    pub duration_ms: u64,
+}
+
+struct Recording {
+    log: ActionLog,
+    path: PathBuf,
+    started_ms: u64,
+    actions: u64,
+    skipped_events: u64,
+    /// Latest position not yet written, with its timestamp.
+    pending_move: Option<(i32, i32, u64)>,
+    last_move_ms: u64,
+}
+
+impl Recording {
+    fn write(&mut self, tool: &str, params: Value, timestamp_ms: u64) {
+        let Value::Object(params) = params else { return };
+        self.log.append(ActionRecord {
+            version: FORMAT_VERSION,
+            timestamp_ms,
+            client: RECORDER_CLIENT.to_string(),
+            tool: tool.to_string(),
+            params: Some(params),
+            status: "success".to_string(),
+            duration_ms: 0,
+            error: None,
+        });
+        self.actions += 1;
+    }
+
+    fn flush_move(&mut self) {
+        if let Some((x, y, timestamp_ms)) = self.pending_move.take() {
+            self.write("move_mouse", json!({ "x": x, "y": y, "coordinate": "Absolute" }), timestamp_ms);
+            self.last_move_ms = timestamp_ms;
+        }
+    }
+
+    fn handle(&mut self, event: Event) {
+        let timestamp_ms = event.time.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_else(|_| now_ms());
+        match event.event_type {
+            EventType::MouseMove { x, y } => {
+                self.pending_move = Some((x.round() as i32, y.round() as i32, timestamp_ms));
+                if timestamp_ms.saturating_sub(self.last_move_ms) >= MOVE_SAMPLE_MS {
+                    self.flush_move();
+                }
+            }
+            EventType::ButtonPress(button) | EventType::ButtonRelease(button) => {
+                self.flush_move();
+                let Some(name) = button_name(button) else {
+                    self.skipped_events += 1;
+                    return;
+                };
+                let click_type = if matches!(event.event_type, EventType::ButtonPress(_)) { "Press" } else { "Release" };
+                self.write("mouse_action", json!({ "button": name, "click_type": click_type }), timestamp_ms);
+            }
+            EventType::Wheel { delta_x, delta_y } => {
+                self.flush_move();
+                // mouse_action scrolls one notch per call
+                let notches = [
+                    (if delta_y > 0 { "ScrollUp" } else { "ScrollDown" }, delta_y.unsigned_abs()),
+                    (if delta_x > 0 { "ScrollRight" } else { "ScrollLeft" }, delta_x.unsigned_abs()),
+                ];
+                for (button, count) in notches {
+                    for _ in 0..count {
+                        self.write("mouse_action", json!({ "button": button, "click_type": "Click" }), timestamp_ms);
+                    }
+                }
+            }
+            EventType::KeyPress(key) | EventType::KeyRelease(key) => {
+                self.flush_move();
+                let Some(name) = key_name(key) else {
+                    self.skipped_events += 1;
+                    return;
+                };
+                let key_action = if matches!(event.event_type, EventType::KeyPress(_)) { "Press" } else { "Release" };
+                self.write("keyboard_action", json!({ "key": name, "key_action": key_action }), timestamp_ms);
+            }
+        }
+    }
+}
+
+fn button_name(button: Button) -> Option<&'static str> {
+    match button {
+        Button::Left => Some("Left"),
+        Button::Right => Some("Right"),
+        Button::Middle => Some("Middle"),
+        Button::Unknown(_) => None,
+    }
+}
+
+// Names as accepted by keyboard_action's `key`. Keys are physical (QWERTY positions), matching
+// what rdev reports regardless of layout
+fn key_name(key: Key) -> Option<&'static str> {
+    Some(match key {
+        Key::Alt => "alt",
+        Key::Backspace => "backspace",
+        Key::CapsLock => "capslock",
+        Key::ControlLeft | Key::ControlRight => "control",
+        Key::Delete | Key::KpDelete => "delete",
+        Key::DownArrow => "down",
+        Key::End => "end",
+        Key::Escape => "escape",
+        Key::F1 => "f1",
+        Key::F2 => "f2",
+        Key::F3 => "f3",
+        Key::F4 => "f4",
+        Key::F5 => "f5",
+        Key::F6 => "f6",
+        Key::F7 => "f7",
+        Key::F8 => "f8",
+        Key::F9 => "f9",
+        Key::F10 => "f10",
+        Key::F11 => "f11",
+        Key::F12 => "f12",
+        Key::Home => "home",
+        Key::LeftArrow => "left",
+        Key::MetaLeft | Key::MetaRight => "meta",
+        Key::PageDown => "pagedown",
+        Key::PageUp => "pageup",
+        Key::Return | Key::KpReturn => "enter",
+        Key::RightArrow => "right",
+        Key::ShiftLeft | Key::ShiftRight => "shift",
+        Key::Space => "space",
+        Key::Tab => "tab",
+        Key::UpArrow => "up",
+        Key::BackQuote => "`",
+        Key::Num1 | Key::Kp1 => "1",
+        Key::Num2 | Key::Kp2 => "2",
+        Key::Num3 | Key::Kp3 => "3",
+        Key::Num4 | Key::Kp4 => "4",
+        Key::Num5 | Key::Kp5 => "5",
+        Key::Num6 | Key::Kp6 => "6",
+        Key::Num7 | Key::Kp7 => "7",
+        Key::Num8 | Key::Kp8 => "8",
+        Key::Num9 | Key::Kp9 => "9",
+        Key::Num0 | Key::Kp0 => "0",
+        Key::Minus | Key::KpMinus => "-",
+        Key::Equal => "=",
+        Key::KpPlus => "+",
+        Key::KpMultiply => "*",
+        Key::KpDivide | Key::Slash => "/",
+        Key::KeyQ => "q",
+        Key::KeyW => "w",
+        Key::KeyE => "e",
+        Key::KeyR => "r",
+        Key::KeyT => "t",
+        Key::KeyY => "y",
+        Key::KeyU => "u",
+        Key::KeyI => "i",
+        Key::KeyO => "o",
+        Key::KeyP => "p",
+        Key::LeftBracket => "[",
+        Key::RightBracket => "]",
+        Key::KeyA => "a",
+        Key::KeyS => "s",
+        Key::KeyD => "d",
+        Key::KeyF => "f",
+        Key::KeyG => "g",
+        Key::KeyH => "h",
+        Key::KeyJ => "j",
+        Key::KeyK => "k",
+        Key::KeyL => "l",
+        Key::SemiColon => ";",
+        Key::Quote => "'",
+        Key::BackSlash | Key::IntlBackslash => "\\",
+        Key::KeyZ => "z",
+        Key::KeyX => "x",
+        Key::KeyC => "c",
+        Key::KeyV => "v",
+        Key::KeyB => "b",
+        Key::KeyN => "n",
+        Key::KeyM => "m",
+        Key::Comma => ",",
+        Key::Dot => ".",
+        // Includes AltGr: keyboard_action has no key for it and "altgraph" replays as plain Alt
+        _ => return None,
+    })
+}
+
+// Directory new recordings go into. It lives in the shared temp dir, so on Unix it is kept
+// private to the current user; a directory someone else created there is refused.
+fn recordings_dir() -> anyhow::Result<PathBuf> {
+    let dir = std::env::temp_dir().join("airc-recordings");
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(&dir).with_context(|| format!("Failed to create recording directory {:?}", dir))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let metadata = std::fs::symlink_metadata(&dir).with_context(|| format!("Failed to inspect recording directory {:?}", dir))?;
+        if !metadata.is_dir() {
+            return Err(anyhow!("Recording directory {:?} is not a plain directory", dir));
+        }
+        // Only the owner may change the mode, so this also fails for a directory we don't own
+        if metadata.permissions().mode() & 0o777 != 0o700 {
+            std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+                .with_context(|| format!("Recording directory {:?} is not private and can't be made so", dir))?;
+        }
+    }
+    Ok(dir)
+}
+
+/// Starts and stops input recordings. Shared by all clients; only one recording runs at a time.
+///
+/// The OS hook can't be removed again once installed, so the listener thread is started on the
+/// first recording and stays alive, dropping events while nothing is being recorded.
+#[derive(Clone, Default)]
+pub struct Recorder {
+    active: Arc<Mutex<Option<Recording>>>,
+    listening: Arc<Mutex<bool>>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panic mid-event at worst loses that event, so a poisoned lock is safe to reuse
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Recorder {
+    fn ensure_listener(&self) -> anyhow::Result<()> {
+        let mut listening = lock(&self.listening);
+        if *listening {
+            return Ok(());
+        }
+        let active = self.active.clone();
+        let (error_tx, error_rx) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("airc-input-recorder".to_string())
+            .spawn(move || {
+                // Only returns if the hook couldn't be installed
+                if let Err(e) = rdev::listen(move |event| {
+                    if let Some(recording) = lock(&active).as_mut() {
+                        recording.handle(event);
+                    }
+                }) {
+                    let _ = error_tx.send(e);
+                }
+            })
+            .context("Failed to start the input recorder thread")?;
+        match error_rx.recv_timeout(LISTENER_STARTUP) {
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                *listening = true;
+                Ok(())
+            }
+            Ok(e) => Err(anyhow!("Failed to hook global input events: {:?}", e)),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(anyhow!("The input hook stopped unexpectedly")),
+        }
+    }
+
+    /// Starts recording into a new file in the recordings directory and returns its path.
+    /// Errors if a recording is already running.
+    pub fn start(&self) -> anyhow::Result<PathBuf> {
+        if let Some(recording) = lock(&self.active).as_ref() {
+            return Err(anyhow!("Already recording to {:?}; stop that recording first", recording.path));
+        }
+        self.ensure_listener()?;
+
+        let dir = recordings_dir()?;
+        let started_ms = now_ms();
+        let path = dir.join(format!("recording-{}-{}.jsonl", std::process::id(), started_ms));
+        let log = ActionLog::open(&path)?;
+
+        let mut active = lock(&self.active);
+        if active.is_some() {
+            return Err(anyhow!("Another recording was started at the same time"));
+        }
+        *active = Some(Recording {
+            log,
+            path: path.clone(),
+            started_ms,
+            actions: 0,
+            skipped_events: 0,
+            pending_move: None,
+            last_move_ms: 0,
+        });
+        info!("Recording user input to {:?}", path);
+        Ok(path)
+    }
+
+    /// Stops the running recording, or returns `None` if there isn't one.
+    pub fn stop(&self) -> Option<RecordingSummary> {
+        let mut recording = lock(&self.active).take()?;
+        recording.flush_move();
+        let summary = RecordingSummary {
+            path: recording.path,
+            actions: recording.actions,
+            skipped_events: recording.skipped_events,
+            duration_ms: now_ms().saturating_sub(recording.started_ms),
+        };
+        if summary.skipped_events > 0 {
+            warn!("{} recorded key/button event(s) had no replayable equivalent and were left out.", summary.skipped_events);
+        }
+        info!("Stopped recording: {} action(s) written to {:?}", summary.actions, summary.path);
+        Some(summary)
+    }
+}
@@ -0,0 +1,85 @@
+// `--replay <path>`: re-runs the actions of an AIRC_ACTION_LOG file through the normal tool handlers
+use crate::action_log::{has_redacted_env, ActionRecord, FORMAT_VERSION};
+use crate::{DesktopToolProvider, READ_ONLY_TOOLS};
+use anyhow::{anyhow, Context};
+use rmcp::model::CallToolRequestParam;
+use rmcp::{serve_client, serve_server};
+use std::path::Path;
+use tokio::time::{sleep, Duration};
+use tracing::{info, warn};
+
+// Waits longer than this between two recorded actions are cut short (before applying --speed)
+const MAX_GAP: Duration = Duration::from_secs(30);
+
+/// Replays every successful, state-changing action in the log at its recorded pace divided by
+/// `speed`, except calls whose environment variables were redacted. Actions go through an
+/// in-process MCP session, so they hit exactly the same handlers (and throttling) as a live
+/// client, without needing the orchestrator.
+pub async fn run(path: &Path, speed: f64, provider: DesktopToolProvider) -> anyhow::Result<()> {
+    if speed.is_nan() || speed <= 0.0 {
+        return Err(anyhow!("--speed must be a positive number, got {}", speed));
+    }
+    let contents = std::fs::read_to_string(path).with_context(|| format!("Failed to read action log {:?}", path))?;
+    let mut records = Vec::new();
+    for (i, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let record: ActionRecord = serde_json::from_str(line)
+            .with_context(|| format!("Invalid action log entry on line {}", i + 1))?;
+        if record.version != FORMAT_VERSION {
+            return Err(anyhow!("Line {} has action log version {}, expected {}", i + 1, record.version, FORMAT_VERSION));
+        }
+        records.push(record);
+    }
+    info!("Replaying {} logged call(s) from {:?} at {}x speed.", records.len(), path, speed);
+
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    let cleanup_provider = provider.clone();
+    // The server only finishes starting once the client has initialized, so both run at once
+    let (server, client) = tokio::join!(serve_server(provider, server_io), serve_client((), client_io));
+    let server = server.context("Failed to start in-process MCP server")?;
+    let client = client.context("Failed to start in-process MCP client")?;
+
+    let mut previous_ms: Option<u64> = None;
+    let (mut replayed, mut skipped) = (0, 0);
+    for record in records {
+        if record.status != "success" || READ_ONLY_TOOLS.contains(&record.tool.as_str()) {
+            skipped += 1;
+            continue;
+        }
+        // Launching with "<redacted>" in place of the real values (often API keys) would run the
+        // program with a broken environment rather than reproduce the call
+        if record.params.as_ref().is_some_and(has_redacted_env) {
+            warn!(
+                "Skipping '{}' {:?}: its environment variables were redacted in the log, so it can't be replayed faithfully. Run it manually if needed.",
+                record.tool, record.params
+            );
+            skipped += 1;
+            continue;
+        }
+        if let Some(previous) = previous_ms {
+            let gap = Duration::from_millis(record.timestamp_ms.saturating_sub(previous)).min(MAX_GAP);
+            sleep(gap.div_f64(speed)).await;
+        }
+        previous_ms = Some(record.timestamp_ms);
+
+        if record.params.as_ref().is_some_and(|p| serde_json::to_string(p).unwrap_or_default().contains("<truncated ")) {
+            warn!("Parameters of '{}' were truncated in the log; replaying the truncated values.", record.tool);
+        }
+        info!("Replaying '{}' {:?}", record.tool, record.params);
+        let request = CallToolRequestParam { name: record.tool.clone().into(), arguments: record.params };
+        match client.call_tool(request).await {
+            Ok(result) if result.is_error == Some(true) => warn!("Replayed '{}' returned a tool error: {:?}", record.tool, result.content),
+            Ok(_) => {}
+            Err(e) => warn!("Replayed '{}' failed: {}", record.tool, e),
+        }
+        replayed += 1;
+    }
+
+    info!("Replay finished: {} action(s) replayed, {} skipped.", replayed, skipped);
+    client.cancel().await.context("Failed to close replay session")?;
+    if let Err(e) = server.waiting().await {
+        warn!("Replay server ended with an error: {}", e);
+    }
+    // A log cut off mid-drag would otherwise leave the button down
+    cleanup_provider.release_held_inputs();
+    Ok(())
+}
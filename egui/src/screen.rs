@@ -0,0 +1,335 @@
+// Screen capture helpers shared by the capture/assertion tools
+use anyhow::{anyhow, Context};
+use image::{Rgba, RgbaImage};
+use serde::Serialize;
+use std::sync::OnceLock;
+use tracing::warn;
+
+// Captures the monitor containing the absolute desktop point (x, y), along with its layout
+fn capture_display_at(x: i32, y: i32) -> anyhow::Result<(RgbaImage, display_info::DisplayInfo)> {
+    let display = display_info::DisplayInfo::from_point(x, y)
+        .map_err(|e| anyhow!("No display contains point ({}, {}): {}", x, y, e))?;
+    let monitor = xcap::Monitor::from_point(x, y)
+        .map_err(|e| anyhow!(e).context(format!("No monitor contains point ({}, {})", x, y)))?;
+    let image = monitor.capture_image().context("Failed to capture monitor")?;
+    Ok((image, display))
+}
+
+/// Captures the monitor containing the absolute desktop point (x, y) and returns the image
+/// together with the point translated into that monitor's local pixel space.
+pub fn capture_monitor_at(x: i32, y: i32) -> anyhow::Result<(RgbaImage, u32, u32)> {
+    let (image, display) = capture_display_at(x, y)?;
+    let (local_x, local_y) = global_to_local(&display, x as f64, y as f64);
+    if local_x < 0 || local_y < 0 || local_x as u32 >= image.width() || local_y as u32 >= image.height() {
+        return Err(anyhow!(
+            "Point ({}, {}) is outside the captured monitor area ({}x{})",
+            x, y, image.width(), image.height()
+        ));
+    }
+    Ok((image, local_x as u32, local_y as u32))
+}
+
+/// Bounding box of all displays in desktop coordinates. The origin can be negative when a
+/// monitor sits left of or above the primary one.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct VirtualBounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Combined bounds of the given displays, or `None` if there are none.
+pub fn virtual_bounds(displays: &[display_info::DisplayInfo]) -> Option<VirtualBounds> {
+    let left = displays.iter().map(|d| d.x).min()?;
+    let top = displays.iter().map(|d| d.y).min()?;
+    let right = displays.iter().map(|d| d.x as i64 + d.width as i64).max()?;
+    let bottom = displays.iter().map(|d| d.y as i64 + d.height as i64).max()?;
+    Some(VirtualBounds {
+        x: left,
+        y: top,
+        width: (right - left as i64) as u32,
+        height: (bottom - top as i64) as u32,
+    })
+}
+
+/// `AIRC_CLAMP_COORDS=1`: absolute mouse targets outside the virtual desktop are moved onto
+/// its nearest edge instead of being sent as-is. Read once.
+fn clamp_coords_enabled() -> bool {
+    static CLAMP: OnceLock<bool> = OnceLock::new();
+    *CLAMP.get_or_init(|| matches!(std::env::var("AIRC_CLAMP_COORDS").as_deref().map(str::trim), Ok("1") | Ok("true")))
+}
+
+/// Applies `AIRC_CLAMP_COORDS` to an absolute mouse target: returns the point to use and
+/// whether it was clamped. A no-op when the option is off.
+pub fn clamp_mouse_target(x: i32, y: i32) -> anyhow::Result<(i32, i32, bool)> {
+    if !clamp_coords_enabled() {
+        return Ok((x, y, false));
+    }
+    let displays = display_info::DisplayInfo::all().map_err(|e| anyhow!("Failed to enumerate displays: {}", e))?;
+    let bounds = virtual_bounds(&displays).ok_or_else(|| anyhow!("No displays found to clamp coordinates to"))?;
+    let clamped_x = x.clamp(bounds.x, bounds.x + bounds.width.saturating_sub(1) as i32);
+    let clamped_y = y.clamp(bounds.y, bounds.y + bounds.height.saturating_sub(1) as i32);
+    let clamped = (clamped_x, clamped_y) != (x, y);
+    if clamped {
+        warn!("Clamped mouse target ({}, {}) to ({}, {}) inside the virtual desktop.", x, y, clamped_x, clamped_y);
+    }
+    Ok((clamped_x, clamped_y, clamped))
+}
+
+// Absorbs the rounding of scaled displays, where the cursor can land a pixel off the target
+const DEFAULT_MOVE_TOLERANCE_PX: u32 = 2;
+
+/// How far (in pixels, on either axis) the cursor may end up from an absolute move target before
+/// the move counts as missed, from `AIRC_MOVE_TOLERANCE_PX` (default 2). Read once.
+fn move_tolerance() -> u32 {
+    static TOLERANCE: OnceLock<u32> = OnceLock::new();
+    *TOLERANCE.get_or_init(|| match std::env::var("AIRC_MOVE_TOLERANCE_PX") {
+        Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+            warn!("Ignoring invalid AIRC_MOVE_TOLERANCE_PX value '{}'", raw);
+            DEFAULT_MOVE_TOLERANCE_PX
+        }),
+        Err(_) => DEFAULT_MOVE_TOLERANCE_PX,
+    })
+}
+
+/// Whether the cursor read back at `actual` is off the absolute move `target` by more than
+/// `AIRC_MOVE_TOLERANCE_PX`, i.e. the OS ignored or redirected the move (locked workstation,
+/// secure desktop, a cursor-confining app).
+pub fn missed_target(target: (i32, i32), actual: (i32, i32)) -> bool {
+    let tolerance = move_tolerance();
+    target.0.abs_diff(actual.0) > tolerance || target.1.abs_diff(actual.1) > tolerance
+}
+
+/// Converts a pixel of a screenshot of `display` to desktop coordinates. Screenshots are in
+/// physical pixels while desktop coordinates are scaled, so the offset is divided by the
+/// display's scale factor.
+pub fn local_to_global(display: &display_info::DisplayInfo, x: f64, y: f64) -> (i32, i32) {
+    let scale = if display.scale_factor > 0.0 { display.scale_factor as f64 } else { 1.0 };
+    ((display.x as f64 + x / scale).round() as i32, (display.y as f64 + y / scale).round() as i32)
+}
+
+/// Converts desktop coordinates to a pixel of a screenshot of `display`; the inverse of
+/// [`local_to_global`]. The result is outside the screenshot if the point isn't on `display`.
+pub fn global_to_local(display: &display_info::DisplayInfo, x: f64, y: f64) -> (i32, i32) {
+    let scale = if display.scale_factor > 0.0 { display.scale_factor as f64 } else { 1.0 };
+    (((x - display.x as f64) * scale).round() as i32, ((y - display.y as f64) * scale).round() as i32)
+}
+
+/// Whether desktop point (x, y) lies within `display`'s bounds.
+pub fn display_contains(display: &display_info::DisplayInfo, x: i32, y: i32) -> bool {
+    let (x, y) = (x as i64, y as i64);
+    x >= display.x as i64
+        && y >= display.y as i64
+        && x < display.x as i64 + display.width as i64
+        && y < display.y as i64 + display.height as i64
+}
+
+/// Whether the absolute desktop point lies on any monitor.
+pub fn point_on_screen(x: i32, y: i32) -> bool {
+    xcap::Monitor::from_point(x, y).is_ok()
+}
+
+/// Reads the color of a single pixel at absolute desktop coordinates.
+pub fn read_screen_pixel(x: i32, y: i32) -> anyhow::Result<Rgba<u8>> {
+    let (image, local_x, local_y) = capture_monitor_at(x, y)?;
+    Ok(*image.get_pixel(local_x, local_y))
+}
+
+/// Parses "#RRGGBB" / "RRGGBB" into an RGB triple.
+pub fn parse_hex_color(hex: &str) -> anyhow::Result<[u8; 3]> {
+    let digits = hex.trim().trim_start_matches('#');
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow!("Invalid hex color '{}'. Expected format '#RRGGBB'.", hex));
+    }
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).unwrap_or(0);
+    Ok([channel(0), channel(2), channel(4)])
+}
+
+/// Formats a pixel as "#rrggbb" (alpha is ignored).
+pub fn to_hex_color(pixel: &Rgba<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", pixel[0], pixel[1], pixel[2])
+}
+
+/// Largest per-channel difference between a pixel and an expected RGB color.
+pub fn color_distance(pixel: &Rgba<u8>, expected: [u8; 3]) -> u8 {
+    (0..3).map(|i| pixel[i].abs_diff(expected[i])).max().unwrap_or(0)
+}
+
+/// Captures a region of the desktop given in absolute coordinates. The region must lie on a
+/// single monitor; it is clipped to that monitor's bounds.
+pub fn capture_region(x: i32, y: i32, width: u32, height: u32) -> anyhow::Result<RgbaImage> {
+    if width == 0 || height == 0 {
+        return Err(anyhow!("Capture region must have a non-zero width and height"));
+    }
+    let (image, display) = capture_display_at(x, y)?;
+    let (left, top) = global_to_local(&display, x as f64, y as f64);
+    let (right, bottom) = global_to_local(&display, x as f64 + width as f64, y as f64 + height as f64);
+    let clip = |value: i32, max: u32| value.clamp(0, max as i32) as u32;
+    let (left, right) = (clip(left, image.width()), clip(right, image.width()));
+    let (top, bottom) = (clip(top, image.height()), clip(bottom, image.height()));
+    if right <= left || bottom <= top {
+        return Err(anyhow!(
+            "Region at ({}, {}) is outside the captured monitor area ({}x{})",
+            x, y, image.width(), image.height()
+        ));
+    }
+    Ok(image::imageops::crop_imm(&image, left, top, right - left, bottom - top).to_image())
+}
+
+// 8K; well above any single monitor, so only stitched or misbehaving captures hit it
+const DEFAULT_MAX_CAPTURE_PIXELS: u64 = 7680 * 4320;
+
+/// Server-side limit on the size of returned screenshots, whatever the client asked for.
+#[derive(Debug, Clone, Copy)]
+pub struct PixelCap {
+    pub max_pixels: u64,
+    /// Reject oversized captures instead of downscaling them.
+    pub reject: bool,
+}
+
+/// The cap from `AIRC_MAX_CAPTURE_PIXELS` (default 7680x4320) and `AIRC_CAPTURE_OVERSIZE`
+/// ("downscale", the default, or "reject"). Read once.
+pub fn pixel_cap() -> PixelCap {
+    static CAP: OnceLock<PixelCap> = OnceLock::new();
+    *CAP.get_or_init(|| {
+        let max_pixels = match std::env::var("AIRC_MAX_CAPTURE_PIXELS") {
+            Ok(raw) => raw.trim().parse().ok().filter(|&pixels: &u64| pixels > 0).unwrap_or_else(|| {
+                warn!("Ignoring invalid AIRC_MAX_CAPTURE_PIXELS value '{}'", raw);
+                DEFAULT_MAX_CAPTURE_PIXELS
+            }),
+            Err(_) => DEFAULT_MAX_CAPTURE_PIXELS,
+        };
+        let reject = match std::env::var("AIRC_CAPTURE_OVERSIZE").as_deref().map(str::trim) {
+            Ok("reject") => true,
+            Ok("downscale") | Err(_) => false,
+            Ok(other) => {
+                warn!("Ignoring invalid AIRC_CAPTURE_OVERSIZE value '{}'; expected 'downscale' or 'reject'", other);
+                false
+            }
+        };
+        PixelCap { max_pixels, reject }
+    })
+}
+
+/// A capture over the pixel cap while `AIRC_CAPTURE_OVERSIZE=reject`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Oversized {
+    pub width: u32,
+    pub height: u32,
+    pub max_pixels: u64,
+}
+
+impl std::fmt::Display for Oversized {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The {}x{} capture exceeds the server's limit of {} pixels", self.width, self.height, self.max_pixels)
+    }
+}
+
+/// Applies [`pixel_cap`] to a capture: returns it unchanged with scale 1.0, downscaled to fit
+/// with the applied scale, or `Oversized` when the server rejects large captures.
+pub fn limit_pixels(image: RgbaImage) -> Result<(RgbaImage, f64), Oversized> {
+    let cap = pixel_cap();
+    let pixels = image.width() as u64 * image.height() as u64;
+    if pixels <= cap.max_pixels {
+        return Ok((image, 1.0));
+    }
+    if cap.reject {
+        return Err(Oversized { width: image.width(), height: image.height(), max_pixels: cap.max_pixels });
+    }
+    let scale = (cap.max_pixels as f64 / pixels as f64).sqrt();
+    let width = ((image.width() as f64 * scale) as u32).max(1);
+    let height = ((image.height() as f64 * scale) as u32).max(1);
+    warn!("Downscaling a {}x{} capture to {}x{} to stay within {} pixels.", image.width(), image.height(), width, height, cap.max_pixels);
+    Ok((image::imageops::resize(&image, width, height, image::imageops::FilterType::Triangle), scale))
+}
+
+/// Captures the primary (first) monitor in full, returning the image and the desktop position
+/// of its top-left pixel.
+pub fn capture_primary() -> anyhow::Result<(RgbaImage, i32, i32)> {
+    let monitors = xcap::Monitor::all().context("Failed to get screen list")?;
+    let monitor = monitors.first().ok_or_else(|| anyhow!("No screen found to capture"))?;
+    let image = monitor.capture_image().context("Failed to capture screen area")?;
+    Ok((image, monitor.x().unwrap_or(0), monitor.y().unwrap_or(0)))
+}
+
+/// Encodes an image as PNG and returns it base64-encoded.
+pub fn encode_png_base64(image: &RgbaImage) -> anyhow::Result<String> {
+    use base64::Engine;
+    let mut buf: Vec<u8> = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+        .context("Failed to encode image as PNG")?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(&buf))
+}
+
+/// File formats a screenshot can be saved in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveFormat {
+    Png,
+    Jpeg,
+}
+
+impl SaveFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "jpeg" | "jpg" => Some(Self::Jpeg),
+            _ => None,
+        }
+    }
+
+    /// The format implied by a file name's extension, if it names one.
+    pub fn from_path(path: &str) -> Option<Self> {
+        std::path::Path::new(path).extension().and_then(|ext| ext.to_str()).and_then(Self::parse)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpeg",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+        }
+    }
+}
+
+/// Downscales `image` to at most `max_width` pixels wide, keeping its aspect ratio. Returns the
+/// image and the scale applied (1.0 when it already fits).
+pub fn fit_width(image: RgbaImage, max_width: u32) -> (RgbaImage, f64) {
+    if max_width == 0 || image.width() <= max_width {
+        return (image, 1.0);
+    }
+    let scale = max_width as f64 / image.width() as f64;
+    let height = ((image.height() as f64 * scale) as u32).max(1);
+    (image::imageops::resize(&image, max_width, height, image::imageops::FilterType::Triangle), scale)
+}
+
+/// Encodes an image in `format`; `quality` (1-100) only applies to JPEG, which also drops the
+/// alpha channel.
+pub fn encode_image(image: &RgbaImage, format: SaveFormat, quality: u8) -> anyhow::Result<Vec<u8>> {
+    let mut buf: Vec<u8> = Vec::new();
+    match format {
+        SaveFormat::Png => image
+            .write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+            .context("Failed to encode image as PNG")?,
+        SaveFormat::Jpeg => {
+            let rgb = image::DynamicImage::ImageRgba8(image.clone()).into_rgb8();
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, quality.clamp(1, 100))
+                .encode_image(&rgb)
+                .context("Failed to encode image as JPEG")?
+        }
+    }
+    Ok(buf)
+}
+
+/// Like [`encode_image`], but returns the encoded bytes base64-encoded.
+pub fn encode_image_base64(image: &RgbaImage, format: SaveFormat, quality: u8) -> anyhow::Result<String> {
+    use base64::Engine;
+    Ok(base64::engine::general_purpose::STANDARD.encode(encode_image(image, format, quality)?))
+}
@@ -0,0 +1,100 @@
+// Smooth (sub-notch) mouse wheel scrolling for smooth_scroll
+use serde::Serialize;
+
+/// Rough on-screen distance of one wheel notch (3 lines in most apps). Used to turn pixels into
+/// wheel deltas, and into a notch count where only whole notches can be sent.
+pub const PIXELS_PER_NOTCH: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrollMode {
+    /// Many small high-resolution wheel deltas.
+    Smooth,
+    /// Whole wheel notches spread over the duration.
+    Notch,
+}
+
+/// Whether this platform can send wheel deltas smaller than a notch.
+pub const SMOOTH_SUPPORTED: bool = imp::SMOOTH_SUPPORTED;
+
+/// Scroll direction, as the content moves: `Down` shows what is further down the page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl ScrollDirection {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "up" => Some(Self::Up),
+            "down" => Some(Self::Down),
+            "left" => Some(Self::Left),
+            "right" => Some(Self::Right),
+            _ => None,
+        }
+    }
+}
+
+/// Ease-in-out progress (0..=1) at fraction `t` of the duration, so the glide starts and ends
+/// gently like a trackpad or an animated page scroll.
+pub fn ease(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Sends one high-resolution wheel event of `pixels` in `direction`. Call only when
+/// [`SMOOTH_SUPPORTED`]; the caller holds the input backend lock so events don't interleave.
+pub fn send_pixels(direction: ScrollDirection, pixels: u32) -> anyhow::Result<()> {
+    imp::send_pixels(direction, pixels)
+}
+
+// SendInput with arbitrary wheel deltas; WHEEL_DELTA (120) is one notch
+#[cfg(windows)]
+mod imp {
+    use super::{ScrollDirection, PIXELS_PER_NOTCH};
+    use anyhow::anyhow;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        SendInput, INPUT, INPUT_0, INPUT_MOUSE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_WHEEL, MOUSEINPUT,
+    };
+
+    pub const SMOOTH_SUPPORTED: bool = true;
+    const WHEEL_DELTA: i64 = 120;
+
+    pub fn send_pixels(direction: ScrollDirection, pixels: u32) -> anyhow::Result<()> {
+        let delta = (pixels as i64 * WHEEL_DELTA / PIXELS_PER_NOTCH as i64).max(1) as i32;
+        // Positive vertical deltas scroll up (away from the user), positive horizontal ones right
+        let (delta, flags) = match direction {
+            ScrollDirection::Up => (delta, MOUSEEVENTF_WHEEL),
+            ScrollDirection::Down => (-delta, MOUSEEVENTF_WHEEL),
+            ScrollDirection::Right => (delta, MOUSEEVENTF_HWHEEL),
+            ScrollDirection::Left => (-delta, MOUSEEVENTF_HWHEEL),
+        };
+        let input = INPUT {
+            r#type: INPUT_MOUSE,
+            Anonymous: INPUT_0 {
+                mi: MOUSEINPUT { dx: 0, dy: 0, mouseData: delta as _, dwFlags: flags, time: 0, dwExtraInfo: 0 },
+            },
+        };
+        // SAFETY: one fully initialized INPUT of the size passed
+        if unsafe { SendInput(1, &input, std::mem::size_of::<INPUT>() as i32) } != 1 {
+            return Err(anyhow!("SendInput failed: {}", std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+}
+
+// XTest only knows wheel buttons (whole notches), and enigo's macOS wheel events are line based
+#[cfg(not(windows))]
+mod imp {
+    use super::ScrollDirection;
+    use anyhow::anyhow;
+
+    pub const SMOOTH_SUPPORTED: bool = false;
+
+    pub fn send_pixels(_direction: ScrollDirection, _pixels: u32) -> anyhow::Result<()> {
+        Err(anyhow!("Smooth scrolling is not supported on this platform"))
+    }
+}
@@ -0,0 +1,276 @@
+// Per-connection state: each MCP client gets its own copy, unlike the shared input backend
+use crate::diff::ScreenSignature;
+use crate::input::HoldTarget;
+use enigo::{Button, Direction, Key};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+// Screenshots remembered per client for screen_changed_since; older tokens expire
+const MAX_SNAPSHOTS: usize = 16;
+const DEFAULT_MIN_ACTION_INTERVAL_MS: u64 = 50;
+
+/// Minimum time between two input actions of one client, from `AIRC_MIN_ACTION_INTERVAL_MS`
+/// (default 50 ms, 0 disables throttling). Read once on first use.
+pub fn min_action_interval() -> Duration {
+    static INTERVAL: OnceLock<Duration> = OnceLock::new();
+    *INTERVAL.get_or_init(|| {
+        let ms = match std::env::var("AIRC_MIN_ACTION_INTERVAL_MS") {
+            Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+                warn!("Ignoring invalid AIRC_MIN_ACTION_INTERVAL_MS value '{}'", raw);
+                DEFAULT_MIN_ACTION_INTERVAL_MS
+            }),
+            Err(_) => DEFAULT_MIN_ACTION_INTERVAL_MS,
+        };
+        Duration::from_millis(ms)
+    })
+}
+
+/// How long a client may go without calling a tool before its connection is closed, from
+/// `AIRC_IDLE_TIMEOUT_SECS` (unset or 0 disables the timeout). Read once on first use.
+pub fn idle_timeout() -> Option<Duration> {
+    static TIMEOUT: OnceLock<Option<Duration>> = OnceLock::new();
+    *TIMEOUT.get_or_init(|| {
+        let secs = match std::env::var("AIRC_IDLE_TIMEOUT_SECS") {
+            Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+                warn!("Ignoring invalid AIRC_IDLE_TIMEOUT_SECS value '{}'", raw);
+                0
+            }),
+            Err(_) => 0,
+        };
+        (secs > 0).then(|| Duration::from_secs(secs))
+    })
+}
+
+/// Settle delay of the input warm-up, from `AIRC_INPUT_WARMUP_MS` (unset or 0 disables it).
+/// When enabled, a client's first input tool call is preceded by a one-pixel cursor nudge and
+/// this delay, so the event a not-yet-focused target or a starting compositor drops is a
+/// harmless one. Read once on first use.
+pub fn input_warmup() -> Option<Duration> {
+    static WARMUP: OnceLock<Option<Duration>> = OnceLock::new();
+    *WARMUP.get_or_init(|| {
+        let ms = match std::env::var("AIRC_INPUT_WARMUP_MS") {
+            Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+                warn!("Ignoring invalid AIRC_INPUT_WARMUP_MS value '{}'", raw);
+                0
+            }),
+            Err(_) => 0,
+        };
+        (ms > 0).then(|| Duration::from_millis(ms))
+    })
+}
+
+/// Inputs a client pressed without releasing, as returned by [`ClientSession::take_held`].
+#[derive(Debug, Default)]
+pub struct HeldInputs {
+    pub keys: Vec<Key>,
+    pub scancodes: Vec<u16>,
+    pub buttons: Vec<Button>,
+}
+
+impl HeldInputs {
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty() && self.scancodes.is_empty() && self.buttons.is_empty()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ClientSession {
+    snapshots: VecDeque<(String, ScreenSignature)>,
+    next_snapshot: u64,
+    /// When the latest input action was (or is scheduled to be) performed.
+    last_action: Option<Instant>,
+    /// Keys and buttons this client pressed and hasn't released yet.
+    held_keys: Vec<Key>,
+    held_scancodes: Vec<u16>,
+    held_buttons: Vec<Button>,
+    /// When the latest tool call started or finished; `None` before the first one.
+    last_activity: Option<Instant>,
+    calls_in_flight: u32,
+    /// Whether the input warm-up has run (or started) for this client.
+    warmed_up: bool,
+}
+
+impl ClientSession {
+    /// Reserves the next input action slot and returns when it may run. Slots are handed out
+    /// in call order, so parallel calls from one client are spaced out rather than bunched.
+    pub fn reserve_action_slot(&mut self, interval: Duration) -> Instant {
+        let now = Instant::now();
+        let slot = match self.last_action {
+            Some(last) => (last + interval).max(now),
+            None => now,
+        };
+        self.last_action = Some(slot);
+        slot
+    }
+
+    /// Returns true exactly once per client: for the call that should run the input warm-up.
+    pub fn begin_warm_up(&mut self) -> bool {
+        !std::mem::replace(&mut self.warmed_up, true)
+    }
+
+    /// Stores a screen signature and returns the token the client passes back to compare with it.
+    pub fn remember_snapshot(&mut self, signature: ScreenSignature) -> String {
+        self.next_snapshot += 1;
+        let token = format!("snap-{}", self.next_snapshot);
+        if self.snapshots.len() >= MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back((token.clone(), signature));
+        token
+    }
+
+    pub fn snapshot(&self, token: &str) -> Option<&ScreenSignature> {
+        self.snapshots.iter().find(|(t, _)| t == token).map(|(_, signature)| signature)
+    }
+
+    /// Records a key event so held keys can be released if the client goes away.
+    pub fn track_key(&mut self, key: Key, direction: Direction) {
+        match direction {
+            Direction::Press if !self.held_keys.contains(&key) => self.held_keys.push(key),
+            Direction::Release => self.held_keys.retain(|k| *k != key),
+            _ => {}
+        }
+    }
+
+    /// Records a scancode event, like [`ClientSession::track_key`].
+    pub fn track_scancode(&mut self, scancode: u16, direction: Direction) {
+        match direction {
+            Direction::Press if !self.held_scancodes.contains(&scancode) => self.held_scancodes.push(scancode),
+            Direction::Release => self.held_scancodes.retain(|s| *s != scancode),
+            _ => {}
+        }
+    }
+
+    /// Records a mouse button event so held buttons can be released if the client goes away.
+    pub fn track_button(&mut self, button: Button, direction: Direction) {
+        match direction {
+            Direction::Press if !self.held_buttons.contains(&button) => self.held_buttons.push(button),
+            Direction::Release => self.held_buttons.retain(|b| *b != button),
+            _ => {}
+        }
+    }
+
+    /// Records the press or release of a hold_key / hold_mouse_button target.
+    pub fn track_hold(&mut self, target: HoldTarget, direction: Direction) {
+        match target {
+            HoldTarget::Key(key) => self.track_key(key, direction),
+            HoldTarget::Scancode(scancode) => self.track_scancode(scancode, direction),
+            HoldTarget::Button(button) => self.track_button(button, direction),
+        }
+    }
+
+    /// The currently held keys, scancodes and buttons, still tracked.
+    pub fn held(&self) -> HeldInputs {
+        HeldInputs {
+            keys: self.held_keys.clone(),
+            scancodes: self.held_scancodes.clone(),
+            buttons: self.held_buttons.clone(),
+        }
+    }
+
+    /// How long the client has gone without a tool call, counted from `connected` if it never
+    /// made one. A client is never idle while one of its calls is running.
+    pub fn idle_for(&self, connected: Instant) -> Duration {
+        if self.calls_in_flight > 0 {
+            return Duration::ZERO;
+        }
+        self.last_activity.unwrap_or(connected).elapsed()
+    }
+
+    /// Takes the currently held keys, scancodes and buttons, leaving none tracked.
+    pub fn take_held(&mut self) -> HeldInputs {
+        HeldInputs {
+            keys: std::mem::take(&mut self.held_keys),
+            scancodes: std::mem::take(&mut self.held_scancodes),
+            buttons: std::mem::take(&mut self.held_buttons),
+        }
+    }
+}
+
+/// Takes the inputs a client still holds and hands them to `release`, which isn't called when
+/// nothing is held. The disconnect cleanup goes through this, so whatever a tool pressed and
+/// tracked is released however the connection ended.
+pub fn release_held(session: &Mutex<ClientSession>, release: impl FnOnce(HeldInputs)) {
+    // Recover from poisoning: releasing stuck inputs matters more than the session's consistency
+    let held = session.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).take_held();
+    if !held.is_empty() {
+        release(held);
+    }
+}
+
+/// Marks a client's tool call as in flight for as long as the guard lives, so the idle timeout
+/// doesn't fire in the middle of a slow call (or stay suppressed after a cancelled one).
+pub struct CallGuard {
+    session: Arc<Mutex<ClientSession>>,
+}
+
+impl CallGuard {
+    pub fn begin(session: &Arc<Mutex<ClientSession>>) -> Self {
+        Self::update(session, |s| s.calls_in_flight += 1);
+        Self { session: session.clone() }
+    }
+
+    // Activity bookkeeping is harmless to apply to a poisoned session
+    fn update(session: &Mutex<ClientSession>, f: impl FnOnce(&mut ClientSession)) {
+        let mut session = session.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&mut session);
+        session.last_activity = Some(Instant::now());
+    }
+}
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        Self::update(&self.session, |s| s.calls_in_flight = s.calls_in_flight.saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_held_returns_unreleased_inputs() {
+        let mut session = ClientSession::default();
+        session.track_button(Button::Left, Direction::Press);
+        session.track_key(Key::Shift, Direction::Press);
+        session.track_key(Key::Shift, Direction::Release);
+        session.track_hold(HoldTarget::Scancode(0x1E), Direction::Press);
+
+        let held = session.take_held();
+        assert_eq!(held.buttons, [Button::Left]);
+        assert!(held.keys.is_empty());
+        assert_eq!(held.scancodes, [0x1E]);
+        assert!(session.take_held().is_empty());
+    }
+
+    #[test]
+    fn release_held_releases_once() {
+        let session = Mutex::new(ClientSession::default());
+        session.lock().unwrap().track_button(Button::Right, Direction::Press);
+
+        let mut released = Vec::new();
+        release_held(&session, |held| released.extend(held.buttons));
+        assert_eq!(released, [Button::Right]);
+
+        release_held(&session, |_| panic!("nothing is held any more"));
+    }
+
+    #[test]
+    fn release_held_recovers_poisoned_session() {
+        let session = Arc::new(Mutex::new(ClientSession::default()));
+        let poisoner = Arc::clone(&session);
+        let _ = std::thread::spawn(move || {
+            let mut session = poisoner.lock().unwrap();
+            session.track_button(Button::Left, Direction::Press);
+            panic!("tool panicked while holding the session");
+        })
+        .join();
+        assert!(session.is_poisoned());
+
+        let mut released = Vec::new();
+        release_held(&session, |held| released.extend(held.buttons));
+        assert_eq!(released, [Button::Left]);
+    }
+}
@@ -0,0 +1,64 @@
+// Per-tool time limits applied around every tool call
+use std::time::Duration;
+use tracing::warn;
+
+const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+
+// Input tools should finish almost instantly; anything slower means a wedged backend.
+// Tools with their own wait or typing delays get room for those on top.
+const TOOL_TIMEOUTS_MS: &[(&str, u64)] = &[
+    ("move_mouse", 5_000),
+    ("move_mouse_relative_to_window", 10_000),
+    ("mouse_action", 5_000),
+    ("keyboard_action", 120_000),
+    // Up to 64 KiB of text, possibly with per-character delays
+    ("type_from_file", 600_000),
+    ("hold_key", 90_000),
+    ("key_sequence", 90_000),
+    ("hold_mouse_button", 90_000),
+    ("reset_input", 5_000),
+    ("reset_input_backend", 10_000),
+    ("get_mouse_position", 5_000),
+    ("activate_window", 10_000),
+    ("ping", 5_000),
+    ("capture_screen", 60_000),
+    ("capture_screen_annotated", 30_000),
+    ("capture_screen_resource", 30_000),
+    ("capture_window", 30_000),
+    // Up to a minute of frames plus GIF encoding
+    ("capture_burst", 180_000),
+    // Cross-process UI Automation calls for up to 2000 elements
+    ("get_accessibility_tree", 60_000),
+    ("click_element", 60_000),
+    ("detect_dialogs", 60_000),
+    ("context_menu_select", 60_000),
+    ("ocr_screen", 60_000),
+    ("find_text_on_screen", 60_000),
+    ("assert_text", 60_000),
+    ("wait_for_pixel", 120_000),
+    ("wait_for_window", 120_000),
+    ("execute_openai_wait", 120_000),
+    ("execute_openai_click", 5_000),
+    ("execute_openai_scroll", 10_000),
+    ("execute_openai_keypress", 5_000),
+    ("execute_openai_type", 120_000),
+];
+
+/// Time limit for one call of `tool`: `AIRC_TIMEOUT_<tool>` in milliseconds if set (e.g.
+/// `AIRC_TIMEOUT_run_shell_command=30000`), otherwise the built-in default for that tool.
+pub fn tool_timeout(tool: &str) -> Duration {
+    let default_ms = TOOL_TIMEOUTS_MS
+        .iter()
+        .find(|(name, _)| *name == tool)
+        .map(|(_, ms)| *ms)
+        .unwrap_or(DEFAULT_TIMEOUT_MS);
+    let var = format!("AIRC_TIMEOUT_{}", tool);
+    let ms = match std::env::var(&var) {
+        Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+            warn!("Ignoring invalid {} value '{}'", var, raw);
+            default_ms
+        }),
+        Err(_) => default_ms,
+    };
+    Duration::from_millis(ms)
+}
@@ -0,0 +1,120 @@
+// Notification-area (system tray) icons, found through UI Automation on Windows
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrayIcon {
+    /// Accessible name of the icon, normally its tooltip text.
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Center of the icon in screen coordinates, where a click lands.
+    pub center_x: i32,
+    pub center_y: i32,
+    /// Whether the icon sits in the "hidden icons" overflow panel rather than on the taskbar.
+    pub overflow: bool,
+    /// False while the icon can't be clicked where it is, e.g. the overflow panel is closed.
+    pub visible: bool,
+}
+
+/// Lists the notification-area icons, taskbar first, then the overflow panel. Returns
+/// `Ok(None)` where the platform has no tray that can be enumerated.
+pub fn list_icons() -> anyhow::Result<Option<Vec<TrayIcon>>> {
+    imp::list_icons()
+}
+
+/// Picks the icon whose name matches `query`: an exact (case-insensitive) name first, then the
+/// first name containing it, preferring icons that are visible.
+pub fn find_icon<'a>(icons: &'a [TrayIcon], query: &str) -> Option<&'a TrayIcon> {
+    let query = query.to_lowercase();
+    let exact = |icon: &&TrayIcon| icon.name.to_lowercase() == query;
+    let partial = |icon: &&TrayIcon| icon.name.to_lowercase().contains(&query);
+    let by_visibility = |visible: bool| icons.iter().filter(move |icon| icon.visible == visible);
+    by_visibility(true)
+        .find(exact)
+        .or_else(|| by_visibility(false).find(exact))
+        .or_else(|| by_visibility(true).find(partial))
+        .or_else(|| by_visibility(false).find(partial))
+}
+
+// Tray icons are buttons in the accessibility tree of the taskbar's notification area and of
+// the overflow panel. Windows 10 hosts them in toolbars, Windows 11 in XAML; UIA covers both.
+#[cfg(windows)]
+mod imp {
+    use super::TrayIcon;
+    use anyhow::Context;
+    use windows::core::{w, PCWSTR, VARIANT};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED};
+    use windows::Win32::UI::Accessibility::{
+        CUIAutomation, IUIAutomation, TreeScope_Descendants, UIA_ButtonControlTypeId, UIA_ControlTypePropertyId,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{FindWindowExW, FindWindowW, IsWindowVisible};
+
+    // Overflow panel window classes on Windows 10 and Windows 11
+    const OVERFLOW_CLASSES: [PCWSTR; 2] = [w!("NotifyIconOverflowWindow"), w!("TopLevelWindowForOverflowXamlIsland")];
+
+    fn buttons(automation: &IUIAutomation, hwnd: HWND, overflow: bool, panel_open: bool) -> anyhow::Result<Vec<TrayIcon>> {
+        let mut icons = Vec::new();
+        // SAFETY: plain COM calls on interfaces that manage their own reference counts
+        unsafe {
+            let root = automation.ElementFromHandle(hwnd)?;
+            let condition =
+                automation.CreatePropertyCondition(UIA_ControlTypePropertyId, &VARIANT::from(UIA_ButtonControlTypeId.0))?;
+            let found = root.FindAll(TreeScope_Descendants, &condition)?;
+            for i in 0..found.Length()? {
+                let element = found.GetElement(i)?;
+                let name = element.CurrentName()?.to_string();
+                if name.is_empty() {
+                    continue;
+                }
+                let rect = element.CurrentBoundingRectangle()?;
+                let width = (rect.right - rect.left).max(0) as u32;
+                let height = (rect.bottom - rect.top).max(0) as u32;
+                let on_screen = !element.CurrentIsOffscreen()?.as_bool() && width > 0 && height > 0;
+                icons.push(TrayIcon {
+                    name,
+                    x: rect.left,
+                    y: rect.top,
+                    width,
+                    height,
+                    center_x: rect.left + width as i32 / 2,
+                    center_y: rect.top + height as i32 / 2,
+                    overflow,
+                    visible: panel_open && on_screen,
+                });
+            }
+        }
+        Ok(icons)
+    }
+
+    pub fn list_icons() -> anyhow::Result<Option<Vec<TrayIcon>>> {
+        // SAFETY: plain COM and window lookups; handles are only passed back to the system
+        unsafe {
+            // S_FALSE / RPC_E_CHANGED_MODE only mean COM was already initialized on this thread
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            let automation: IUIAutomation = CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER)
+                .context("Failed to create the UI Automation client")?;
+            let taskbar = FindWindowW(w!("Shell_TrayWnd"), PCWSTR::null()).context("Taskbar window not found")?;
+            // Windows 11 has no separate notification-area window; search the whole taskbar
+            let notify_area = FindWindowExW(taskbar, HWND::default(), w!("TrayNotifyWnd"), PCWSTR::null()).unwrap_or(taskbar);
+            let mut icons = buttons(&automation, notify_area, false, true)?;
+            for class in OVERFLOW_CLASSES {
+                if let Ok(panel) = FindWindowW(class, PCWSTR::null()) {
+                    icons.extend(buttons(&automation, panel, true, IsWindowVisible(panel).as_bool())?);
+                }
+            }
+            Ok(Some(icons))
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use super::TrayIcon;
+
+    pub fn list_icons() -> anyhow::Result<Option<Vec<TrayIcon>>> {
+        Ok(None)
+    }
+}
@@ -0,0 +1,182 @@
+// Master output volume of the default audio device
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct VolumeState {
+    /// 0-100.
+    pub level: u8,
+    pub muted: bool,
+}
+
+/// Reads the default output device's volume, or `Ok(None)` when no audio backend is available.
+pub fn volume() -> anyhow::Result<Option<VolumeState>> {
+    imp::volume()
+}
+
+/// Sets the volume (clamped to 0-100) and returns the new state, or `Ok(None)` when unsupported.
+pub fn set_volume(level: u8) -> anyhow::Result<Option<VolumeState>> {
+    imp::set_volume(level.min(100))?;
+    imp::volume()
+}
+
+/// Mutes or unmutes the default output device, returning the new state (`Ok(None)` when unsupported).
+pub fn set_muted(muted: bool) -> anyhow::Result<Option<VolumeState>> {
+    imp::set_muted(muted)?;
+    imp::volume()
+}
+
+// Core Audio: IAudioEndpointVolume of the default render endpoint
+#[cfg(windows)]
+mod imp {
+    use super::VolumeState;
+    use windows::Win32::Foundation::BOOL;
+    use windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolume;
+    use windows::Win32::Media::Audio::{eConsole, eRender, IMMDeviceEnumerator, MMDeviceEnumerator};
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_MULTITHREADED};
+
+    fn endpoint() -> anyhow::Result<IAudioEndpointVolume> {
+        // SAFETY: plain COM calls; the returned interfaces manage their own reference counts
+        unsafe {
+            // S_FALSE / RPC_E_CHANGED_MODE only mean COM was already initialized on this thread
+            let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+            let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+            let device = enumerator.GetDefaultAudioEndpoint(eRender, eConsole)?;
+            Ok(device.Activate(CLSCTX_ALL, None)?)
+        }
+    }
+
+    pub fn volume() -> anyhow::Result<Option<VolumeState>> {
+        let endpoint = endpoint()?;
+        // SAFETY: endpoint is a valid interface pointer
+        let (scalar, muted) = unsafe { (endpoint.GetMasterVolumeLevelScalar()?, endpoint.GetMute()?) };
+        Ok(Some(VolumeState { level: (scalar * 100.0).round() as u8, muted: muted.as_bool() }))
+    }
+
+    pub fn set_volume(level: u8) -> anyhow::Result<()> {
+        // SAFETY: a null event context GUID is allowed
+        unsafe { endpoint()?.SetMasterVolumeLevelScalar(level as f32 / 100.0, std::ptr::null())? };
+        Ok(())
+    }
+
+    pub fn set_muted(muted: bool) -> anyhow::Result<()> {
+        // SAFETY: a null event context GUID is allowed
+        unsafe { endpoint()?.SetMute(BOOL::from(muted), std::ptr::null())? };
+        Ok(())
+    }
+}
+
+// PulseAudio (or PipeWire's pulse shim) via `pactl`, falling back to ALSA's `amixer`
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::VolumeState;
+    use anyhow::{anyhow, Context};
+    use std::process::Command;
+
+    /// Runs a command, returning `Ok(None)` if it isn't installed.
+    fn run(program: &str, args: &[&str]) -> anyhow::Result<Option<String>> {
+        let output = match Command::new(program).args(args).output() {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to run '{}'", program)),
+        };
+        if !output.status.success() {
+            return Err(anyhow!("'{} {}' failed: {}", program, args.join(" "), String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+    }
+
+    // First "NN%" in the output, e.g. "Volume: front-left: 32768 /  50% / -18.06 dB" or "[50%]"
+    fn first_percent(output: &str) -> Option<u8> {
+        output
+            .split(|c: char| c.is_whitespace() || c == '[' || c == ']')
+            .find_map(|token| token.strip_suffix('%')?.parse::<u32>().ok())
+            .map(|level| level.min(100) as u8)
+    }
+
+    pub fn volume() -> anyhow::Result<Option<VolumeState>> {
+        let pulse = run("pactl", &["get-sink-volume", "@DEFAULT_SINK@"]);
+        if let Ok(Some(output)) = &pulse {
+            let level = first_percent(output).ok_or_else(|| anyhow!("Unexpected pactl output '{}'", output.trim()))?;
+            let mute = run("pactl", &["get-sink-mute", "@DEFAULT_SINK@"])?.unwrap_or_default();
+            return Ok(Some(VolumeState { level, muted: mute.trim().ends_with("yes") }));
+        }
+        // "  Front Left: Playback 65536 [100%] [on]"
+        let Some(output) = run("amixer", &["get", "Master"])? else {
+            // Neither backend answered; surface pactl's error if it had one
+            return pulse.map(|_| None);
+        };
+        let level = first_percent(&output).ok_or_else(|| anyhow!("Unexpected amixer output '{}'", output.trim()))?;
+        Ok(Some(VolumeState { level, muted: output.contains("[off]") }))
+    }
+
+    // Runs the pactl command, or the amixer one if pactl is missing or has no server to talk to
+    fn pactl_or_amixer(pactl_args: &[&str], amixer_args: &[&str]) -> anyhow::Result<()> {
+        match run("pactl", pactl_args) {
+            Ok(Some(_)) => Ok(()),
+            pulse => match run("amixer", amixer_args)? {
+                Some(_) => Ok(()),
+                None => pulse.map(|_| ()),
+            },
+        }
+    }
+
+    pub fn set_volume(level: u8) -> anyhow::Result<()> {
+        let percent = format!("{}%", level);
+        pactl_or_amixer(&["set-sink-volume", "@DEFAULT_SINK@", &percent], &["-q", "set", "Master", &percent])
+    }
+
+    pub fn set_muted(muted: bool) -> anyhow::Result<()> {
+        pactl_or_amixer(
+            &["set-sink-mute", "@DEFAULT_SINK@", if muted { "1" } else { "0" }],
+            &["-q", "set", "Master", if muted { "mute" } else { "unmute" }],
+        )
+    }
+}
+
+// Output volume through AppleScript's volume settings, which front CoreAudio
+#[cfg(target_os = "macos")]
+mod imp {
+    use super::VolumeState;
+    use anyhow::{anyhow, Context};
+    use std::process::Command;
+
+    fn osascript(script: &str) -> anyhow::Result<String> {
+        let output = Command::new("osascript").args(["-e", script]).output().context("Failed to run 'osascript'")?;
+        if !output.status.success() {
+            return Err(anyhow!("osascript failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    pub fn volume() -> anyhow::Result<Option<VolumeState>> {
+        let level = osascript("output volume of (get volume settings)")?;
+        let muted = osascript("output muted of (get volume settings)")?;
+        let level = level.parse::<u32>().map_err(|_| anyhow!("Unexpected output volume '{}'", level))?;
+        Ok(Some(VolumeState { level: level.min(100) as u8, muted: muted == "true" }))
+    }
+
+    pub fn set_volume(level: u8) -> anyhow::Result<()> {
+        osascript(&format!("set volume output volume {}", level)).map(|_| ())
+    }
+
+    pub fn set_muted(muted: bool) -> anyhow::Result<()> {
+        osascript(&format!("set volume output muted {}", muted)).map(|_| ())
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+mod imp {
+    use super::VolumeState;
+
+    pub fn volume() -> anyhow::Result<Option<VolumeState>> {
+        Ok(None)
+    }
+
+    pub fn set_volume(_level: u8) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    pub fn set_muted(_muted: bool) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
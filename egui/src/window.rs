@@ -0,0 +1,159 @@
+// Native window queries that xcap doesn't cover (dialogs, owners, modality, focus)
+use serde::Serialize;
+
+#[cfg(windows)]
+mod win32;
+#[cfg(windows)]
+use win32 as imp;
+
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+use x11 as imp;
+
+#[cfg(not(any(windows, target_os = "linux")))]
+mod unsupported;
+#[cfg(not(any(windows, target_os = "linux")))]
+use unsupported as imp;
+
+/// A top-level dialog, modal or other owned popup window.
+#[derive(Debug, Clone, Serialize)]
+pub struct DialogInfo {
+    /// Native window handle (HWND on Windows, X11 window id on Linux).
+    pub id: u64,
+    pub title: String,
+    pub class: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// The window this dialog belongs to, when the platform reports one.
+    pub owner_id: Option<u64>,
+    pub owner_title: Option<String>,
+    /// Whether the dialog blocks input to its owner.
+    pub modal: bool,
+}
+
+/// A top-level window and the process that owns it: the focused window, or the one at a point.
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveWindow {
+    /// Native window handle (HWND on Windows, X11 window id on Linux).
+    pub id: u64,
+    pub title: String,
+    pub class: Option<String>,
+    pub pid: Option<u32>,
+    pub process_name: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Outer bounds of a window in absolute screen coordinates.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct WindowBounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Show state of a top-level window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowState {
+    Normal,
+    Minimized,
+    Maximized,
+    /// The window no longer exists.
+    Closed,
+}
+
+/// A virtual desktop (workspace).
+#[derive(Debug, Clone, Serialize)]
+pub struct VirtualDesktop {
+    /// Position in the desktop switcher, from 0.
+    pub index: u32,
+    /// User-assigned name, when the platform stores one.
+    pub name: Option<String>,
+    pub current: bool,
+}
+
+/// A show-state change requested by the window tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowCommand {
+    Minimize,
+    Maximize,
+    Restore,
+    /// Polite close request (WM_CLOSE / `_NET_CLOSE_WINDOW`); the application may ask to save
+    /// first or ignore it.
+    Close,
+}
+
+/// Lists the open dialogs ordered topmost first, i.e. in the order they need to be dismissed.
+///
+/// Returns `Ok(None)` when dialog detection isn't available on this platform or session
+/// (e.g. Wayland, where window stacking isn't exposed to clients).
+pub fn list_dialogs() -> anyhow::Result<Option<Vec<DialogInfo>>> {
+    imp::list_dialogs()
+}
+
+/// Returns the focused window, or `Ok(None)` when focus can't be queried on this platform or
+/// session. Errors if the platform reports no focused window (e.g. mid focus change).
+pub fn active_window() -> anyhow::Result<Option<ActiveWindow>> {
+    imp::active_window()
+}
+
+/// Returns the topmost visible top-level window containing the screen point, or `Ok(None)` when
+/// there is none or window stacking can't be queried on this platform or session.
+pub fn window_at(x: i32, y: i32) -> anyhow::Result<Option<ActiveWindow>> {
+    imp::window_at(x, y)
+}
+
+/// Restores (if minimized), raises and focuses the window with the given native id.
+///
+/// Returns `Ok(false)` when window activation isn't available on this platform or session.
+pub fn activate_window(id: u64) -> anyhow::Result<bool> {
+    imp::activate_window(id)
+}
+
+/// Current bounds of the window with the given native id, or `Ok(None)` when window geometry
+/// isn't available on this platform or session.
+pub fn window_bounds(id: u64) -> anyhow::Result<Option<WindowBounds>> {
+    imp::window_bounds(id)
+}
+
+/// Moves and/or resizes a window; `None` keeps that coordinate or dimension as it is. A
+/// maximized or minimized window is restored first, since its geometry can't be set directly.
+///
+/// Returns `Ok(false)` when window placement isn't available on this platform or session. The
+/// window manager may adjust the request (minimum sizes, snapping), so read the bounds back.
+pub fn set_window_bounds(id: u64, x: Option<i32>, y: Option<i32>, width: Option<u32>, height: Option<u32>) -> anyhow::Result<bool> {
+    imp::set_window_bounds(id, x, y, width, height)
+}
+
+/// Current show state of the window with the given native id, or `Ok(None)` when it can't be
+/// queried on this platform or session.
+pub fn window_state(id: u64) -> anyhow::Result<Option<WindowState>> {
+    imp::window_state(id)
+}
+
+/// Minimizes, maximizes, restores or asks the window to close.
+///
+/// Returns `Ok(false)` when the command isn't available on this platform or session.
+pub fn apply_window_command(id: u64, command: WindowCommand) -> anyhow::Result<bool> {
+    imp::apply_window_command(id, command)
+}
+
+/// Lists the virtual desktops in switcher order, or `Ok(None)` when they can't be queried on
+/// this platform or session (e.g. a window manager without EWMH desktops).
+pub fn virtual_desktops() -> anyhow::Result<Option<Vec<VirtualDesktop>>> {
+    imp::virtual_desktops()
+}
+
+/// Switches to the virtual desktop at `index` (see [`virtual_desktops`]).
+///
+/// Returns `Ok(false)` when switching isn't available on this platform or session.
+pub fn switch_virtual_desktop(index: u32) -> anyhow::Result<bool> {
+    imp::switch_virtual_desktop(index)
+}
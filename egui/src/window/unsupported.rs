@@ -0,0 +1,42 @@
+// Fallback for platforms without a native window backend
+use super::{ActiveWindow, DialogInfo, VirtualDesktop, WindowBounds, WindowCommand, WindowState};
+
+pub fn list_dialogs() -> anyhow::Result<Option<Vec<DialogInfo>>> {
+    Ok(None)
+}
+
+pub fn active_window() -> anyhow::Result<Option<ActiveWindow>> {
+    Ok(None)
+}
+
+pub fn window_at(_x: i32, _y: i32) -> anyhow::Result<Option<ActiveWindow>> {
+    Ok(None)
+}
+
+pub fn activate_window(_id: u64) -> anyhow::Result<bool> {
+    Ok(false)
+}
+
+pub fn window_bounds(_id: u64) -> anyhow::Result<Option<WindowBounds>> {
+    Ok(None)
+}
+
+pub fn set_window_bounds(_id: u64, _x: Option<i32>, _y: Option<i32>, _width: Option<u32>, _height: Option<u32>) -> anyhow::Result<bool> {
+    Ok(false)
+}
+
+pub fn window_state(_id: u64) -> anyhow::Result<Option<WindowState>> {
+    Ok(None)
+}
+
+pub fn apply_window_command(_id: u64, _command: WindowCommand) -> anyhow::Result<bool> {
+    Ok(false)
+}
+
+pub fn virtual_desktops() -> anyhow::Result<Option<Vec<VirtualDesktop>>> {
+    Ok(None)
+}
+
+pub fn switch_virtual_desktop(_index: u32) -> anyhow::Result<bool> {
+    Ok(false)
+}
@@ -0,0 +1,417 @@
+// Win32 window backend
+use super::{ActiveWindow, DialogInfo, VirtualDesktop, WindowBounds, WindowCommand, WindowState};
+use anyhow::anyhow;
+use windows_sys::Win32::Foundation::{CloseHandle, BOOL, ERROR_SUCCESS, HWND, LPARAM, POINT, RECT, TRUE};
+use windows_sys::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_BINARY, RRF_RT_REG_SZ};
+use windows_sys::Win32::System::RemoteDesktop::ProcessIdToSessionId;
+use windows_sys::Win32::System::Threading::{
+    AttachThreadInput, GetCurrentProcessId, GetCurrentThreadId, OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+    PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+    IsWindowEnabled, SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP,
+    VIRTUAL_KEY, VK_LCONTROL, VK_LEFT, VK_LWIN, VK_RIGHT,
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    BringWindowToTop, EnumWindows, GetAncestor, GetClassNameW, GetForegroundWindow, GetWindow, GetWindowLongW,
+    GetWindowRect, GetWindowTextW, GetWindowThreadProcessId, IsIconic, IsWindow, IsWindowVisible, IsZoomed,
+    PostMessageW, SetForegroundWindow, SetWindowPos, ShowWindow, WindowFromPoint, GA_ROOT, GWL_EXSTYLE, GW_OWNER,
+    SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER, SW_MAXIMIZE, SW_MINIMIZE, SW_RESTORE, WM_CLOSE,
+    WS_EX_DLGMODALFRAME,
+};
+
+// Window class used by the standard Win32 dialog manager (MessageBox, common dialogs, ...)
+const DIALOG_CLASS: &str = "#32770";
+
+unsafe extern "system" fn collect_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    // SAFETY: lparam is the &mut Vec<HWND> passed by top_level_windows for the duration of EnumWindows
+    let windows = unsafe { &mut *(lparam as *mut Vec<HWND>) };
+    windows.push(hwnd);
+    TRUE
+}
+
+/// All top-level windows in Z order, topmost first.
+pub(super) fn top_level_windows() -> Vec<HWND> {
+    let mut windows: Vec<HWND> = Vec::new();
+    // SAFETY: the callback only touches the Vec, which outlives the call
+    unsafe { EnumWindows(Some(collect_window), &mut windows as *mut Vec<HWND> as LPARAM) };
+    windows
+}
+
+pub(super) fn window_title(hwnd: HWND) -> String {
+    let mut buf = [0u16; 512];
+    // SAFETY: buf is valid for buf.len() UTF-16 units
+    let len = unsafe { GetWindowTextW(hwnd, buf.as_mut_ptr(), buf.len() as i32) };
+    String::from_utf16_lossy(&buf[..len.max(0) as usize])
+}
+
+pub(super) fn window_class(hwnd: HWND) -> String {
+    let mut buf = [0u16; 256];
+    // SAFETY: buf is valid for buf.len() UTF-16 units
+    let len = unsafe { GetClassNameW(hwnd, buf.as_mut_ptr(), buf.len() as i32) };
+    String::from_utf16_lossy(&buf[..len.max(0) as usize])
+}
+
+/// Window bounds as (x, y, width, height).
+pub(super) fn window_rect(hwnd: HWND) -> Option<(i32, i32, u32, u32)> {
+    let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+    // SAFETY: rect is a valid out pointer
+    if unsafe { GetWindowRect(hwnd, &mut rect) } == 0 {
+        return None;
+    }
+    Some((rect.left, rect.top, (rect.right - rect.left).max(0) as u32, (rect.bottom - rect.top).max(0) as u32))
+}
+
+/// Id of the process owning the window, if the window still exists.
+pub(super) fn window_pid(hwnd: HWND) -> Option<u32> {
+    let mut pid = 0u32;
+    // SAFETY: pid is a valid out pointer
+    unsafe { GetWindowThreadProcessId(hwnd, &mut pid) };
+    (pid != 0).then_some(pid)
+}
+
+/// Executable file name (e.g. `notepad.exe`) of a process. Fails for protected processes.
+pub(super) fn process_name(pid: u32) -> Option<String> {
+    // SAFETY: the handle is checked for null and closed before returning
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            return None;
+        }
+        let mut buf = [0u16; 1024];
+        let mut len = buf.len() as u32;
+        let ok = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, buf.as_mut_ptr(), &mut len) != 0;
+        CloseHandle(process);
+        if !ok {
+            return None;
+        }
+        let path = String::from_utf16_lossy(&buf[..len as usize]);
+        path.rsplit('\\').next().map(str::to_string)
+    }
+}
+
+fn describe(hwnd: HWND) -> anyhow::Result<ActiveWindow> {
+    let (x, y, width, height) = window_rect(hwnd).ok_or_else(|| anyhow!("Failed to get the window's bounds"))?;
+    let pid = window_pid(hwnd);
+
+    Ok(ActiveWindow {
+        id: hwnd as usize as u64,
+        title: window_title(hwnd),
+        class: Some(window_class(hwnd)),
+        pid,
+        process_name: pid.and_then(process_name),
+        x,
+        y,
+        width,
+        height,
+    })
+}
+
+pub fn active_window() -> anyhow::Result<Option<ActiveWindow>> {
+    // SAFETY: no arguments; returns null when no window is focused
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.is_null() {
+        return Err(anyhow!("No window currently has focus"));
+    }
+    describe(hwnd).map(Some)
+}
+
+pub fn window_at(x: i32, y: i32) -> anyhow::Result<Option<ActiveWindow>> {
+    // SAFETY: plain value arguments; returns null when no window is at the point
+    let child = unsafe { WindowFromPoint(POINT { x, y }) };
+    if child.is_null() {
+        return Ok(None);
+    }
+    // WindowFromPoint returns the deepest child (a button, an edit box); report its top-level window
+    // SAFETY: child is a window handle that was just returned; GetAncestor tolerates stale handles
+    let hwnd = unsafe { GetAncestor(child, GA_ROOT) };
+    describe(if hwnd.is_null() { child } else { hwnd }).map(Some)
+}
+
+pub fn activate_window(id: u64) -> anyhow::Result<bool> {
+    let hwnd = id as usize as HWND;
+    // SAFETY: IsWindow accepts any value and just reports whether it is a live window handle
+    if unsafe { IsWindow(hwnd) } == 0 {
+        return Err(anyhow!("Window 0x{:x} no longer exists", id));
+    }
+
+    // SAFETY: plain calls on a validated window handle; the thread input attachment is undone
+    // before returning
+    let focused = unsafe {
+        if IsIconic(hwnd) != 0 {
+            ShowWindow(hwnd, SW_RESTORE);
+        }
+        // Windows only lets the foreground thread change the foreground window, so briefly share
+        // its input state; otherwise SetForegroundWindow just flashes the taskbar button
+        let foreground = GetForegroundWindow();
+        let foreground_thread =
+            if foreground.is_null() { 0 } else { GetWindowThreadProcessId(foreground, std::ptr::null_mut()) };
+        let current_thread = GetCurrentThreadId();
+        let attached = foreground_thread != 0
+            && foreground_thread != current_thread
+            && AttachThreadInput(current_thread, foreground_thread, TRUE) != 0;
+        BringWindowToTop(hwnd);
+        let focused = SetForegroundWindow(hwnd) != 0;
+        if attached {
+            AttachThreadInput(current_thread, foreground_thread, 0);
+        }
+        focused
+    };
+    if !focused {
+        return Err(anyhow!("Windows refused to bring window 0x{:x} to the foreground", id));
+    }
+    Ok(true)
+}
+
+pub fn window_bounds(id: u64) -> anyhow::Result<Option<WindowBounds>> {
+    let hwnd = id as usize as HWND;
+    let (x, y, width, height) = window_rect(hwnd).ok_or_else(|| anyhow!("Window 0x{:x} no longer exists", id))?;
+    Ok(Some(WindowBounds { x, y, width, height }))
+}
+
+pub fn set_window_bounds(id: u64, x: Option<i32>, y: Option<i32>, width: Option<u32>, height: Option<u32>) -> anyhow::Result<bool> {
+    let hwnd = id as usize as HWND;
+    // SAFETY: IsWindow accepts any value and just reports whether it is a live window handle
+    if unsafe { IsWindow(hwnd) } == 0 {
+        return Err(anyhow!("Window 0x{:x} no longer exists", id));
+    }
+
+    // SAFETY: plain calls on a validated window handle
+    unsafe {
+        // SetWindowPos on a maximized/minimized window only changes its restore position
+        if IsZoomed(hwnd) != 0 || IsIconic(hwnd) != 0 {
+            ShowWindow(hwnd, SW_RESTORE);
+        }
+    }
+    let (cur_x, cur_y, cur_width, cur_height) =
+        window_rect(hwnd).ok_or_else(|| anyhow!("Failed to get the bounds of window 0x{:x}", id))?;
+    let mut flags = SWP_NOZORDER | SWP_NOACTIVATE;
+    if x.is_none() && y.is_none() {
+        flags |= SWP_NOMOVE;
+    }
+    if width.is_none() && height.is_none() {
+        flags |= SWP_NOSIZE;
+    }
+    // SAFETY: validated window handle; a null insert-after handle is ignored with SWP_NOZORDER
+    let ok = unsafe {
+        SetWindowPos(
+            hwnd,
+            std::ptr::null_mut(),
+            x.unwrap_or(cur_x),
+            y.unwrap_or(cur_y),
+            width.unwrap_or(cur_width) as i32,
+            height.unwrap_or(cur_height) as i32,
+            flags,
+        )
+    } != 0;
+    if !ok {
+        return Err(anyhow!("SetWindowPos failed for window 0x{:x}: {}", id, std::io::Error::last_os_error()));
+    }
+    Ok(true)
+}
+
+pub fn window_state(id: u64) -> anyhow::Result<Option<WindowState>> {
+    let hwnd = id as usize as HWND;
+    // SAFETY: all three accept any value and just report on the handle
+    let state = unsafe {
+        if IsWindow(hwnd) == 0 {
+            WindowState::Closed
+        } else if IsIconic(hwnd) != 0 {
+            WindowState::Minimized
+        } else if IsZoomed(hwnd) != 0 {
+            WindowState::Maximized
+        } else {
+            WindowState::Normal
+        }
+    };
+    Ok(Some(state))
+}
+
+pub fn apply_window_command(id: u64, command: WindowCommand) -> anyhow::Result<bool> {
+    let hwnd = id as usize as HWND;
+    // SAFETY: IsWindow accepts any value and just reports whether it is a live window handle
+    if unsafe { IsWindow(hwnd) } == 0 {
+        return Err(anyhow!("Window 0x{:x} no longer exists", id));
+    }
+    // SAFETY: plain calls on a validated window handle. ShowWindow's return value is the previous
+    // visibility, not success, so it isn't checked
+    unsafe {
+        match command {
+            WindowCommand::Minimize => {
+                ShowWindow(hwnd, SW_MINIMIZE);
+            }
+            WindowCommand::Maximize => {
+                ShowWindow(hwnd, SW_MAXIMIZE);
+            }
+            WindowCommand::Restore => {
+                ShowWindow(hwnd, SW_RESTORE);
+            }
+            // Posted rather than sent so a "save changes?" prompt can't block this thread
+            WindowCommand::Close => {
+                if PostMessageW(hwnd, WM_CLOSE, 0, 0) == 0 {
+                    return Err(anyhow!("Failed to post WM_CLOSE to window 0x{:x}: {}", id, std::io::Error::last_os_error()));
+                }
+            }
+        }
+    }
+    Ok(true)
+}
+
+pub fn list_dialogs() -> anyhow::Result<Option<Vec<DialogInfo>>> {
+    let mut dialogs = Vec::new();
+    for hwnd in top_level_windows() {
+        // SAFETY: plain queries on a window handle; stale handles just return 0/null
+        let (visible, minimized, owner, ex_style) = unsafe {
+            (
+                IsWindowVisible(hwnd) != 0,
+                IsIconic(hwnd) != 0,
+                GetWindow(hwnd, GW_OWNER),
+                GetWindowLongW(hwnd, GWL_EXSTYLE) as u32,
+            )
+        };
+        if !visible || minimized {
+            continue;
+        }
+
+        let class = window_class(hwnd);
+        let is_dialog = class == DIALOG_CLASS || !owner.is_null() || ex_style & WS_EX_DLGMODALFRAME != 0;
+        if !is_dialog {
+            continue;
+        }
+        let Some((x, y, width, height)) = window_rect(hwnd) else { continue };
+        if width == 0 || height == 0 {
+            continue;
+        }
+
+        // A modal dialog disables its owner until it is closed
+        // SAFETY: owner is checked for null first
+        let modal = !owner.is_null() && unsafe { IsWindowEnabled(owner) } == 0;
+        dialogs.push(DialogInfo {
+            id: hwnd as usize as u64,
+            title: window_title(hwnd),
+            class: Some(class),
+            x,
+            y,
+            width,
+            height,
+            owner_id: (!owner.is_null()).then(|| owner as usize as u64),
+            owner_title: (!owner.is_null()).then(|| window_title(owner)),
+            modal,
+        });
+    }
+    Ok(Some(dialogs))
+}
+
+// Explorer keeps the desktop list here; the public IVirtualDesktopManager can't enumerate or
+// switch desktops, and the interfaces that can are undocumented and change between builds
+const VIRTUAL_DESKTOPS_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Explorer\VirtualDesktops";
+// Pause between Ctrl+Win+arrow presses so the switch animation doesn't swallow the next one
+const DESKTOP_SWITCH_STEP: std::time::Duration = std::time::Duration::from_millis(150);
+
+fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+// A binary or string value under HKEY_CURRENT_USER, or None if the key or value doesn't exist
+fn read_user_value(subkey: &str, value: &str) -> Option<Vec<u8>> {
+    let (subkey, value) = (wide(subkey), wide(value));
+    let flags = RRF_RT_REG_BINARY | RRF_RT_REG_SZ;
+    let mut size = 0u32;
+    // SAFETY: the first call only reports the size; the second writes at most `size` bytes to buf
+    unsafe {
+        let queried = RegGetValueW(HKEY_CURRENT_USER, subkey.as_ptr(), value.as_ptr(), flags, std::ptr::null_mut(), std::ptr::null_mut(), &mut size);
+        if queried != ERROR_SUCCESS {
+            return None;
+        }
+        let mut buf = vec![0u8; size as usize];
+        let read = RegGetValueW(HKEY_CURRENT_USER, subkey.as_ptr(), value.as_ptr(), flags, std::ptr::null_mut(), buf.as_mut_ptr().cast(), &mut size);
+        if read != ERROR_SUCCESS {
+            return None;
+        }
+        buf.truncate(size as usize);
+        Some(buf)
+    }
+}
+
+// Registry form of a GUID, e.g. {A1B2C3D4-...}, from its 16-byte binary layout
+fn format_guid(bytes: &[u8]) -> String {
+    let data1 = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let data2 = u16::from_le_bytes([bytes[4], bytes[5]]);
+    let data3 = u16::from_le_bytes([bytes[6], bytes[7]]);
+    let tail: String = bytes[8..16].iter().map(|b| format!("{:02X}", b)).collect();
+    format!("{{{:08X}-{:04X}-{:04X}-{}-{}}}", data1, data2, data3, &tail[..4], &tail[4..])
+}
+
+// Windows 11 stores the current desktop next to the list, Windows 10 per logon session
+fn current_desktop_id() -> Option<Vec<u8>> {
+    read_user_value(VIRTUAL_DESKTOPS_KEY, "CurrentVirtualDesktop").or_else(|| {
+        let mut session = 0u32;
+        // SAFETY: session is a valid out pointer
+        if unsafe { ProcessIdToSessionId(GetCurrentProcessId(), &mut session) } == 0 {
+            return None;
+        }
+        let key = format!(r"Software\Microsoft\Windows\CurrentVersion\Explorer\SessionInfo\{}\VirtualDesktops", session);
+        read_user_value(&key, "CurrentVirtualDesktop")
+    })
+}
+
+pub fn virtual_desktops() -> anyhow::Result<Option<Vec<VirtualDesktop>>> {
+    // The list is only written once a second desktop has been created
+    let Some(ids) = read_user_value(VIRTUAL_DESKTOPS_KEY, "VirtualDesktopIDs").filter(|ids| ids.len() >= 16) else {
+        return Ok(Some(vec![VirtualDesktop { index: 0, name: None, current: true }]));
+    };
+    let current = current_desktop_id();
+    Ok(Some(
+        ids.chunks_exact(16)
+            .enumerate()
+            .map(|(index, id)| {
+                let name = read_user_value(&format!(r"{}\Desktops\{}", VIRTUAL_DESKTOPS_KEY, format_guid(id)), "Name")
+                    .map(|raw| {
+                        let units: Vec<u16> = raw.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+                        String::from_utf16_lossy(&units).trim_end_matches('\0').to_string()
+                    })
+                    .filter(|name| !name.is_empty());
+                VirtualDesktop { index: index as u32, name, current: current.as_deref() == Some(id) }
+            })
+            .collect(),
+    ))
+}
+
+fn key_input(key: VIRTUAL_KEY, up: bool) -> INPUT {
+    let mut flags = if up { KEYEVENTF_KEYUP } else { 0 };
+    if matches!(key, VK_LEFT | VK_RIGHT) {
+        flags |= KEYEVENTF_EXTENDEDKEY;
+    }
+    INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 { ki: KEYBDINPUT { wVk: key, wScan: 0, dwFlags: flags, time: 0, dwExtraInfo: 0 } },
+    }
+}
+
+pub fn switch_virtual_desktop(index: u32) -> anyhow::Result<bool> {
+    let desktops = virtual_desktops()?.unwrap_or_default();
+    let current = desktops
+        .iter()
+        .position(|desktop| desktop.current)
+        .ok_or_else(|| anyhow!("Could not determine the current virtual desktop"))? as i64;
+    // Windows has no "go to desktop N"; step there with the Ctrl+Win+Left/Right shortcut
+    let steps = index as i64 - current;
+    let arrow = if steps < 0 { VK_LEFT } else { VK_RIGHT };
+    for _ in 0..steps.unsigned_abs() {
+        let inputs = [
+            key_input(VK_LCONTROL, false),
+            key_input(VK_LWIN, false),
+            key_input(arrow, false),
+            key_input(arrow, true),
+            key_input(VK_LWIN, true),
+            key_input(VK_LCONTROL, true),
+        ];
+        // SAFETY: fully initialized INPUTs of the size passed
+        let sent = unsafe { SendInput(inputs.len() as u32, inputs.as_ptr(), std::mem::size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            return Err(anyhow!("SendInput failed: {}", std::io::Error::last_os_error()));
+        }
+        std::thread::sleep(DESKTOP_SWITCH_STEP);
+    }
+    Ok(true)
+}
@@ -0,0 +1,341 @@
+// X11 window backend built on the standard `xprop` / `xwininfo` utilities
+use super::{ActiveWindow, DialogInfo, VirtualDesktop, WindowBounds, WindowCommand, WindowState};
+use anyhow::{anyhow, Context};
+use std::process::Command;
+
+/// Properties of a top-level X11 client window relevant to the window tools.
+#[derive(Debug, Default)]
+pub(super) struct X11Window {
+    pub title: String,
+    pub class: Option<String>,
+    pub window_types: Vec<String>,
+    pub states: Vec<String>,
+    pub transient_for: Option<u64>,
+    pub pid: Option<u32>,
+}
+
+/// X11 tools only work inside an X session (or XWayland with DISPLAY set).
+pub(super) fn is_available() -> bool {
+    std::env::var_os("DISPLAY").is_some()
+}
+
+pub(super) fn run(program: &str, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run '{}'. Is it installed?", program))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "'{} {}' failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub(super) fn parse_window_id(raw: &str) -> Option<u64> {
+    u64::from_str_radix(raw.trim().trim_start_matches("0x"), 16).ok()
+}
+
+// Value part of an xprop line such as `NAME(TYPE) = value` or `NAME(WINDOW): window id # 0x...`
+fn property<'a>(output: &'a str, name: &str) -> Option<&'a str> {
+    output
+        .lines()
+        .find(|line| line.starts_with(name) && line[name.len()..].starts_with('('))
+        .and_then(|line| line.split_once(" = ").or_else(|| line.split_once(" # ")))
+        .map(|(_, value)| value.trim())
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches('"').to_string()
+}
+
+/// Client windows in stacking order, bottommost first.
+pub(super) fn client_windows_stacking() -> anyhow::Result<Vec<u64>> {
+    let output = run("xprop", &["-root", "_NET_CLIENT_LIST_STACKING"])?;
+    let ids = property(&output, "_NET_CLIENT_LIST_STACKING")
+        .ok_or_else(|| anyhow!("Window manager does not expose _NET_CLIENT_LIST_STACKING"))?;
+    Ok(ids.split(',').filter_map(parse_window_id).collect())
+}
+
+pub(super) fn query_window(id: u64) -> anyhow::Result<X11Window> {
+    let id_arg = format!("0x{:x}", id);
+    let output = run(
+        "xprop",
+        &["-id", &id_arg, "_NET_WM_NAME", "WM_NAME", "WM_CLASS", "_NET_WM_WINDOW_TYPE", "_NET_WM_STATE", "WM_TRANSIENT_FOR", "_NET_WM_PID"],
+    )?;
+    let list = |name: &str| -> Vec<String> {
+        property(&output, name)
+            .map(|v| v.split(',').map(|s| s.trim().to_string()).collect())
+            .unwrap_or_default()
+    };
+
+    Ok(X11Window {
+        title: property(&output, "_NET_WM_NAME")
+            .or_else(|| property(&output, "WM_NAME"))
+            .map(unquote)
+            .unwrap_or_default(),
+        // WM_CLASS is "instance", "Class"; the class name is the more stable of the two
+        class: property(&output, "WM_CLASS").and_then(|v| v.split(',').next_back()).map(unquote),
+        window_types: list("_NET_WM_WINDOW_TYPE"),
+        states: list("_NET_WM_STATE"),
+        transient_for: property(&output, "WM_TRANSIENT_FOR").and_then(parse_window_id).filter(|id| *id != 0),
+        pid: property(&output, "_NET_WM_PID").and_then(|v| v.parse().ok()),
+    })
+}
+
+/// Absolute window bounds as (x, y, width, height).
+pub(super) fn geometry(id: u64) -> anyhow::Result<(i32, i32, u32, u32)> {
+    let output = run("xwininfo", &["-id", &format!("0x{:x}", id)])?;
+    let field = |label: &str| -> anyhow::Result<i64> {
+        output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(label))
+            .and_then(|v| v.trim().parse().ok())
+            .ok_or_else(|| anyhow!("xwininfo output is missing '{}'", label))
+    };
+    Ok((
+        field("Absolute upper-left X:")? as i32,
+        field("Absolute upper-left Y:")? as i32,
+        field("Width:")?.max(0) as u32,
+        field("Height:")?.max(0) as u32,
+    ))
+}
+
+pub fn list_dialogs() -> anyhow::Result<Option<Vec<DialogInfo>>> {
+    if !is_available() {
+        return Ok(None);
+    }
+
+    let mut dialogs = Vec::new();
+    // Stacking order is bottom-to-top; report topmost first
+    for id in client_windows_stacking()?.into_iter().rev() {
+        let Ok(window) = query_window(id) else { continue };
+        if window.states.iter().any(|s| s == "_NET_WM_STATE_HIDDEN") {
+            continue;
+        }
+        let modal = window.states.iter().any(|s| s == "_NET_WM_STATE_MODAL");
+        let is_dialog = modal
+            || window.transient_for.is_some()
+            || window.window_types.iter().any(|t| t == "_NET_WM_WINDOW_TYPE_DIALOG");
+        if !is_dialog {
+            continue;
+        }
+        let Ok((x, y, width, height)) = geometry(id) else { continue };
+
+        dialogs.push(DialogInfo {
+            id,
+            title: window.title,
+            class: window.class,
+            x,
+            y,
+            width,
+            height,
+            owner_id: window.transient_for,
+            owner_title: window.transient_for.and_then(|owner| query_window(owner).ok()).map(|w| w.title),
+            modal,
+        });
+    }
+    Ok(Some(dialogs))
+}
+
+fn describe(id: u64, window: X11Window, (x, y, width, height): (i32, i32, u32, u32)) -> ActiveWindow {
+    // /proc/<pid>/comm holds the executable name; only meaningful for local clients
+    let process_name = window
+        .pid
+        .and_then(|pid| std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok())
+        .map(|name| name.trim().to_string());
+
+    ActiveWindow {
+        id,
+        title: window.title,
+        class: window.class,
+        pid: window.pid,
+        process_name,
+        x,
+        y,
+        width,
+        height,
+    }
+}
+
+pub fn active_window() -> anyhow::Result<Option<ActiveWindow>> {
+    if !is_available() {
+        return Ok(None);
+    }
+
+    let output = run("xprop", &["-root", "_NET_ACTIVE_WINDOW"])?;
+    let id = property(&output, "_NET_ACTIVE_WINDOW")
+        .and_then(parse_window_id)
+        .filter(|id| *id != 0)
+        .ok_or_else(|| anyhow!("No window currently has focus"))?;
+    let window = query_window(id)?;
+    let bounds = geometry(id)?;
+    Ok(Some(describe(id, window, bounds)))
+}
+
+pub fn window_at(x: i32, y: i32) -> anyhow::Result<Option<ActiveWindow>> {
+    if !is_available() {
+        return Ok(None);
+    }
+
+    // Stacking order is bottom-to-top; the first hit from the top is the visible window
+    for id in client_windows_stacking()?.into_iter().rev() {
+        let Ok(bounds) = geometry(id) else { continue };
+        let (left, top, width, height) = bounds;
+        let inside = x >= left && y >= top && (x - left) < width as i32 && (y - top) < height as i32;
+        if !inside {
+            continue;
+        }
+        let Ok(window) = query_window(id) else { continue };
+        if window.states.iter().any(|s| s == "_NET_WM_STATE_HIDDEN") {
+            continue;
+        }
+        return Ok(Some(describe(id, window, bounds)));
+    }
+    Ok(None)
+}
+
+pub fn activate_window(id: u64) -> anyhow::Result<bool> {
+    if !is_available() {
+        return Ok(false);
+    }
+    let id_arg = format!("0x{:x}", id);
+    // Both send the EWMH _NET_ACTIVE_WINDOW request, which also de-iconifies and switches desktop
+    if let Err(wmctrl_err) = run("wmctrl", &["-i", "-a", &id_arg]) {
+        run("xdotool", &["windowactivate", "--sync", &id.to_string()])
+            .with_context(|| format!("wmctrl failed too: {:#}", wmctrl_err))?;
+    }
+    Ok(true)
+}
+
+pub fn window_bounds(id: u64) -> anyhow::Result<Option<WindowBounds>> {
+    if !is_available() {
+        return Ok(None);
+    }
+    let (x, y, width, height) = geometry(id)?;
+    Ok(Some(WindowBounds { x, y, width, height }))
+}
+
+pub fn set_window_bounds(id: u64, x: Option<i32>, y: Option<i32>, width: Option<u32>, height: Option<u32>) -> anyhow::Result<bool> {
+    if !is_available() {
+        return Ok(false);
+    }
+    let id_arg = format!("0x{:x}", id);
+    // wmctrl takes gravity,x,y,w,h with -1 meaning "leave unchanged"; window managers ignore
+    // geometry requests for maximized windows, so drop that state first
+    let arg = |value: Option<i64>| value.map_or("-1".to_string(), |v| v.to_string());
+    let geometry_arg = format!(
+        "0,{},{},{},{}",
+        arg(x.map(i64::from)),
+        arg(y.map(i64::from)),
+        arg(width.map(i64::from)),
+        arg(height.map(i64::from))
+    );
+    let wmctrl = run("wmctrl", &["-i", "-r", &id_arg, "-b", "remove,maximized_vert,maximized_horz"])
+        .and_then(|_| run("wmctrl", &["-i", "-r", &id_arg, "-e", &geometry_arg]));
+    if let Err(wmctrl_err) = wmctrl {
+        // xdotool needs both values of a pair, so fill in the missing one from the current geometry
+        let (cur_x, cur_y, cur_width, cur_height) = geometry(id)?;
+        let window = id.to_string();
+        if x.is_some() || y.is_some() {
+            let (x, y) = (x.unwrap_or(cur_x).to_string(), y.unwrap_or(cur_y).to_string());
+            run("xdotool", &["windowmove", "--sync", &window, &x, &y])
+                .with_context(|| format!("wmctrl failed too: {:#}", wmctrl_err))?;
+        }
+        if width.is_some() || height.is_some() {
+            let (width, height) = (width.unwrap_or(cur_width).to_string(), height.unwrap_or(cur_height).to_string());
+            run("xdotool", &["windowsize", "--sync", &window, &width, &height])
+                .with_context(|| format!("wmctrl failed too: {:#}", wmctrl_err))?;
+        }
+    }
+    Ok(true)
+}
+
+pub fn window_state(id: u64) -> anyhow::Result<Option<WindowState>> {
+    if !is_available() {
+        return Ok(None);
+    }
+    // A closed window drops out of the client list (querying it directly would just fail)
+    if !client_windows_stacking()?.contains(&id) {
+        return Ok(Some(WindowState::Closed));
+    }
+    let window = query_window(id)?;
+    let has_state = |name: &str| window.states.iter().any(|s| s == name);
+    let state = if has_state("_NET_WM_STATE_HIDDEN") {
+        WindowState::Minimized
+    } else if has_state("_NET_WM_STATE_MAXIMIZED_VERT") && has_state("_NET_WM_STATE_MAXIMIZED_HORZ") {
+        WindowState::Maximized
+    } else {
+        WindowState::Normal
+    };
+    Ok(Some(state))
+}
+
+pub fn apply_window_command(id: u64, command: WindowCommand) -> anyhow::Result<bool> {
+    if !is_available() {
+        return Ok(false);
+    }
+    let id_arg = format!("0x{:x}", id);
+    match command {
+        // EWMH has no minimize request; xdotool sends the ICCCM WM_CHANGE_STATE message instead
+        WindowCommand::Minimize => {
+            if let Err(xdotool_err) = run("xdotool", &["windowminimize", "--sync", &id.to_string()]) {
+                run("wmctrl", &["-i", "-r", &id_arg, "-b", "add,hidden"])
+                    .with_context(|| format!("xdotool failed too: {:#}", xdotool_err))?;
+            }
+        }
+        WindowCommand::Maximize => {
+            run("wmctrl", &["-i", "-r", &id_arg, "-b", "add,maximized_vert,maximized_horz"])?;
+        }
+        WindowCommand::Restore => {
+            run("wmctrl", &["-i", "-r", &id_arg, "-b", "remove,maximized_vert,maximized_horz"])?;
+            // Activating is the reliable way to de-iconify under EWMH window managers
+            activate_window(id)?;
+        }
+        // _NET_CLOSE_WINDOW lets the window manager send WM_DELETE_WINDOW, like the close button
+        WindowCommand::Close => {
+            run("wmctrl", &["-i", "-c", &id_arg])?;
+        }
+    }
+    Ok(true)
+}
+
+pub fn virtual_desktops() -> anyhow::Result<Option<Vec<VirtualDesktop>>> {
+    if !is_available() {
+        return Ok(None);
+    }
+    let output = run("xprop", &["-root", "_NET_NUMBER_OF_DESKTOPS", "_NET_CURRENT_DESKTOP", "_NET_DESKTOP_NAMES"])?;
+    // Window managers without EWMH desktop support don't set these at all
+    let Some(count) = property(&output, "_NET_NUMBER_OF_DESKTOPS").and_then(|v| v.parse::<u32>().ok()) else {
+        return Ok(None);
+    };
+    let current = property(&output, "_NET_CURRENT_DESKTOP").and_then(|v| v.parse::<u32>().ok());
+    // xprop prints the names as "a", "b"; a name containing `", "` would be split, which is rare enough
+    let names: Vec<String> = property(&output, "_NET_DESKTOP_NAMES")
+        .map(|v| v.split("\", \"").map(unquote).collect())
+        .unwrap_or_default();
+    Ok(Some(
+        (0..count)
+            .map(|index| VirtualDesktop {
+                index,
+                name: names.get(index as usize).filter(|name| !name.is_empty()).cloned(),
+                current: current == Some(index),
+            })
+            .collect(),
+    ))
+}
+
+pub fn switch_virtual_desktop(index: u32) -> anyhow::Result<bool> {
+    if !is_available() {
+        return Ok(false);
+    }
+    // Both send the EWMH _NET_CURRENT_DESKTOP request to the root window
+    let index_arg = index.to_string();
+    if let Err(wmctrl_err) = run("wmctrl", &["-s", &index_arg]) {
+        run("xdotool", &["set_desktop", &index_arg]).with_context(|| format!("wmctrl failed too: {:#}", wmctrl_err))?;
+    }
+    Ok(true)
+}
@@ -0,0 +1,46 @@
+// WebSocket transport (`--transport ws`): one JSON-RPC message per text frame
+use futures::{future, Sink, SinkExt, Stream, StreamExt};
+use rmcp::service::{RoleServer, RxJsonRpcMessage, TxJsonRpcMessage};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tracing::warn;
+
+/// Splits an accepted WebSocket into the sink/stream pair `serve_server` expects.
+///
+/// Binary frames are accepted as JSON too. The stream ends on a close frame or socket error, which
+/// `serve_server` treats like a closed TCP connection; frames that aren't valid JSON-RPC are
+/// logged and skipped.
+pub fn into_transport<S>(
+    socket: WebSocketStream<S>,
+) -> (
+    impl Sink<TxJsonRpcMessage<RoleServer>, Error = std::io::Error> + Send + 'static,
+    impl Stream<Item = RxJsonRpcMessage<RoleServer>> + Send + 'static,
+)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (sink, stream) = socket.split();
+    let sink = sink.sink_map_err(std::io::Error::other).with(|message: TxJsonRpcMessage<RoleServer>| {
+        future::ready(serde_json::to_string(&message).map(|text| Message::Text(text.into())).map_err(std::io::Error::other))
+    });
+    let stream = stream
+        .take_while(|frame| {
+            if let Err(e) = frame {
+                warn!("WebSocket read failed: {}", e);
+            }
+            future::ready(matches!(frame, Ok(message) if !message.is_close()))
+        })
+        .filter_map(|frame| {
+            let parsed = match frame {
+                Ok(Message::Text(text)) => Some(serde_json::from_str::<RxJsonRpcMessage<RoleServer>>(&text)),
+                Ok(Message::Binary(data)) => Some(serde_json::from_slice::<RxJsonRpcMessage<RoleServer>>(&data)),
+                // Pings are answered by tungstenite itself
+                _ => None,
+            };
+            future::ready(parsed.and_then(|result| {
+                result.map_err(|e| warn!("Ignoring WebSocket frame that isn't a JSON-RPC message: {}", e)).ok()
+            }))
+        });
+    (sink, stream)
+}
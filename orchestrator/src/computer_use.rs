@@ -6,7 +6,7 @@ use openai_responses::{
         config::Truncation,
         // Use SDK types based on list provided
         // *** Corrected import path for OutputItem, added InputItem ***
-        item::{ClickButton, ComputerAction, ComputerCallOutput, ComputerToolCall, InputItem, OutputItem, SafetyCheck},
+        item::{ClickButton, ComputerAction, ComputerCallOutput, ComputerToolCall, InputItem, OutputContent, OutputItem, ReasoningSummary, SafetyCheck},
         // *** Added InputListItem, removed unused ContentItem, ImageDetail ***
         request::{Input, InputListItem, Request},
         tools::{Environment, Tool},
@@ -25,19 +25,87 @@ use rmcp::{
 use serde::{Deserialize, Serialize};
 use serde_json::Value; // Removed json macro import
 // Removed std::env import
+use std::time::{Duration, Instant};
 use tokio::net::TcpSocket;
 use tracing::{debug, error, info, warn};
 
 // Configuration
 const MCP_SERVER_ADDR: &str = "127.0.0.1:9001";
+// Fallback display size, only used if the server's screen details can't be read
 const DISPLAY_WIDTH: u32 = 1920;
 const DISPLAY_HEIGHT: u32 = 1080;
 const ENVIRONMENT: Environment = Environment::Windows; // Use SDK Enum
-
-// Helper struct to deserialize screenshot results from MCP server
+// Shortest time between two screenshots unless overridden; roughly a few display refreshes
+const DEFAULT_MIN_CAPTURE_INTERVAL_MS: u64 = 250;
+// Screenshots are downscaled and sent as JPEG to keep each turn cheap; DisplayMapping maps
+// the model's coordinates on the smaller image back to the desktop
+const CAPTURE_MAX_WIDTH: u32 = 1280;
+const CAPTURE_FORMAT: &str = "jpeg";
+const CAPTURE_QUALITY: u8 = 80;
+
+// Size fields of the capture_screen metadata
 #[derive(Deserialize, Debug)]
 struct ScreenshotResultData {
-    base64_data: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+// One entry of the get_screen_details result
+#[derive(Deserialize, Debug)]
+struct ScreenDetails {
+    width: u32,
+    height: u32,
+    scale_factor: f32,
+    x: i32,
+    y: i32,
+}
+
+/// Maps between the screenshot pixel space the model reasons in and the desktop coordinates
+/// the input tools expect.
+///
+/// Screenshots are captured in physical pixels while the input backend works in the
+/// display's (possibly scaled) coordinate space, so on a 150% display a point at (300, 300)
+/// in the screenshot is (200, 200) on the desktop, offset by the monitor's origin.
+#[derive(Debug, Clone, Copy)]
+struct DisplayMapping {
+    /// Size reported to the model as `display_width`/`display_height`.
+    model_width: u32,
+    model_height: u32,
+    origin_x: i32,
+    origin_y: i32,
+    scale_x: f64,
+    scale_y: f64,
+}
+
+impl DisplayMapping {
+    /// `screenshot_size` is the pixel size of an actual capture; when unavailable it is derived
+    /// from the screen size and `scale_factor`.
+    fn new(screen: &ScreenDetails, screenshot_size: Option<(u32, u32)>) -> Self {
+        let scale = if screen.scale_factor > 0.0 { screen.scale_factor as f64 } else { 1.0 };
+        let (model_width, model_height) = screenshot_size
+            .filter(|(w, h)| *w > 0 && *h > 0)
+            .unwrap_or_else(|| ((screen.width as f64 * scale).round() as u32, (screen.height as f64 * scale).round() as u32));
+        Self {
+            model_width,
+            model_height,
+            origin_x: screen.x,
+            origin_y: screen.y,
+            scale_x: screen.width as f64 / model_width as f64,
+            scale_y: screen.height as f64 / model_height as f64,
+        }
+    }
+
+    fn fallback() -> Self {
+        Self { model_width: DISPLAY_WIDTH, model_height: DISPLAY_HEIGHT, origin_x: 0, origin_y: 0, scale_x: 1.0, scale_y: 1.0 }
+    }
+
+    /// Converts a point from model (screenshot) space to absolute desktop coordinates.
+    fn to_screen(&self, x: f64, y: f64) -> (i32, i32) {
+        (
+            self.origin_x + (x * self.scale_x).round() as i32,
+            self.origin_y + (y * self.scale_y).round() as i32,
+        )
+    }
 }
 
 // Parameter structs for calling MCP execute_openai_* tools
@@ -46,8 +114,113 @@ struct ScreenshotResultData {
 #[derive(Debug, Serialize)] struct OpenAIKeyPressParams { keys: Vec<String> }
 #[derive(Debug, Serialize)] struct OpenAITypeParams { text: String }
 #[derive(Debug, Serialize)] struct OpenAIWaitParams { duration_ms: Option<u64> }
-#[derive(Debug, Serialize)] struct CaptureScreenParams { x: Option<i32>, y: Option<i32>, width: Option<u32>, height: Option<u32> }
+#[derive(Debug, Serialize)] struct CaptureScreenParams { x: Option<i32>, y: Option<i32>, width: Option<u32>, height: Option<u32>, max_width: u32, format: &'static str, quality: u8 }
+
+
+/// When the loop takes a fresh screenshot to send back after an action.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum CapturePolicy {
+    /// `--capture-policy=always`: capture after every action.
+    Always,
+    /// `--capture-policy=changes`: skip the capture after actions that leave the screen as it
+    /// was (a bare mouse move, a blocked action) and send the previous screenshot again.
+    #[default]
+    Changes,
+}
+
+impl CapturePolicy {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "always" => Some(Self::Always),
+            "changes" => Some(Self::Changes),
+            _ => None,
+        }
+    }
 
+    /// Whether a fresh capture is needed after `action`, given a previous screenshot to reuse.
+    /// The model asking for a screenshot always gets a new one.
+    fn needs_capture(self, action: &ComputerAction, blocked: bool) -> bool {
+        if self == Self::Always || matches!(action, ComputerAction::Screenshot) {
+            return true;
+        }
+        !(blocked || matches!(action, ComputerAction::Move { .. }))
+    }
+}
+
+/// Command-line options for the Computer Use loop.
+#[derive(Debug, Default)]
+struct ComputerUseOptions {
+    /// `--auto-approve`: acknowledge every safety check without asking (unattended runs).
+    auto_approve: bool,
+    capture_policy: CapturePolicy,
+    /// `--min-capture-interval-ms=N` (or `AIRC_MIN_CAPTURE_INTERVAL_MS`): a capture due sooner
+    /// than this after the previous one waits out the rest. This only rate-limits captures;
+    /// every action still gets its own screenshot, since the model expects one per call.
+    /// 0 disables it.
+    min_capture_interval: Duration,
+}
+
+impl ComputerUseOptions {
+    fn parse() -> Self {
+        let mut options = Self { min_capture_interval: Self::env_capture_interval(), ..Self::default() };
+        for arg in std::env::args().skip(1) {
+            match arg.as_str() {
+                "--auto-approve" => options.auto_approve = true,
+                other => {
+                    if let Some(value) = other.strip_prefix("--min-capture-interval-ms=") {
+                        match value.parse() {
+                            Ok(ms) => options.min_capture_interval = Duration::from_millis(ms),
+                            Err(_) => warn!("Ignoring {}: expected a number of milliseconds.", other),
+                        }
+                        continue;
+                    }
+                    match other.strip_prefix("--capture-policy=").map(CapturePolicy::parse) {
+                        Some(Some(policy)) => options.capture_policy = policy,
+                        Some(None) => warn!("Ignoring {}: expected 'always' or 'changes'.", other),
+                        None => warn!("Ignoring unknown argument: {}", other),
+                    }
+                }
+            }
+        }
+        options
+    }
+
+    fn env_capture_interval() -> Duration {
+        let ms = match std::env::var("AIRC_MIN_CAPTURE_INTERVAL_MS") {
+            Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+                warn!("Ignoring invalid AIRC_MIN_CAPTURE_INTERVAL_MS value '{}'", raw);
+                DEFAULT_MIN_CAPTURE_INTERVAL_MS
+            }),
+            Err(_) => DEFAULT_MIN_CAPTURE_INTERVAL_MS,
+        };
+        Duration::from_millis(ms)
+    }
+}
+
+/// Asks the operator to approve each pending safety check.
+///
+/// Returns the checks to acknowledge, or `None` if any check was declined, in which case
+/// the action must not be performed.
+fn review_safety_checks(checks: &[SafetyCheck], auto_approve: bool) -> Result<Option<Vec<SafetyCheck>>> {
+    if auto_approve {
+        warn!("Auto-approving {} safety check(s): {:?}", checks.len(), checks);
+        return Ok(Some(checks.to_vec()));
+    }
+
+    for check in checks {
+        println!("\n--- Safety check ({}) ---\n{}", check.code, check.message);
+        print!("Allow the model to proceed? [y/N]: ");
+        use std::io::Write;
+        std::io::stdout().flush().unwrap_or_default();
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer).context("Failed to read safety check answer")?;
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            warn!("Safety check '{}' declined by operator.", check.code);
+            return Ok(None);
+        }
+    }
+    Ok(Some(checks.to_vec()))
+}
 
 pub async fn run_computer_use() -> Result<()> {
     // Initialize tracing
@@ -57,6 +230,8 @@ pub async fn run_computer_use() -> Result<()> {
         .with_ansi(true)
         .init();
 
+    let options = ComputerUseOptions::parse();
+
     // Load OpenAI API Key
     dotenv::dotenv().ok();
     let openai_client = ResponsesClient::from_env()
@@ -76,6 +251,9 @@ pub async fn run_computer_use() -> Result<()> {
         .context("Failed to establish MCP client service (ensure 'client' feature is enabled for rmcp)")?;
     let mcp_peer = mcp_client.peer().clone();
 
+    let display = detect_display_mapping(&mcp_peer).await;
+    info!("Display mapping: {:?}", display);
+
     // --- Get Initial User Task ---
     println!("\nEnter the computer task you want the AI to perform (or type 'quit'):");
     let mut user_input = String::new();
@@ -92,8 +270,8 @@ pub async fn run_computer_use() -> Result<()> {
     // Define the Computer Use tool for the request using SDK types
     // TODO: Verify Tool::ComputerUse variant name and fields
     let computer_tool = Tool::ComputerUse {
-        display_width: DISPLAY_WIDTH as u64,
-        display_height: DISPLAY_HEIGHT as u64,
+        display_width: display.model_width as u64,
+        display_height: display.model_height as u64,
         environment: ENVIRONMENT,
     };
 
@@ -114,6 +292,11 @@ pub async fn run_computer_use() -> Result<()> {
     // --- Main Computer Use Loop ---
     let mut current_request = initial_request;
     // Removed last_response_id, use response.id directly
+    // Last screenshot sent to the model, resent when the capture policy skips a capture
+    let mut last_screenshot: Option<String> = None;
+    // When the last fresh screenshot was taken, for the minimum capture interval
+    let mut last_capture_at: Option<Instant> = None;
+    info!("Capture policy: {:?}, minimum interval between captures: {:?}", options.capture_policy, options.min_capture_interval);
 
     loop {
         debug!("Sending request...");
@@ -136,8 +319,24 @@ pub async fn run_computer_use() -> Result<()> {
 
             info!("Received action type: {:?}", action);
 
+            // --- Review Safety Checks ---
+            // Declined checks are answered with an empty acknowledgment list and the action is skipped
+            let (acknowledged_safety_checks, action_blocked): (Option<Vec<SafetyCheck>>, bool) =
+                if computer_call.pending_safety_checks.is_empty() {
+                    (None, false)
+                } else {
+                    match review_safety_checks(&computer_call.pending_safety_checks, options.auto_approve)? {
+                        Some(approved) => (Some(approved), false),
+                        None => (Some(Vec::new()), true),
+                    }
+                };
+
             // --- Execute Action using MCP Server ---
-            let execution_result = match action {
+            let execution_result = if action_blocked {
+                warn!("Action {:?} blocked by operator; not executing it.", action);
+                println!("Action blocked.");
+                Ok(())
+            } else { match action {
                 ComputerAction::Click { x, y, button } => {
                     let button_str = match button {
                          ClickButton::Left=>"left",
@@ -146,11 +345,13 @@ pub async fn run_computer_use() -> Result<()> {
                          ClickButton::Back => "back",
                          ClickButton::Forward => "forward",
                     }.to_string();
-                    let params = OpenAIClickParams { x: x.to_owned() as i32, y: y.to_owned() as i32, button: button_str };
+                    let (x, y) = display.to_screen(*x as f64, *y as f64);
+                    let params = OpenAIClickParams { x, y, button: button_str };
                     call_mcp_tool(&mcp_peer, "execute_openai_click", params).await
                 }
                 ComputerAction::Scroll { x, y, scroll_x, scroll_y } => {
-                    let params = OpenAIScrollParams { x: x.to_owned() as i32, y: y.to_owned() as i32, scroll_x: scroll_x.to_owned() as i32, scroll_y: scroll_y.to_owned() as i32 };
+                    let (x, y) = display.to_screen(*x as f64, *y as f64);
+                    let params = OpenAIScrollParams { x, y, scroll_x: scroll_x.to_owned() as i32, scroll_y: scroll_y.to_owned() as i32 };
                     call_mcp_tool(&mcp_peer, "execute_openai_scroll", params).await
                 }
                 ComputerAction::KeyPress { keys } => {
@@ -171,19 +372,21 @@ pub async fn run_computer_use() -> Result<()> {
                  }
                  ComputerAction::Move { x, y } => {
                      warn!("Received Move action. Mapping to execute_openai_click at ({}, {}) with no button press.", x, y);
-                     let params = OpenAIClickParams { x: x.to_owned() as i32, y: y.to_owned() as i32, button: "none".to_string() };
+                     let (x, y) = display.to_screen(*x as f64, *y as f64);
+                     let params = OpenAIClickParams { x, y, button: "none".to_string() };
                      call_mcp_tool(&mcp_peer, "execute_openai_click", params).await
                  }
                  ComputerAction::DoubleClick { x, y } => {
                     warn!("Received DoubleClick action. Mapping to single left click for now.");
-                    let params = OpenAIClickParams { x: x.to_owned() as i32, y: y.to_owned() as i32, button: "left".to_string() };
+                    let (x, y) = display.to_screen(*x as f64, *y as f64);
+                    let params = OpenAIClickParams { x, y, button: "left".to_string() };
                     call_mcp_tool(&mcp_peer, "execute_openai_click", params).await
                  }
                  ComputerAction::Drag { .. } => {
                      warn!("Received Drag action, which is not implemented yet.");
                      Ok(())
                  }
-            };
+            } };
 
             if let Err(e) = execution_result {
                 error!("Failed to execute MCP tool for action '{:?}': {}", action, e);
@@ -192,28 +395,38 @@ pub async fn run_computer_use() -> Result<()> {
             }
 
             // --- Capture Screenshot ---
-            info!("Capturing screen after action...");
-            let screenshot_base64 = match call_capture_screen(&mcp_peer, None, None, None, None).await {
-                 Ok(b64) => b64,
-                 Err(e) => {
-                     error!("Failed to capture screen: {}", e);
-                     println!("Error capturing screen. Stopping.");
-                     break;
-                 }
+            let screenshot_url = match last_screenshot.take() {
+                Some(previous) if !options.capture_policy.needs_capture(action, action_blocked) => {
+                    info!("Action left the screen unchanged; reusing the previous screenshot.");
+                    previous
+                }
+                _ => {
+                    // Rate limit only: each action still gets its own capture, but never sooner
+                    // than the interval after the previous one
+                    if let Some(wait) = last_capture_at.and_then(|at| options.min_capture_interval.checked_sub(at.elapsed())) {
+                        debug!("Waiting {:?} before capturing to respect the minimum capture interval.", wait);
+                        tokio::time::sleep(wait).await;
+                    }
+                    info!("Capturing screen after action...");
+                    let capture = call_capture_screen(&mcp_peer, None, None, None, None).await;
+                    last_capture_at = Some(Instant::now());
+                    match capture {
+                        Ok(data) => data.data_url,
+                        Err(e) => {
+                            error!("Failed to capture screen: {}", e);
+                            println!("Error capturing screen. Stopping.");
+                            break;
+                        }
+                    }
+                }
             };
+            last_screenshot = Some(screenshot_url.clone());
 
             // --- Construct Next Request ---
-            let acknowledged_safety_checks: Option<Vec<SafetyCheck>> = if computer_call.pending_safety_checks.is_empty() {
-                None
-            } else {
-                warn!("Received pending safety checks: {:?}. Acknowledging all for now.", computer_call.pending_safety_checks);
-                Some(computer_call.pending_safety_checks.clone())
-            };
-
             // 1. Construct the ComputerCallOutput enum variant (Screenshot)
             let output_enum_variant = ComputerCallOutput::Screenshot {
                 file_id: None,
-                image_url: Some(format!("data:image/png;base64,{}", screenshot_base64)),
+                image_url: Some(screenshot_url),
             };
 
             // 2. Construct the ComputerToolCallOutput struct
@@ -251,16 +464,16 @@ pub async fn run_computer_use() -> Result<()> {
             // Parse final text output from response.output
             for item in response.output {
                  match item {
-                     // TODO: Verify actual variant name for text output (e.g., Message, TextData?)
-                     OutputItem::Message(msg_item) => { // Guessing variant name
-                        // TODO: Verify structure of msg_item and how to get text
-                         println!("Message: {:?}", msg_item);
+                     OutputItem::Message(msg_item) => {
+                         let text = message_text(&msg_item.content);
+                         if !text.is_empty() {
+                             println!("{}", text);
+                         }
                      }
                      OutputItem::Reasoning(reasoning_item) => {
-                         // summary is Vec<ReasoningSummary>, not Option
-                         for summary_item in reasoning_item.summary {
-                             // TODO: Check actual structure of ReasoningSummary type
-                             println!("Reasoning: {:?}", summary_item);
+                         let summary = reasoning_summary_text(&reasoning_item.summary);
+                         if !summary.is_empty() {
+                             println!("Reasoning: {}", summary);
                          }
                      }
                      _ => {}
@@ -275,6 +488,29 @@ pub async fn run_computer_use() -> Result<()> {
     Ok(())
 }
 
+// Concatenates the text parts of an output message; refusals are printed with a prefix
+fn message_text(content: &[OutputContent]) -> String {
+    content
+        .iter()
+        .map(|part| match part {
+            OutputContent::Text { text, .. } => text.clone(),
+            OutputContent::Refusal { refusal } => format!("[Refusal] {}", refusal),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Joins the reasoning summary paragraphs into one block of text
+fn reasoning_summary_text(summary: &[ReasoningSummary]) -> String {
+    summary
+        .iter()
+        .map(|item| match item {
+            ReasoningSummary::Text { text } => text.as_str(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 // Helper function to call an MCP tool and handle potential errors
 // *** Updated signature to take Peer<RoleClient> ***
 async fn call_mcp_tool<P: Serialize + std::fmt::Debug>(mcp_peer: &Peer<RoleClient>, tool_name: &str, params: P) -> Result<()> {
@@ -291,27 +527,63 @@ async fn call_mcp_tool<P: Serialize + std::fmt::Debug>(mcp_peer: &Peer<RoleClien
     Ok(())
 }
 
-// Helper function to call capture_screen and extract base64 data
+// Queries the server's screens and a sample capture to build the coordinate mapping.
+// Falls back to the fixed DISPLAY_WIDTH x DISPLAY_HEIGHT 1:1 mapping on any failure.
+async fn detect_display_mapping(mcp_peer: &Peer<RoleClient>) -> DisplayMapping {
+    let screens = match call_mcp_tool_with_result(mcp_peer, "get_screen_details", serde_json::json!({})).await {
+        Ok(result) => match result.content.into_iter().next().map(|c| c.raw) {
+            Some(RawContent::Text(raw_text)) => serde_json::from_str::<Vec<ScreenDetails>>(&raw_text.text)
+                .map_err(|e| anyhow!("Failed to parse get_screen_details result: {}", e)),
+            _ => Err(anyhow!("get_screen_details returned no text content")),
+        },
+        Err(e) => Err(e),
+    };
+    let screens = match screens {
+        Ok(screens) if !screens.is_empty() => screens,
+        Ok(_) => {
+            warn!("get_screen_details returned no screens. Using {}x{} without scaling.", DISPLAY_WIDTH, DISPLAY_HEIGHT);
+            return DisplayMapping::fallback();
+        }
+        Err(e) => {
+            warn!("Could not read screen details ({}). Using {}x{} without scaling.", e, DISPLAY_WIDTH, DISPLAY_HEIGHT);
+            return DisplayMapping::fallback();
+        }
+    };
+
+    // The primary display sits at the desktop origin
+    let primary = screens.iter().find(|s| s.x == 0 && s.y == 0).unwrap_or(&screens[0]);
+    let screenshot_size = match call_capture_screen(mcp_peer, None, None, None, None).await {
+        Ok(capture) => capture.size,
+        Err(e) => {
+            warn!("Sample capture failed ({}). Deriving screenshot size from scale_factor.", e);
+            None
+        }
+    };
+    DisplayMapping::new(primary, screenshot_size)
+}
+
+// Result of a capture_screen call
+struct Capture {
+    /// The image as a data URL, in whatever format the server returned it.
+    data_url: String,
+    size: Option<(u32, u32)>,
+}
+
+// Helper function to call capture_screen with the cheap capture options and extract the image
 // *** Updated signature to take Peer<RoleClient> ***
 async fn call_capture_screen(
     mcp_peer: &Peer<RoleClient>,
     x: Option<i32>, y: Option<i32>, width: Option<u32>, height: Option<u32>
-) -> Result<String> {
-    let params = CaptureScreenParams { x, y, width, height };
+) -> Result<Capture> {
+    let params = CaptureScreenParams { x, y, width, height, max_width: CAPTURE_MAX_WIDTH, format: CAPTURE_FORMAT, quality: CAPTURE_QUALITY };
     // *** Pass mcp_peer directly ***
     let mcp_result = call_mcp_tool_with_result(mcp_peer, "capture_screen", params).await?;
-    match mcp_result.content.into_iter().next() {
-        Some(content) => match content.raw {
-            RawContent::Text(raw_text) => {
-                match serde_json::from_str::<ScreenshotResultData>(&raw_text.text) {
-                    Ok(data) => data.base64_data.ok_or_else(|| anyhow!("'base64_data' field missing in capture_screen result")),
-                    Err(e) => Err(anyhow!("Failed to parse capture_screen JSON result: {}", e)),
-                }
-            }
-            _ => Err(anyhow!("capture_screen returned non-text content")),
-        },
-        None => Err(anyhow!("capture_screen returned no content")),
-    }
+    let screenshot = crate::mcp::screenshot(&mcp_result).ok_or_else(|| anyhow!("capture_screen returned no screenshot"))?;
+    let size = screenshot.metadata.and_then(|metadata| serde_json::from_value::<ScreenshotResultData>(metadata).ok());
+    Ok(Capture {
+        data_url: screenshot.data_url(),
+        size: size.and_then(|data| data.width.zip(data.height)),
+    })
 }
 
 // Helper to call MCP tool and get Result
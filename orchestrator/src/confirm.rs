@@ -0,0 +1,61 @@
+// Operator confirmation before the model runs tools that can do real damage
+use std::io::Write;
+use tracing::{info, warn};
+
+use crate::mcp::{self, McpManager};
+
+// Used when AIRC_CONFIRM_TOOLS is unset and no server reports a tool catalog
+const DEFAULT_CONFIRM_TOOLS: &[&str] = &["run_shell_command", "write_file", "terminate_application", "kill_process", "start_recording"];
+
+/// Which tool calls need a y/n from the operator before they are forwarded to the MCP server.
+#[derive(Debug, Clone)]
+pub struct ConfirmPolicy {
+    tools: Vec<String>,
+}
+
+impl ConfirmPolicy {
+    /// Builds the confirm list: `AIRC_CONFIRM_TOOLS` (comma-separated tool names; empty
+    /// disables confirmation) if set, otherwise the tools the servers' catalogs mark as
+    /// destructive, or a built-in list if no server offers a catalog.
+    /// `yolo` (`--yolo`) skips confirmation entirely.
+    pub async fn resolve(yolo: bool, mcp: &McpManager) -> Self {
+        if yolo {
+            warn!("--yolo given: tool calls will run without confirmation.");
+            return Self { tools: Vec::new() };
+        }
+        let tools: Vec<String> = match std::env::var("AIRC_CONFIRM_TOOLS") {
+            Ok(raw) => raw.split(',').map(str::trim).filter(|t| !t.is_empty()).map(String::from).collect(),
+            Err(_) => match mcp.destructive_tools().await {
+                Some(tools) => tools,
+                None => DEFAULT_CONFIRM_TOOLS.iter().map(|t| t.to_string()).collect(),
+            },
+        };
+        if !tools.is_empty() {
+            info!("Tools requiring confirmation: {}", tools.join(", "));
+        }
+        Self { tools }
+    }
+
+    /// Whether `tool` (as exposed to the model, possibly `server__tool`) is on the confirm list.
+    /// Entries match either the bare tool name or the namespaced one.
+    pub fn requires_confirmation(&self, tool: &str) -> bool {
+        let bare = mcp::unqualified(tool);
+        self.tools.iter().any(|t| t == tool || t == bare)
+    }
+
+    /// Prints the call and waits for the operator's answer on stdin. Anything but `y`/`yes`
+    /// (including a read failure or EOF) declines.
+    pub fn ask(&self, tool: &str, arguments: &str) -> bool {
+        println!("\nThe assistant wants to run '{}' with arguments:\n{}", tool, arguments);
+        print!("Allow this call? [y/N]: ");
+        let _ = std::io::stdout().flush();
+        let mut answer = String::new();
+        match std::io::stdin().read_line(&mut answer) {
+            Ok(_) => matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes"),
+            Err(e) => {
+                warn!("Failed to read confirmation for '{}': {}", tool, e);
+                false
+            }
+        }
+    }
+}
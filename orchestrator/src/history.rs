@@ -0,0 +1,149 @@
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPartText, ChatCompletionRequestUserMessage,
+    ChatCompletionRequestUserMessageContent, ChatCompletionRequestUserMessageContentPart,
+};
+use std::collections::{HashSet, VecDeque};
+use tracing::{info, warn};
+
+// Short role label used in trim logging
+fn role_name(message: &ChatCompletionRequestMessage) -> &'static str {
+    match message {
+        ChatCompletionRequestMessage::System(_) => "System",
+        ChatCompletionRequestMessage::Developer(_) => "Developer",
+        ChatCompletionRequestMessage::User(_) => "User",
+        ChatCompletionRequestMessage::Assistant(_) => "Assistant",
+        ChatCompletionRequestMessage::Tool(_) => "Tool",
+        ChatCompletionRequestMessage::Function(_) => "Function",
+    }
+}
+
+/// Number of messages starting at `start` that must be removed together.
+///
+/// An assistant message that made tool calls is grouped with every following `Tool` message
+/// answering one of those calls, so trimming never leaves a tool result without its call.
+/// Any other message is its own group.
+fn group_len(history: &VecDeque<ChatCompletionRequestMessage>, start: usize) -> usize {
+    let call_ids: HashSet<&str> = match history.get(start) {
+        Some(ChatCompletionRequestMessage::Assistant(assistant)) => assistant
+            .tool_calls
+            .iter()
+            .flatten()
+            .map(|call| call.id.as_str())
+            .collect(),
+        _ => return 1,
+    };
+
+    let mut len = 1;
+    while let Some(ChatCompletionRequestMessage::Tool(tool)) = history.get(start + len) {
+        if !call_ids.contains(tool.tool_call_id.as_str()) {
+            break;
+        }
+        len += 1;
+    }
+    len
+}
+
+/// Trims the oldest messages until the history fits in `max_depth` items.
+///
+/// The system prompt at index 0 is always kept, and assistant tool-call messages are removed
+/// together with their tool results. Stray tool messages left at the front (e.g. from an old
+/// session file) are dropped as well, since OpenAI rejects a tool message that doesn't follow
+/// its assistant call. The newest group is never removed, so a single oversized tool-call round
+/// may leave the history temporarily above `max_depth`.
+pub fn trim_history(history: &mut VecDeque<ChatCompletionRequestMessage>, max_depth: usize) {
+    loop {
+        let orphaned_tool = matches!(history.get(1), Some(ChatCompletionRequestMessage::Tool(_)));
+        if history.len() <= max_depth && !orphaned_tool {
+            break;
+        }
+        if history.len() < 2 {
+            break;
+        }
+
+        let len = group_len(history, 1);
+        if 1 + len >= history.len() && !orphaned_tool {
+            warn!(
+                "History length {} exceeds max depth {}, but only the latest exchange is left to trim. Keeping it.",
+                history.len(), max_depth
+            );
+            break;
+        }
+
+        info!(
+            "Trimming history: removing {} message(s) starting at index 1 (Role: {}). Current length: {}",
+            len,
+            history.get(1).map(role_name).unwrap_or("None"),
+            history.len()
+        );
+        history.drain(1..1 + len);
+    }
+}
+
+/// Replaces the images in earlier user messages with a short note, so only the newest attached
+/// screenshots are sent again on every request (each one costs hundreds of tokens or more).
+pub fn drop_old_screenshots(history: &mut VecDeque<ChatCompletionRequestMessage>) {
+    for message in history.iter_mut() {
+        let ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+            content: ChatCompletionRequestUserMessageContent::Array(parts),
+            ..
+        }) = message
+        else {
+            continue;
+        };
+        for part in parts.iter_mut() {
+            if matches!(part, ChatCompletionRequestUserMessageContentPart::ImageUrl(_)) {
+                *part = ChatCompletionRequestUserMessageContentPart::Text(ChatCompletionRequestMessageContentPartText {
+                    text: "[Earlier screenshot removed; the latest one is further down.]".to_string(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{result, tool_calls, user};
+    use async_openai::types::{ChatCompletionRequestSystemMessage, ChatCompletionRequestSystemMessageContent};
+
+    fn system() -> ChatCompletionRequestMessage {
+        ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage {
+            content: ChatCompletionRequestSystemMessageContent::Text("system".to_string()),
+            ..Default::default()
+        })
+    }
+
+    fn roles(history: &VecDeque<ChatCompletionRequestMessage>) -> Vec<&'static str> {
+        history.iter().map(role_name).collect()
+    }
+
+    #[test]
+    fn drops_multi_call_group_together() {
+        let mut history = VecDeque::from(vec![
+            system(),
+            tool_calls(&["a", "b", "c"]),
+            result("a"),
+            result("b"),
+            result("c"),
+            user("next"),
+            user("latest"),
+        ]);
+        assert_eq!(group_len(&history, 1), 4);
+        trim_history(&mut history, 4);
+        assert_eq!(roles(&history), ["System", "User", "User"]);
+    }
+
+    #[test]
+    fn removes_orphaned_tool_message() {
+        let mut history = VecDeque::from(vec![system(), result("gone"), user("hi")]);
+        trim_history(&mut history, 10);
+        assert_eq!(roles(&history), ["System", "User"]);
+    }
+
+    #[test]
+    fn keeps_newest_group_over_max_depth() {
+        let mut history = VecDeque::from(vec![system(), user("look"), tool_calls(&["a", "b"]), result("a"), result("b")]);
+        trim_history(&mut history, 2);
+        assert_eq!(roles(&history), ["System", "Assistant", "Tool", "Tool"]);
+    }
+}
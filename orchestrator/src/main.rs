@@ -1,40 +1,43 @@
 use anyhow::{Context, Result};
 use async_openai::{
-    config::Config,
+    config::{Config, OpenAIConfig},
     error::OpenAIError,
     types::{
         ChatCompletionRequestAssistantMessage, ChatCompletionRequestAssistantMessageContent,
         ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPartImage,
         ChatCompletionRequestMessageContentPartText, ChatCompletionRequestSystemMessage, ChatCompletionRequestSystemMessageContent,
         ChatCompletionRequestToolMessage, ChatCompletionRequestToolMessageContent, ChatCompletionRequestUserMessage,
-        ChatCompletionRequestUserMessageContent, ChatCompletionTool, ChatCompletionToolChoiceOption,
-        ChatCompletionToolType, CreateChatCompletionRequest, FunctionObject, ImageDetail, ImageUrl
+        ChatCompletionRequestUserMessageContent, ChatCompletionRequestUserMessageContentPart, ChatCompletionToolChoiceOption,
+        CreateChatCompletionRequest, ImageDetail, ImageUrl
     },
     Client as OpenAIClient,
 };
-use rmcp::{
-    model::{CallToolRequestParam, RawContent}, 
-    service::{RoleClient, RunningService},
-    serve_client,
-};
+use rmcp::model::{CallToolRequestParam, RawContent};
 use serde_json::{json, Map, Value};
-use std::{collections::VecDeque, env};
-use tokio::net::TcpSocket; 
-use tracing::{info, error, debug, warn};
+use std::{collections::VecDeque, env, path::PathBuf, time::Duration};
+use tracing::{info, error, warn};
 use futures::stream::StreamExt;
 use futures::future::join_all;
 use tokio::task::JoinHandle;
 use std::collections::HashMap;
 
 pub mod computer_use;
+pub mod confirm;
+pub mod history;
+pub mod mcp;
+pub mod recapture;
+pub mod retry;
+pub mod session;
+#[cfg(test)]
+mod test_support;
+pub mod vision;
 
 // Configuration
-const MCP_SERVER_ADDR: &str = "127.0.0.1:9001"; // Address of your TCP MCP Server
-const MAX_CONVERSATION_DEPTH: usize = 15; // Max history items (including System prompt)
-const OPENAI_CHAT_MODEL: &str = "gpt-4.1-mini"; // Or your preferred model like gpt-4o-mini if desired
-const OPENAI_VISION_MODEL: &str = "gpt-4.1-nano"; // Specific model for image analysis
-// const OPENAI_CHAT_MODEL: &str = "gemini-2.0-flash"; // Or your preferred model like gpt-4o-mini if desired
-// const OPENAI_VISION_MODEL: &str = "gemini-2.0-flash"; // Specific model for image analysis
+const MCP_SERVER_ADDR: &str = "127.0.0.1:9001"; // Address of your TCP MCP Server (used when no --mcp-server is given)
+// Defaults below can be overridden with AIRC_MAX_DEPTH / AIRC_CHAT_MODEL / AIRC_VISION_MODEL (and --model)
+const DEFAULT_MAX_CONVERSATION_DEPTH: usize = 15; // Max history items (including System prompt)
+const DEFAULT_CHAT_MODEL: &str = "gpt-4.1-mini"; // Or your preferred model like gpt-4o-mini if desired
+const DEFAULT_VISION_MODEL: &str = "gpt-4.1-nano"; // Specific model for image analysis
 
 #[derive(Debug, Clone, Default)]
 struct PartialToolCall {
@@ -45,6 +48,134 @@ struct PartialToolCall {
     arguments: String,
 }
 
+/// Command-line options for the chat orchestrator.
+#[derive(Debug, Default)]
+struct CliArgs {
+    /// `--session <path>`: persist the conversation to this file and resume from it on startup.
+    session: Option<PathBuf>,
+    /// `--model <name>`: chat model to use; overrides `AIRC_CHAT_MODEL`.
+    model: Option<String>,
+    /// `--api-base <url>`: OpenAI-compatible endpoint to use; overrides `AIRC_API_BASE`.
+    api_base: Option<String>,
+    /// `--ping-interval <secs>`: ping the MCP server between turns; overrides `AIRC_PING_INTERVAL_SECS`.
+    ping_interval: Option<String>,
+    /// `--mcp-server [name=]addr`, repeatable: MCP servers to connect to. Defaults to `MCP_SERVER_ADDR`.
+    mcp_servers: Vec<String>,
+    /// `--yolo`: run every tool call without asking, ignoring `AIRC_CONFIRM_TOOLS`.
+    yolo: bool,
+}
+
+impl CliArgs {
+    fn parse() -> Result<Self> {
+        let mut parsed = CliArgs::default();
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--session" => {
+                    let path = args.next().context("--session requires a file path")?;
+                    parsed.session = Some(PathBuf::from(path));
+                }
+                "--model" => {
+                    parsed.model = Some(args.next().context("--model requires a model name")?);
+                }
+                "--api-base" => {
+                    parsed.api_base = Some(args.next().context("--api-base requires a URL")?);
+                }
+                "--ping-interval" => {
+                    parsed.ping_interval = Some(args.next().context("--ping-interval requires a number of seconds")?);
+                }
+                "--mcp-server" => {
+                    parsed.mcp_servers.push(args.next().context("--mcp-server requires an address (optionally name=addr)")?);
+                }
+                "--yolo" => parsed.yolo = true,
+                other => anyhow::bail!("Unknown argument: {}", other),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Model and history settings, resolved from env vars and CLI flags with the defaults above.
+#[derive(Debug, Clone)]
+struct ModelConfig {
+    chat_model: String,
+    vision_model: String,
+    /// `AIRC_VISION_DETAIL`: image detail requested from the vision model.
+    vision_detail: ImageDetail,
+    /// `AIRC_VISION_PROMPT` / `AIRC_VISION_PROMPT_FILE`: what to ask about each screenshot.
+    vision_prompt: String,
+    /// `AIRC_ATTACH_SCREENSHOTS`: whether the chat model sees screenshots themselves.
+    screenshot_mode: vision::ScreenshotMode,
+    max_depth: usize,
+}
+
+impl ModelConfig {
+    fn resolve(cli: &CliArgs) -> Result<Self> {
+        let chat_model = cli.model.clone()
+            .or_else(|| env::var("AIRC_CHAT_MODEL").ok())
+            .unwrap_or_else(|| DEFAULT_CHAT_MODEL.to_string());
+        let vision_model = env::var("AIRC_VISION_MODEL").unwrap_or_else(|_| DEFAULT_VISION_MODEL.to_string());
+        let max_depth = match env::var("AIRC_MAX_DEPTH") {
+            Ok(raw) => raw.trim().parse::<usize>()
+                .with_context(|| format!("AIRC_MAX_DEPTH must be a positive integer, got '{}'", raw))?,
+            Err(_) => DEFAULT_MAX_CONVERSATION_DEPTH,
+        };
+        // The system prompt plus at least one message must always survive trimming
+        if max_depth < 2 {
+            anyhow::bail!("AIRC_MAX_DEPTH must be at least 2, got {}", max_depth);
+        }
+        let vision_detail = vision::detail_from_env()?;
+        let vision_prompt = vision::prompt_from_env()?;
+        let screenshot_mode = vision::ScreenshotMode::from_env()?;
+        Ok(Self { chat_model, vision_model, vision_detail, vision_prompt, screenshot_mode, max_depth })
+    }
+}
+
+/// Builds the chat client, pointing it at an OpenAI-compatible endpoint (Ollama, LM Studio, vLLM,
+/// Gemini's `/v1beta/openai/`, ...) when `--api-base` or `AIRC_API_BASE` is set.
+///
+/// The default OpenAI endpoint requires `OPENAI_API_KEY`; custom endpoints use it when present,
+/// since many local servers don't check the key at all.
+fn build_openai_client(cli: &CliArgs) -> Result<OpenAIClient<OpenAIConfig>> {
+    let api_key = env::var("OPENAI_API_KEY").ok();
+    let api_base = cli.api_base.clone().or_else(|| env::var("AIRC_API_BASE").ok());
+
+    match api_base {
+        Some(api_base) => {
+            info!("Using OpenAI-compatible API base: {}", api_base);
+            let config = OpenAIConfig::new()
+                .with_api_base(api_base)
+                .with_api_key(api_key.unwrap_or_default());
+            Ok(OpenAIClient::with_config(config))
+        }
+        None => {
+            if api_key.is_none() {
+                anyhow::bail!("OPENAI_API_KEY environment variable not set.");
+            }
+            Ok(OpenAIClient::new())
+        }
+    }
+}
+
+/// Heartbeat interval from `--ping-interval` or `AIRC_PING_INTERVAL_SECS`; `None` or 0 disables it.
+fn ping_interval(cli: &CliArgs) -> Result<Option<Duration>> {
+    let Some(raw) = cli.ping_interval.clone().or_else(|| env::var("AIRC_PING_INTERVAL_SECS").ok()) else {
+        return Ok(None);
+    };
+    let secs = raw.trim().parse::<u64>()
+        .with_context(|| format!("Ping interval must be a whole number of seconds, got '{}'", raw))?;
+    Ok((secs > 0).then(|| Duration::from_secs(secs)))
+}
+
+// Saves the history if a session file was requested. Failures are logged, never fatal.
+fn persist_session(cli: &CliArgs, history: &VecDeque<ChatCompletionRequestMessage>) {
+    if let Some(path) = &cli.session {
+        if let Err(e) = session::save_session(path, history) {
+            warn!("Failed to save session to {}: {:#}", path.display(), e);
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // i have to wait for the computer-use-model to become available. it is only allowed for 'select' devs
@@ -63,81 +194,34 @@ async fn run_gpt_computer_use() -> anyhow::Result<(), anyhow::Error> {
         .with_ansi(true)
         .init();
 
+    let cli = CliArgs::parse()?;
+
     // Load OpenAI API Key
     dotenv::dotenv().ok();
-    if env::var("OPENAI_API_KEY").is_err() {
-        anyhow::bail!("OPENAI_API_KEY environment variable not set.");
-    } 
-
-    // let gemini_key = dotenv::env::var("GEMINI_KEY").unwrap();
-
-    // let c = OpenAIConfig::new()
-    //     .with_api_base("https://generativelanguage.googleapis.com/v1beta/openai/")
-    //     .with_api_key("");
-
-    // let openai_client = OpenAIClient::with_config(c);
-    let openai_client = OpenAIClient::new();
+    let model_config = ModelConfig::resolve(&cli)?;
+    info!("Using chat model '{}', vision model '{}' ({:?} detail), max history depth {}.", model_config.chat_model, model_config.vision_model, model_config.vision_detail, model_config.max_depth);
+    let openai_client = build_openai_client(&cli)?;
+    let retry_policy = retry::RetryPolicy::from_env();
+    let mut vision_cache = vision::VisionCache::from_env();
 
     // --- Connect to MCP Server ---
-    info!("Connecting to MCP Server at {}...", MCP_SERVER_ADDR);
-    let stream = TcpSocket::new_v4()?
-        .connect(MCP_SERVER_ADDR.parse()?)
-        .await
-        .context(format!("Failed to connect to MCP server at {}", MCP_SERVER_ADDR))?;
-    info!("Connected to MCP Server.");
-
-    // Start the MCP client service
-    let mcp_client: RunningService<RoleClient, ()> = serve_client((), stream)
-        .await
-        .context("Failed to establish MCP client service (ensure 'client' feature is enabled for rmcp)")?;
-    let mcp_peer = mcp_client.peer().clone();
-
-    // --- Fetch Tools from MCP Server ---
-    info!("Fetching tools from MCP server...");
-    let mcp_tools_result = mcp_peer
-        .list_tools(None) // Use None for default options
-        .await
-        .context("Failed to list tools from MCP server")?;
-    info!("Available tools: {:#?}", mcp_tools_result.tools.iter().map(|t| &t.name).collect::<Vec<_>>());
-
-    // Convert MCP tools to OpenAI tool format
-    let openai_tools: Vec<ChatCompletionTool> = mcp_tools_result
-        .tools
-        .into_iter()
-        .map(|mcp_tool| {
-            // Schema Patching Logic
-            let parameters_value: Option<Value> = {
-                let needs_patch = mcp_tool.input_schema.get("properties").is_none();
-                if needs_patch {
-                    info!("Patching schema for parameterless tool: {}", mcp_tool.name);
-                    Some(json!({"type": "object", "properties": {}}))
-                } else {
-                    Some(Value::Object(mcp_tool.input_schema.as_ref().clone()))
-                }
-            };
-
-
-            ChatCompletionTool {
-                r#type: ChatCompletionToolType::Function,
-                function: FunctionObject {
-                    name: mcp_tool.name.to_string(),
-                    description: Some(mcp_tool.description.to_string()),
-                    parameters: parameters_value,
-                    strict: None,
-                },
-            }
-        })
-        .collect();
-
-    if openai_tools.is_empty() {
-         warn!("No tools with schemas found on the server. OpenAI cannot use tools.");
-    } else {
-         info!("{} tools converted for OpenAI.", openai_tools.len());
-    }
-
+    let server_specs = if cli.mcp_servers.is_empty() { vec![MCP_SERVER_ADDR.to_string()] } else { cli.mcp_servers.clone() };
+    let servers: Vec<(String, String)> = server_specs.iter().enumerate().map(|(i, spec)| mcp::parse_server_spec(spec, i)).collect();
+    let mcp = mcp::McpManager::connect(&servers).await?;
+    let confirm_policy = confirm::ConfirmPolicy::resolve(cli.yolo, &mcp).await;
+    let mut recapture_policy = recapture::RecapturePolicy::resolve(&mcp).await;
+    mcp.log_server_environments().await;
+    mcp.warn_if_desktop_unavailable().await;
+    let mut heartbeat = ping_interval(&cli)?.map(|interval| {
+        info!("Pinging the MCP server(s) every {:?} between turns.", interval);
+        mcp::Heartbeat::new(interval)
+    });
 
     // --- Main Interaction Loop ---
-    let mut conversation_history: VecDeque<ChatCompletionRequestMessage> = VecDeque::new();
+    // Resume a previous conversation if --session points at a valid saved file
+    let restored_history = cli.session.as_deref().and_then(session::load_session);
+    let history_restored = restored_history.is_some();
+    let mut conversation_history: VecDeque<ChatCompletionRequestMessage> = restored_history.unwrap_or_default();
     let system_prompt = r#"You are a helpful AI assistant designed to control the user's desktop via function calls.
 
     **Core Functionality:**
@@ -153,19 +237,17 @@ async fn run_gpt_computer_use() -> anyhow::Result<(), anyhow::Error> {
         * **Click:** `button: "Left", click_type: "Click"` (or omit `click_type`).
         * **Press & Hold:** `button: "Left", click_type: "Press"`.
         * **Release:** `button: "Left", click_type: "Release"`.
+    * **`activate_window`**: Bring a window found with `find_window` to the foreground (by its `id`) before clicking or typing in it.
+    * **`get_active_window`**: Check which window has focus before typing, so keystrokes don't land in the wrong window.
     * **`keyboard_action`**: Types text or simulates key presses (like Enter, Ctrl+C).
+    * **`reset_input`**: Call this if you suspect a stuck key or mouse button (e.g. unexpected capitals, clicks acting like drags), or after an interrupted drag.
     * **`run_shell_command`**: Executes commands like opening applications (e.g., `command: "notepad"`).
+    * **`ocr_screen`**: Reads on-screen text with bounding boxes and click-ready center points. Prefer it over `capture_screen` when you only need to read or locate text.
     * **`capture_screen`**: Captures the screen. Use the resulting text description (which includes vision model analysis) for subsequent analysis or actions. Do not attempt to interpret the base64 data directly.
 
-    **Complex Actions (Example: Dragging a Window):**
-    1.  Use `find_window` to get the window's position (e.g., title bar coordinates `x`, `y`).
-    2.  Call `move_mouse` to position the cursor on the title bar (e.g., `x`, `y + 10`).
-    3.  Call `wait(duration_ms=150)` to ensure the cursor is settled.
-    4.  Call `mouse_action` with `button: "Left", click_type: "Press"` to grab the title bar.
-    5.  Call `wait(duration_ms=100)` to ensure the press is registered.
-    6.  Call `move_mouse` to the *new* desired window position (e.g., `new_x`, `new_y + 10`).
-    7.  Call `wait(duration_ms=100)` to ensure the move is complete.
-    8.  Call `mouse_action` with `button: "Left", click_type: "Release"` to drop the window.
+    **Moving and Resizing Windows:**
+    * Use `move_window` (position only), `resize_window` (size only) or `set_window_bounds` (both) with the window's `id` from `find_window`. Don't drag title bars or borders.
+    * Check the returned `bounds`: the window manager may adjust the requested geometry.
 
     **Interaction:**
     * Ask for clarification if a request is ambiguous or requires information you don't have (e.g., "Where should I move the window?").
@@ -173,11 +255,15 @@ async fn run_gpt_computer_use() -> anyhow::Result<(), anyhow::Error> {
     * Report any errors encountered during tool execution."#.to_string();
 
 
-    // Add initial system message
-    conversation_history.push_back(ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage{
-        content: ChatCompletionRequestSystemMessageContent::Text(system_prompt.clone()), 
-        name: None
-    }));
+    // Add initial system message (a restored session already starts with one)
+    if !history_restored {
+        conversation_history.push_back(ChatCompletionRequestMessage::System(ChatCompletionRequestSystemMessage{
+            content: ChatCompletionRequestSystemMessageContent::Text(system_prompt.clone()), 
+            name: None
+        }));
+    } else {
+        info!("Resuming session with {} messages of history.", conversation_history.len());
+    }
 
 
     loop { // Outer loop (user input)
@@ -193,6 +279,7 @@ async fn run_gpt_computer_use() -> anyhow::Result<(), anyhow::Error> {
         if user_input.is_empty() {
             continue;
         }
+        recapture_policy.start_turn();
 
         // Add User message directly
         conversation_history.push_back(ChatCompletionRequestMessage::User(
@@ -205,52 +292,27 @@ async fn run_gpt_computer_use() -> anyhow::Result<(), anyhow::Error> {
         // --- Call OpenAI Loop (Handles potential multi-step tool calls) ---
         loop { // Inner loop (OpenAI calls)
 
-            // --- Trim History ---
-            // Keep the system prompt (index 0) and trim older messages from index 1 if history exceeds max depth
-            while conversation_history.len() > MAX_CONVERSATION_DEPTH {
-                if conversation_history.len() >= 2 { // Ensure System prompt + one other exists
-                    info!("Trimming history: Removing message at index 1. Current length: {}", conversation_history.len());
-                    // *** Add logging to see what's being removed ***
-                    let removed_message_role = match conversation_history.get(1) {
-                        Some(ChatCompletionRequestMessage::User(_)) => "User",
-                        Some(ChatCompletionRequestMessage::Assistant(_)) => "Assistant",
-                        Some(ChatCompletionRequestMessage::Tool(_)) => "Tool",
-                        Some(ChatCompletionRequestMessage::System(_)) => "System (Error!)", // Should not happen
-                        Some(ChatCompletionRequestMessage::Function(_)) => "Function (Error!)", // Should not happen
-                        Some(ChatCompletionRequestMessage::Developer(_)) => "Developer (Error!)", // Should not happen
-                        None => "None (Error!)",
-                   };
-
-                   info!(
-                        "Trimming history: Removing message at index 1 (Role: {}). Current length: {}",
-                        removed_message_role,
-                        conversation_history.len()
-                    );
-                    conversation_history.remove(1); // Remove oldest non-system message
-                } else {
-                    warn!("Attempted to trim history below 2 messages. Breaking trim loop.");
-                    break; // Should not happen if MAX_DEPTH >= 1
-                }
-            }
-            // --- End Trim History ---
-
-            // *** Defensive Check ***
-            if conversation_history.len() >= 2 {
-                if let Some(ChatCompletionRequestMessage::Tool(_)) = conversation_history.get(1) {
-                        // This should NOT happen with the current logic if VecDeque::remove(1) works as expected.
-                        error!("CRITICAL: History state invalid after trimming! Message at index 1 is Tool.");
-                        debug!("Invalid History State: {:#?}", conversation_history);
-                        // Handle this critical error, maybe break or return?
-                        println!("Internal error: Invalid conversation history state detected. Please report this.");
-                        break; // Break inner loop
+            // --- Liveness Check ---
+            // Catch a dropped MCP connection before the model plans tool calls against it
+            if let Some(heartbeat) = heartbeat.as_mut() {
+                if let Err(e) = heartbeat.check(&mcp).await {
+                    error!("{:#}", e);
+                    println!("Lost connection to the MCP server and could not reconnect. Check that it is running.");
+                    break; // Break inner loop
                 }
             }
+            let openai_tools = mcp.tools().await;
+
+            // --- Trim History ---
+            // Keep the system prompt (index 0) and drop the oldest messages, never splitting an
+            // assistant tool call from its results
+            history::trim_history(&mut conversation_history, model_config.max_depth);
 
             info!("Sending request to OpenAI chat model...");
             info!("Conversation History (len={}): {:#?}", conversation_history.len(), conversation_history); // Log length and content
 
             let request = CreateChatCompletionRequest {
-                model: OPENAI_CHAT_MODEL.to_string(),
+                model: model_config.chat_model.clone(),
                 messages: conversation_history.iter().cloned().collect(), // Use current trimmed history
                 tools: if openai_tools.is_empty() { None } else { Some(openai_tools.clone()) },
                 tool_choice: if openai_tools.is_empty() { None } else { Some(ChatCompletionToolChoiceOption::Auto) },
@@ -260,10 +322,10 @@ async fn run_gpt_computer_use() -> anyhow::Result<(), anyhow::Error> {
             };
 
             // --- Stream Handling ---
-            let mut stream = match openai_client.chat().create_stream(request).await {
+            let mut stream = match retry::create_stream_with_retry(&openai_client, request, &retry_policy).await {
                 Ok(s) => s,
                 Err(e) => {
-                    error!("OpenAI API stream creation error: {}", e);
+                    error!("OpenAI API stream creation error (giving up): {}", e);
                     // Handle error appropriately (e.g., print message, break inner loop)
                      match e {
                          OpenAIError::ApiError(api_error) => { error!("--> API Error Details: {:#?}", api_error); }
@@ -355,12 +417,17 @@ async fn run_gpt_computer_use() -> anyhow::Result<(), anyhow::Error> {
                 ..Default::default() // Use default for other fields like name, refusal, audio
             };
             conversation_history.push_back(ChatCompletionRequestMessage::Assistant(assistant_message));
+            // A message with tool calls is saved together with their results further down; saved
+            // on its own, an interrupted run would leave a history the API rejects
+            if final_tool_calls.is_empty() {
+                persist_session(&cli, &conversation_history);
+            }
 
             // --- Handle Tool Calls (Parallel Execution) ---
             if !final_tool_calls.is_empty() {
                 info!("Executing {} tool call(s) in parallel...", final_tool_calls.len());
 
-                let mut tool_tasks: Vec<JoinHandle<(String, String, Result<rmcp::model::CallToolResult, rmcp::ServiceError>)>> = Vec::new();
+                let mut tool_tasks: Vec<JoinHandle<(String, String, Result<rmcp::model::CallToolResult>)>> = Vec::new();
 
                 for tool_call in final_tool_calls {
                     let call_id = tool_call.id.clone();
@@ -387,16 +454,27 @@ async fn run_gpt_computer_use() -> anyhow::Result<(), anyhow::Error> {
                         }
                     };
 
+                    // Ask the operator before dangerous calls; a declined call still needs a result for its call_id
+                    if confirm_policy.requires_confirmation(&tool_name) && !confirm_policy.ask(&tool_name, &arguments_str) {
+                        info!("Operator declined tool '{}' (call_id: {})", tool_name, call_id);
+                        let declined = json!({ "status": "error", "message": format!("User declined to run tool '{}'. Do not retry it unless the user asks.", tool_name) });
+                        conversation_history.push_back(ChatCompletionRequestMessage::Tool(ChatCompletionRequestToolMessage{
+                            tool_call_id: call_id.clone(),
+                            content: ChatCompletionRequestToolMessageContent::Text(declined.to_string())
+                        }));
+                        continue; // Skip spawning task for this declined call
+                    }
+
                     info!("Spawning task for MCP tool '{}' (call_id: {}) with args: {:#?}", tool_name, call_id, arguments_map);
 
-                    let mcp_peer_clone = mcp_peer.clone();
+                    let mcp_clone = mcp.clone();
                     let mcp_request = CallToolRequestParam { name: tool_name.clone().into(), arguments: arguments_map };
                     let call_id_clone = call_id.clone();
                     let tool_name_clone = tool_name.clone(); // Clone tool_name for the task
 
                     // Spawn the MCP tool call task
                     tool_tasks.push(tokio::spawn(async move {
-                        let result = mcp_peer_clone.call_tool(mcp_request).await;
+                        let result = mcp_clone.call_tool(mcp_request).await;
                         (call_id_clone, tool_name_clone, result) // Return call_id, tool_name, result
                     }));
                 }
@@ -405,53 +483,55 @@ async fn run_gpt_computer_use() -> anyhow::Result<(), anyhow::Error> {
                 let task_results = join_all(tool_tasks).await;
                 // Use a temporary vec to store results before adding to history to avoid borrowing issues
                 let mut tool_message_results = Vec::new();
+                // Position in tool_message_results and tool name of the last failed input action
+                let mut failed_input: Option<(usize, String)> = None;
+                // (call id, data URL) of screenshots to show the chat model itself
+                let mut attachments: Vec<(String, String)> = Vec::new();
 
                 // Process results and add Tool messages to history
                 for task_result in task_results {
                     match task_result {
                         Ok((call_id, tool_name, mcp_call_result)) => {
+                            let input_failed = recapture_policy.is_failed_input(&tool_name, &mcp_call_result);
+                            let error_category = mcp::error_category(&mcp_call_result);
                             // Process the result in an async block to allow calling analyze_image_with_vision
                             let tool_result_content_str = async {
                                 match mcp_call_result {
                                     Ok(mcp_result_data) => {
-                                        info!("MCP tool '{}' (call_id: '{}') executed successfully.", tool_name, call_id);
+                                        // is_error results carry a message the model can recover from; pass them through as-is
+                                        if mcp_result_data.is_error == Some(true) {
+                                            warn!("MCP tool '{}' (call_id: '{}') reported an error (category: {}).", tool_name, call_id, error_category.as_deref().unwrap_or("unknown"));
+                                        } else {
+                                            info!("MCP tool '{}' (call_id: '{}') executed successfully.", tool_name, call_id);
+                                        }
+                                        // Any screenshot (image content or base64 JSON) goes through the vision model
+                                        if let Some(screenshot) = mcp::screenshot(&mcp_result_data) {
+                                            info!("Processing screenshot from {} (call_id: {})...", tool_name, call_id);
+                                            let mode = model_config.screenshot_mode;
+                                            let description = if mode.describes() {
+                                                describe_screenshot(&openai_client, &model_config, &mut vision_cache, &screenshot, &call_id).await
+                                            } else {
+                                                "Screenshot captured; it is attached to the conversation after the tool results.".to_string()
+                                            };
+                                            if mode.attaches() {
+                                                attachments.push((call_id.clone(), screenshot.data_url()));
+                                            }
+                                            return match screenshot.metadata.as_ref().and_then(|m| m.get("elements")) {
+                                                Some(elements) => format!("{}\n\nMarked elements (box id -> label and absolute coordinates):\n{}", description, elements),
+                                                None => description,
+                                            };
+                                        }
                                         match mcp_result_data.content.into_iter().next() {
                                             Some(content) => match content.raw {
-                                                RawContent::Text(raw_text) => {
-                                                    // <<< Check if it was capture_screen >>>
-                                                    if tool_name == "capture_screen" {
-                                                        info!("Processing capture_screen result (call_id: {})...", call_id);
-                                                        match serde_json::from_str::<Value>(&raw_text.text) {
-                                                            Ok(json_val) => {
-                                                                if let Some(base64_data) = json_val.get("base64_data").and_then(|v| v.as_str()) {
-                                                                    let vision_prompt = "Describe this screenshot in detail, focusing on visible text, UI elements, and overall layout.".to_string();
-                                                                    // Call vision analysis
-                                                                    match analyze_image_with_vision(&openai_client, vision_prompt, base64_data).await {
-                                                                        Ok(desc) => { info!("Vision analysis successful for call_id: {}", call_id); desc }
-                                                                        Err(e) => { error!("Vision analysis failed for call_id '{}': {}", call_id, e); format!("Screenshot captured but vision analysis failed: {}", e) }
-                                                                    }
-                                                                } else {
-                                                                    warn!("capture_screen JSON missing 'base64_data' for call_id: {}", call_id);
-                                                                    raw_text.text // Return raw JSON if no base64
-                                                                }
-                                                            }
-                                                            Err(e) => {
-                                                                warn!("Failed to parse capture_screen JSON for call_id '{}': {}. Returning raw text.", call_id, e);
-                                                                raw_text.text // Return raw text if parse fails
-                                                            }
-                                                        }
-                                                    } else {
-                                                        raw_text.text // Return text for other tools
-                                                    }
-                                                }
+                                                RawContent::Text(raw_text) => raw_text.text,
                                                 _ => format!("Tool '{}' (call_id: '{}') returned non-text content.", tool_name, call_id),
                                             },
                                             None => format!("Tool '{}' (call_id: '{}') returned no content.", tool_name, call_id),
                                         }
                                     }
                                     Err(e) => {
-                                        error!("MCP tool '{}' (call_id: '{}') failed: {}", tool_name, call_id, e);
-                                        json!({ "status": "error", "message": format!("Failed MCP execution for tool '{}' (call_id: '{}'): {}", tool_name, call_id, e) }).to_string()
+                                        error!("MCP tool '{}' (call_id: '{}') failed (category: {}): {:#}", tool_name, call_id, error_category.as_deref().unwrap_or("unknown"), e);
+                                        json!({ "status": "error", "message": format!("Failed MCP execution for tool '{}' (call_id: '{}'): {:#}", tool_name, call_id, e) }).to_string()
                                     }
                                 }
                             }.await; // Await the async block processing the result
@@ -461,6 +541,9 @@ async fn run_gpt_computer_use() -> anyhow::Result<(), anyhow::Error> {
                                 tool_call_id: call_id,
                                 content: ChatCompletionRequestToolMessageContent::Text(tool_result_content_str)
                             }));
+                            if input_failed {
+                                failed_input = Some((tool_message_results.len() - 1, tool_name));
+                            }
                         }
                         Err(join_err) => {
                             error!("Tool execution task failed to join: {}", join_err);
@@ -469,11 +552,47 @@ async fn run_gpt_computer_use() -> anyhow::Result<(), anyhow::Error> {
                     }
                 }
 
+                // After a failed input action, show the model the screen as it is now so it
+                // re-plans from there instead of from its last (stale) screenshot
+                if let Some((index, tool_name)) = failed_input.filter(|_| recapture_policy.take()) {
+                    info!("Input action '{}' failed; capturing the screen for the model.", tool_name);
+                    let screenshot = match recapture::capture_tool_for(&mcp, &tool_name).await {
+                        Some(capture_tool) => match mcp.call_tool(CallToolRequestParam { name: capture_tool.into(), arguments: None }).await {
+                            Ok(result) => mcp::screenshot(&result),
+                            Err(e) => { warn!("Screenshot after the failed '{}' call failed: {:#}", tool_name, e); None }
+                        },
+                        None => { warn!("The server providing '{}' has no capture_screen tool; not recapturing.", tool_name); None }
+                    };
+                    if let Some(screenshot) = screenshot {
+                        let mode = model_config.screenshot_mode;
+                        let description = if mode.describes() {
+                            describe_screenshot(&openai_client, &model_config, &mut vision_cache, &screenshot, "recapture").await
+                        } else {
+                            "It is attached to the conversation after the tool results.".to_string()
+                        };
+                        if let ChatCompletionRequestMessage::Tool(ChatCompletionRequestToolMessage { tool_call_id, content: ChatCompletionRequestToolMessageContent::Text(text) }) = &mut tool_message_results[index] {
+                            text.push_str(&format!("\n\nThe action failed; this is the screen right now (re-plan from it):\n{}", description));
+                            if mode.attaches() {
+                                attachments.push((tool_call_id.clone(), screenshot.data_url()));
+                            }
+                        }
+                    }
+                }
+
                 // *** Add the collected tool results to the main history ***
                 info!("Adding {} tool result messages to history.", tool_message_results.len());
+                if !attachments.is_empty() {
+                    history::drop_old_screenshots(&mut conversation_history);
+                }
                 for msg in tool_message_results {
                     conversation_history.push_back(msg);
                 }
+                // Tool messages can only carry text, so the images follow them as a user message
+                if !attachments.is_empty() {
+                    info!("Attaching {} screenshot(s) for the chat model.", attachments.len());
+                    conversation_history.push_back(screenshot_message(attachments, model_config.vision_detail.clone()));
+                }
+                persist_session(&cli, &conversation_history);
 
                 // After processing all tool results, continue the inner loop to send them back
                 continue;
@@ -494,26 +613,71 @@ async fn run_gpt_computer_use() -> anyhow::Result<(), anyhow::Error> {
     } // End main user input loop
 }
 
+/// A user message showing the chat model the screenshots returned by the given tool calls.
+fn screenshot_message(attachments: Vec<(String, String)>, detail: ImageDetail) -> ChatCompletionRequestMessage {
+    let mut parts = Vec::with_capacity(attachments.len() * 2);
+    for (call_id, data_url) in attachments {
+        parts.push(ChatCompletionRequestUserMessageContentPart::Text(ChatCompletionRequestMessageContentPartText {
+            text: format!("Screenshot from tool call {}:", call_id),
+        }));
+        parts.push(ChatCompletionRequestUserMessageContentPart::ImageUrl(ChatCompletionRequestMessageContentPartImage {
+            image_url: ImageUrl { url: data_url, detail: Some(detail.clone()) },
+        }));
+    }
+    ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+        content: ChatCompletionRequestUserMessageContent::Array(parts),
+        name: None,
+    })
+}
+
+/// Describes a screenshot with the vision model, unless this exact screen was already described.
+/// A failed analysis is reported in the returned text, which goes to the chat model either way.
+async fn describe_screenshot<C: Config>(
+    client: &OpenAIClient<C>,
+    model_config: &ModelConfig,
+    cache: &mut vision::VisionCache,
+    screenshot: &mcp::Screenshot,
+    call_id: &str,
+) -> String {
+    let prompt = vision::screenshot_prompt(&model_config.vision_prompt, screenshot.annotated);
+    let cache_key = vision::VisionCache::key(&screenshot.base64, &prompt, &model_config.vision_model);
+    if let Some(cached) = cache.get(cache_key) {
+        info!("Screen unchanged; reusing the cached vision description for call_id: {}", call_id);
+        return cached;
+    }
+    match analyze_image_with_vision(client, &model_config.vision_model, model_config.vision_detail.clone(), prompt, &screenshot.data_url()).await {
+        Ok(description) => {
+            info!("Vision analysis successful for call_id: {}", call_id);
+            cache.insert(cache_key, description.clone());
+            description
+        }
+        Err(e) => {
+            error!("Vision analysis failed for call_id '{}': {}", call_id, e);
+            format!("Screenshot captured but vision analysis failed: {}", e)
+        }
+    }
+}
+
 // Vision analysis function (remains the same)
 async fn analyze_image_with_vision<C: Config>(
     client: &OpenAIClient<C>, // Use Client<C>
+    model: &str,
+    detail: ImageDetail,
     prompt: String,
-    base64_image: &str,
+    data_url: &str,
 ) -> Result<String> {
-    info!("Calling vision model '{}'...", OPENAI_VISION_MODEL);
-
-    let data_url = format!("data:image/png;base64,{}", base64_image);
+    info!("Calling vision model '{}' ({:?} detail)...", model, detail);
 
     // Create the request message with text and image parts
     let request_message = ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
         content: ChatCompletionRequestUserMessageContent::Array(vec![
-            async_openai::types::ChatCompletionRequestUserMessageContentPart::Text(ChatCompletionRequestMessageContentPartText {
+            ChatCompletionRequestUserMessageContentPart::Text(ChatCompletionRequestMessageContentPartText {
                 text: prompt,
             }),
-            async_openai::types::ChatCompletionRequestUserMessageContentPart::ImageUrl(ChatCompletionRequestMessageContentPartImage {
+            ChatCompletionRequestUserMessageContentPart::ImageUrl(ChatCompletionRequestMessageContentPartImage {
                 image_url: ImageUrl {
-                    url: data_url,
-                    detail: Some(ImageDetail::Auto), // Or High / Low
+                    url: data_url.to_string(),
+                    detail: Some(detail),
                 },
             }),
         ]),
@@ -522,7 +686,7 @@ async fn analyze_image_with_vision<C: Config>(
 
     // Create the chat completion request for the vision model
     let request = CreateChatCompletionRequest {
-        model: OPENAI_VISION_MODEL.to_string(),
+        model: model.to_string(),
         messages: vec![request_message],
         ..Default::default()
     };
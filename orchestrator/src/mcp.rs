@@ -0,0 +1,474 @@
+use anyhow::{Context, Result};
+use async_openai::types::{ChatCompletionTool, ChatCompletionToolType, FunctionObject};
+use rmcp::{
+    handler::client::ClientHandler,
+    model::{CallToolRequestParam, CallToolResult, ProgressNotificationParam, RawContent},
+    service::{Peer, RoleClient, RunningService},
+    serve_client, ServiceError,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::{net::TcpStream, sync::RwLock};
+use tracing::{info, warn};
+
+// A ping that takes longer than this is treated as a dead connection
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Client-side handler that logs the progress notifications servers send for long-running
+/// tools, so the operator can tell a slow call from a hung one.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressLogger {
+    peer: Option<Peer<RoleClient>>,
+}
+
+impl ClientHandler for ProgressLogger {
+    fn get_peer(&self) -> Option<Peer<RoleClient>> {
+        self.peer.clone()
+    }
+
+    fn set_peer(&mut self, peer: Peer<RoleClient>) {
+        self.peer = Some(peer);
+    }
+
+    async fn on_progress(&self, params: ProgressNotificationParam) {
+        match params.total {
+            Some(total) => info!("Tool still running: {}/{}", params.progress, total),
+            None => info!("Tool still running: {}", params.progress),
+        }
+    }
+}
+
+/// A live MCP client session together with the tools the server advertised on connect.
+pub struct McpConnection {
+    addr: String,
+    client: RunningService<RoleClient, ProgressLogger>,
+    /// Bumped on every reconnect so concurrent callers can tell whether someone else already
+    /// replaced the session they saw fail.
+    generation: u64,
+    /// Server tools converted to the OpenAI function-calling format.
+    pub tools: Vec<ChatCompletionTool>,
+}
+
+impl McpConnection {
+    /// Connects to the MCP server over TCP, starts the client service and lists its tools.
+    pub async fn connect(addr: &str) -> Result<Self> {
+        info!("Connecting to MCP Server at {}...", addr);
+        // Resolves hostnames and IPv6 literals, trying each resolved address in turn
+        let stream = TcpStream::connect(addr)
+            .await
+            .context(format!("Failed to connect to MCP server at {}", addr))?;
+        info!("Connected to MCP Server.");
+
+        // Start the MCP client service
+        let client: RunningService<RoleClient, ProgressLogger> = serve_client(ProgressLogger::default(), stream)
+            .await
+            .context("Failed to establish MCP client service (ensure 'client' feature is enabled for rmcp)")?;
+
+        // --- Fetch Tools from MCP Server ---
+        info!("Fetching tools from MCP server...");
+        let mcp_tools_result = client
+            .list_tools(None) // Use None for default options
+            .await
+            .context("Failed to list tools from MCP server")?;
+        info!("Available tools: {:#?}", mcp_tools_result.tools.iter().map(|t| &t.name).collect::<Vec<_>>());
+
+        // Convert MCP tools to OpenAI tool format
+        let tools: Vec<ChatCompletionTool> = mcp_tools_result
+            .tools
+            .into_iter()
+            .map(|mcp_tool| ChatCompletionTool {
+                r#type: ChatCompletionToolType::Function,
+                function: FunctionObject {
+                    name: mcp_tool.name.to_string(),
+                    description: Some(mcp_tool.description.to_string()),
+                    // Parameterless tools already advertise `{"type": "object", "properties": {}}`
+                    parameters: Some(Value::Object(mcp_tool.input_schema.as_ref().clone())),
+                    strict: None,
+                },
+            })
+            .collect();
+
+        if tools.is_empty() {
+            warn!("No tools with schemas found on the server. OpenAI cannot use tools.");
+        } else {
+            info!("{} tools converted for OpenAI.", tools.len());
+        }
+
+        Ok(Self { addr: addr.to_string(), client, generation: 0, tools })
+    }
+
+    pub fn peer(&self) -> &Peer<RoleClient> {
+        self.client.peer()
+    }
+
+    /// Calls the server's `ping` tool. Any MCP-level reply counts as alive (an older server
+    /// without `ping` answers with a tool error); transport failures and timeouts do not.
+    pub async fn ping(&self) -> Result<()> {
+        let request = CallToolRequestParam { name: "ping".into(), arguments: None };
+        match tokio::time::timeout(PING_TIMEOUT, self.peer().call_tool(request)).await {
+            Ok(Ok(_)) | Ok(Err(ServiceError::McpError(_))) => Ok(()),
+            Ok(Err(e)) => Err(anyhow::anyhow!(e).context("MCP ping failed")),
+            Err(_) => anyhow::bail!("MCP ping timed out after {:?}", PING_TIMEOUT),
+        }
+    }
+
+    /// Replaces this session with a fresh connection to the same address, re-listing tools
+    /// since a restarted server may expose a different set.
+    pub async fn reconnect(&mut self) -> Result<()> {
+        let mut fresh = Self::connect(&self.addr).await?;
+        fresh.generation = self.generation + 1;
+        let stale = std::mem::replace(self, fresh);
+        // The old transport is most likely already gone; just make sure its task is stopped
+        if let Err(e) = stale.client.cancel().await {
+            warn!("Failed to shut down previous MCP session cleanly: {}", e);
+        }
+        info!("Reconnected to MCP Server at {} ({} tools).", self.addr, self.tools.len());
+        Ok(())
+    }
+}
+
+/// Optional periodic liveness check, run between turns.
+///
+/// Enabled with `--ping-interval <secs>` or `AIRC_PING_INTERVAL_SECS`; disabled by default.
+#[derive(Debug)]
+pub struct Heartbeat {
+    interval: Duration,
+    last_check: Instant,
+}
+
+impl Heartbeat {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, last_check: Instant::now() }
+    }
+
+    /// Pings every server if the interval has elapsed since the last check, reconnecting those
+    /// whose ping fails. Returns an error only if reconnecting also fails.
+    pub async fn check(&mut self, manager: &McpManager) -> Result<()> {
+        if self.last_check.elapsed() < self.interval {
+            return Ok(());
+        }
+        self.last_check = Instant::now();
+
+        for server in manager.servers.iter() {
+            let (result, generation) = {
+                let connection = server.connection.read().await;
+                (connection.ping().await, connection.generation)
+            };
+            if let Err(e) = result {
+                warn!("MCP connection to '{}' looks dead ({:#}). Reconnecting...", server.name, e);
+                server.reconnect_if_stale(generation).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Joins a server name and tool name for tools that exist on more than one server. OpenAI
+/// function names only allow `[a-zA-Z0-9_-]`, so a `.` can't be used here.
+pub const NAMESPACE_SEPARATOR: &str = "__";
+
+/// The tool name without its server namespace, for matching on specific tools regardless of
+/// which server provides them.
+pub fn unqualified(name: &str) -> &str {
+    name.split_once(NAMESPACE_SEPARATOR).map(|(_, tool)| tool).unwrap_or(name)
+}
+
+/// The `category` a server attached to a failed call ("coordinate", "not_found", "backend",
+/// ...), read from the tool's error result or from the protocol error's data.
+pub fn error_category(result: &Result<CallToolResult>) -> Option<String> {
+    match result {
+        Ok(result) if result.is_error == Some(true) => result.content.iter().find_map(|content| match &content.raw {
+            RawContent::Text(text) => serde_json::from_str::<Value>(&text.text).ok()?.get("category")?.as_str().map(String::from),
+            _ => None,
+        }),
+        Ok(_) => None,
+        Err(e) => match e.downcast_ref::<ServiceError>()? {
+            ServiceError::McpError(error) => error.data.as_ref()?.get("category")?.as_str().map(String::from),
+            _ => None,
+        },
+    }
+}
+
+/// A screenshot carried by a tool result.
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    /// Base64 image data.
+    pub base64: String,
+    /// "image/png" or "image/jpeg".
+    pub mime_type: String,
+    /// Whether it is the set-of-marks copy with numbered boxes.
+    pub annotated: bool,
+    /// The JSON item next to the image (size, marked elements, ...), if any.
+    pub metadata: Option<Value>,
+}
+
+impl Screenshot {
+    /// The image as a `data:` URL for the OpenAI image inputs.
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, self.base64)
+    }
+}
+
+// Formats a vision model accepts; others (e.g. GIF bursts) aren't screenshots it can take
+fn screenshot_mime_type(format: &str) -> Option<&'static str> {
+    match format {
+        "png" | "image/png" => Some("image/png"),
+        "jpeg" | "image/jpeg" => Some("image/jpeg"),
+        _ => None,
+    }
+}
+
+/// The PNG or JPEG screenshot in a tool result, preferring the annotated copy: MCP image
+/// content (with its JSON metadata item), or the `base64_data` / `annotated_base64_data`
+/// fields of tools and servers that still return a single JSON item.
+pub fn screenshot(result: &CallToolResult) -> Option<Screenshot> {
+    let metadata = result.content.iter().find_map(|content| match &content.raw {
+        RawContent::Text(text) => serde_json::from_str::<Value>(&text.text).ok(),
+        _ => None,
+    });
+    let images: Vec<(&str, &'static str)> = result.content.iter().filter_map(|content| match &content.raw {
+        RawContent::Image(image) => Some((image.data.as_str(), screenshot_mime_type(&image.mime_type)?)),
+        _ => None,
+    }).collect();
+    let annotated_listed = metadata.as_ref().and_then(|m| m.get("annotated")).and_then(Value::as_bool).unwrap_or(false);
+    let (base64, mime_type, annotated) = match images.as_slice() {
+        // The annotated copy follows the plain screenshot
+        [_, (annotated, mime_type), ..] if annotated_listed => (annotated.to_string(), *mime_type, true),
+        [(image, mime_type), ..] => (image.to_string(), *mime_type, false),
+        [] => {
+            let mime_type = screenshot_mime_type(metadata.as_ref()?.get("format")?.as_str()?)?;
+            let field = |name: &str| metadata.as_ref()?.get(name)?.as_str().map(String::from);
+            match field("annotated_base64_data") {
+                Some(annotated) => (annotated, mime_type, true),
+                None => (field("base64_data")?, mime_type, false),
+            }
+        }
+    };
+    Some(Screenshot { base64, mime_type: mime_type.to_string(), annotated, metadata })
+}
+
+/// Parses a `--mcp-server` value: `addr` or `name=addr`. Unnamed servers are called
+/// `server1`, `server2`, ... by position.
+pub fn parse_server_spec(spec: &str, position: usize) -> (String, String) {
+    match spec.split_once('=') {
+        Some((name, addr)) => (name.trim().to_string(), addr.trim().to_string()),
+        None => (format!("server{}", position + 1), spec.trim().to_string()),
+    }
+}
+
+// One configured server and its (replaceable) session
+struct McpServer {
+    name: String,
+    connection: RwLock<McpConnection>,
+}
+
+impl McpServer {
+    /// Reconnects unless another caller already replaced the session from `generation`.
+    async fn reconnect_if_stale(&self, generation: u64) -> Result<()> {
+        let mut connection = self.connection.write().await;
+        if connection.generation != generation {
+            return Ok(());
+        }
+        connection
+            .reconnect()
+            .await
+            .with_context(|| format!("Failed to reconnect to MCP server '{}' at {}", self.name, connection.addr))
+    }
+
+    /// Calls a tool, and if the connection turns out to be closed, reconnects (refreshing the
+    /// tool list) and retries the call once.
+    async fn call_tool(&self, request: CallToolRequestParam) -> Result<CallToolResult> {
+        let (peer, generation) = {
+            let connection = self.connection.read().await;
+            (connection.peer().clone(), connection.generation)
+        };
+        let error = match peer.call_tool(request.clone()).await {
+            Ok(result) => return Ok(result),
+            Err(e) if is_connection_lost(&e) => e,
+            Err(e) => return Err(e.into()),
+        };
+
+        warn!("MCP connection lost while calling '{}' ({}). Reconnecting and retrying once...", request.name, error);
+        self.reconnect_if_stale(generation)
+            .await
+            .with_context(|| format!("Tool '{}' failed because the MCP connection was lost ({})", request.name, error))?;
+
+        let peer = self.connection.read().await.peer().clone();
+        peer.call_tool(request.clone())
+            .await
+            .with_context(|| format!("Tool '{}' failed again after reconnecting to the MCP server", request.name))
+    }
+}
+
+/// Shared handle to one or more MCP servers that transparently reconnects when a stream drops.
+///
+/// Tools of all servers are offered to the model as one list. A tool name that exists on
+/// several servers is namespaced as `<server>__<tool>` for each of them; unique names are kept
+/// as-is. Cloning is cheap; all clones (e.g. the parallel tool-call tasks) share the sessions.
+#[derive(Clone)]
+pub struct McpManager {
+    servers: Arc<Vec<McpServer>>,
+}
+
+// A closed TCP stream or stopped client service surfaces as a transport error on every call
+fn is_connection_lost(error: &ServiceError) -> bool {
+    matches!(error, ServiceError::Transport(_))
+}
+
+impl McpManager {
+    /// Connects to every `(name, addr)` server; fails if any of them can't be reached.
+    pub async fn connect(servers: &[(String, String)]) -> Result<Self> {
+        let mut connected = Vec::with_capacity(servers.len());
+        for (name, addr) in servers {
+            let connection = McpConnection::connect(addr)
+                .await
+                .with_context(|| format!("Failed to connect to MCP server '{}'", name))?;
+            connected.push(McpServer { name: name.clone(), connection: RwLock::new(connection) });
+        }
+        Ok(Self { servers: Arc::new(connected) })
+    }
+
+    // (exposed name, server index, tool) for every tool of every server, namespacing duplicates
+    async fn routed_tools(&self) -> Vec<(String, usize, ChatCompletionTool)> {
+        let mut all = Vec::new();
+        for (index, server) in self.servers.iter().enumerate() {
+            for tool in &server.connection.read().await.tools {
+                all.push((index, tool.clone()));
+            }
+        }
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for (_, tool) in &all {
+            *counts.entry(tool.function.name.clone()).or_default() += 1;
+        }
+        all.into_iter()
+            .map(|(index, tool)| {
+                let name = &tool.function.name;
+                let exposed = if counts[name] > 1 {
+                    format!("{}{}{}", self.servers[index].name, NAMESPACE_SEPARATOR, name)
+                } else {
+                    name.clone()
+                };
+                (exposed, index, tool)
+            })
+            .collect()
+    }
+
+    /// Tools of all servers, in OpenAI format with collision-free names. May change after a reconnect.
+    pub async fn tools(&self) -> Vec<ChatCompletionTool> {
+        self.routed_tools()
+            .await
+            .into_iter()
+            .map(|(exposed, _, mut tool)| {
+                tool.function.name = exposed;
+                tool
+            })
+            .collect()
+    }
+
+    /// The exposed name of `tool` on the server that provides the exposed tool `sibling`, or
+    /// `None` if that server doesn't offer `tool` (or nothing provides `sibling`).
+    pub async fn sibling_tool(&self, sibling: &str, tool: &str) -> Option<String> {
+        let routed = self.routed_tools().await;
+        let server = routed.iter().find(|(exposed, _, _)| exposed == sibling)?.1;
+        routed
+            .into_iter()
+            .find(|(_, index, candidate)| *index == server && candidate.function.name == tool)
+            .map(|(exposed, _, _)| exposed)
+    }
+
+    /// Calls the parameterless tool `tool` on every server that exposes it and returns each
+    /// server's name with the parsed JSON result. Servers that fail to answer are skipped with
+    /// a warning.
+    async fn query_servers(&self, tool: &str) -> Vec<(String, Value)> {
+        let mut results = Vec::new();
+        for server in self.servers.iter() {
+            let exposes_tool = server.connection.read().await.tools.iter().any(|t| t.function.name == tool);
+            if !exposes_tool {
+                continue;
+            }
+            let request = CallToolRequestParam { name: tool.to_string().into(), arguments: None };
+            match server.call_tool(request).await {
+                Ok(result) => {
+                    let json = result.content.into_iter().find_map(|content| match content.raw {
+                        RawContent::Text(text) => serde_json::from_str::<Value>(&text.text).ok(),
+                        _ => None,
+                    });
+                    results.push((server.name.clone(), json.unwrap_or(Value::Null)));
+                }
+                Err(e) => warn!("Failed to call '{}' on '{}': {:#}", tool, server.name, e),
+            }
+        }
+        results
+    }
+
+    /// Names of the tools every server's `get_tool_catalog` puts in the "destructive" category,
+    /// without namespaces. `None` if no server offers the catalog.
+    pub async fn destructive_tools(&self) -> Option<Vec<String>> {
+        self.catalog_tools("destructive").await
+    }
+
+    /// Names of the tools every server's `get_tool_catalog` puts in `category` ("input",
+    /// "destructive", ...), without namespaces. `None` if no server offers the catalog;
+    /// servers that fail to answer are skipped with a warning.
+    pub async fn catalog_tools(&self, category: &str) -> Option<Vec<String>> {
+        let mut matching = Vec::new();
+        let mut any_catalog = false;
+        for (server, catalog) in self.query_servers("get_tool_catalog").await {
+            let Some(tools) = catalog.get("tools").and_then(Value::as_array) else {
+                warn!("Tool catalog of '{}' has an unexpected shape; ignoring it.", server);
+                continue;
+            };
+            any_catalog = true;
+            matching.extend(
+                tools
+                    .iter()
+                    .filter(|tool| tool.get("category").and_then(Value::as_str) == Some(category))
+                    .filter_map(|tool| tool.get("name").and_then(Value::as_str).map(String::from)),
+            );
+        }
+        any_catalog.then_some(matching)
+    }
+
+    /// Logs each server's `get_server_environment` report, so logs attached to bug reports say
+    /// exactly what the servers ran on.
+    pub async fn log_server_environments(&self) {
+        for (server, report) in self.query_servers("get_server_environment").await {
+            match report.get("environment") {
+                Some(environment) => info!("MCP server '{}' environment: {}", server, environment),
+                None => warn!("MCP server '{}' returned an unexpected environment report: {}", server, report),
+            }
+        }
+    }
+
+    /// Asks every server that offers `get_session_state` whether its desktop can take input,
+    /// and warns the operator about locked screens and running screensavers, on which every
+    /// click and keystroke of the run would silently go nowhere.
+    pub async fn warn_if_desktop_unavailable(&self) {
+        for (server, state) in self.query_servers("get_session_state").await {
+            if state.get("input_reaches_desktop").and_then(Value::as_bool) != Some(false) {
+                continue;
+            }
+            let session = state.get("session");
+            let flag = |key: &str| session.and_then(|s| s.get(key)).and_then(Value::as_bool) == Some(true);
+            let reason = if flag("locked") { "the workstation is locked" } else { "the screensaver is running" };
+            warn!("On MCP server '{}' {}: input actions will have no effect until it is unlocked/woken.", server, reason);
+        }
+    }
+
+    /// Routes a call by its (possibly namespaced) tool name to the server that provides it.
+    pub async fn call_tool(&self, mut request: CallToolRequestParam) -> Result<CallToolResult> {
+        let route = self
+            .routed_tools()
+            .await
+            .into_iter()
+            .find(|(exposed, _, _)| *exposed == request.name)
+            .map(|(_, index, tool)| (index, tool.function.name));
+        let Some((index, original_name)) = route else {
+            anyhow::bail!("No connected MCP server provides a tool named '{}'", request.name);
+        };
+        request.name = original_name.into();
+        self.servers[index].call_tool(request).await
+    }
+}
@@ -0,0 +1,92 @@
+// Fresh screenshot after a failed input action, so the model re-plans from the current screen
+use rmcp::model::{CallToolResult, RawContent};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::mcp::{self, McpManager};
+
+const CAPTURE_TOOL: &str = "capture_screen";
+// Used when no server reports a tool catalog
+const DEFAULT_INPUT_TOOLS: &[&str] = &[
+    "mouse_action",
+    "move_mouse",
+    "keyboard_action",
+    "click_element",
+    "click_tray_icon",
+];
+
+/// When to attach a fresh screenshot to a failed input action's result.
+#[derive(Debug, Clone)]
+pub struct RecapturePolicy {
+    /// Tools whose failures trigger a recapture, without namespaces.
+    tools: Vec<String>,
+    /// Recaptures allowed per user request; 0 disables the policy.
+    max_per_turn: u32,
+    used: u32,
+}
+
+impl RecapturePolicy {
+    /// Reads `AIRC_RECAPTURE_ON_FAILURE`, the number of recaptures allowed per user request
+    /// (unset or 0 disables them). Input tools are the ones the servers' catalogs put in the
+    /// "input" category, or a built-in list if no server offers a catalog.
+    pub async fn resolve(mcp: &McpManager) -> Self {
+        let max_per_turn = match std::env::var("AIRC_RECAPTURE_ON_FAILURE") {
+            Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+                warn!("Ignoring invalid AIRC_RECAPTURE_ON_FAILURE value '{}'", raw);
+                0
+            }),
+            Err(_) => 0,
+        };
+        if max_per_turn == 0 {
+            return Self { tools: Vec::new(), max_per_turn, used: 0 };
+        }
+        let tools = match mcp.catalog_tools("input").await {
+            Some(tools) => tools,
+            None => DEFAULT_INPUT_TOOLS.iter().map(|t| t.to_string()).collect(),
+        };
+        info!("Failed input actions get a fresh screenshot (at most {} per request).", max_per_turn);
+        Self { tools, max_per_turn, used: 0 }
+    }
+
+    /// Resets the budget; called for each new user request.
+    pub fn start_turn(&mut self) {
+        self.used = 0;
+    }
+
+    /// Whether the call of `tool` (possibly namespaced) is an input action that failed: the
+    /// call itself failed, the tool reported an error, or it succeeded with a `warning` such as
+    /// the cursor missing its target. Malformed and denied calls don't count, since the screen
+    /// has nothing to do with them.
+    pub fn is_failed_input(&self, tool: &str, result: &anyhow::Result<CallToolResult>) -> bool {
+        if !self.tools.iter().any(|t| t == mcp::unqualified(tool)) {
+            return false;
+        }
+        if matches!(mcp::error_category(result).as_deref(), Some("invalid_params" | "permission")) {
+            return false;
+        }
+        match result {
+            Err(_) => true,
+            Ok(result) if result.is_error == Some(true) => true,
+            Ok(result) => result.content.iter().any(|content| match &content.raw {
+                RawContent::Text(text) => serde_json::from_str::<Value>(&text.text)
+                    .is_ok_and(|json| json.get("warning").is_some()),
+                _ => false,
+            }),
+        }
+    }
+
+    /// Takes one recapture from this request's budget; false once it is spent.
+    pub fn take(&mut self) -> bool {
+        if self.used >= self.max_per_turn {
+            return false;
+        }
+        self.used += 1;
+        true
+    }
+}
+
+/// The screenshot tool of the server that owns `tool` (an exposed, possibly namespaced name),
+/// under the name the model sees it by. `None` if that server has no screenshot tool.
+pub async fn capture_tool_for(mcp: &McpManager, tool: &str) -> Option<String> {
+    mcp.sibling_tool(tool, CAPTURE_TOOL).await
+}
@@ -0,0 +1,124 @@
+use async_openai::{
+    config::Config,
+    error::OpenAIError,
+    types::{ChatCompletionResponseStream, CreateChatCompletionRequest},
+    Client as OpenAIClient,
+};
+use futures::stream::{self, StreamExt};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// Exponential backoff settings for opening chat completion streams.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying.
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_retries: 4, base_delay: Duration::from_millis(500), max_delay: Duration::from_secs(30) }
+    }
+}
+
+impl RetryPolicy {
+    /// Default policy, with the retry count overridable via `AIRC_MAX_RETRIES`.
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        if let Ok(raw) = std::env::var("AIRC_MAX_RETRIES") {
+            match raw.trim().parse() {
+                Ok(n) => policy.max_retries = n,
+                Err(_) => warn!("Ignoring invalid AIRC_MAX_RETRIES value '{}'", raw),
+            }
+        }
+        policy
+    }
+
+    /// Delay before retry number `attempt` (0-based): exponential, capped, with up to 50% jitter
+    /// so parallel clients don't retry in lockstep.
+    fn delay(&self, attempt: u32) -> Duration {
+        let exp = self.base_delay.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_delay);
+        let half = exp / 2;
+        half + half.mul_f64(jitter_fraction())
+    }
+}
+
+// Cheap [0, 1) jitter source; cryptographic quality isn't needed for spreading retries
+fn jitter_fraction() -> f64 {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    let mixed = nanos.wrapping_mul(2_654_435_761);
+    (mixed % 1_000_000) as f64 / 1_000_000.0
+}
+
+fn is_retryable_status(status: u16) -> bool {
+    status == 408 || status == 409 || status == 429 || (500..600).contains(&status)
+}
+
+/// Whether a failed request is worth retrying: rate limits, server errors and network failures.
+/// Invalid requests, auth failures and exhausted quota fail fast.
+pub fn is_retryable(error: &OpenAIError) -> bool {
+    match error {
+        OpenAIError::ApiError(api_error) => {
+            let code = api_error.code.as_deref().unwrap_or_default();
+            let kind = api_error.r#type.as_deref().unwrap_or_default();
+            if code == "insufficient_quota" {
+                return false;
+            }
+            matches!(code, "rate_limit_exceeded" | "server_error")
+                || matches!(kind, "rate_limit_error" | "server_error" | "overloaded_error")
+        }
+        OpenAIError::Reqwest(req_err) => {
+            req_err.is_timeout()
+                || req_err.is_connect()
+                || req_err.status().is_some_and(|s| is_retryable_status(s.as_u16()))
+        }
+        // Streaming failures arrive as text, e.g. "Invalid status code: 429 Too Many Requests"
+        // or "Transport error: ..."
+        OpenAIError::StreamError(message) => match message.split_once("Invalid status code: ") {
+            Some((_, rest)) => rest
+                .split_whitespace()
+                .next()
+                .and_then(|s| s.parse().ok())
+                .is_some_and(is_retryable_status),
+            None => message.contains("Transport error"),
+        },
+        _ => false,
+    }
+}
+
+/// Opens a chat completion stream, retrying transient failures with exponential backoff.
+///
+/// HTTP errors on a streaming request only show up as the stream's first item, so the first
+/// item is awaited here and, if it's a success, stitched back onto the front of the stream.
+pub async fn create_stream_with_retry<C: Config>(
+    client: &OpenAIClient<C>,
+    request: CreateChatCompletionRequest,
+    policy: &RetryPolicy,
+) -> Result<ChatCompletionResponseStream, OpenAIError> {
+    let mut attempt = 0;
+    loop {
+        let error = match client.chat().create_stream(request.clone()).await {
+            Ok(mut stream) => match stream.next().await {
+                Some(Err(e)) => e,
+                first => {
+                    let head = stream::iter(first);
+                    return Ok(Box::pin(head.chain(stream)));
+                }
+            },
+            Err(e) => e,
+        };
+
+        if attempt >= policy.max_retries || !is_retryable(&error) {
+            return Err(error);
+        }
+        let delay = policy.delay(attempt);
+        attempt += 1;
+        warn!(
+            "OpenAI stream creation failed ({}). Retrying in {:?} (attempt {}/{}).",
+            error, delay, attempt, policy.max_retries
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
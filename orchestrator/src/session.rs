@@ -0,0 +1,129 @@
+use anyhow::{Context, Result};
+use async_openai::types::ChatCompletionRequestMessage;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Bump this whenever the on-disk layout (or the async-openai message types) change incompatibly.
+const SESSION_SCHEMA_VERSION: u32 = 1;
+
+// On-disk representation of a saved conversation
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionFile {
+    version: u32,
+    history: VecDeque<ChatCompletionRequestMessage>,
+}
+
+/// Loads a previously saved conversation history.
+///
+/// Returns `None` (and the caller starts fresh) when the file doesn't exist, can't be parsed,
+/// or was written with a different schema version.
+pub fn load_session(path: &Path) -> Option<VecDeque<ChatCompletionRequestMessage>> {
+    if !path.exists() {
+        info!("No session file at {}, starting a new session.", path.display());
+        return None;
+    }
+
+    let raw = match std::fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(e) => {
+            warn!("Failed to read session file {}: {}. Starting fresh.", path.display(), e);
+            return None;
+        }
+    };
+
+    // Peek at the version first so a layout change doesn't show up as a confusing parse error
+    let version = serde_json::from_str::<serde_json::Value>(&raw)
+        .ok()
+        .and_then(|v| v.get("version").and_then(|v| v.as_u64()));
+    if version != Some(SESSION_SCHEMA_VERSION as u64) {
+        warn!(
+            "Session file {} has schema version {:?}, expected {}. Starting fresh.",
+            path.display(), version, SESSION_SCHEMA_VERSION
+        );
+        return None;
+    }
+
+    match serde_json::from_str::<SessionFile>(&raw).map(|session| session.history) {
+        Ok(mut history) if !history.is_empty() => {
+            drop_unanswered_tool_calls(&mut history);
+            info!("Restored {} messages from session file {}.", history.len(), path.display());
+            Some(history)
+        }
+        Ok(_) => {
+            warn!("Session file {} contains no messages. Starting fresh.", path.display());
+            None
+        }
+        Err(e) => {
+            warn!("Failed to parse session file {}: {}. Starting fresh.", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Removes a trailing assistant message whose tool calls didn't all get a result, along with
+/// the results that did arrive. Such a tail is left by a run interrupted mid-tool-call (older
+/// versions saved before the results existed), and the API rejects any request containing it.
+fn drop_unanswered_tool_calls(history: &mut VecDeque<ChatCompletionRequestMessage>) {
+    let Some(index) = history.iter().rposition(|message| {
+        matches!(message, ChatCompletionRequestMessage::Assistant(assistant) if assistant.tool_calls.is_some())
+    }) else {
+        return;
+    };
+    let ChatCompletionRequestMessage::Assistant(assistant) = &history[index] else {
+        return;
+    };
+    let answered: HashSet<&str> = history
+        .iter()
+        .skip(index + 1)
+        .filter_map(|message| match message {
+            ChatCompletionRequestMessage::Tool(tool) => Some(tool.tool_call_id.as_str()),
+            _ => None,
+        })
+        .collect();
+    let unanswered = assistant.tool_calls.iter().flatten().filter(|call| !answered.contains(call.id.as_str())).count();
+    if unanswered > 0 {
+        warn!(
+            "Session ends with {} unanswered tool call(s); dropping the last {} message(s).",
+            unanswered, history.len() - index
+        );
+        history.truncate(index);
+    }
+}
+
+/// Writes the conversation history to `path`, replacing any previous contents.
+///
+/// The file is written to a temporary sibling first and then renamed so a crash mid-write
+/// doesn't leave a truncated session behind.
+pub fn save_session(path: &Path, history: &VecDeque<ChatCompletionRequestMessage>) -> Result<()> {
+    let session = SessionFile { version: SESSION_SCHEMA_VERSION, history: history.clone() };
+    let json = serde_json::to_string_pretty(&session).context("Failed to serialize session")?;
+
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, json)
+        .with_context(|| format!("Failed to write session file {}", tmp_path.display()))?;
+    std::fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to move session file into place at {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{result, tool_calls, user};
+
+    #[test]
+    fn drops_tool_calls_without_results() {
+        let mut history = VecDeque::from(vec![user("hi"), tool_calls(&["a", "b"]), result("a")]);
+        drop_unanswered_tool_calls(&mut history);
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn keeps_answered_tool_calls() {
+        let mut history = VecDeque::from(vec![user("hi"), tool_calls(&["a", "b"]), result("b"), result("a")]);
+        drop_unanswered_tool_calls(&mut history);
+        assert_eq!(history.len(), 4);
+    }
+}
@@ -0,0 +1,34 @@
+// Chat message fixtures shared by the history and session tests.
+
+use async_openai::types::{
+    ChatCompletionMessageToolCall, ChatCompletionRequestAssistantMessage, ChatCompletionRequestMessage,
+    ChatCompletionRequestToolMessage, ChatCompletionRequestToolMessageContent, ChatCompletionRequestUserMessage,
+    ChatCompletionRequestUserMessageContent, ChatCompletionToolType, FunctionCall,
+};
+
+pub fn user(text: &str) -> ChatCompletionRequestMessage {
+    ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+        content: ChatCompletionRequestUserMessageContent::Text(text.to_string()),
+        ..Default::default()
+    })
+}
+
+// Assistant turn requesting one capture_screen call per id
+pub fn tool_calls(ids: &[&str]) -> ChatCompletionRequestMessage {
+    let calls = ids
+        .iter()
+        .map(|id| ChatCompletionMessageToolCall {
+            id: id.to_string(),
+            r#type: ChatCompletionToolType::Function,
+            function: FunctionCall { name: "capture_screen".to_string(), arguments: "{}".to_string() },
+        })
+        .collect();
+    ChatCompletionRequestMessage::Assistant(ChatCompletionRequestAssistantMessage { tool_calls: Some(calls), ..Default::default() })
+}
+
+pub fn result(id: &str) -> ChatCompletionRequestMessage {
+    ChatCompletionRequestMessage::Tool(ChatCompletionRequestToolMessage {
+        tool_call_id: id.to_string(),
+        content: ChatCompletionRequestToolMessageContent::Text("{}".to_string()),
+    })
+}
@@ -0,0 +1,134 @@
+use anyhow::{Context, Result};
+use async_openai::types::ImageDetail;
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use tracing::warn;
+
+const DEFAULT_CACHE_SIZE: usize = 32;
+const DEFAULT_PROMPT: &str = "Describe this screenshot in detail, focusing on visible text, UI elements, and overall layout.";
+// Appended when the screenshot carries set-of-marks boxes
+const ANNOTATED_SUFFIX: &str = "Numbered magenta boxes mark detected elements; refer to them by their box number.";
+
+/// Image detail level from `AIRC_VISION_DETAIL` ("low", "high" or "auto", the default). Low
+/// detail costs a fraction of the tokens and is enough for simple checks.
+pub fn detail_from_env() -> Result<ImageDetail> {
+    let Ok(raw) = std::env::var("AIRC_VISION_DETAIL") else {
+        return Ok(ImageDetail::Auto);
+    };
+    match raw.trim().to_lowercase().as_str() {
+        "low" => Ok(ImageDetail::Low),
+        "high" => Ok(ImageDetail::High),
+        "auto" => Ok(ImageDetail::Auto),
+        _ => anyhow::bail!("AIRC_VISION_DETAIL must be 'low', 'high' or 'auto', got '{}'", raw),
+    }
+}
+
+/// How screenshots from tool results reach the chat model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScreenshotMode {
+    /// Only the vision model's text description (the default; works with text-only chat models).
+    #[default]
+    Describe,
+    /// The image itself, attached to the conversation; no separate vision call. Needs a
+    /// vision-capable chat model.
+    Attach,
+    /// Both the image and the description.
+    Both,
+}
+
+impl ScreenshotMode {
+    /// From `AIRC_ATTACH_SCREENSHOTS`: "off" (default), "on" (image only) or "both".
+    pub fn from_env() -> Result<Self> {
+        let Ok(raw) = std::env::var("AIRC_ATTACH_SCREENSHOTS") else {
+            return Ok(Self::Describe);
+        };
+        match raw.trim().to_lowercase().as_str() {
+            "off" | "0" | "false" => Ok(Self::Describe),
+            "on" | "1" | "true" => Ok(Self::Attach),
+            "both" => Ok(Self::Both),
+            _ => anyhow::bail!("AIRC_ATTACH_SCREENSHOTS must be 'off', 'on' or 'both', got '{}'", raw),
+        }
+    }
+
+    pub fn attaches(self) -> bool {
+        self != Self::Describe
+    }
+
+    pub fn describes(self) -> bool {
+        self != Self::Attach
+    }
+}
+
+/// The screenshot prompt: `AIRC_VISION_PROMPT`, else the contents of the file named by
+/// `AIRC_VISION_PROMPT_FILE`, else the built-in one. Lets operators narrow what the vision model
+/// reports (e.g. "Only describe error dialogs").
+pub fn prompt_from_env() -> Result<String> {
+    if let Some(prompt) = std::env::var("AIRC_VISION_PROMPT").ok().filter(|p| !p.trim().is_empty()) {
+        return Ok(prompt.trim().to_string());
+    }
+    if let Ok(path) = std::env::var("AIRC_VISION_PROMPT_FILE") {
+        let prompt = std::fs::read_to_string(&path).with_context(|| format!("Failed to read AIRC_VISION_PROMPT_FILE '{}'", path))?;
+        if prompt.trim().is_empty() {
+            anyhow::bail!("AIRC_VISION_PROMPT_FILE '{}' is empty", path);
+        }
+        return Ok(prompt.trim().to_string());
+    }
+    Ok(DEFAULT_PROMPT.to_string())
+}
+
+/// The prompt for one screenshot, with the set-of-marks note when it is annotated.
+pub fn screenshot_prompt(base: &str, annotated: bool) -> String {
+    if annotated { format!("{} {}", base, ANNOTATED_SUFFIX) } else { base.to_string() }
+}
+
+/// Recent vision-model descriptions, keyed by screenshot, prompt and model, so an unchanged
+/// screen isn't sent to the vision model again. Least recently used entries are evicted first.
+#[derive(Debug)]
+pub struct VisionCache {
+    capacity: usize,
+    /// Oldest first; a hit moves the entry to the back.
+    entries: VecDeque<(u64, String)>,
+}
+
+impl VisionCache {
+    /// Cache holding `AIRC_VISION_CACHE_SIZE` descriptions (default 32, 0 disables caching).
+    pub fn from_env() -> Self {
+        let capacity = match std::env::var("AIRC_VISION_CACHE_SIZE") {
+            Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+                warn!("Ignoring invalid AIRC_VISION_CACHE_SIZE value '{}'", raw);
+                DEFAULT_CACHE_SIZE
+            }),
+            Err(_) => DEFAULT_CACHE_SIZE,
+        };
+        Self { capacity, entries: VecDeque::with_capacity(capacity) }
+    }
+
+    /// Cache key of one analysis. The PNG encoder is deterministic, so identical screens give
+    /// identical base64 and the encoded text can be hashed without decoding it.
+    pub fn key(base64_image: &str, prompt: &str, model: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        base64_image.hash(&mut hasher);
+        prompt.hash(&mut hasher);
+        model.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn get(&mut self, key: u64) -> Option<String> {
+        let position = self.entries.iter().position(|(k, _)| *k == key)?;
+        let entry = self.entries.remove(position)?;
+        let description = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(description)
+    }
+
+    pub fn insert(&mut self, key: u64, description: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.entries.retain(|(k, _)| *k != key);
+        while self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((key, description));
+    }
+}